@@ -0,0 +1,58 @@
+use crate::youtube::YoutubeClient;
+use std::cell::Cell;
+
+/// Google accounts to post with, each with its own token. The first one also reads everything. Posting sticks to one
+/// account and moves on to the next when it gets its comment rejected, alternates between them with
+/// --rotate-accounts, or goes on with the one that landed first with --race-accounts.
+pub struct Accounts {
+  /// Name (to tell them apart in the logs, empty without --account) and client of every account
+  accounts: Vec<(String, YoutubeClient)>,
+  current: Cell<usize>,
+}
+
+impl Accounts {
+  pub fn new(accounts: Vec<(String, YoutubeClient)>) -> Self {
+    assert!(!accounts.is_empty(), "at least one account is needed");
+
+    Self {
+      accounts,
+      current: Cell::new(0),
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.accounts.len()
+  }
+
+  /// The account everything but posting goes through
+  pub fn main(&self) -> &YoutubeClient {
+    &self.accounts[0].1
+  }
+
+  pub fn clients(&self) -> impl Iterator<Item = &YoutubeClient> {
+    self.accounts.iter().map(|(_, client)| client)
+  }
+
+  pub fn name(&self, index: usize) -> &str {
+    &self.accounts[index].0
+  }
+
+  /// The account the next comment is posted with
+  pub fn poster(&self) -> &YoutubeClient {
+    &self.accounts[self.current.get()].1
+  }
+
+  pub fn poster_name(&self) -> &str {
+    &self.accounts[self.current.get()].0
+  }
+
+  /// Makes the account at `index` the one posting
+  pub fn select(&self, index: usize) {
+    self.current.set(index);
+  }
+
+  /// Moves on to the next account, wrapping around
+  pub fn next(&self) {
+    self.current.set((self.current.get() + 1) % self.accounts.len());
+  }
+}
@@ -0,0 +1,126 @@
+use crate::{
+  console, playlists,
+  schedule::UploadPattern,
+  uploads::resolve_channel_id,
+  youtube::{get_latest_videos, YoutubeClient},
+};
+use google_youtube3::chrono::{DateTime, Utc};
+use std::{collections::HashMap, error::Error, fs, io, path::Path, time::Duration};
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Hot windows suggested at most, a channel uploading all over the day has no usual time anyway
+const MAX_SUGGESTED_WINDOWS: usize = 3;
+
+fn load(path: &Path) -> io::Result<HashMap<String, Vec<DateTime<Utc>>>> {
+  match fs::read_to_string(path) {
+    Ok(contents) => serde_json::from_str(&contents).map_err(io::Error::other),
+    Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(HashMap::new()),
+    Err(e) => Err(e),
+  }
+}
+
+fn save(path: &Path, channel_id: &str, published: &[DateTime<Utc>]) -> io::Result<()> {
+  let mut patterns = load(path).unwrap_or_default();
+  patterns.insert(channel_id.into(), published.to_vec());
+
+  if let Some(dir) = path.parent() {
+    fs::create_dir_all(dir)?;
+  }
+
+  fs::write(path, serde_json::to_string_pretty(&patterns).map_err(io::Error::other)?)
+}
+
+/// The publish times saved by the last `yfc analyze` of the channel, for --auto-schedule
+pub fn saved_publish_times(data_dir: &Path, channel_id: &str) -> Vec<DateTime<Utc>> {
+  load(&data_dir.join("upload_patterns.json"))
+    .ok()
+    .and_then(|mut patterns| patterns.remove(channel_id))
+    .unwrap_or_default()
+}
+
+/// Prints when the channel usually uploads, from its latest `limit` uploads (Shorts left out), and saves the publish
+/// times in `<data dir>/upload_patterns.json` for --auto-schedule
+pub async fn run(
+  client: &YoutubeClient,
+  data_dir: &Path,
+  channel: &str,
+  limit: u32,
+  api_key: Option<&str>,
+  burst_window: Duration,
+) -> Result<(), Box<dyn Error>> {
+  let channel_id = resolve_channel_id(client, channel, api_key).await?;
+  let playlist_id = playlists::uploads_playlist_id(client, data_dir, &channel_id, api_key, false)
+    .await
+    .ok_or_else(|| format!("Failed to get the uploads playlist of channel {channel_id}"))?;
+  let uploads = get_latest_videos(client, &playlist_id, limit, api_key, None)
+    .await?
+    .map(|page| page.videos)
+    .unwrap_or_default();
+  let mut published: Vec<DateTime<Utc>> = uploads
+    .iter()
+    .filter(|upload| !upload.is_short)
+    .filter_map(|upload| upload.published_at)
+    .collect();
+  published.sort_unstable();
+
+  let (Some(first), Some(last)) = (published.first(), published.last()) else {
+    println!("Channel {channel_id} has no uploads to learn from");
+    return Ok(());
+  };
+
+  println!(
+    "{} uploads of channel {channel_id} from {} to {}, Shorts left out",
+    published.len(),
+    first.format("%Y-%m-%d"),
+    last.format("%Y-%m-%d")
+  );
+
+  if published.len() > 1 {
+    let average_gap = (*last - *first).num_seconds() as u64 / (published.len() as u64 - 1);
+    println!(
+      "One upload every {} on average",
+      console::format_duration(Duration::from_secs(average_gap))
+    );
+  }
+
+  let pattern = UploadPattern::new(&published);
+
+  println!("\nUploads per weekday (UTC):");
+
+  for (weekday, count) in WEEKDAYS.iter().zip(pattern.weekdays()) {
+    println!("  {weekday}  {:<20}  {count}", "#".repeat(count * 20 / pattern.len()));
+  }
+
+  println!(
+    "\nUsual upload times (UTC, ±{}):",
+    console::format_duration(burst_window)
+  );
+
+  let windows = pattern.hot_windows(burst_window);
+
+  for (window, count) in &windows {
+    println!("  {window}  {count} upload(s), {}%", count * 100 / pattern.len());
+  }
+
+  // A single upload at some time isn't a habit
+  let suggested: Vec<String> = windows
+    .iter()
+    .filter(|(_, count)| *count > 1)
+    .take(MAX_SUGGESTED_WINDOWS)
+    .map(|(window, _)| format!("--hot-window {window}"))
+    .collect();
+
+  if suggested.is_empty() {
+    println!("\nNo usual upload time stands out");
+  } else {
+    println!("\nSuggested: {}", suggested.join(" "));
+  }
+
+  match save(&data_dir.join("upload_patterns.json"), &channel_id, &published) {
+    Ok(()) => println!("Saved the upload times, --auto-schedule polls fast around them from now on"),
+    Err(e) => console::error(format!("Failed to save the upload times: {e}")),
+  }
+
+  Ok(())
+}
@@ -0,0 +1,189 @@
+use crate::{
+  console,
+  control::{self, ControlCommand, Reply},
+  hints,
+  status::{self, SharedWatchState},
+  youtube::HttpsClient,
+};
+use google_youtube3::{
+  client::GetToken,
+  hyper::{
+    self,
+    header::{AUTHORIZATION, CONTENT_TYPE, WWW_AUTHENTICATE},
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+  },
+};
+use ring::constant_time;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{convert::Infallible, error::Error, net::SocketAddr, sync::Arc};
+
+/// Body of a comment change
+#[derive(Deserialize)]
+struct CommentRequest {
+  text: String,
+}
+
+/// Body of a push request
+#[derive(Serialize, Deserialize)]
+struct PushRequest {
+  video_id: String,
+}
+
+fn is_video_id(value: &str) -> bool {
+  value.len() == 11 && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Whether the request carries the bearer token, compared in constant time
+fn is_authorized(request: &Request<Body>, token: &str) -> bool {
+  request
+    .headers()
+    .get(AUTHORIZATION)
+    .and_then(|value| value.to_str().ok())
+    .and_then(|value| value.strip_prefix("Bearer "))
+    .is_some_and(|given| constant_time::verify_slices_are_equal(given.as_bytes(), token.as_bytes()).is_ok())
+}
+
+async fn set_comment(request: Request<Body>, state: &SharedWatchState, auth: &dyn GetToken) -> Response<Body> {
+  let body = hyper::body::to_bytes(request.into_body()).await.unwrap_or_default();
+
+  match serde_json::from_slice::<CommentRequest>(&body) {
+    Ok(comment) => reply(control::execute(ControlCommand::SetComment { text: comment.text }, state, auth).await),
+    Err(_) => status::json_response(
+      StatusCode::BAD_REQUEST,
+      json!({ "error": "expected {\"text\": \"<comment>\"}" }),
+    ),
+  }
+}
+
+/// Queues a video for the main loop to post on right away
+async fn push_video(request: Request<Body>, state: &SharedWatchState) -> Response<Body> {
+  let body = hyper::body::to_bytes(request.into_body()).await.unwrap_or_default();
+
+  match serde_json::from_slice::<PushRequest>(&body) {
+    Ok(push) if is_video_id(&push.video_id) => {
+      console::info(format!("Video {} was pushed remotely", push.video_id));
+      state.lock().unwrap().pushed_video_ids.push_back(push.video_id.clone());
+
+      status::json_response(StatusCode::ACCEPTED, json!({ "queued": push.video_id }))
+    }
+    _ => status::json_response(
+      StatusCode::BAD_REQUEST,
+      json!({ "error": "expected {\"video_id\": \"<11 character video ID>\"}" }),
+    ),
+  }
+}
+
+/// Starts a burst of API polls, e.g. for a WebSub subscriber told about a new upload
+fn hint(state: &SharedWatchState) -> Response<Body> {
+  hints::hint(state, "received remotely");
+
+  status::json_response(StatusCode::ACCEPTED, json!({ "hinted": true }))
+}
+
+fn reply(reply: Reply) -> Response<Body> {
+  match reply {
+    Reply::Status(status) => status::json_response(StatusCode::OK, serde_json::to_value(&status).unwrap()),
+    Reply::Done(message) => status::json_response(StatusCode::OK, json!({ "message": message })),
+  }
+}
+
+async fn handle(
+  request: Request<Body>,
+  token: Arc<str>,
+  state: SharedWatchState,
+  auth: Box<dyn GetToken>,
+) -> Result<Response<Body>, Infallible> {
+  if !is_authorized(&request, &token) {
+    let mut response = status::json_response(StatusCode::UNAUTHORIZED, json!({ "error": "invalid bearer token" }));
+    response
+      .headers_mut()
+      .insert(WWW_AUTHENTICATE, "Bearer".parse().unwrap());
+
+    return Ok(response);
+  }
+
+  let command = match (request.method(), request.uri().path()) {
+    (&Method::GET, "/status") => ControlCommand::Status,
+    (&Method::POST, "/pause") => ControlCommand::Pause,
+    (&Method::POST, "/resume") => ControlCommand::Resume,
+    (&Method::POST, "/stop") => ControlCommand::Stop,
+    (&Method::PUT, "/comment") => return Ok(set_comment(request, &state, auth.as_ref()).await),
+    (&Method::DELETE, "/comment") => ControlCommand::SetComment { text: String::new() },
+    (&Method::POST, "/push") => return Ok(push_video(request, &state).await),
+    (&Method::POST, "/hint") => return Ok(hint(&state)),
+    _ => {
+      return Ok(status::json_response(
+        StatusCode::NOT_FOUND,
+        json!({ "error": "no such endpoint" }),
+      ))
+    }
+  };
+
+  Ok(reply(control::execute(command, &state, auth.as_ref()).await))
+}
+
+/// Starts the control API in the background: the controls of the control socket, plus pushing a video to post on,
+/// over HTTP for a watcher running on another machine. Every request needs the bearer token.
+pub fn serve(
+  addr: SocketAddr,
+  token: &str,
+  state: SharedWatchState,
+  auth: Box<dyn GetToken>,
+) -> Result<(), hyper::Error> {
+  let token: Arc<str> = token.into();
+  let make_service = make_service_fn(move |_| {
+    let token = token.clone();
+    let state = state.clone();
+    let auth = auth.clone_box();
+
+    async move {
+      Ok::<_, Infallible>(service_fn(move |request| {
+        handle(request, token.clone(), state.clone(), auth.clone_box())
+      }))
+    }
+  });
+
+  let server = Server::try_bind(&addr)?.serve(make_service);
+
+  console::info(format!("Control API listening on http://{addr}"));
+
+  tokio::spawn(async move {
+    if let Err(e) = server.await {
+      console::error(format!("Control API error: {e}"));
+    }
+  });
+
+  Ok(())
+}
+
+/// Pushes a video to the watcher serving the control API on the given address, which posts on it right away
+pub async fn push(client: &HttpsClient, addr: &str, token: &str, video_id: &str) -> Result<(), Box<dyn Error>> {
+  let base_url = if addr.contains("://") {
+    addr.trim_end_matches('/').to_string()
+  } else {
+    format!("http://{addr}")
+  };
+  let request = Request::post(format!("{base_url}/push"))
+    .header(AUTHORIZATION, format!("Bearer {token}"))
+    .header(CONTENT_TYPE, "application/json")
+    .body(Body::from(serde_json::to_string(&PushRequest {
+      video_id: video_id.into(),
+    })?))?;
+  let response = client.request(request).await?;
+  let status = response.status();
+  let body = hyper::body::to_bytes(response.into_body()).await?;
+
+  if !status.is_success() {
+    return Err(
+      format!(
+        "The watcher refused the push ({status}): {}",
+        String::from_utf8_lossy(&body)
+      )
+      .into(),
+    );
+  }
+
+  Ok(())
+}
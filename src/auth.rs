@@ -0,0 +1,320 @@
+#[cfg(feature = "otel")]
+use crate::otel;
+use crate::token_storage::EncryptedStorage;
+use crate::{
+  cli::AuthFlow,
+  get_data_dir,
+  youtube::{new_client, with_timeout, YoutubeClient},
+};
+use crate::{console, quota, token_storage};
+use google_youtube3::{
+  chrono::Utc,
+  hyper::client::HttpConnector,
+  hyper_rustls::HttpsConnector,
+  oauth2::{
+    authenticator::{Authenticator, AuthenticatorBuilder},
+    authenticator_delegate::InstalledFlowDelegate,
+    ApplicationSecret, DeviceFlowAuthenticator, InstalledFlowAuthenticator, InstalledFlowReturnMethod,
+  },
+};
+use std::{
+  env,
+  error::Error,
+  fs,
+  future::Future,
+  io::{self},
+  path::PathBuf,
+  pin::Pin,
+  sync::Arc,
+  time::Duration,
+};
+use tokio::{sync::Notify, time::sleep};
+
+/// Each --account has its own directory, without any the token stays at the root of the data directory
+pub fn get_token_storage_path(account: &str) -> PathBuf {
+  match account {
+    "" => get_data_dir().join("token.json"),
+    account => get_data_dir().join(account).join("token.json"),
+  }
+}
+
+/// Tokens of the read projects are kept apart from the one of the main project
+pub fn get_read_token_storage_path(client_id: &str) -> PathBuf {
+  get_data_dir().join(format!("token-{client_id}.json"))
+}
+
+pub const READ_SCOPE: &str = "https://www.googleapis.com/auth/youtube.readonly";
+pub const WRITE_SCOPE: &str = "https://www.googleapis.com/auth/youtube.force-ssl";
+
+pub async fn get_youtube_client(
+  client_id: &str,
+  client_secret: &str,
+  token_path: PathBuf,
+  scopes: &[&str],
+  flow: AuthFlow,
+) -> io::Result<YoutubeClient> {
+  let secret = ApplicationSecret {
+    client_id: client_id.into(),
+    client_secret: client_secret.into(),
+    auth_uri: "https://accounts.google.com/o/oauth2/auth".into(),
+    token_uri: "https://oauth2.googleapis.com/token".into(),
+    ..Default::default()
+  };
+
+  let app_cache_path = token_path.parent().unwrap();
+
+  if !fs::exists(app_cache_path)? {
+    fs::create_dir_all(app_cache_path)?;
+  }
+
+  let refused = Arc::new(Notify::new());
+  let auth = match flow {
+    AuthFlow::Browser => {
+      with_token_storage(
+        InstalledFlowAuthenticator::builder(secret, InstalledFlowReturnMethod::HTTPRedirect).flow_delegate(Box::new(
+          BrowserLogin {
+            refused: refused.clone(),
+          },
+        )),
+        token_path,
+      )?
+      .build()
+      .await?
+    }
+    AuthFlow::Device => {
+      with_token_storage(DeviceFlowAuthenticator::builder(secret), token_path)?
+        .build()
+        .await?
+    }
+  };
+
+  // This will request every scope at once instead of having to wait for a comment creation to log in again and give
+  // the other scope
+  tokio::select! {
+    token = auth.token(scopes) => token.map_err(io::Error::other)?,
+    () = refused.notified() => return Err(io::Error::other(
+      "There's no terminal to log in with the browser: log in with --auth-flow device (which --headless uses), or copy \
+       the token of a login made elsewhere into the data directory",
+    )),
+  };
+
+  tokio::spawn(keep_token_fresh(
+    auth.clone(),
+    scopes.iter().map(|scope| scope.to_string()).collect(),
+  ));
+
+  new_client(auth)
+}
+
+/// Shows where to log in with the browser, or gives up on a login nobody can do (e.g. in a container). The redirect
+/// flow would wait for it forever whatever the answer, hence `refused`.
+struct BrowserLogin {
+  refused: Arc<Notify>,
+}
+
+impl InstalledFlowDelegate for BrowserLogin {
+  fn present_user_url<'a>(
+    &'a self,
+    url: &'a str,
+    _need_code: bool,
+  ) -> Pin<Box<dyn Future<Output = Result<String, String>> + Send + 'a>> {
+    Box::pin(async move {
+      if !console::is_interactive() {
+        self.refused.notify_one();
+        return Err("no terminal to log in with the browser".into());
+      }
+
+      console::info(format!(
+        "Please direct your browser to {url} and follow the instructions displayed there."
+      ));
+
+      Ok(String::new())
+    })
+  }
+}
+
+/// How long before the access token expires it gets refreshed
+pub const TOKEN_REFRESH_AHEAD: Duration = Duration::from_secs(5 * 60);
+
+/// Refreshes the access token ahead of its expiry, in the background, so the refresh never delays an API call, least
+/// of all posting the comment
+pub async fn keep_token_fresh(auth: Authenticator<HttpsConnector<HttpConnector>>, scopes: Vec<String>) {
+  loop {
+    let expires_at = match auth.token(&scopes).await {
+      Ok(token) => token.expiration_time(),
+      Err(e) => {
+        console::error(format!("Failed to get the access token, retrying in a minute: {e}"));
+        sleep(Duration::from_secs(60)).await;
+        continue;
+      }
+    };
+
+    // A token without an expiry never needs a refresh
+    let Some(expires_at) = expires_at else {
+      return;
+    };
+
+    let refresh_at = expires_at.unix_timestamp() - TOKEN_REFRESH_AHEAD.as_secs() as i64;
+    sleep(Duration::from_secs((refresh_at - Utc::now().timestamp()).max(0) as u64)).await;
+
+    #[cfg(feature = "otel")]
+    let refresh_started_at = std::time::SystemTime::now();
+    let result = auth.force_refreshed_token(&scopes).await;
+
+    #[cfg(feature = "otel")]
+    otel::span(
+      "auth.refresh",
+      refresh_started_at,
+      &[("yfc.success", result.is_ok().into())],
+    );
+
+    if let Err(e) = result {
+      console::error(format!(
+        "Failed to refresh the access token ahead of time, retrying in a minute: {e}"
+      ));
+      sleep(Duration::from_secs(60)).await;
+    }
+  }
+}
+
+/// Keeps the tokens in `token_path`, encrypted when YFC_TOKEN_KEY is set. Otherwise, they are kept in the OS keychain
+/// when built with it (falling back to `token_path`).
+pub fn with_token_storage<C, F>(
+  builder: AuthenticatorBuilder<C, F>,
+  token_path: PathBuf,
+) -> io::Result<AuthenticatorBuilder<C, F>> {
+  if let Some(passphrase) = env::var(token_storage::TOKEN_KEY_VAR)
+    .ok()
+    .filter(|key| !key.is_empty())
+  {
+    let storage = EncryptedStorage::open(token_path, passphrase)?;
+
+    return Ok(builder.with_storage(Box::new(storage)));
+  }
+
+  #[cfg(feature = "keyring")]
+  return Ok(builder.with_storage(Box::new(crate::keychain::KeychainStorage::new(token_path))));
+
+  #[cfg(not(feature = "keyring"))]
+  Ok(builder.persist_tokens_to_disk(token_path))
+}
+
+/// Deletes the tokens kept for `token_path`, wherever `with_token_storage` keeps them, so the next login starts over
+pub fn clear_tokens(token_path: PathBuf) -> io::Result<()> {
+  #[cfg(feature = "keyring")]
+  if env::var(token_storage::TOKEN_KEY_VAR).map_or(true, |key| key.is_empty()) {
+    return crate::keychain::KeychainStorage::new(token_path).clear();
+  }
+
+  match fs::remove_file(token_path) {
+    Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+    _ => Ok(()),
+  }
+}
+
+#[cfg(feature = "keyring")]
+pub fn stored_client_secret(client_id: &str) -> Option<String> {
+  crate::keychain::client_secret(client_id)
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn stored_client_secret(_client_id: &str) -> Option<String> {
+  None
+}
+
+/// The client secret given on the command line, else the one stored in the keychain
+pub fn get_client_secret(client_id: &str, client_secret: Option<&str>) -> Result<String, Box<dyn Error>> {
+  client_secret
+    .map(String::from)
+    .or_else(|| stored_client_secret(client_id))
+    .ok_or_else(|| {
+      format!("No client secret for {client_id}, pass --google-client-secret or store it with yfc auth store-secret")
+        .into()
+    })
+}
+
+/// Logs the account in (named by --account, empty for the default one) with every scope the watcher needs
+pub async fn authenticate_account(
+  client_id: &str,
+  client_secret: &str,
+  account: &str,
+  flow: AuthFlow,
+) -> io::Result<YoutubeClient> {
+  get_youtube_client(
+    client_id,
+    client_secret,
+    get_token_storage_path(account),
+    &[READ_SCOPE, WRITE_SCOPE],
+    flow,
+  )
+  .await
+}
+
+/// The ID and title of the channel the client posts as, the one picked when logging in
+pub async fn get_acting_channel(client: &YoutubeClient) -> google_youtube3::Result<Option<(String, String)>> {
+  quota::spend(quota::LIST_COST);
+
+  let (_, response) = with_timeout(
+    client
+      .channels()
+      .list(&vec!["snippet".into()])
+      .mine(true)
+      .param("fields", "items(id,snippet/title)")
+      .doit(),
+  )
+  .await?;
+
+  Ok(response.items.unwrap_or_default().into_iter().find_map(|channel| {
+    Some((
+      channel.id?,
+      channel.snippet.and_then(|snippet| snippet.title).unwrap_or_default(),
+    ))
+  }))
+}
+
+/// Whether the client posts as `channel_id`, telling which channel it posts as otherwise
+pub async fn acts_as(client: &YoutubeClient, channel_id: &str) -> google_youtube3::Result<bool> {
+  match get_acting_channel(client).await? {
+    Some((id, title)) if id == channel_id => {
+      console::info(format!("Posting as {title} ({id})"));
+      Ok(true)
+    }
+    Some((id, title)) => {
+      console::warning(format!(
+        "The login posts as {title} ({id}), log in again and pick channel {channel_id} when asked"
+      ));
+      Ok(false)
+    }
+    None => {
+      console::warning(format!(
+        "The login has no channel, log in again and pick channel {channel_id} when asked"
+      ));
+      Ok(false)
+    }
+  }
+}
+
+pub async fn list_channels(
+  client_id: &str,
+  client_secret: &str,
+  accounts: Vec<String>,
+  flow: AuthFlow,
+) -> Result<(), Box<dyn Error>> {
+  let accounts = if accounts.is_empty() {
+    vec![String::new()]
+  } else {
+    accounts
+  };
+
+  for account in accounts {
+    let client = authenticate_account(client_id, client_secret, &account, flow).await?;
+    let name = if account.is_empty() { "default" } else { &account };
+
+    match get_acting_channel(&client).await? {
+      Some((id, title)) => println!("{name}: {id} {title}"),
+      None => println!("{name}: no channel"),
+    }
+  }
+
+  Ok(())
+}
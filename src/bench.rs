@@ -0,0 +1,195 @@
+use crate::{
+  console::{self, format_duration},
+  get_data_dir, quota,
+  uploads::resolve_channel_id,
+  youtube::{
+    delete_comment, get_latest_videos, get_uploads_playlist_id, is_quota_exceeded, is_transient, post_comment,
+    YoutubeClient,
+  },
+};
+use std::{
+  error::Error,
+  time::{Duration, Instant},
+};
+use tokio::time::sleep;
+
+/// What `yfc bench` times
+pub struct Bench<'a> {
+  /// Polls the uploads, with the API key if given
+  pub reader: &'a YoutubeClient,
+  pub api_key: Option<&'a str>,
+  /// Inserts the comments, `None` to only time the polls
+  pub poster: Option<(&'a YoutubeClient, &'a str)>,
+  /// The comment really posted (and deleted right away), `None` to send one YouTube refuses
+  pub comment: Option<&'a str>,
+  pub iterations: u32,
+  pub spacing: Duration,
+}
+
+/// The round trips of one kind of call, the first one (opening the connection) kept apart
+#[derive(Default)]
+struct Samples {
+  first: Option<Duration>,
+  rest: Vec<Duration>,
+  failures: usize,
+}
+
+impl Samples {
+  fn add(&mut self, elapsed: Duration) {
+    match self.first {
+      None => self.first = Some(elapsed),
+      Some(_) => self.rest.push(elapsed),
+    }
+  }
+
+  /// Nearest-rank percentile of the calls after the first one
+  fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    sorted[(sorted.len() * percent).div_ceil(100).max(1) - 1]
+  }
+
+  fn report(&self, name: &str) {
+    let Some(first) = self.first else {
+      return console::error(format!("{name:<7} every call failed"));
+    };
+    let mut line = format!("{name:<7} first {:>6}", format_ms(first));
+    let mut sorted = self.rest.clone();
+
+    sorted.sort();
+
+    if let (Some(min), Some(max)) = (sorted.first(), sorted.last()) {
+      let mean = sorted.iter().sum::<Duration>() / sorted.len() as u32;
+
+      line.push_str(&format!(
+        "  min {:>6}  p50 {:>6}  p90 {:>6}  p99 {:>6}  max {:>6}  mean {:>6}",
+        format_ms(*min),
+        format_ms(Self::percentile(&sorted, 50)),
+        format_ms(Self::percentile(&sorted, 90)),
+        format_ms(Self::percentile(&sorted, 99)),
+        format_ms(*max),
+        format_ms(mean)
+      ));
+    }
+
+    if self.failures > 0 {
+      line.push_str(&format!("  ({} failed)", self.failures));
+    }
+
+    println!("{line}");
+  }
+}
+
+fn format_ms(duration: Duration) -> String {
+  format!("{}ms", duration.as_millis())
+}
+
+/// Times a comment insert on `video_id`. Without a comment to post, an empty one is sent: YouTube refuses it, but only
+/// after going through the same front end, so nothing is posted and the round trip is close to a real one.
+async fn time_insert(
+  poster: &YoutubeClient,
+  video_id: &str,
+  comment: Option<&str>,
+) -> Result<Duration, google_youtube3::Error> {
+  let started_at = Instant::now();
+  let result = post_comment(poster, video_id, comment.unwrap_or_default(), None).await;
+  let elapsed = started_at.elapsed();
+
+  match result {
+    Ok(comment_id) => {
+      if let Err(e) = delete_comment(poster, &comment_id).await {
+        console::error(format!(
+          "Failed to delete the comment {comment_id}, delete it with `yfc delete-comment`: {e}"
+        ));
+      }
+
+      Ok(elapsed)
+    }
+    // Refused as expected, the answer came back all the same
+    Err(e)
+      if comment.is_none()
+        && matches!(
+          e,
+          google_youtube3::Error::BadRequest(_) | google_youtube3::Error::Failure(_)
+        )
+        && !is_transient(&e)
+        && !is_quota_exceeded(&e) =>
+    {
+      Ok(elapsed)
+    }
+    Err(e) => Err(e),
+  }
+}
+
+/// Times `iterations` polls of the uploads of the channel, and as many comment inserts with a poster, against the real
+/// API. Calls failing on the network are counted apart, running out of quota stops the bench.
+pub async fn run(bench: Bench<'_>, channel: &str) -> Result<(), Box<dyn Error>> {
+  quota::track(&get_data_dir());
+
+  let channel_id = resolve_channel_id(bench.reader, channel, bench.api_key).await?;
+  let playlist_id = get_uploads_playlist_id(bench.reader, &channel_id, bench.api_key)
+    .await
+    .ok_or_else(|| format!("Failed to find channel {channel_id}"))?;
+  let insert_cost = match bench.comment {
+    Some(_) => quota::INSERT_COST + quota::DELETE_COST,
+    None => quota::INSERT_COST,
+  };
+  let cost = u64::from(bench.iterations) * (quota::LIST_COST + bench.poster.map_or(0, |_| insert_cost));
+
+  match bench.poster {
+    Some((_, video_id)) => println!(
+      "Timing {} polls of the uploads of channel {channel_id} and {} comment inserts on video {video_id}, about {cost} \
+       quota units",
+      bench.iterations, bench.iterations
+    ),
+    None => println!(
+      "Timing {} polls of the uploads of channel {channel_id}, about {cost} quota units",
+      bench.iterations
+    ),
+  }
+
+  let started_at = Instant::now();
+  let mut polls = Samples::default();
+  let mut inserts = Samples::default();
+
+  for iteration in 0..bench.iterations {
+    if iteration > 0 {
+      sleep(bench.spacing).await;
+    }
+
+    let poll_started_at = Instant::now();
+
+    match get_latest_videos(bench.reader, &playlist_id, 1, bench.api_key, None).await {
+      Ok(_) => polls.add(poll_started_at.elapsed()),
+      Err(e) if is_quota_exceeded(&e) => return Err("The API quota ran out".into()),
+      Err(e) => {
+        console::warning(format!("Poll {} failed: {e}", iteration + 1));
+        polls.failures += 1;
+      }
+    }
+
+    if let Some((poster, video_id)) = bench.poster {
+      match time_insert(poster, video_id, bench.comment).await {
+        Ok(elapsed) => inserts.add(elapsed),
+        Err(e) if is_quota_exceeded(&e) => return Err("The API quota ran out".into()),
+        Err(e) => {
+          console::warning(format!("Insert {} failed: {e}", iteration + 1));
+          inserts.failures += 1;
+        }
+      }
+    }
+
+    console::countdown(format!("{}/{} done", iteration + 1, bench.iterations));
+  }
+
+  console::clear_countdown();
+  println!(
+    "Round trips over {}, the first call of each kind opening the connection:",
+    format_duration(started_at.elapsed())
+  );
+  polls.report("Poll");
+
+  if bench.poster.is_some() {
+    inserts.report("Insert");
+  }
+
+  Ok(())
+}
@@ -0,0 +1,108 @@
+use std::{
+  sync::{Mutex, OnceLock},
+  time::Duration,
+};
+use tokio::{sync::Notify, time::sleep};
+
+/// How long a channel waits before posting when another one has a higher priority, for that one to go first if it
+/// posts at the same time
+const SIMULTANEOUS: Duration = Duration::from_secs(1);
+
+/// Lines up the posts of the channels watched at once: when several channels want to post at the same time, the one
+/// with the highest priority goes first, then the others in turn.
+struct PostGate {
+  /// The highest priority of the channels watched
+  highest: i64,
+  state: Mutex<GateState>,
+  released: Notify,
+}
+
+#[derive(Default)]
+struct GateState {
+  posting: bool,
+  /// The priority and ticket of every watcher waiting to post
+  waiting: Vec<(i64, u64)>,
+  next_ticket: u64,
+}
+
+/// Only set when watching several channels, a single watcher posts right away
+static GATE: OnceLock<PostGate> = OnceLock::new();
+
+/// Held by the watcher posting, the next one in line posts when it's dropped
+pub struct Turn;
+
+/// A place in line, given up when the watcher stops waiting
+struct Ticket<'a> {
+  gate: &'a PostGate,
+  number: u64,
+}
+
+/// Makes the watchers of the channels with these priorities take turns to post, see `turn`
+pub fn enable(priorities: impl IntoIterator<Item = i64>) {
+  GATE.get_or_init(|| PostGate {
+    highest: priorities.into_iter().max().unwrap_or_default(),
+    state: Mutex::default(),
+    released: Notify::new(),
+  });
+}
+
+/// Waits until no other channel is posting and none with a higher priority is waiting to. Ties go to the one that
+/// asked first.
+pub async fn turn(priority: i64) -> Option<Turn> {
+  let gate = GATE.get()?;
+
+  if priority < gate.highest {
+    sleep(SIMULTANEOUS).await;
+  }
+  let ticket = {
+    let mut state = gate.state.lock().unwrap();
+    let number = state.next_ticket;
+
+    state.next_ticket += 1;
+    state.waiting.push((priority, number));
+    Ticket { gate, number }
+  };
+
+  loop {
+    // Created before looking, so a release in between isn't missed
+    let released = gate.released.notified();
+
+    {
+      let mut state = gate.state.lock().unwrap();
+      let next = state
+        .waiting
+        .iter()
+        .max_by_key(|(priority, ticket)| (*priority, u64::MAX - ticket))
+        .map(|(_, ticket)| *ticket);
+
+      if !state.posting && next == Some(ticket.number) {
+        state.posting = true;
+        return Some(Turn);
+      }
+    }
+
+    released.await;
+  }
+}
+
+impl Drop for Ticket<'_> {
+  fn drop(&mut self) {
+    self
+      .gate
+      .state
+      .lock()
+      .unwrap()
+      .waiting
+      .retain(|(_, number)| *number != self.number);
+    self.gate.released.notify_waiters();
+  }
+}
+
+impl Drop for Turn {
+  fn drop(&mut self) {
+    if let Some(gate) = GATE.get() {
+      gate.state.lock().unwrap().posting = false;
+      gate.released.notify_waiters();
+    }
+  }
+}
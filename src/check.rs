@@ -0,0 +1,242 @@
+use crate::{
+  auth::{
+    authenticate_account, get_acting_channel, get_client_secret, get_read_token_storage_path, get_youtube_client,
+    READ_SCOPE, WRITE_SCOPE,
+  },
+  build_comment_source,
+  cli::Args,
+  config::Config,
+  console,
+  console::format_duration,
+  get_data_dir, preview_comment, quota,
+  watcher::Feed,
+  youtube::{build_https_client, get_latest_videos, get_uploads_playlist_id, new_client, YoutubeClient},
+};
+use google_youtube3::{client::NoToken, hyper};
+use serde::Deserialize;
+use std::error::Error;
+
+/// The part of Google's token info that tells which scopes were granted
+#[derive(Deserialize)]
+struct TokenInfo {
+  scope: String,
+}
+
+/// Every check runs, even after one failed, to report everything that needs fixing at once
+#[derive(Default)]
+struct Report {
+  failures: usize,
+}
+
+impl Report {
+  fn pass(&mut self, message: impl AsRef<str>) {
+    console::success(format!("✓ {}", message.as_ref()));
+  }
+
+  fn fail(&mut self, message: impl AsRef<str>) {
+    console::error(format!("✗ {}", message.as_ref()));
+    self.failures += 1;
+  }
+}
+
+/// The scopes the user actually granted, which may be fewer than the ones asked for
+async fn get_granted_scopes(client: &YoutubeClient) -> Result<Vec<String>, Box<dyn Error>> {
+  let token = client
+    .auth
+    .get_token(&[READ_SCOPE, WRITE_SCOPE])
+    .await
+    .map_err(|e| e as Box<dyn Error>)?
+    .ok_or("no access token")?;
+  let url = format!("https://oauth2.googleapis.com/tokeninfo?access_token={token}");
+  let response = build_https_client()?.get(url.parse()?).await?;
+
+  if !response.status().is_success() {
+    return Err(format!("the token info returned {}", response.status()).into());
+  }
+
+  let body = hyper::body::to_bytes(response.into_body()).await?;
+  let info: TokenInfo = serde_json::from_slice(&body)?;
+
+  Ok(info.scope.split(' ').map(String::from).collect())
+}
+
+async fn check_account(name: &str, client: &YoutubeClient, args: &Args, report: &mut Report) {
+  match get_granted_scopes(client).await {
+    Ok(scopes) => {
+      let missing: Vec<&str> = [READ_SCOPE, WRITE_SCOPE]
+        .into_iter()
+        .filter(|scope| !scopes.iter().any(|granted| granted == scope))
+        .collect();
+
+      if missing.is_empty() {
+        report.pass(format!("{name} granted both scopes"));
+      } else {
+        report.fail(format!(
+          "{name} didn't grant {}, log in again and tick every box on the consent screen",
+          missing.join(" and ")
+        ));
+      }
+    }
+    Err(e) => report.fail(format!("Failed to check the scopes granted by {name}: {e}")),
+  }
+
+  match (get_acting_channel(client).await, &args.post_as) {
+    (Ok(Some((id, title))), Some(post_as)) if id != *post_as => report.fail(format!(
+      "{name} posts as {title} ({id}) rather than {post_as}, log in again and pick it when asked"
+    )),
+    (Ok(Some((id, title))), _) => report.pass(format!("{name} posts as {title} ({id})")),
+    (Ok(None), _) => report.fail(format!("{name} has no channel to post as")),
+    (Err(e), _) => report.fail(format!("Failed to get the channel {name} posts as: {e}")),
+  }
+}
+
+async fn check_search(reader: &YoutubeClient, feed: &Feed, args: &Args, report: &mut Report) {
+  let query = args.query.as_deref().unwrap_or_default();
+
+  match feed.latest_videos(reader, 1, args.api_key.as_deref(), None).await {
+    Ok(page) => match page.and_then(|page| page.videos.into_iter().next()) {
+      Some(video) => report.pass(format!(
+        "Searched for \"{query}\", the latest match is {} ({})",
+        video.title, video.id
+      )),
+      None => report.pass(format!("Searched for \"{query}\", nothing matches yet")),
+    },
+    Err(e) => report.fail(format!("Failed to search for \"{query}\": {e}")),
+  }
+}
+
+async fn check_channel(reader: &YoutubeClient, args: &Args, report: &mut Report) {
+  if let Some(feed) = Feed::search(args) {
+    return check_search(reader, &feed, args, report).await;
+  }
+
+  let api_key = args.api_key.as_deref();
+  let (playlist_id, watched) = match &args.playlist_id {
+    Some(playlist_id) => (playlist_id.clone(), "playlist"),
+    None => {
+      let Some(playlist_id) = get_uploads_playlist_id(reader, &args.channel_id, api_key).await else {
+        report.fail(format!(
+          "Failed to find channel {}, check --channel-id (and --api-key if given)",
+          args.channel_id
+        ));
+        return;
+      };
+
+      report.pass(format!(
+        "Found channel {}, its uploads are in playlist {playlist_id}",
+        args.channel_id
+      ));
+      (playlist_id, "uploads")
+    }
+  };
+
+  match get_latest_videos(reader, &playlist_id, 1, api_key, None).await {
+    Ok(page) => match page.and_then(|page| page.videos.into_iter().next()) {
+      Some(video) => report.pass(format!(
+        "Read the {watched}, the latest video is {} ({})",
+        video.title, video.id
+      )),
+      None => report.pass(format!("Read the {watched}, there are no videos yet")),
+    },
+    Err(e) if args.playlist_id.is_some() => report.fail(format!(
+      "Failed to read playlist {playlist_id}, check --playlist-id: {e}"
+    )),
+    Err(e) => report.fail(format!("Failed to read the uploads playlist {playlist_id}: {e}")),
+  }
+}
+
+fn check_quota(args: &Args, report: &mut Report) {
+  let budget = args.quota_budget.unwrap_or(quota::DEFAULT_DAILY_QUOTA);
+  let left = budget.saturating_sub(quota::used_today());
+  let poll_cost = Feed::search(args).map_or(quota::LIST_COST, |feed| feed.poll_cost());
+  let message = format!(
+    "About {left} of {budget} quota units are left today as counted by yfc, {} polls after keeping enough to post, \
+     and the quota resets in {}",
+    left.saturating_sub(quota::POST_RESERVE) / poll_cost,
+    format_duration(quota::until_reset())
+  );
+
+  if left >= quota::POST_RESERVE {
+    report.pass(message);
+  } else {
+    report.fail(message);
+  }
+}
+
+/// Checks that the watcher would work with these options, without posting anything: the config file and the comment,
+/// the logins and the scopes they granted, the channel and its uploads, and the quota left. Logging in for the first
+/// time goes through the OAuth flow, so it doesn't have to happen when the video is about to be out.
+pub async fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+  quota::track(&get_data_dir());
+
+  let mut report = Report::default();
+
+  let comment_source = Config::load(args.config.as_deref())
+    .and_then(|config| build_comment_source(args, config.comment.as_ref(), &config.comments));
+
+  match comment_source.and_then(|source| preview_comment(args, source.as_ref())) {
+    Ok(_) => report.pass("The config file and the comment are valid"),
+    Err(e) => report.fail(format!("Invalid config: {e}")),
+  }
+
+  let client_secret = get_client_secret(&args.google_client_id, args.google_client_secret.as_deref())?;
+  let names = match args.account.as_slice() {
+    [] => vec![String::new()],
+    names => names.to_vec(),
+  };
+  let mut main_client = None;
+
+  for account in names {
+    let name = match account.as_str() {
+      "" => "The default account".to_string(),
+      account => format!("Account {account}"),
+    };
+
+    match authenticate_account(&args.google_client_id, &client_secret, &account, args.login_flow()).await {
+      Ok(client) => {
+        report.pass(format!("{name} is logged in"));
+        check_account(&name, &client, args, &mut report).await;
+        main_client.get_or_insert(client);
+      }
+      Err(e) => report.fail(format!("{name} failed to log in: {e}")),
+    }
+  }
+
+  for project in &args.read_project {
+    let result = get_youtube_client(
+      &project.client_id,
+      &project.client_secret,
+      get_read_token_storage_path(&project.client_id),
+      &[READ_SCOPE],
+      args.login_flow(),
+    )
+    .await;
+
+    match result {
+      Ok(_) => report.pass(format!("Read project {} is logged in", project.client_id)),
+      Err(e) => report.fail(format!("Read project {} failed to log in: {e}", project.client_id)),
+    }
+  }
+
+  // The channel is read the way the watcher reads it
+  let anonymous_client = match args.api_key {
+    Some(_) => Some(new_client(NoToken)?),
+    None => None,
+  };
+
+  match anonymous_client.as_ref().or(main_client.as_ref()) {
+    Some(reader) => check_channel(reader, args, &mut report).await,
+    None => println!("Skipped checking the channel, there's no login to read it with"),
+  }
+
+  check_quota(args, &mut report);
+
+  match report.failures {
+    0 => {
+      console::success("Everything is ready, nothing was posted");
+      Ok(())
+    }
+    1 => Err("1 check failed".into()),
+    failures => Err(format!("{failures} checks failed").into()),
+  }
+}
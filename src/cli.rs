@@ -0,0 +1,1243 @@
+use crate::config::Config;
+use crate::control::ControlCommand;
+use crate::filters::parse_category;
+use crate::projects::ProjectCredentials;
+use crate::report::ReportFormat;
+use crate::sanitize::SanitizePolicy;
+use crate::schedule::{HotWindow, WatchSchedule};
+use crate::search::SearchOrder;
+use crate::stats::StatsFormat;
+use crate::status::StatusFormat;
+use crate::{exit, search, systemd};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use google_youtube3::chrono::{DateTime, Local, NaiveDateTime, Utc};
+use rand::Rng;
+use std::{env, error::Error, ffi::OsString, fs, io, net::SocketAddr, path::PathBuf, process, time::Duration};
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum AgeRestrictedPolicy {
+  /// Don't comment on age-restricted videos and keep watching
+  Skip,
+  /// Post the regular comment anyway
+  Post,
+  /// Post the text given by --age-restricted-comment instead
+  Alternate,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum MembersOnlyPolicy {
+  /// Don't comment on videos for channel members only and keep watching
+  Skip,
+  /// Post the comment anyway, the account has to be a member of the channel
+  Post,
+}
+
+/// How to log in to Google
+#[derive(Clone, Copy, ValueEnum)]
+pub enum AuthFlow {
+  /// Open the consent screen in a local browser, which redirects back to yfc
+  Browser,
+  /// Print a code to enter on another device (e.g. a phone), for servers reached over SSH
+  Device,
+}
+
+/// What to do when the API rejects the comment as a duplicate of one already on the video
+#[derive(Clone, Copy, ValueEnum)]
+pub enum DuplicatePolicy {
+  /// Count it as posted, since the comment is there
+  Success,
+  /// Post another text from the comment source (pools, templates, commands and LLMs can vary)
+  Variant,
+  /// Fail like any other rejected post
+  Fail,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum QuotaPolicy {
+  /// Stop with exit code 3
+  Exit,
+  /// Sleep until the quota resets at midnight Pacific time and go on watching
+  Wait,
+}
+
+/// How new uploads are detected
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum DetectBackend {
+  /// The uploads playlist, through the Data API
+  Api,
+  /// The RSS feed of the channel, which costs no quota but often lags behind the API
+  Rss,
+  /// The Videos tab of the channel, like the website loads it. Unsupported by YouTube, it may break at any time.
+  #[cfg(feature = "web-detect")]
+  Web,
+}
+
+/// A cheap signal of a new upload, which starts a burst of API polls to confirm it
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum HintSource {
+  /// The RSS feed of the channel listing a new video, which costs no quota
+  Rss,
+  /// The Videos tab of the channel on the website listing a new video. Unsupported by YouTube, it may break at any
+  /// time.
+  #[cfg(feature = "web-detect")]
+  Web,
+}
+
+/// Where the comment is posted
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum CommentTarget {
+  /// A new comment thread on the video
+  Thread,
+  /// A reply under the comment of the channel owner, usually the pinned one, which is more visible
+  ReplyToPinned,
+}
+
+/// How the wait between two tries at posting grows
+#[derive(Clone, Copy, ValueEnum)]
+pub enum RetryStrategy {
+  /// Wait --post-retry-delay before every retry
+  Fixed,
+  /// Double the wait after every retry, starting from --post-retry-delay
+  Exponential,
+}
+
+/// How far a poll interval may randomly stray from its nominal value, either way
+#[derive(Clone, Copy)]
+pub enum IntervalJitter {
+  Fixed(Duration),
+  /// A percentage of the interval
+  Relative(u32),
+}
+
+impl IntervalJitter {
+  pub fn apply(self, interval: Duration) -> Duration {
+    let band = match self {
+      Self::Fixed(band) => band.as_secs_f64(),
+      Self::Relative(percent) => interval.as_secs_f64() * percent.min(100) as f64 / 100.0,
+    };
+    let offset = rand::thread_rng().gen_range(-band..=band);
+
+    Duration::from_secs_f64((interval.as_secs_f64() + offset).max(0.0))
+  }
+}
+
+#[derive(Parser)]
+#[command(
+  name = "yfc",
+  about = "A tool to create a new comment on YouTube when a new video is published for the specified channel",
+  args_conflicts_with_subcommands = true,
+  subcommand_negates_reqs = true,
+  // Lets the command line override the options coming from the config file
+  args_override_self = true,
+  after_help = exit::EXIT_CODES_HELP
+)]
+pub struct Cli {
+  #[command(subcommand)]
+  pub command: Option<Command>,
+
+  /// Where the tokens, the state and the history are kept, e.g. a Docker volume or one directory per profile
+  /// [default: <cache dir>/yfc]
+  #[arg(long, env = "YFC_DATA_DIR", global = true)]
+  pub data_dir: Option<PathBuf>,
+
+  #[command(flatten)]
+  pub args: Option<Args>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+  /// Print the status of a running watcher (requires it to be started with --status-addr)
+  Status {
+    /// Address of the watcher status endpoint
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    addr: String,
+
+    /// Output format
+    #[arg(long, value_enum, default_value = "json")]
+    format: StatusFormat,
+  },
+  /// Have a running watcher post on a video right away, e.g. when you spot the upload before it does (requires it to
+  /// be started with --api-addr)
+  Push {
+    /// ID of the video to post on
+    video_id: String,
+
+    /// Address of the watcher control API
+    #[arg(long, default_value = "127.0.0.1:8081")]
+    addr: String,
+
+    /// Bearer token of the control API
+    #[arg(long, env = "YFC_API_TOKEN")]
+    token: String,
+  },
+  /// List past comment attempts, newest first
+  History {
+    /// Only list attempts on this channel
+    #[arg(long)]
+    channel_id: Option<String>,
+
+    /// Only list attempts more recent than this (e.g. 30d)
+    #[arg(long, value_parser = humantime::parse_duration)]
+    since: Option<Duration>,
+
+    /// Maximum number of attempts listed
+    #[arg(long, default_value = "20")]
+    limit: usize,
+  },
+  /// Aggregate the history (success rate, latency, quota) per channel
+  Stats {
+    /// Only count attempts more recent than this (e.g. 30d)
+    #[arg(long, value_parser = humantime::parse_duration)]
+    since: Option<Duration>,
+
+    /// Output format, json and csv are meant for exporting
+    #[arg(long, value_enum, default_value = "table")]
+    format: StatsFormat,
+  },
+  /// Watch a fake channel served locally, with a scripted upload, to see the whole flow without any credentials
+  Demo {
+    /// When the fake channel uploads its new video
+    #[arg(long, default_value = "10s", value_parser = parse_seconds)]
+    upload_after: Duration,
+  },
+  /// Manage the config file
+  Config {
+    #[command(subcommand)]
+    command: ConfigCommand,
+  },
+  /// Maintain the local database
+  Db {
+    #[command(subcommand)]
+    command: DbCommand,
+  },
+  /// Control a watcher started with --control-socket
+  Ctl {
+    /// Control socket of the watcher
+    #[arg(long, env = "YFC_CONTROL_SOCKET")]
+    socket: PathBuf,
+
+    #[command(subcommand)]
+    command: ControlCommand,
+  },
+  /// Manage the Google logins
+  Auth {
+    #[command(subcommand)]
+    command: AuthCommand,
+  },
+  /// Write a user-level systemd unit running the watcher with the options of the config file
+  InstallService {
+    /// Config file the service runs with [default: <config dir>/yfc/config.toml]
+    #[arg(long, env = "YFC_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Name of the unit
+    #[arg(long, default_value = systemd::DEFAULT_UNIT_NAME)]
+    name: String,
+
+    /// How long the service may stay unresponsive before systemd restarts it
+    #[arg(long, default_value = "1m", value_parser = humantime::parse_duration)]
+    watchdog: Duration,
+
+    /// Replace an existing unit
+    #[arg(long)]
+    force: bool,
+  },
+  /// Check that everything is ready to watch with the given options, without posting anything: the config file, the
+  /// logins and their scopes, the channel and the quota
+  Check {
+    #[command(flatten)]
+    args: Box<Args>,
+  },
+  /// List the latest uploads of a channel as the watcher sees them, e.g. to find out why a video was skipped
+  ListUploads {
+    /// Channel ID or @handle
+    #[arg(long, env = "YFC_CHANNEL_ID")]
+    channel: String,
+
+    /// Number of uploads to list
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..=50))]
+    limit: u32,
+
+    /// API key to read the channel with, rather than logging in
+    #[arg(long, env = "YFC_API_KEY")]
+    api_key: Option<String>,
+
+    /// Google client ID, to read the channel with a login when no API key is given
+    #[arg(long, env = "YFC_GOOGLE_CLIENT_ID", required_unless_present = "api_key")]
+    google_client_id: Option<String>,
+
+    /// Google client secret
+    #[arg(long, env = "YFC_GOOGLE_CLIENT_SECRET")]
+    google_client_secret: Option<String>,
+
+    /// Account to read with [default: the account used without --account]
+    #[arg(long)]
+    account: Option<String>,
+
+    /// How to log in when the account has no token yet
+    #[arg(long, value_enum, default_value = "browser")]
+    auth_flow: AuthFlow,
+  },
+  /// Learn when a channel usually uploads from its latest uploads, suggest hot windows and save the upload times for
+  /// --auto-schedule
+  Analyze {
+    /// Channel ID or @handle
+    #[arg(long, env = "YFC_CHANNEL_ID")]
+    channel: String,
+
+    /// Number of latest uploads to learn from
+    #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u32).range(1..=50))]
+    limit: u32,
+
+    /// How long before and after a usual upload time to poll fast, as with the watcher
+    #[arg(long, env = "YFC_BURST_WINDOW", default_value = "30m", value_parser = parse_minutes)]
+    burst_window: Duration,
+
+    /// API key to read the channel with, rather than logging in
+    #[arg(long, env = "YFC_API_KEY")]
+    api_key: Option<String>,
+
+    /// Google client ID, to read the channel with a login when no API key is given
+    #[arg(long, env = "YFC_GOOGLE_CLIENT_ID", required_unless_present = "api_key")]
+    google_client_id: Option<String>,
+
+    /// Google client secret
+    #[arg(long, env = "YFC_GOOGLE_CLIENT_SECRET")]
+    google_client_secret: Option<String>,
+
+    /// Account to read with [default: the account used without --account]
+    #[arg(long)]
+    account: Option<String>,
+
+    /// How to log in when the account has no token yet
+    #[arg(long, value_enum, default_value = "browser")]
+    auth_flow: AuthFlow,
+  },
+  /// Time the polls and the comment inserts the watcher races with against the real API, e.g. to pick a poll interval
+  /// or compare servers. The inserts send an empty comment YouTube refuses, unless --post.
+  Bench {
+    /// Channel ID or @handle whose uploads are polled
+    #[arg(long, env = "YFC_CHANNEL_ID")]
+    channel: String,
+
+    /// Times each call is made
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..=100))]
+    iterations: u32,
+
+    /// Pause between two rounds of calls
+    #[arg(long, default_value = "1s", value_parser = parse_seconds)]
+    spacing: Duration,
+
+    /// Video to time the comment inserts on, e.g. one of your own unlisted videos. Without it only the polls are timed
+    #[arg(long, requires = "google_client_id")]
+    video_id: Option<String>,
+
+    /// Really post the comment and delete it right away, twice the quota of the insert alone
+    #[arg(long, requires = "video_id")]
+    post: bool,
+
+    /// Comment posted with --post
+    #[arg(long, default_value = "yfc bench", requires = "post")]
+    comment: String,
+
+    /// API key to poll with, rather than logging in
+    #[arg(long, env = "YFC_API_KEY")]
+    api_key: Option<String>,
+
+    /// Google client ID, to poll with a login when no API key is given and to insert the comments
+    #[arg(long, env = "YFC_GOOGLE_CLIENT_ID", required_unless_present = "api_key")]
+    google_client_id: Option<String>,
+
+    /// Google client secret
+    #[arg(long, env = "YFC_GOOGLE_CLIENT_SECRET")]
+    google_client_secret: Option<String>,
+
+    /// Account to insert the comments with [default: the account used without --account]
+    #[arg(long)]
+    account: Option<String>,
+
+    /// How to log in when the account has no token yet
+    #[arg(long, value_enum, default_value = "browser")]
+    auth_flow: AuthFlow,
+  },
+  /// Delete a comment posted by yfc, e.g. one that didn't end up first (the ID is in `yfc history`)
+  DeleteComment {
+    /// ID of the comment
+    comment_id: String,
+
+    /// Google client ID
+    #[arg(long, env = "YFC_GOOGLE_CLIENT_ID")]
+    google_client_id: String,
+
+    /// Google client secret
+    #[cfg_attr(not(feature = "keyring"), arg(required = true))]
+    #[arg(long, env = "YFC_GOOGLE_CLIENT_SECRET")]
+    google_client_secret: Option<String>,
+
+    /// Account that posted the comment [default: the account used without --account]
+    #[arg(long)]
+    account: Option<String>,
+
+    /// How to log in when the account has no token yet
+    #[arg(long, value_enum, default_value = "browser")]
+    auth_flow: AuthFlow,
+  },
+  /// Print the completion script of a shell
+  Completions {
+    /// Shell to complete in
+    shell: Shell,
+  },
+  /// Print the man page
+  Manpage,
+  /// Run the watcher in the background as a Windows service
+  #[cfg(windows)]
+  Service {
+    #[command(subcommand)]
+    command: ServiceCommand,
+  },
+}
+
+#[cfg(windows)]
+#[derive(Subcommand)]
+pub enum ServiceCommand {
+  /// Register a service starting the watcher with the options of the config file when Windows starts (requires an
+  /// administrator prompt)
+  Install {
+    /// Config file the service runs with [default: <config dir>/yfc/config.toml]
+    #[arg(long, env = "YFC_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Name of the service
+    #[arg(long, default_value = crate::service::DEFAULT_NAME)]
+    name: String,
+  },
+  /// Stop and remove the service (requires an administrator prompt)
+  Uninstall {
+    /// Name of the service
+    #[arg(long, default_value = crate::service::DEFAULT_NAME)]
+    name: String,
+  },
+  /// Run the watcher as the service, which is how Windows starts it
+  Run {
+    /// Config file holding the options of the watcher
+    #[arg(long)]
+    config: PathBuf,
+
+    /// Name of the service
+    #[arg(long, default_value = crate::service::DEFAULT_NAME)]
+    name: String,
+  },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+  /// Print the config file equivalent to a flag-based invocation of yfc
+  Migrate {
+    /// The command line, e.g. pasted from the shell history [default: read from stdin]
+    line: Option<String>,
+  },
+}
+
+#[derive(Subcommand)]
+pub enum DbCommand {
+  /// Validate the database schema and integrity
+  Check {
+    /// Migrate old schemas, rebuild indexes, prune old entries and compact the database
+    #[arg(long)]
+    repair: bool,
+
+    /// With --repair, delete history entries older than this (e.g. 180d)
+    #[arg(long, value_parser = humantime::parse_duration)]
+    retention: Option<Duration>,
+  },
+}
+
+#[derive(Subcommand)]
+pub enum AuthCommand {
+  /// Print the channel each account posts as, logging in first when needed
+  ListChannels {
+    /// Google client ID
+    #[arg(long, env = "YFC_GOOGLE_CLIENT_ID")]
+    google_client_id: String,
+
+    /// Google client secret
+    #[cfg_attr(not(feature = "keyring"), arg(required = true))]
+    #[arg(long, env = "YFC_GOOGLE_CLIENT_SECRET")]
+    google_client_secret: Option<String>,
+
+    /// Account to list the channel of (can be repeated) [default: the account used without --account]
+    #[arg(long)]
+    account: Vec<String>,
+
+    /// How to log in when an account has no token yet
+    #[arg(long, value_enum, default_value = "browser")]
+    auth_flow: AuthFlow,
+  },
+  /// Store the client secret in the OS keychain, read from stdin, so --google-client-secret can be left out
+  #[cfg(feature = "keyring")]
+  StoreSecret {
+    /// Google client ID the secret belongs to
+    #[arg(long)]
+    google_client_id: String,
+  },
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+  /// Path to the config file [default: <config dir>/yfc/config.toml]
+  #[arg(long, env = "YFC_CONFIG")]
+  pub config: Option<PathBuf>,
+
+  /// Google client ID
+  #[arg(long, env = "YFC_GOOGLE_CLIENT_ID")]
+  pub google_client_id: String,
+
+  /// Google client secret
+  #[cfg_attr(not(feature = "keyring"), arg(required = true))]
+  #[arg(long, env = "YFC_GOOGLE_CLIENT_SECRET")]
+  pub google_client_secret: Option<String>,
+
+  /// How to log in when there is no token yet, the device flow needs an OAuth client of the "TVs and Limited Input
+  /// devices" type
+  #[arg(long, env = "YFC_AUTH_FLOW", value_enum, default_value = "browser")]
+  pub auth_flow: AuthFlow,
+
+  /// Run unattended, e.g. in a container: log in with the device flow, never prompt, and print plain lines without
+  /// colors nor countdown
+  #[arg(long, env = "YFC_HEADLESS", conflicts_with_all = ["auth_flow", "tui"])]
+  pub headless: bool,
+
+  /// API key to read the channel and poll its uploads with, so detection is charged to the project of the key and only
+  /// posting uses the OAuth client
+  #[arg(long, env = "YFC_API_KEY", conflicts_with = "read_project")]
+  pub api_key: Option<String>,
+
+  /// OAuth client of another Google Cloud project to poll with, as <client id>:<client secret> (can be repeated).
+  /// Polling moves on to the next one when a project runs out of quota, keeping the quota of the main project for
+  /// posting.
+  #[arg(long, env = "YFC_READ_PROJECT")]
+  pub read_project: Vec<ProjectCredentials>,
+
+  /// Google account to post with (can be repeated), each one logs in once and keeps its own token. Posting moves on to
+  /// the next account when one gets its comment rejected. The first account also reads everything.
+  #[arg(long, env = "YFC_ACCOUNT")]
+  pub account: Vec<String>,
+
+  /// Alternate between the accounts, posting each comment with the next one
+  #[arg(long, env = "YFC_ROTATE_ACCOUNTS", requires = "account")]
+  pub rotate_accounts: bool,
+
+  /// Post with every account at once on each video, and report which comment landed first
+  #[arg(
+    long,
+    env = "YFC_RACE_ACCOUNTS",
+    requires = "account",
+    conflicts_with = "rotate_accounts"
+  )]
+  pub race_accounts: bool,
+
+  /// With --race-accounts, how long each account waits after the previous one before posting (e.g. 200ms), so the
+  /// comments don't all arrive at once
+  #[arg(long, env = "YFC_RACE_STAGGER", default_value = "0s", value_parser = parse_seconds, requires = "race_accounts")]
+  pub race_stagger: Duration,
+
+  /// ID of the channel to post as, for a login managing several (brand) channels. The channel is picked when logging
+  /// in, so yfc makes you log in again until the picked one is this channel.
+  #[arg(long, env = "YFC_POST_AS", conflicts_with = "account")]
+  pub post_as: Option<String>,
+
+  /// The comment body (- to read it from stdin), takes precedence over the [comment] section of the config file
+  #[arg(long, env = "YFC_COMMENT")]
+  pub comment: Option<String>,
+
+  /// File to read the comment body from, on every post, so multi-line comments don't need shell escaping
+  #[arg(long, env = "YFC_COMMENT_FILE", conflicts_with = "comment")]
+  pub comment_file: Option<PathBuf>,
+
+  /// Warn about or strip what often gets comments held for review as spam: links, phone numbers, too many capital
+  /// letters or emoji
+  #[arg(long, env = "YFC_SANITIZE", value_enum)]
+  pub sanitize: Option<SanitizePolicy>,
+
+  /// YouTube channel ID
+  #[arg(long, env = "YFC_CHANNEL_ID", required_unless_present = "query", default_value = "")]
+  pub channel_id: String,
+
+  /// Watch this playlist for new videos rather than the uploads of the channel, e.g. a playlist of podcast episodes.
+  /// New videos have to be added at the top of it.
+  #[arg(long, env = "YFC_PLAYLIST_ID")]
+  pub playlist_id: Option<String>,
+
+  /// Watch for new videos matching this search query rather than the uploads of a channel. Every poll is a search,
+  /// which costs 100 quota units.
+  #[arg(long, env = "YFC_QUERY", conflicts_with_all = ["channel_id", "playlist_id", "auto_schedule"])]
+  pub query: Option<String>,
+
+  /// Only search the videos of this channel (can be repeated, every channel is a search of its own)
+  #[arg(long, env = "YFC_QUERY_CHANNEL", requires = "query", conflicts_with = "channel_id")]
+  pub query_channel: Vec<String>,
+
+  /// How the search results are sorted, only the first few are looked at
+  #[arg(long, env = "YFC_ORDER", value_enum, default_value = "date")]
+  pub order: SearchOrder,
+
+  /// When several of the channels of the config file publish at the same time, the one with the highest priority
+  /// posts first
+  #[arg(long, env = "YFC_PRIORITY", default_value_t = 0, allow_negative_numbers = true)]
+  pub priority: i64,
+
+  /// Poll interval (e.g. 90s, 2m30s, 500ms; bare numbers are seconds), at least 500ms
+  #[arg(
+    long,
+    alias = "pool-interval",
+    env = "YFC_POLL_INTERVAL",
+    default_value = "60s",
+    value_parser = parse_interval
+  )]
+  pub poll_interval: Duration,
+
+  /// Randomize every poll interval by up to this much either way, so polls don't come at a detectable fixed cadence
+  /// (e.g. 10s or 20%; bare numbers are seconds)
+  #[arg(long, env = "YFC_INTERVAL_JITTER", value_parser = parse_jitter)]
+  pub interval_jitter: Option<IntervalJitter>,
+
+  /// Daily quota units not to exceed (the default project quota is 10000). Polling slows down to make what's left last
+  /// until the quota resets at midnight Pacific time, always keeping enough to post.
+  #[arg(long, env = "YFC_QUOTA_BUDGET")]
+  pub quota_budget: Option<u64>,
+
+  /// Max wait time (e.g. 1h30m, 90m; bare numbers are minutes). Leave it out or pass 0 to wait forever.
+  #[arg(long, env = "YFC_WAIT_LIMIT", required = false, value_parser = parse_minutes)]
+  pub wait_limit: Option<Duration>,
+
+  /// After the last poll right at the wait limit, poll once more this long after it (e.g. 2m), for an upload announced
+  /// for the end of the wait that runs late
+  #[arg(long, env = "YFC_GRACE_PERIOD", default_value = "0s", value_parser = parse_seconds, requires = "wait_limit")]
+  pub grace_period: Duration,
+
+  /// Log in, then wait until this time before polling (RFC 3339, e.g. 2024-06-01T16:55:00+02:00, or local time,
+  /// e.g. "2024-06-01 16:55"). The wait limit only starts counting once polling begins.
+  #[arg(long, env = "YFC_START_AT", value_parser = parse_start_at, conflicts_with = "start_in")]
+  pub start_at: Option<DateTime<Utc>>,
+
+  /// Log in, then wait this long before polling (e.g. 2h, 90m; bare numbers are minutes)
+  #[arg(long, env = "YFC_START_IN", value_parser = parse_minutes)]
+  pub start_in: Option<Duration>,
+
+  /// Poll interval used while waiting for an unlisted/scheduled video to become public (bare numbers are seconds)
+  #[arg(long, env = "YFC_PRIVACY_POLL_INTERVAL", default_value = "5s", value_parser = parse_interval)]
+  pub privacy_poll_interval: Duration,
+
+  /// What to do when the new video is age-restricted
+  #[arg(long, env = "YFC_AGE_RESTRICTED_POLICY", value_enum, default_value = "post")]
+  pub age_restricted_policy: AgeRestrictedPolicy,
+
+  /// The comment body used for age-restricted videos when the policy is "alternate"
+  #[arg(
+    long,
+    env = "YFC_AGE_RESTRICTED_COMMENT",
+    required_if_eq("age_restricted_policy", "alternate")
+  )]
+  pub age_restricted_comment: Option<String>,
+
+  /// What to do when the new video is for channel members only (checking costs a quota unit per video)
+  #[arg(long, env = "YFC_MEMBERS_ONLY_POLICY", value_enum, default_value = "skip")]
+  pub members_only_policy: MembersOnlyPolicy,
+
+  /// What to do when the comment is rejected as a duplicate of one already on the video
+  #[arg(long, env = "YFC_ON_DUPLICATE", value_enum, default_value = "fail")]
+  pub on_duplicate: DuplicatePolicy,
+
+  /// Before posting, look for a comment of the account among the latest ones on the video, and skip the video when
+  /// there's one, e.g. after the state was lost (a quota unit per video)
+  #[arg(long, env = "YFC_CHECK_OWN_COMMENTS")]
+  pub check_own_comments: bool,
+
+  /// What to do when the API quota runs out, while polling (once every read project is out too) or posting
+  #[arg(long, env = "YFC_ON_QUOTA_EXCEEDED", value_enum, default_value = "exit")]
+  pub on_quota_exceeded: QuotaPolicy,
+
+  /// Where to post the comment
+  #[arg(long, env = "YFC_TARGET", value_enum, default_value = "thread")]
+  pub target: CommentTarget,
+
+  /// With --target reply-to-pinned, how long to wait for the channel owner to comment before posting a new thread
+  /// instead
+  #[arg(long, env = "YFC_PINNED_WAIT", default_value = "5m", value_parser = parse_seconds)]
+  pub pinned_wait: Duration,
+
+  /// How long after detecting a video to post on it (e.g. 45s), to be among the first comments rather than the very
+  /// first. The comment still goes out by the wait limit.
+  #[arg(long, env = "YFC_POST_DELAY", default_value = "0s", value_parser = parse_seconds)]
+  pub post_delay: Duration,
+
+  /// After --post-delay, fetch the video again so the comment gets its current title and language
+  #[arg(long, env = "YFC_REFRESH_AFTER_DELAY", requires = "post_delay")]
+  pub refresh_after_delay: bool,
+
+  /// Show a live dashboard (countdown, wait limit, quota, log) instead of the scrolling output
+  #[arg(long, env = "YFC_TUI")]
+  pub tui: bool,
+
+  /// Address to serve a JSON status endpoint on (e.g. 127.0.0.1:8080)
+  #[arg(long, env = "YFC_STATUS_ADDR")]
+  pub status_addr: Option<SocketAddr>,
+
+  /// Listen for `yfc ctl` commands (pause, resume, set-comment, stop, status) on this Unix socket, or named pipe on
+  /// Windows (e.g. \\.\pipe\yfc)
+  #[arg(long, env = "YFC_CONTROL_SOCKET")]
+  pub control_socket: Option<PathBuf>,
+
+  /// Address to serve the control API on (e.g. 0.0.0.0:8081), to manage the watcher remotely with --api-token
+  #[arg(long, env = "YFC_API_ADDR", requires = "api_token")]
+  pub api_addr: Option<SocketAddr>,
+
+  /// Bearer token every request to the control API must carry
+  #[arg(long, env = "YFC_API_TOKEN")]
+  pub api_token: Option<String>,
+
+  /// URL pinged after every successful poll, and with "/fail" appended on fatal errors (e.g. a healthchecks.io check)
+  #[arg(long, env = "YFC_HEARTBEAT_URL")]
+  pub heartbeat_url: Option<String>,
+
+  /// Show a desktop notification when the comment is posted or posting fails
+  #[cfg(feature = "desktop-notify")]
+  #[arg(long, env = "YFC_NOTIFY")]
+  pub notify: bool,
+
+  /// Ring the terminal bell when the comment is posted or posting fails
+  #[arg(long, env = "YFC_BELL")]
+  pub bell: bool,
+
+  /// Audio file (e.g. WAV, MP3, OGG or FLAC) played when the comment is posted or posting fails
+  #[cfg(feature = "sound")]
+  #[arg(long, env = "YFC_SOUND")]
+  pub sound: Option<PathBuf>,
+
+  /// Shell command run when a new video is detected (gets YFC_VIDEO_ID, YFC_VIDEO_TITLE and YFC_VIDEO_PUBLISHED_AT)
+  #[arg(long, env = "YFC_ON_NEW_VIDEO")]
+  pub on_new_video: Option<String>,
+
+  /// Shell command run when the comment is posted (gets YFC_VIDEO_ID, YFC_COMMENT_ID, YFC_LATENCY_MS and
+  /// YFC_PUBLISH_LATENCY_MS)
+  #[arg(long, env = "YFC_ON_COMMENT_POSTED")]
+  pub on_comment_posted: Option<String>,
+
+  /// Shell command run when posting fails or the watcher stops on an error (gets YFC_VIDEO_ID and YFC_ERROR)
+  #[arg(long, env = "YFC_ON_ERROR")]
+  pub on_error: Option<String>,
+
+  /// Start a new wait instead of resuming an interrupted one
+  #[arg(long, env = "YFC_FRESH")]
+  pub fresh: bool,
+
+  /// Look the uploads playlist of the channel up again instead of using the cached one
+  #[arg(long, env = "YFC_REFRESH_CACHE")]
+  pub refresh_cache: bool,
+
+  /// Start even when another yfc is already watching the channel, which may post the comment twice
+  #[arg(long, env = "YFC_ALLOW_MULTIPLE")]
+  pub allow_multiple: bool,
+
+  /// Comment on the latest video right away, if it's eligible, instead of only waiting for the next one. For when yfc
+  /// is started after the video already came out.
+  #[arg(long, env = "YFC_COMMENT_ON_CURRENT")]
+  pub comment_on_current: bool,
+
+  /// Comment on the new Shorts of the channel, and only on them, rather than skipping them. Shorts are told apart by
+  /// their page on the Shorts shelf (or their length), not by the #shorts tag.
+  #[arg(long, env = "YFC_SHORTS_ONLY")]
+  pub shorts_only: bool,
+
+  /// Skip the recordings of finished live streams, which come back at the top of the uploads when the stream ends
+  #[arg(long, env = "YFC_SKIP_VODS")]
+  pub skip_vods: bool,
+
+  /// Only comment on videos of this category, by ID or name (e.g. gaming or 20, can be repeated)
+  #[arg(long, env = "YFC_CATEGORY", value_parser = parse_category)]
+  pub category: Vec<String>,
+
+  /// Never comment on videos of this category, by ID or name (e.g. music or 10, can be repeated)
+  #[arg(long, env = "YFC_EXCLUDE_CATEGORY", value_parser = parse_category)]
+  pub exclude_category: Vec<String>,
+
+  /// Only comment on videos with this tag, case-insensitive (can be repeated)
+  #[arg(long, env = "YFC_TAG")]
+  pub tag: Vec<String>,
+
+  /// Never comment on videos with this tag, case-insensitive (can be repeated)
+  #[arg(long, env = "YFC_EXCLUDE_TAG")]
+  pub exclude_tag: Vec<String>,
+
+  /// Start in safe mode, which requires --force or a confirmation, after this many runs in a row ended in a rejected
+  /// comment or an auth failure (0 disables it)
+  #[arg(long, env = "YFC_SAFE_MODE_AFTER", default_value = "3")]
+  pub safe_mode_after: usize,
+
+  /// Post even when safe mode is active, and post the comment as written despite --sanitize
+  #[arg(long, env = "YFC_FORCE")]
+  pub force: bool,
+
+  /// Keep a weekly activity report (uploads, posts, failures, quota) under the data directory
+  #[arg(long, env = "YFC_REPORT", value_enum)]
+  pub report: Option<ReportFormat>,
+
+  /// Write a summary of the run (channel, video, comment, latency, quota, retries, exit reason) to this file when it
+  /// ends, as TOML if it ends in .toml and as JSON otherwise
+  #[arg(long, env = "YFC_SUMMARY_OUT")]
+  pub summary_out: Option<PathBuf>,
+
+  /// URL receiving a JSON POST for every lifecycle event (video detected, comment posted, failure, wait limit)
+  #[arg(long, env = "YFC_WEBHOOK_URL")]
+  pub webhook_url: Option<String>,
+
+  /// Record anonymous comment latency samples (no channel, video or comment) to compare where instances win races
+  #[arg(long, env = "YFC_TELEMETRY")]
+  pub telemetry: bool,
+
+  /// Label of this instance placement in telemetry samples (e.g. hetzner-fsn1)
+  #[arg(long, env = "YFC_TELEMETRY_PLACEMENT", requires = "telemetry")]
+  pub telemetry_placement: Option<String>,
+
+  /// URL of a self-hosted collector receiving every telemetry sample as a JSON POST
+  #[arg(long, env = "YFC_TELEMETRY_ENDPOINT", requires = "telemetry")]
+  pub telemetry_endpoint: Option<String>,
+
+  /// OpenTelemetry collector receiving traces of the polls, token refreshes and races, over OTLP/HTTP (e.g.
+  /// http://localhost:4318)
+  #[cfg(feature = "otel")]
+  #[arg(long, env = "YFC_OTEL_ENDPOINT")]
+  pub otel_endpoint: Option<String>,
+
+  /// How the uploads are detected, in order of preference (e.g. rss,api). A backend failing --failover-after polls
+  /// in a row, or lagging more than --max-detection-lag, is left out for 10 minutes. The API is the last resort
+  /// whether listed or not.
+  #[arg(
+    long,
+    env = "YFC_DETECT",
+    value_enum,
+    value_delimiter = ',',
+    default_value = "api",
+    conflicts_with_all = ["query", "playlist_id", "shorts_only"]
+  )]
+  pub detect: Vec<DetectBackend>,
+
+  /// Polls in a row a detection backend may fail before the next one of --detect takes over
+  #[arg(long, env = "YFC_FAILOVER_AFTER", default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+  pub failover_after: u32,
+
+  /// How long after its publication a detection backend may list a new video before the next one of --detect takes
+  /// over (e.g. 2m)
+  #[arg(long, env = "YFC_MAX_DETECTION_LAG", value_parser = parse_seconds)]
+  pub max_detection_lag: Option<Duration>,
+
+  /// Learn when the channel usually uploads from its RSS feed and poll at --burst-interval around those times
+  #[arg(long, env = "YFC_AUTO_SCHEDULE")]
+  pub auto_schedule: bool,
+
+  /// Poll fast for this long after starting (e.g. when the upload is announced but its exact time isn't)
+  #[arg(long, env = "YFC_HOT_AFTER_START", value_parser = parse_minutes)]
+  pub hot_after_start: Option<Duration>,
+
+  /// Daily time range, in UTC, to poll fast in (e.g. 17:30-19:00, can be repeated)
+  #[arg(long, env = "YFC_HOT_WINDOW")]
+  pub hot_window: Vec<HotWindow>,
+
+  /// Weekly window to watch in, e.g. "Mon-Fri 16:45-18:00 Europe/Berlin" (days and time zone are optional, the time
+  /// zone defaults to UTC, can be repeated). Outside of the windows the watcher sleeps, inside it polls fast. The
+  /// time spent sleeping doesn't count against the wait limit.
+  #[arg(long, env = "YFC_SCHEDULE")]
+  pub schedule: Vec<WatchSchedule>,
+
+  /// Poll interval used in hot windows (--hot-after-start, --hot-window, --schedule and --auto-schedule). Outside of
+  /// them the interval doubles after every poll until it's back to --poll-interval.
+  #[arg(long, env = "YFC_BURST_INTERVAL", default_value = "5s", value_parser = parse_interval)]
+  pub burst_interval: Duration,
+
+  /// How long before and after a usual upload time to poll at the burst interval
+  #[arg(long, env = "YFC_BURST_WINDOW", default_value = "30m", value_parser = parse_minutes)]
+  pub burst_window: Duration,
+
+  /// Check a cheap source for a new upload (can be repeated), and poll the API at --burst-interval for --hint-burst
+  /// as soon as it lists one. `POST /hint` on the control API starts a burst too, e.g. from a WebSub subscriber.
+  #[arg(long, env = "YFC_HINT", value_enum, value_delimiter = ',', conflicts_with = "query")]
+  pub hint: Vec<HintSource>,
+
+  /// How often the sources of --hint are checked
+  #[arg(long, env = "YFC_HINT_INTERVAL", default_value = "10s", value_parser = parse_interval)]
+  pub hint_interval: Duration,
+
+  /// How long the API is polled at --burst-interval after a hint
+  #[arg(long, env = "YFC_HINT_BURST", default_value = "2m", value_parser = parse_minutes)]
+  pub hint_burst: Duration,
+
+  /// Keep watching for this long after posting to detect the video being deleted and re-uploaded under a new ID
+  #[arg(long, env = "YFC_REUPLOAD_WINDOW", value_parser = parse_seconds)]
+  pub reupload_window: Option<Duration>,
+
+  /// Post the comment again on a detected re-upload
+  #[arg(long, env = "YFC_REPOST_ON_REUPLOAD", requires = "reupload_window")]
+  pub repost_on_reupload: bool,
+
+  /// How long an API call may take before it's given up, and retried when posting
+  #[arg(long, env = "YFC_REQUEST_TIMEOUT", default_value = "10s", value_parser = parse_seconds)]
+  pub request_timeout: Duration,
+
+  /// How many times a post failing with a network, rate limit or server error is retried, never past the wait limit
+  #[arg(long, env = "YFC_POST_RETRIES", default_value = "3")]
+  pub post_retries: u32,
+
+  /// Wait before retrying a failed post, on its own timer rather than the poll interval
+  #[arg(long, env = "YFC_POST_RETRY_DELAY", default_value = "1s", value_parser = parse_seconds)]
+  pub post_retry_delay: Duration,
+
+  /// Whether the wait before retrying stays the same or doubles every time
+  #[arg(long, env = "YFC_POST_RETRY_STRATEGY", value_enum, default_value = "exponential")]
+  pub post_retry_strategy: RetryStrategy,
+
+  /// Like the video right after posting the comment
+  #[arg(long, env = "YFC_LIKE_VIDEO")]
+  pub like_video: bool,
+
+  /// After posting, check the position of the comment among the comments of the video
+  #[arg(long, env = "YFC_VERIFY_RANK")]
+  pub verify_rank: bool,
+
+  /// Detect videos and report who got the first comment, without ever posting (to scout the timing of a channel)
+  #[arg(long, env = "YFC_OBSERVE")]
+  pub observe: bool,
+
+  /// How long after detecting a video the observer mode looks for its first comment
+  #[arg(long, env = "YFC_OBSERVE_DELAY", default_value = "1m", requires = "observe", value_parser = parse_seconds)]
+  pub observe_delay: Duration,
+
+  /// Save the API calls of this run and their answers in a fixtures file, to watch them again with --replay
+  #[arg(long, env = "YFC_RECORD", conflicts_with = "replay")]
+  pub record: Option<PathBuf>,
+
+  /// Watch the API calls saved by --record instead of YouTube, without network, login or notifications
+  #[arg(long, env = "YFC_REPLAY")]
+  pub replay: Option<PathBuf>,
+
+  /// How many times faster than recorded --replay goes
+  #[arg(long, env = "YFC_REPLAY_SPEED", default_value_t = 10, requires = "replay", value_parser = clap::value_parser!(u32).range(1..))]
+  pub replay_speed: u32,
+
+  /// Check the position again after this delay, as comments held for review show up late
+  #[arg(long, env = "YFC_VERIFY_RANK_DELAY", requires = "verify_rank", value_parser = parse_seconds)]
+  pub verify_rank_delay: Option<Duration>,
+
+  /// Check the position of the comment again every minute for this long, as comments held for review show up late, and
+  /// keep the final one in the history
+  #[arg(long, env = "YFC_VERIFY_FIRST_FOR", value_parser = parse_seconds)]
+  pub verify_first_for: Option<Duration>,
+
+  /// Delete the comment when the rank check finds it isn't the first one, rather than leave a "First!" in second place
+  #[arg(long, env = "YFC_DELETE_IF_NOT_FIRST", requires = "verify_rank")]
+  pub delete_if_not_first: bool,
+
+  /// After posting, wait this long and check that the comment is publicly listed (not held for review or filtered)
+  #[arg(long, env = "YFC_VERIFY_VISIBLE_AFTER", value_parser = parse_seconds)]
+  pub verify_visible_after: Option<Duration>,
+
+  /// API key used to check the comment visibility as an anonymous viewer rather than as its author
+  #[arg(long, env = "YFC_VIEWER_API_KEY", requires = "verify_visible_after")]
+  pub viewer_api_key: Option<String>,
+
+  /// Post an alternate comment from the comment source when the first one isn't publicly visible
+  #[arg(long, env = "YFC_REPOST_IF_HIDDEN", requires = "verify_visible_after")]
+  pub repost_if_hidden: bool,
+
+  /// After posting, keep checking the comment for this long and notify when it gets likes or replies
+  #[arg(long, env = "YFC_MONITOR_FOR", value_parser = parse_seconds)]
+  pub monitor_for: Option<Duration>,
+
+  /// How often the comment is checked with --monitor-for
+  #[arg(long, env = "YFC_MONITOR_INTERVAL", default_value = "1m", requires = "monitor_for", value_parser = parse_seconds)]
+  pub monitor_interval: Duration,
+
+  /// Reply this to your own comment once it's posted, e.g. a timestamped joke or a link
+  #[arg(long, env = "YFC_FOLLOW_UP")]
+  pub follow_up: Option<String>,
+
+  /// How long after the comment the --follow-up reply is posted
+  #[arg(long, env = "YFC_FOLLOW_UP_DELAY", default_value = "30s", requires = "follow_up", value_parser = parse_seconds)]
+  pub follow_up_delay: Duration,
+
+  /// Number of new videos to comment on before stopping (0 keeps watching forever)
+  #[arg(long, env = "YFC_MAX_COMMENTS", default_value = "1")]
+  pub max_comments: usize,
+
+  /// When several videos are published at once (e.g. a backlog dump), only comment on the latest ones, up to this many
+  /// within the time the batch takes to post, however many polls find them
+  #[arg(long, env = "YFC_MAX_PER_BATCH", default_value = "3")]
+  pub max_per_batch: usize,
+
+  /// Delay between two comments of a batch, so they don't all land within a minute
+  #[arg(long, env = "YFC_BATCH_SPACING", default_value = "2m", value_parser = parse_seconds)]
+  pub batch_spacing: Duration,
+
+  /// Most comments to post in a day (UTC), counting every channel watched with the same data directory
+  #[arg(long, env = "YFC_MAX_COMMENTS_PER_DAY")]
+  pub max_comments_per_day: Option<u32>,
+
+  /// Least time between two comments, on any channel watched with the same data directory
+  #[arg(long, env = "YFC_COOLDOWN", value_parser = parse_seconds)]
+  pub cooldown: Option<Duration>,
+}
+
+/// Parses a human-readable duration, falling back to seconds for bare numbers
+pub fn parse_seconds(value: &str) -> Result<Duration, String> {
+  parse_duration(value, 1)
+}
+
+/// Shortest poll interval, polling more often would only hammer the API
+pub const MIN_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Parses a poll interval, falling back to seconds for bare numbers
+pub fn parse_interval(value: &str) -> Result<Duration, String> {
+  let interval = parse_seconds(value)?;
+
+  if interval < MIN_POLL_INTERVAL {
+    return Err(format!(
+      "polling more often than every {}ms would hammer the API",
+      MIN_POLL_INTERVAL.as_millis()
+    ));
+  }
+
+  Ok(interval)
+}
+
+/// Parses a human-readable duration, falling back to minutes for bare numbers
+pub fn parse_minutes(value: &str) -> Result<Duration, String> {
+  parse_duration(value, 60)
+}
+
+pub fn parse_duration(value: &str, bare_unit_secs: u64) -> Result<Duration, String> {
+  // Bare numbers keep working with the unit each flag used before durations were supported
+  if let Ok(number) = value.parse::<u64>() {
+    return number
+      .checked_mul(bare_unit_secs)
+      .map(Duration::from_secs)
+      .ok_or_else(|| format!("duration {value} is too large"));
+  }
+
+  humantime::parse_duration(value).map_err(|e| format!("{e} (expected a duration such as 90s, 2m30s or 1h)"))
+}
+
+pub fn parse_jitter(value: &str) -> Result<IntervalJitter, String> {
+  match value.strip_suffix('%') {
+    Some(percent) => percent
+      .parse()
+      .map(IntervalJitter::Relative)
+      .map_err(|_| format!("invalid percentage {value} (expected e.g. 20%)")),
+    None => parse_seconds(value).map(IntervalJitter::Fixed),
+  }
+}
+
+/// Parses an RFC 3339 time, or a time without offset in the local time zone (e.g. 2024-06-01 16:55)
+pub fn parse_start_at(value: &str) -> Result<DateTime<Utc>, String> {
+  if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+    return Ok(time.with_timezone(&Utc));
+  }
+
+  [
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+  ]
+  .iter()
+  .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+  .and_then(|time| time.and_local_timezone(Local).earliest())
+  .map(|time| time.with_timezone(&Utc))
+  .ok_or_else(|| format!("invalid time {value} (expected e.g. 2024-06-01T16:55:00+02:00 or 2024-06-01 16:55)"))
+}
+
+/// Sets the variables of the `.env` file in the working directory, if any. Variables already set take precedence.
+pub fn load_dotenv() -> io::Result<()> {
+  let contents = match fs::read_to_string(".env") {
+    Ok(contents) => contents,
+    Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+    Err(e) => return Err(e),
+  };
+
+  for line in contents.lines().map(str::trim) {
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+
+    let Some((name, value)) = line.strip_prefix("export ").unwrap_or(line).split_once('=') else {
+      continue;
+    };
+    let value = value.trim();
+    let value = [('"', '"'), ('\'', '\'')]
+      .iter()
+      .find_map(|(start, end)| value.strip_prefix(*start)?.strip_suffix(*end))
+      .unwrap_or(value);
+
+    if env::var_os(name.trim()).is_none() {
+      env::set_var(name.trim(), value);
+    }
+  }
+
+  Ok(())
+}
+
+/// Inserts the `[watch]` options of the config file before the command line arguments, so those take precedence
+pub fn with_config_options(args: Vec<OsString>) -> Result<Vec<OsString>, Box<dyn Error>> {
+  // `check` takes the options of the watcher, the other subcommands don't
+  let watch_options_at = match subcommand(&args) {
+    Some("check") => 1,
+    Some(_) => return Ok(args),
+    None => 0,
+  };
+
+  let config = load_config(&args)?;
+  let mut args = args;
+  let rest = args.split_off(watch_options_at);
+
+  args.extend(with_watch_options(rest, &config));
+
+  Ok(args)
+}
+
+/// Inserts the `[watch]` options before the command line arguments
+pub fn with_watch_options(args: Vec<OsString>, config: &Config) -> Vec<OsString> {
+  with_options(args, config.watch_args())
+}
+
+/// Puts the options before those of the command line, which take precedence
+pub fn with_options(args: Vec<OsString>, options: Vec<String>) -> Vec<OsString> {
+  let mut args = args.into_iter();
+
+  args
+    .next()
+    .into_iter()
+    .chain(options.into_iter().map(OsString::from))
+    .chain(args)
+    .collect()
+}
+
+/// The subcommand of the command line, if any
+pub fn subcommand(args: &[OsString]) -> Option<&str> {
+  args
+    .get(1)
+    .and_then(|arg| arg.to_str())
+    .filter(|arg| Cli::command().find_subcommand(arg).is_some())
+}
+
+/// Whether the watcher replays a fixtures file. Clap can't be asked yet, as it would want the client first.
+pub fn replays(args: &[OsString]) -> bool {
+  subcommand(args).is_none()
+    && (env::var_os("YFC_REPLAY").is_some()
+      || args
+        .iter()
+        .skip(1)
+        .filter_map(|arg| arg.to_str())
+        .any(|arg| arg == "--replay" || arg.starts_with("--replay=")))
+}
+
+/// The config file of the command line: the one given with --config, or the default one
+pub fn load_config(args: &[OsString]) -> Result<Config, Box<dyn Error>> {
+  // Clap can't be asked yet, so look for --config by hand
+  let config_path = args.iter().enumerate().skip(1).find_map(|(index, arg)| {
+    let arg = arg.to_str()?;
+
+    match arg.strip_prefix("--config=") {
+      Some(path) => Some(PathBuf::from(path)),
+      None if arg == "--config" => args.get(index + 1).map(PathBuf::from),
+      None => None,
+    }
+  });
+  let config_path = config_path.or_else(|| env::var_os("YFC_CONFIG").map(PathBuf::from));
+
+  Config::load(config_path.as_deref())
+}
+
+/// Parses the command line, exiting on invalid options and once --help or --version is printed
+pub fn parse_cli(args: Vec<OsString>) -> Cli {
+  Cli::try_parse_from(args).unwrap_or_else(|e| {
+    let _ = e.print();
+    // --help and --version are no errors
+    process::exit(if e.use_stderr() { exit::USAGE } else { 0 });
+  })
+}
+
+/// The watcher arguments of a command line, for when it doesn't come from the shell (the config options included)
+pub fn parse_watcher_args(args: Vec<OsString>) -> Result<Args, Box<dyn Error>> {
+  Ok(with_search_key(
+    Cli::try_parse_from(args)?.args.ok_or("No watcher options were given")?,
+  ))
+}
+
+impl Args {
+  /// How to log in, always with the device flow when --headless
+  pub fn login_flow(&self) -> AuthFlow {
+    if self.headless {
+      AuthFlow::Device
+    } else {
+      self.auth_flow
+    }
+  }
+}
+
+/// Searches have no channel, the state, history and lock of the watcher go under the query instead
+pub fn with_search_key(mut args: Args) -> Args {
+  if let Some(query) = &args.query {
+    args.channel_id = search::state_key(query);
+  }
+
+  args
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use google_youtube3::chrono::TimeZone;
+
+  #[test]
+  fn parses_bare_numbers_in_the_unit_of_the_flag() {
+    assert_eq!(parse_seconds("90"), Ok(Duration::from_secs(90)));
+    assert_eq!(parse_minutes("90"), Ok(Duration::from_secs(90 * 60)));
+  }
+
+  #[test]
+  fn parses_human_readable_durations() {
+    assert_eq!(parse_seconds("2m30s"), Ok(Duration::from_secs(150)));
+    assert_eq!(parse_minutes("1h"), Ok(Duration::from_secs(3600)));
+    assert_eq!(parse_interval("750ms"), Ok(Duration::from_millis(750)));
+    assert!(parse_seconds("soon").is_err());
+  }
+
+  #[test]
+  fn refuses_durations_too_large() {
+    assert!(parse_minutes("999999999999999999").is_err());
+    assert!(parse_seconds("99999999999999999999").is_err());
+  }
+
+  #[test]
+  fn refuses_poll_intervals_below_the_minimum() {
+    assert!(parse_interval("100ms").is_err());
+    assert!(parse_interval("0").is_err());
+    assert_eq!(parse_interval("1"), Ok(Duration::from_secs(1)));
+  }
+
+  #[test]
+  fn parses_fixed_and_relative_jitter() {
+    assert!(matches!(parse_jitter("20%"), Ok(IntervalJitter::Relative(20))));
+    assert!(matches!(parse_jitter("5"), Ok(IntervalJitter::Fixed(duration)) if duration == Duration::from_secs(5)));
+    assert!(parse_jitter("many%").is_err());
+  }
+
+  #[test]
+  fn parses_start_times() {
+    assert_eq!(
+      parse_start_at("2024-06-01T16:55:00+02:00"),
+      Ok(Utc.with_ymd_and_hms(2024, 6, 1, 14, 55, 0).unwrap())
+    );
+    assert!(parse_start_at("2024-06-01 16:55").is_ok());
+    assert!(parse_start_at("tomorrow").is_err());
+  }
+}
@@ -0,0 +1,81 @@
+use crate::console;
+use google_youtube3::chrono::{self, DateTime, Utc};
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+/// Longest sleep between two checks of the wall clock (and of the early wake-up condition, which pushed videos rely on
+/// to be posted right away)
+const STEP: Duration = Duration::from_secs(1);
+
+/// Smallest difference between the wall clock and the monotonic clock considered a jump
+const JUMP_THRESHOLD: Duration = Duration::from_secs(10);
+
+/// Smallest forward jump considered to be the host waking up from sleep rather than a clock correction. The monotonic
+/// clock stands still while the host sleeps, the wall clock doesn't.
+const SUSPEND_THRESHOLD: Duration = Duration::from_secs(60);
+
+/// Deadlines are measured with the monotonic clock, but schedules (burst windows) follow the wall clock. This keeps an
+/// eye on both to notice when the wall clock jumps (NTP corrections, suspend/resume) and schedules need another look.
+pub struct ClockGuard {
+  monotonic: Instant,
+  wall: DateTime<Utc>,
+}
+
+impl ClockGuard {
+  pub fn new() -> Self {
+    Self {
+      monotonic: Instant::now(),
+      wall: Utc::now(),
+    }
+  }
+
+  /// Returns how far the wall clock moved on its own since the last check (negative when it went back), if that's
+  /// more than a jitter
+  pub fn check(&mut self) -> Option<chrono::Duration> {
+    let monotonic_elapsed = chrono::Duration::from_std(self.monotonic.elapsed()).unwrap_or_default();
+    let wall_elapsed = Utc::now() - self.wall;
+    let jump = wall_elapsed - monotonic_elapsed;
+
+    self.monotonic = Instant::now();
+    self.wall = Utc::now();
+
+    (jump.abs().to_std().unwrap_or_default() >= JUMP_THRESHOLD).then_some(jump)
+  }
+
+  /// Sleeps for `duration` of monotonic time in short steps, waking up early as soon as `wake_early` returns true for
+  /// the time slept so far. Checking in steps lets schedule decisions notice a wall clock jump mid-sleep. Returns how
+  /// long the host was suspended if it woke up from sleep meanwhile, in which case it returns right away to catch up.
+  pub async fn sleep(&mut self, duration: Duration, wake_early: impl Fn(Duration) -> bool) -> Option<Duration> {
+    let started_at = Instant::now();
+
+    loop {
+      let remaining = duration.saturating_sub(started_at.elapsed());
+
+      if remaining.is_zero() {
+        return None;
+      }
+
+      sleep(remaining.min(STEP)).await;
+
+      if let Some(jump) = self.check() {
+        match jump.to_std() {
+          Ok(suspended) if suspended >= SUSPEND_THRESHOLD => {
+            console::warning(format!(
+              "The host was asleep for {}s, polling right away",
+              suspended.as_secs()
+            ));
+            return Some(suspended);
+          }
+          _ => console::warning(format!(
+            "The system clock jumped by {}s, re-evaluating the polling schedule",
+            jump.num_seconds()
+          )),
+        }
+      }
+
+      if wake_early(started_at.elapsed()) {
+        return None;
+      }
+    }
+  }
+}
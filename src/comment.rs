@@ -0,0 +1,421 @@
+use crate::youtube::HttpsClient;
+use async_trait::async_trait;
+use google_youtube3::hyper::{self, header, Body, Method, Request};
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+use serde_json::json;
+use std::{
+  env,
+  error::Error,
+  fs,
+  path::PathBuf,
+  sync::{Arc, RwLock},
+};
+use tokio::process::Command;
+
+/// Longest comment YouTube accepts, in characters
+pub const MAX_LENGTH: usize = 10_000;
+
+/// What is known about the video when the comment text gets resolved.
+pub struct CommentContext<'a> {
+  pub channel_id: &'a str,
+  pub video_id: &'a str,
+  pub title: &'a str,
+  /// Consecutive videos of the channel commented on, this one included
+  pub my_streak: u32,
+  /// Which video of the channel this is in the current month, starting at 1
+  pub upload_number_this_month: u32,
+  /// Language of the video (e.g. `pt-BR`), its audio language or else its metadata language, when the creator set it
+  pub language: Option<&'a str>,
+}
+
+/// Produces the comment text. Sources are resolved right before each post, so dynamic ones (files, commands, LLMs)
+/// always reflect the latest state.
+#[async_trait]
+pub trait CommentSource: Send + Sync {
+  async fn resolve(&self, context: &CommentContext<'_>) -> Result<String, Box<dyn Error + Send + Sync>>;
+
+  /// Every comment the source could produce for the context, to check them before the video is out. Sources running
+  /// something (commands, LLMs) have none, their comment is only known when posting.
+  fn preview(&self, _context: &CommentContext<'_>) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    Ok(Vec::new())
+  }
+}
+
+/// Rejects the comments YouTube would refuse: empty, too long or with control characters
+pub fn validate(text: &str) -> Result<(), String> {
+  if text.trim().is_empty() {
+    return Err("The comment is empty".into());
+  }
+
+  let length = text.chars().count();
+
+  if length > MAX_LENGTH {
+    return Err(format!(
+      "The comment is {length} characters long, YouTube takes {MAX_LENGTH} at most"
+    ));
+  }
+
+  if let Some(c) = text
+    .chars()
+    .find(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t'))
+  {
+    return Err(format!("The comment has a control character ({})", c.escape_unicode()));
+  }
+
+  Ok(())
+}
+
+/// Whether the comment has a link, which often gets it held for review as spam
+pub fn has_link(text: &str) -> bool {
+  text.split_whitespace().any(|word| {
+    let word = word.to_lowercase();
+    word.contains("http://") || word.contains("https://") || word.starts_with("www.")
+  })
+}
+
+/// The placeholders of templates and LLM prompts
+const PLACEHOLDERS: &[&str] = &[
+  "channel_id",
+  "video_id",
+  "video_url",
+  "title",
+  "language",
+  "my_streak",
+  "upload_number_this_month",
+];
+
+/// The first `{word}` of the template that isn't a placeholder, most likely a typo. Braces around anything else (e.g.
+/// `{ }` or `{:}`) are left for the comment.
+fn unknown_placeholder(template: &str) -> Option<&str> {
+  template
+    .split('{')
+    .skip(1)
+    .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+    .filter(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+    .find(|name| !PLACEHOLDERS.contains(name))
+}
+
+/// Replaces `{channel_id}`, `{video_id}`, `{video_url}`, `{title}` and `{language}` with the video details, and
+/// `{my_streak}` and `{upload_number_this_month}` with what the history tells. Unknown placeholders are left untouched.
+pub fn render_template(template: &str, context: &CommentContext) -> String {
+  template
+    .replace("{channel_id}", context.channel_id)
+    .replace("{video_id}", context.video_id)
+    .replace(
+      "{video_url}",
+      &format!("https://www.youtube.com/watch?v={}", context.video_id),
+    )
+    .replace("{title}", context.title)
+    .replace("{language}", context.language.unwrap_or_default())
+    .replace("{my_streak}", &context.my_streak.to_string())
+    .replace(
+      "{upload_number_this_month}",
+      &context.upload_number_this_month.to_string(),
+    )
+}
+
+/// How the comment text is produced, as written in the `[comment]` section of the config file.
+#[derive(Deserialize)]
+#[serde(tag = "source", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum CommentSourceConfig {
+  /// A fixed text
+  Text { text: String },
+  /// The contents of a file, read on every post
+  File { path: PathBuf },
+  /// A random entry from a list
+  Pool { comments: Vec<String> },
+  /// A text with `{placeholders}` filled from the video details
+  Template { template: String },
+  /// The standard output of a shell command, which gets the video details as YFC_* environment variables
+  Command { command: String },
+  /// The reply of an OpenAI-compatible chat completions endpoint, prompted with a template
+  Llm {
+    endpoint: String,
+    model: String,
+    prompt: String,
+    api_key_env: Option<String>,
+  },
+}
+
+impl CommentSourceConfig {
+  /// Catches the mistakes that would otherwise only show when posting
+  pub fn validate(&self) -> Result<(), String> {
+    let template = match self {
+      Self::Text { text } if text.trim().is_empty() => return Err("the text is empty".into()),
+      Self::Pool { comments } if comments.is_empty() => return Err("the comment pool is empty".into()),
+      Self::Template { template } => template,
+      Self::Llm { prompt, .. } => prompt,
+      _ => return Ok(()),
+    };
+
+    match unknown_placeholder(template) {
+      Some(name) => Err(format!(
+        "unknown placeholder {{{name}}}, expected one of {}",
+        PLACEHOLDERS
+          .iter()
+          .map(|name| format!("{{{name}}}"))
+          .collect::<Vec<_>>()
+          .join(", ")
+      )),
+      None => Ok(()),
+    }
+  }
+
+  pub fn build(&self, client: &HttpsClient) -> Box<dyn CommentSource> {
+    match self {
+      Self::Text { text } => Box::new(StaticText(text.clone())),
+      Self::File { path } => Box::new(FileSource(path.clone())),
+      Self::Pool { comments } => Box::new(PoolSource(comments.clone())),
+      Self::Template { template } => Box::new(TemplateSource(template.clone())),
+      Self::Command { command } => Box::new(CommandSource(command.clone())),
+      Self::Llm {
+        endpoint,
+        model,
+        prompt,
+        api_key_env,
+      } => Box::new(LlmSource {
+        client: client.clone(),
+        endpoint: endpoint.clone(),
+        model: model.clone(),
+        prompt: prompt.clone(),
+        api_key_env: api_key_env.clone(),
+      }),
+    }
+  }
+}
+
+/// Picks the source of the language of the video among those of the `[comments]` section, falling back to the
+/// default one. A language tag matches the same tag, or else the same language in another region (`pt-BR` and `pt`).
+pub struct LanguageSource {
+  pub languages: Vec<(String, Box<dyn CommentSource>)>,
+  /// `None` when every comment is set per language
+  pub default: Option<Box<dyn CommentSource>>,
+}
+
+/// The language of a tag without its region, e.g. `pt` of `pt-BR`
+fn primary_language(tag: &str) -> &str {
+  tag.split(['-', '_']).next().unwrap_or(tag)
+}
+
+impl LanguageSource {
+  fn source(&self, language: Option<&str>) -> Result<&dyn CommentSource, String> {
+    let exact = |language: &str| {
+      self
+        .languages
+        .iter()
+        .find(|(tag, _)| tag.eq_ignore_ascii_case(language))
+    };
+    let similar = |language: &str| {
+      self
+        .languages
+        .iter()
+        .find(|(tag, _)| primary_language(tag).eq_ignore_ascii_case(primary_language(language)))
+    };
+
+    language
+      .and_then(|language| exact(language).or_else(|| similar(language)))
+      .map(|(_, source)| source.as_ref())
+      .or(self.default.as_deref())
+      .ok_or_else(|| match language {
+        Some(language) => format!("No comment is set for language {language} and there's no default one"),
+        None => "The video has no language and there's no default comment".into(),
+      })
+  }
+}
+
+#[async_trait]
+impl CommentSource for LanguageSource {
+  async fn resolve(&self, context: &CommentContext<'_>) -> Result<String, Box<dyn Error + Send + Sync>> {
+    self.source(context.language)?.resolve(context).await
+  }
+
+  /// The comments of every language, as the language of the video isn't known yet
+  fn preview(&self, context: &CommentContext<'_>) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    let mut comments = Vec::new();
+
+    for source in self.languages.iter().map(|(_, source)| source).chain(&self.default) {
+      comments.extend(source.preview(context)?);
+    }
+
+    Ok(comments)
+  }
+}
+
+pub struct StaticText(pub String);
+
+#[async_trait]
+impl CommentSource for StaticText {
+  async fn resolve(&self, _context: &CommentContext<'_>) -> Result<String, Box<dyn Error + Send + Sync>> {
+    Ok(self.0.clone())
+  }
+
+  fn preview(&self, _context: &CommentContext<'_>) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    Ok(vec![self.0.clone()])
+  }
+}
+
+/// A source that can be replaced while watching, when the config file changes
+pub struct ReloadableSource(RwLock<Arc<dyn CommentSource>>);
+
+impl ReloadableSource {
+  pub fn new(source: Box<dyn CommentSource>) -> Self {
+    Self(RwLock::new(source.into()))
+  }
+
+  /// Posts from now on resolve the new source, one being resolved finishes with the old one
+  pub fn replace(&self, source: Box<dyn CommentSource>) {
+    *self.0.write().unwrap() = source.into();
+  }
+}
+
+#[async_trait]
+impl CommentSource for ReloadableSource {
+  async fn resolve(&self, context: &CommentContext<'_>) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let source = self.0.read().unwrap().clone();
+
+    source.resolve(context).await
+  }
+
+  fn preview(&self, context: &CommentContext<'_>) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    self.0.read().unwrap().preview(context)
+  }
+}
+
+/// The contents of a file, read on every post
+pub struct FileSource(pub PathBuf);
+
+impl FileSource {
+  fn read(&self) -> Result<String, String> {
+    let text = fs::read_to_string(&self.0).map_err(|e| format!("Failed to read {}: {e}", self.0.display()))?;
+
+    Ok(text.trim_end().into())
+  }
+}
+
+#[async_trait]
+impl CommentSource for FileSource {
+  async fn resolve(&self, _context: &CommentContext<'_>) -> Result<String, Box<dyn Error + Send + Sync>> {
+    Ok(self.read()?)
+  }
+
+  /// The file as it is now, it may still change before the video is out
+  fn preview(&self, _context: &CommentContext<'_>) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    Ok(vec![self.read()?])
+  }
+}
+
+struct PoolSource(Vec<String>);
+
+#[async_trait]
+impl CommentSource for PoolSource {
+  async fn resolve(&self, _context: &CommentContext<'_>) -> Result<String, Box<dyn Error + Send + Sync>> {
+    self
+      .0
+      .choose(&mut rand::thread_rng())
+      .cloned()
+      .ok_or_else(|| "The comment pool is empty".into())
+  }
+
+  fn preview(&self, _context: &CommentContext<'_>) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    if self.0.is_empty() {
+      return Err("The comment pool is empty".into());
+    }
+
+    Ok(self.0.clone())
+  }
+}
+
+struct TemplateSource(String);
+
+#[async_trait]
+impl CommentSource for TemplateSource {
+  async fn resolve(&self, context: &CommentContext<'_>) -> Result<String, Box<dyn Error + Send + Sync>> {
+    Ok(render_template(&self.0, context))
+  }
+
+  fn preview(&self, context: &CommentContext<'_>) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    Ok(vec![render_template(&self.0, context)])
+  }
+}
+
+struct CommandSource(String);
+
+#[async_trait]
+impl CommentSource for CommandSource {
+  async fn resolve(&self, context: &CommentContext<'_>) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let output = Command::new("sh")
+      .arg("-c")
+      .arg(&self.0)
+      .env("YFC_CHANNEL_ID", context.channel_id)
+      .env("YFC_VIDEO_ID", context.video_id)
+      .env("YFC_VIDEO_TITLE", context.title)
+      .env("YFC_MY_STREAK", context.my_streak.to_string())
+      .env(
+        "YFC_UPLOAD_NUMBER_THIS_MONTH",
+        context.upload_number_this_month.to_string(),
+      )
+      .env("YFC_VIDEO_LANGUAGE", context.language.unwrap_or_default())
+      .output()
+      .await?;
+
+    if !output.status.success() {
+      return Err(format!("Comment command exited with {}", output.status).into());
+    }
+
+    let text = String::from_utf8(output.stdout)?.trim().to_string();
+
+    if text.is_empty() {
+      return Err("Comment command printed nothing".into());
+    }
+
+    Ok(text)
+  }
+}
+
+struct LlmSource {
+  client: HttpsClient,
+  endpoint: String,
+  model: String,
+  prompt: String,
+  api_key_env: Option<String>,
+}
+
+#[async_trait]
+impl CommentSource for LlmSource {
+  async fn resolve(&self, context: &CommentContext<'_>) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let payload = json!({
+      "model": self.model,
+      "messages": [{ "role": "user", "content": render_template(&self.prompt, context) }],
+    });
+
+    let mut request = Request::builder()
+      .method(Method::POST)
+      .uri(&self.endpoint)
+      .header(header::CONTENT_TYPE, "application/json");
+
+    if let Some(name) = &self.api_key_env {
+      let key = env::var(name).map_err(|_| format!("The {name} environment variable is not set"))?;
+      request = request.header(header::AUTHORIZATION, format!("Bearer {key}"));
+    }
+
+    let response = self
+      .client
+      .request(request.body(Body::from(payload.to_string()))?)
+      .await?;
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+
+    if !status.is_success() {
+      return Err(format!("LLM endpoint returned {status}: {}", String::from_utf8_lossy(&body)).into());
+    }
+
+    let reply: serde_json::Value = serde_json::from_slice(&body)?;
+
+    reply["choices"][0]["message"]["content"]
+      .as_str()
+      .map(|text| text.trim().to_string())
+      .filter(|text| !text.is_empty())
+      .ok_or_else(|| "LLM endpoint returned no comment".into())
+  }
+}
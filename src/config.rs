@@ -0,0 +1,416 @@
+use crate::comment::CommentSourceConfig;
+use clap::{ArgAction, Command};
+use dirs::config_dir;
+use serde::Deserialize;
+use std::{
+  collections::BTreeMap,
+  error::Error,
+  fs,
+  path::{Path, PathBuf},
+};
+use toml::{Table, Value};
+
+/// Settings that don't fit on the command line, read from a TOML file.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  pub comment: Option<CommentSourceConfig>,
+  /// Comment sources by language tag (e.g. `[comments.pt-BR]`), picked by the language of the video over `[comment]`
+  pub comments: BTreeMap<String, CommentSourceConfig>,
+  pub notify: NotifyConfig,
+  /// Watcher options, keyed by flag name (e.g. `poll-interval = "10s"`). Flags given on the command line win.
+  pub watch: Table,
+  /// Channels watched at once, keyed by a name of your choice
+  pub channels: BTreeMap<String, ChannelConfig>,
+}
+
+/// A channel of the `[channels]` section, watched along with the others
+#[derive(Deserialize)]
+#[serde(try_from = "Table")]
+pub struct ChannelConfig {
+  /// Its own comment source, instead of the `[comment]` section
+  pub comment: Option<CommentSourceConfig>,
+  /// Its own comment sources by language, instead of the `[comments]` section
+  pub comments: BTreeMap<String, CommentSourceConfig>,
+  /// Watcher options, keyed by flag name like in `[watch]`, which they override
+  pub watch: Table,
+}
+
+impl TryFrom<Table> for ChannelConfig {
+  type Error = String;
+
+  fn try_from(mut watch: Table) -> Result<Self, String> {
+    // `comment = "..."` is the --comment option, a table is a comment source like the `[comment]` section
+    let comment = match watch.remove("comment") {
+      Some(Value::Table(source)) => Some(
+        Value::Table(source)
+          .try_into()
+          .map_err(|e| format!("invalid comment: {e}"))?,
+      ),
+      Some(value) => {
+        watch.insert("comment".into(), value);
+        None
+      }
+      None => None,
+    };
+
+    let comments = match watch.remove("comments") {
+      Some(comments) => comments.try_into().map_err(|e| format!("invalid comments: {e}"))?,
+      None => BTreeMap::new(),
+    };
+
+    Ok(Self {
+      comment,
+      comments,
+      watch,
+    })
+  }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotifyConfig {
+  pub discord: Option<DiscordConfig>,
+  pub email: Option<EmailConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DiscordConfig {
+  pub webhook_url: String,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+  /// Upgrade a plain connection with STARTTLS (usually port 587)
+  #[default]
+  Starttls,
+  /// TLS from the start (usually port 465)
+  Tls,
+  /// No encryption at all, only meant for local relays
+  None,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmailConfig {
+  pub host: String,
+  pub port: Option<u16>,
+  #[serde(default)]
+  pub security: SmtpSecurity,
+  pub username: Option<String>,
+  pub password: Option<String>,
+  pub from: String,
+  pub to: String,
+}
+
+pub fn get_default_config_path() -> Option<PathBuf> {
+  config_dir().map(|dir| dir.join("yfc").join("config.toml"))
+}
+
+impl Config {
+  /// The config file in use: the given one, or the default one when it exists
+  pub fn path(path: Option<&Path>) -> Option<PathBuf> {
+    match path {
+      Some(path) => Some(path.to_path_buf()),
+      None => get_default_config_path().filter(|path| path.exists()),
+    }
+  }
+
+  /// Loads the config from the given path, or from the default location when it exists. A missing default config is
+  /// not an error, but an explicitly given one is.
+  pub fn load(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+    let Some(path) = Self::path(path) else {
+      return Ok(Self::default());
+    };
+
+    let contents =
+      fs::read_to_string(&path).map_err(|e| format!("Failed to read the config file {}: {e}", path.display()))?;
+
+    let config: Self = toml::from_str(&contents).map_err(|e| format!("Invalid config file {}: {e}", path.display()))?;
+
+    config
+      .validate()
+      .map_err(|e| format!("Invalid config file {}: {e}", path.display()))?;
+
+    Ok(config)
+  }
+
+  /// Checks every comment source up front, those of a channel included, so a typo doesn't wait for its next upload
+  fn validate(&self) -> Result<(), String> {
+    validate_sources("", self.comment.as_ref(), &self.comments)?;
+
+    for (name, channel) in &self.channels {
+      validate_sources(
+        &format!("channels.{name}."),
+        channel.comment.as_ref(),
+        &channel.comments,
+      )?;
+    }
+
+    Ok(())
+  }
+
+  /// The `[watch]` options as command line arguments
+  pub fn watch_args(&self) -> Vec<String> {
+    options_args(&self.watch)
+  }
+}
+
+impl ChannelConfig {
+  /// The options of the channel as command line arguments
+  pub fn watch_args(&self) -> Vec<String> {
+    options_args(&self.watch)
+  }
+}
+
+/// The comment source and the ones by language, named after their section in errors
+fn validate_sources(
+  prefix: &str,
+  comment: Option<&CommentSourceConfig>,
+  comments: &BTreeMap<String, CommentSourceConfig>,
+) -> Result<(), String> {
+  if let Some(source) = comment {
+    source.validate().map_err(|e| format!("[{prefix}comment] {e}"))?;
+  }
+
+  for (language, source) in comments {
+    source
+      .validate()
+      .map_err(|e| format!("[{prefix}comments.{language}] {e}"))?;
+  }
+
+  Ok(())
+}
+
+/// Options keyed by flag name as command line arguments
+fn options_args(options: &Table) -> Vec<String> {
+  let mut args = Vec::new();
+
+  for (name, value) in options {
+    let values = match value {
+      Value::Array(values) => values.iter().collect(),
+      value => vec![value],
+    };
+
+    for value in values {
+      match value {
+        Value::Boolean(true) => args.push(format!("--{name}")),
+        Value::Boolean(false) => {}
+        Value::String(value) => args.push(format!("--{name}={value}")),
+        value => args.push(format!("--{name}={value}")),
+      }
+    }
+  }
+
+  args
+}
+
+/// Splits a command line the way a POSIX shell would, minus expansions
+fn split_command_line(line: &str) -> Result<Vec<String>, String> {
+  let mut words = Vec::new();
+  let mut word: Option<String> = None;
+  let mut chars = line.chars();
+
+  while let Some(c) = chars.next() {
+    match c {
+      '\'' => {
+        let word = word.get_or_insert_with(String::new);
+
+        loop {
+          match chars.next() {
+            Some('\'') => break,
+            Some(c) => word.push(c),
+            None => return Err("unterminated single quote".into()),
+          }
+        }
+      }
+      '"' => {
+        let word = word.get_or_insert_with(String::new);
+
+        loop {
+          match chars.next() {
+            Some('"') => break,
+            Some('\\') => match chars.next() {
+              Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+              Some(c) => {
+                word.push('\\');
+                word.push(c);
+              }
+              None => return Err("unterminated double quote".into()),
+            },
+            Some(c) => word.push(c),
+            None => return Err("unterminated double quote".into()),
+          }
+        }
+      }
+      '\\' => match chars.next() {
+        // A line continuation
+        Some('\n') => {}
+        Some(c) => word.get_or_insert_with(String::new).push(c),
+        None => {}
+      },
+      c if c.is_whitespace() => words.extend(word.take()),
+      c => word.get_or_insert_with(String::new).push(c),
+    }
+  }
+
+  words.extend(word);
+
+  Ok(words)
+}
+
+/// Converts a flag-based invocation of yfc (anything before the first flag, like the program path, is ignored) into
+/// the equivalent config file. `command` describes the flags, to tell switches from options taking a value.
+pub fn migrate(line: &str, command: &Command) -> Result<String, Box<dyn Error>> {
+  let words = split_command_line(line).map_err(|e| format!("Invalid command line: {e}"))?;
+  let mut words = words.into_iter().skip_while(|word| !word.starts_with("--"));
+  let mut comment = None;
+  let mut comment_file = None;
+  let mut watch = Table::new();
+
+  while let Some(word) = words.next() {
+    let flag = word
+      .strip_prefix("--")
+      .ok_or_else(|| format!("Unexpected argument {word}"))?;
+    let (name, inline_value) = match flag.split_once('=') {
+      Some((name, value)) => (name, Some(value.to_string())),
+      None => (flag, None),
+    };
+    // Old names (e.g. --pool-interval) are aliases, the config file gets the current one
+    let arg = command
+      .get_arguments()
+      .find(|arg| arg.get_long() == Some(name) || arg.get_all_aliases().is_some_and(|aliases| aliases.contains(&name)))
+      .ok_or_else(|| format!("Unknown flag --{name}"))?;
+    let name = arg.get_long().unwrap_or(name);
+
+    if let ArgAction::SetTrue = arg.get_action() {
+      watch.insert(name.into(), Value::Boolean(true));
+      continue;
+    }
+
+    let value = inline_value
+      .or_else(|| words.next())
+      .ok_or_else(|| format!("Missing value for --{name}"))?;
+
+    match name {
+      // Pointless in the config file itself
+      "config" => {}
+      "comment" => comment = Some(value),
+      "comment-file" => comment_file = Some(value),
+      _ => {
+        // Bare numbers stay numbers, so durations keep the unit of their flag
+        let value = match value.parse() {
+          Ok(number) => Value::Integer(number),
+          Err(_) => Value::String(value),
+        };
+
+        if let ArgAction::Append = arg.get_action() {
+          match watch.entry(name).or_insert_with(|| Value::Array(Vec::new())) {
+            Value::Array(values) => values.push(value),
+            _ => unreachable!("appended flags are always arrays"),
+          }
+        } else {
+          watch.insert(name.into(), value);
+        }
+      }
+    }
+  }
+
+  let mut config = Table::new();
+
+  // --comment - reads stdin, which the config file can't
+  if let Some(text) = comment.filter(|text| text != "-") {
+    let mut source = Table::new();
+    source.insert("source".into(), "text".into());
+    source.insert("text".into(), text.into());
+    config.insert("comment".into(), source.into());
+  } else if let Some(path) = comment_file {
+    let mut source = Table::new();
+    source.insert("source".into(), "file".into());
+    source.insert("path".into(), path.into());
+    config.insert("comment".into(), source.into());
+  }
+
+  config.insert("watch".into(), watch.into());
+
+  Ok(toml::to_string(&config)?)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::cli::Cli;
+  use clap::CommandFactory;
+
+  fn migrated(line: &str) -> Table {
+    migrate(line, &Cli::command()).unwrap().parse().unwrap()
+  }
+
+  #[test]
+  fn splits_quoted_words() {
+    assert_eq!(
+      split_command_line(r#"yfc --comment "First! \"really\"" --tag 'a b' --x=a\ b"#),
+      Ok(vec![
+        "yfc".to_string(),
+        "--comment".into(),
+        "First! \"really\"".into(),
+        "--tag".into(),
+        "a b".into(),
+        "--x=a b".into(),
+      ])
+    );
+  }
+
+  #[test]
+  fn joins_continued_lines() {
+    assert_eq!(
+      split_command_line("yfc \\\n  --like-video"),
+      Ok(vec!["yfc".to_string(), "--like-video".into()])
+    );
+  }
+
+  #[test]
+  fn refuses_unterminated_quotes() {
+    assert!(split_command_line("yfc --comment 'First!").is_err());
+    assert!(split_command_line("yfc --comment \"First!").is_err());
+  }
+
+  #[test]
+  fn migrates_options_and_switches() {
+    let config = migrated("/usr/bin/yfc --channel-id=UC123 --max-comments 3 --like-video --tag rust --tag news");
+    let watch = &config["watch"];
+
+    assert_eq!(watch["channel-id"].as_str(), Some("UC123"));
+    assert_eq!(watch["max-comments"].as_integer(), Some(3));
+    assert_eq!(watch["like-video"].as_bool(), Some(true));
+    assert_eq!(
+      watch["tag"].as_array().unwrap(),
+      &vec![Value::from("rust"), Value::from("news")]
+    );
+  }
+
+  #[test]
+  fn migrates_the_comment_to_its_section() {
+    let config = migrated("yfc --comment 'First!'");
+
+    assert_eq!(config["comment"]["source"].as_str(), Some("text"));
+    assert_eq!(config["comment"]["text"].as_str(), Some("First!"));
+    assert!(config["watch"].get("comment").is_none());
+  }
+
+  #[test]
+  fn refuses_unknown_flags_and_missing_values() {
+    assert!(migrate("yfc --no-such-flag", &Cli::command()).is_err());
+    assert!(migrate("yfc --channel-id", &Cli::command()).is_err());
+  }
+
+  #[test]
+  fn migrates_a_flag_by_its_old_name() {
+    let config = migrated("yfc --channel-id UC123 --pool-interval 60");
+
+    assert_eq!(config["watch"]["poll-interval"].as_integer(), Some(60));
+    assert!(config["watch"].get("pool-interval").is_none());
+  }
+}
@@ -0,0 +1,142 @@
+use google_youtube3::chrono::{DateTime, Local, Utc};
+use ratatui::crossterm::{
+  cursor::MoveToColumn,
+  execute,
+  style::{Color, Print, ResetColor, SetForegroundColor},
+  terminal::{Clear, ClearType},
+};
+use std::{
+  env,
+  fmt::Display,
+  io::{self, IsTerminal},
+  sync::atomic::{AtomicBool, Ordering},
+  time::Duration,
+};
+
+/// Whether the countdown line is on screen, the next line printed replacing it
+static COUNTDOWN_SHOWN: AtomicBool = AtomicBool::new(false);
+/// Set when several watchers share the console, their countdowns would overwrite each other
+static COUNTDOWN_HIDDEN: AtomicBool = AtomicBool::new(false);
+/// Set by --headless, whose output goes to a log even when a TTY is attached (e.g. `docker run -t`)
+static HEADLESS: AtomicBool = AtomicBool::new(false);
+
+/// Prints plain lines and never asks anything from now on
+pub fn set_headless() {
+  HEADLESS.store(true, Ordering::Relaxed);
+}
+
+/// Whether someone is there to answer a prompt or to log in with the browser
+pub fn is_interactive() -> bool {
+  !HEADLESS.load(Ordering::Relaxed) && io::stdin().is_terminal()
+}
+
+/// Whether the output goes to a terminal rather than to a log
+fn is_terminal() -> bool {
+  !HEADLESS.load(Ordering::Relaxed) && io::stdout().is_terminal()
+}
+
+/// Colors are only for a terminal, and not for those who opted out with NO_COLOR (https://no-color.org)
+fn is_colored() -> bool {
+  is_terminal() && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+fn print(color: Option<Color>, message: impl Display) {
+  clear_countdown();
+
+  match color.filter(|_| is_colored()) {
+    Some(color) => {
+      let _ = execute!(
+        io::stdout(),
+        SetForegroundColor(color),
+        Print(message),
+        ResetColor,
+        Print("\n")
+      );
+    }
+    None => println!("{message}"),
+  }
+}
+
+/// Prints a line, like println but in place of the countdown line
+pub fn info(message: impl Display) {
+  print(None, message);
+}
+
+/// Prints a line in green, e.g. for a comment posted
+pub fn success(message: impl Display) {
+  print(Some(Color::Green), message);
+}
+
+/// Prints a line in yellow, for what didn't go as planned but doesn't stop the watcher
+pub fn warning(message: impl Display) {
+  print(Some(Color::Yellow), message);
+}
+
+/// Prints a line in red, for what failed
+pub fn error(message: impl Display) {
+  print(Some(Color::Red), message);
+}
+
+/// Shows a line that's updated in place (e.g. "next poll in 37s"), until the next line is printed. Only on a terminal,
+/// a log file would get a line per update.
+pub fn countdown(message: impl Display) {
+  if !is_terminal() || COUNTDOWN_HIDDEN.load(Ordering::Relaxed) {
+    return;
+  }
+
+  let _ = execute!(
+    io::stdout(),
+    MoveToColumn(0),
+    Clear(ClearType::CurrentLine),
+    Print(message)
+  );
+
+  COUNTDOWN_SHOWN.store(true, Ordering::Relaxed);
+}
+
+/// Stops showing the countdown line
+pub fn hide_countdown() {
+  COUNTDOWN_HIDDEN.store(true, Ordering::Relaxed);
+}
+
+/// Rings the terminal bell. Only on a terminal, a log file would get the control character.
+pub fn bell() {
+  if is_terminal() {
+    let _ = execute!(io::stdout(), Print('\x07'));
+  }
+}
+
+/// Removes the countdown line, if it's shown
+pub fn clear_countdown() {
+  if COUNTDOWN_SHOWN.swap(false, Ordering::Relaxed) {
+    let _ = execute!(io::stdout(), MoveToColumn(0), Clear(ClearType::CurrentLine));
+  }
+}
+
+/// A duration to the second, e.g. "1h 30m 5s"
+pub fn format_duration(duration: Duration) -> String {
+  humantime::format_duration(Duration::from_secs(duration.as_secs())).to_string()
+}
+
+/// A time in the local time zone, with its offset so logs read the same once copied elsewhere, e.g.
+/// "2024-06-01 16:55:03 +02:00"
+pub fn format_time(time: DateTime<Utc>) -> String {
+  time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S %:z").to_string()
+}
+
+/// The line updated in place while waiting for the next poll, e.g. "Next poll in 37s, wait limit in 12m"
+pub fn format_countdown(until_poll: Duration, until_limit: Option<Duration>) -> String {
+  let mut line = format!(
+    "Next poll in {}",
+    format_duration(until_poll.max(Duration::from_secs(1)))
+  );
+
+  if let Some(until_limit) = until_limit {
+    line.push_str(&format!(
+      ", wait limit in {}",
+      format_duration(until_limit.max(Duration::from_secs(1)))
+    ));
+  }
+
+  line
+}
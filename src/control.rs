@@ -0,0 +1,287 @@
+use crate::{
+  comment::{CommentContext, CommentSource},
+  console, shutdown,
+  status::{self, Phase, SharedWatchState, StatusResponse},
+};
+use async_trait::async_trait;
+use clap::Subcommand;
+use google_youtube3::client::GetToken;
+use std::{error::Error, path::Path};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+/// Commands of the control socket. On the wire, each one is a line like `set-comment <text>`, answered with a line:
+/// the JSON status, `ok: <message>` or `error: <message>`.
+#[derive(Subcommand)]
+pub enum ControlCommand {
+  /// Print the status of the watcher, as JSON
+  Status,
+  /// Stop polling and posting until resumed
+  Pause,
+  /// Poll and post again, from the next poll on
+  Resume,
+  /// Post this text from now on instead of the configured comment, an empty text going back to it
+  SetComment {
+    /// The comment, with the same placeholders as a template
+    text: String,
+  },
+  /// Stop the watcher cleanly, as Ctrl+C does
+  Stop,
+}
+
+/// `\` and line breaks are escaped, a command being a single line
+fn escape(text: &str) -> String {
+  text.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+  let mut unescaped = String::new();
+  let mut chars = text.chars();
+
+  while let Some(c) = chars.next() {
+    match (c, chars.clone().next()) {
+      ('\\', Some('n')) => {
+        unescaped.push('\n');
+        chars.next();
+      }
+      ('\\', Some('\\')) => {
+        unescaped.push('\\');
+        chars.next();
+      }
+      (c, _) => unescaped.push(c),
+    }
+  }
+
+  unescaped
+}
+
+impl ControlCommand {
+  fn to_line(&self) -> String {
+    match self {
+      Self::Status => "status".into(),
+      Self::Pause => "pause".into(),
+      Self::Resume => "resume".into(),
+      Self::SetComment { text } => format!("set-comment {}", escape(text)),
+      Self::Stop => "stop".into(),
+    }
+  }
+
+  fn parse(line: &str) -> Result<Self, String> {
+    let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+
+    match command {
+      "status" => Ok(Self::Status),
+      "pause" => Ok(Self::Pause),
+      "resume" => Ok(Self::Resume),
+      "set-comment" => Ok(Self::SetComment {
+        text: unescape(argument),
+      }),
+      "stop" => Ok(Self::Stop),
+      _ => Err(format!(
+        "unknown command {command:?}, expected status, pause, resume, set-comment <text> or stop"
+      )),
+    }
+  }
+}
+
+/// The configured comment source, unless a comment was set through the control socket or API
+pub struct ControlledSource<'a> {
+  state: SharedWatchState,
+  source: &'a dyn CommentSource,
+}
+
+impl<'a> ControlledSource<'a> {
+  pub fn new(state: SharedWatchState, source: &'a dyn CommentSource) -> Self {
+    Self { state, source }
+  }
+}
+
+#[async_trait]
+impl CommentSource for ControlledSource<'_> {
+  async fn resolve(&self, context: &CommentContext<'_>) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let comment = self.state.lock().unwrap().comment.clone();
+
+    match comment {
+      Some(comment) => Ok(crate::comment::render_template(&comment, context)),
+      None => self.source.resolve(context).await,
+    }
+  }
+}
+
+/// What a command is answered with
+pub enum Reply {
+  Status(StatusResponse),
+  Done(String),
+}
+
+/// Carries out a command, for the control socket and the control API alike
+pub async fn execute(command: ControlCommand, state: &SharedWatchState, auth: &dyn GetToken) -> Reply {
+  let message = match command {
+    ControlCommand::Status => return Reply::Status(status::snapshot(state, auth).await),
+    ControlCommand::Pause => {
+      let mut state = state.lock().unwrap();
+      state.paused = true;
+
+      if let Phase::Watching = state.phase {
+        state.phase = Phase::Paused;
+      }
+
+      console::info("Paused remotely");
+      "paused, nothing is polled nor posted until resumed"
+    }
+    ControlCommand::Resume => {
+      let mut state = state.lock().unwrap();
+      state.paused = false;
+
+      if let Phase::Paused = state.phase {
+        state.phase = Phase::Watching;
+      }
+
+      console::info("Resumed remotely");
+      "resumed from the next poll on"
+    }
+    ControlCommand::SetComment { text } if text.trim().is_empty() => {
+      state.lock().unwrap().comment = None;
+      console::info("The comment was reset remotely");
+      "back to the configured comment"
+    }
+    ControlCommand::SetComment { text } => {
+      console::info(format!("The comment was set remotely: {text}"));
+      state.lock().unwrap().comment = Some(text);
+      "the next comments are the new text"
+    }
+    ControlCommand::Stop => {
+      shutdown::request();
+      "stopping after the current poll"
+    }
+  };
+
+  Reply::Done(message.into())
+}
+
+/// Answers the one command of a connection
+async fn handle<S>(stream: S, state: SharedWatchState, auth: Box<dyn GetToken>)
+where
+  S: AsyncRead + AsyncWrite + Unpin,
+{
+  let mut stream = BufReader::new(stream);
+  let mut line = String::new();
+
+  if stream.read_line(&mut line).await.is_err() {
+    return;
+  }
+
+  let reply = match ControlCommand::parse(line.trim_end_matches(['\r', '\n'])) {
+    Ok(command) => match execute(command, &state, auth.as_ref()).await {
+      Reply::Status(status) => serde_json::to_string(&status).unwrap(),
+      Reply::Done(message) => format!("ok: {message}"),
+    },
+    Err(e) => format!("error: {e}"),
+  };
+
+  let _ = stream.get_mut().write_all(format!("{reply}\n").as_bytes()).await;
+}
+
+/// Starts listening for `yfc ctl` commands on a Unix socket in the background. Only the user can connect to it.
+#[cfg(unix)]
+pub fn serve(path: &Path, state: SharedWatchState, auth: Box<dyn GetToken>) -> Result<(), Box<dyn Error>> {
+  use std::{fs, os::unix::fs::PermissionsExt};
+  use tokio::net::UnixListener;
+
+  if path.exists() {
+    if std::os::unix::net::UnixStream::connect(path).is_ok() {
+      return Err(format!("{} is the control socket of a running watcher", path.display()).into());
+    }
+
+    // Left behind by a watcher that didn't stop cleanly
+    fs::remove_file(path)?;
+  }
+
+  let listener =
+    UnixListener::bind(path).map_err(|e| format!("Failed to create the control socket {}: {e}", path.display()))?;
+
+  // Whoever can connect can post as you
+  fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+
+  console::info(format!("Control socket listening on {}", path.display()));
+
+  tokio::spawn(async move {
+    loop {
+      match listener.accept().await {
+        Ok((stream, _)) => {
+          tokio::spawn(handle(stream, state.clone(), auth.clone_box()));
+        }
+        Err(e) => {
+          console::info(format!("Control socket error: {e}"));
+          break;
+        }
+      }
+    }
+  });
+
+  Ok(())
+}
+
+/// Starts listening for `yfc ctl` commands on a named pipe (e.g. `\\.\pipe\yfc`) in the background
+#[cfg(windows)]
+pub fn serve(path: &Path, state: SharedWatchState, auth: Box<dyn GetToken>) -> Result<(), Box<dyn Error>> {
+  use tokio::net::windows::named_pipe::ServerOptions;
+
+  let name = path.as_os_str().to_owned();
+  let mut server = ServerOptions::new()
+    .first_pipe_instance(true)
+    .create(&name)
+    .map_err(|e| format!("Failed to create the control pipe {}: {e}", path.display()))?;
+
+  console::info(format!("Control pipe listening on {}", path.display()));
+
+  tokio::spawn(async move {
+    loop {
+      if let Err(e) = server.connect().await {
+        console::info(format!("Control pipe error: {e}"));
+        break;
+      }
+
+      // A new instance takes the next client while this one is answered
+      let connected = server;
+      server = match ServerOptions::new().create(&name) {
+        Ok(server) => server,
+        Err(e) => {
+          console::info(format!("Control pipe error: {e}"));
+          break;
+        }
+      };
+
+      tokio::spawn(handle(connected, state.clone(), auth.clone_box()));
+    }
+  });
+
+  Ok(())
+}
+
+/// Sends a command to the watcher listening on the control socket, returning its answer
+pub async fn send(path: &Path, command: &ControlCommand) -> Result<String, Box<dyn Error>> {
+  let unreachable = |e: std::io::Error| format!("Failed to reach the watcher on {}: {e}", path.display());
+
+  #[cfg(unix)]
+  let stream = tokio::net::UnixStream::connect(path).await.map_err(unreachable)?;
+  #[cfg(windows)]
+  let stream = tokio::net::windows::named_pipe::ClientOptions::new()
+    .open(path)
+    .map_err(unreachable)?;
+
+  let mut stream = BufReader::new(stream);
+  stream
+    .get_mut()
+    .write_all(format!("{}\n", command.to_line()).as_bytes())
+    .await?;
+
+  let mut reply = String::new();
+  stream.read_line(&mut reply).await?;
+
+  let reply = reply.trim_end();
+
+  match reply.strip_prefix("error: ") {
+    Some(error) => Err(error.into()),
+    None => Ok(reply.strip_prefix("ok: ").unwrap_or(reply).into()),
+  }
+}
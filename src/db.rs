@@ -0,0 +1,477 @@
+use crate::console;
+use google_youtube3::chrono::{DateTime, Datelike, NaiveTime, Utc};
+use rusqlite::{params, Connection};
+use std::{error::Error, fs, path::Path, time::Duration};
+
+/// Schema migrations, the database `user_version` is the number of them already applied. Never edit a released
+/// migration, append a new one instead.
+const MIGRATIONS: &[&str] = &[
+  "
+  CREATE TABLE attempts (
+    id INTEGER PRIMARY KEY,
+    attempted_at TEXT NOT NULL,
+    channel_id TEXT NOT NULL,
+    video_id TEXT NOT NULL,
+    comment TEXT,
+    comment_id TEXT,
+    result TEXT NOT NULL,
+    latency_ms INTEGER
+  );
+",
+  "
+  ALTER TABLE attempts ADD COLUMN error TEXT;
+",
+  "
+  ALTER TABLE attempts ADD COLUMN deleted_at TEXT;
+",
+  "
+  ALTER TABLE attempts ADD COLUMN final_rank TEXT;
+  ALTER TABLE attempts ADD COLUMN earlier_comments_appeared INTEGER;
+",
+];
+
+/// Indexes expected by the queries, recreated when missing
+const INDEXES: &[(&str, &str)] = &[
+  (
+    "attempts_attempted_at",
+    "CREATE INDEX IF NOT EXISTS attempts_attempted_at ON attempts (attempted_at)",
+  ),
+  (
+    "attempts_video_id",
+    "CREATE INDEX IF NOT EXISTS attempts_video_id ON attempts (video_id)",
+  ),
+];
+
+const TABLES: &[&str] = &["attempts"];
+
+fn schema_version(connection: &Connection) -> rusqlite::Result<usize> {
+  connection.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+fn migrate(connection: &mut Connection) -> rusqlite::Result<()> {
+  let version = schema_version(connection)?;
+  let transaction = connection.transaction()?;
+
+  for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+    transaction.execute_batch(migration)?;
+    transaction.pragma_update(None, "user_version", index + 1)?;
+  }
+
+  for (_, sql) in INDEXES {
+    transaction.execute(sql, [])?;
+  }
+
+  transaction.commit()
+}
+
+/// Opens the database, creating it and applying pending migrations as needed
+pub fn open(data_dir: &Path) -> Result<Connection, Box<dyn Error>> {
+  fs::create_dir_all(data_dir)?;
+
+  let mut connection = Connection::open(data_dir.join("yfc.db"))?;
+  let version = schema_version(&connection)?;
+
+  if version > MIGRATIONS.len() {
+    return Err(format!("The database schema (v{version}) is newer than this yfc version supports").into());
+  }
+
+  migrate(&mut connection)?;
+
+  Ok(connection)
+}
+
+#[derive(Clone, Copy)]
+pub enum AttemptResult {
+  Posted,
+  Failed,
+}
+
+/// One try at commenting on a video
+pub struct Attempt<'a> {
+  pub channel_id: &'a str,
+  pub video_id: &'a str,
+  pub comment: &'a str,
+  pub comment_id: Option<&'a str>,
+  pub result: AttemptResult,
+  /// Time between detecting the video and the comment being created
+  pub latency: Option<Duration>,
+  pub error: Option<&'a str>,
+}
+
+/// Where the ongoing run of comments on a channel stands, counting the video about to be commented on
+pub struct Progress {
+  /// Consecutive videos commented on, since the last one where posting failed
+  pub streak: u32,
+  /// Videos of the current month (UTC) commented on or attempted
+  pub uploads_this_month: u32,
+}
+
+/// The comments recently posted on any channel, to keep the account from looking like a spammer
+#[derive(Default)]
+pub struct RecentComments {
+  /// Comments posted since midnight (UTC)
+  pub today: u32,
+  pub last_posted_at: Option<DateTime<Utc>>,
+}
+
+/// The history of every comment attempt, kept in the database for auditing.
+pub struct History {
+  /// `None` when the database couldn't be opened, the history is then not recorded
+  connection: Option<Connection>,
+}
+
+impl AttemptResult {
+  fn as_str(self) -> &'static str {
+    match self {
+      AttemptResult::Posted => "posted",
+      AttemptResult::Failed => "failed",
+    }
+  }
+}
+
+impl History {
+  /// Opens the history. Watching doesn't depend on it, so a broken database only gets logged.
+  pub fn open(data_dir: &Path) -> Self {
+    let connection = open(data_dir)
+      .inspect_err(|e| {
+        console::error(format!(
+          "Failed to open the database, the history won't be recorded: {e}"
+        ))
+      })
+      .ok();
+
+    Self { connection }
+  }
+
+  /// Where the run of comments on the channel stands with `video_id`. Without a history, every video is the first.
+  pub fn progress(&self, channel_id: &str, video_id: &str) -> Progress {
+    let (streak, uploads_this_month) = self
+      .connection
+      .as_ref()
+      .map(|connection| query_progress(connection, channel_id, video_id))
+      .transpose()
+      .inspect_err(|e| console::error(format!("Failed to read the progress from the history: {e}")))
+      .ok()
+      .flatten()
+      .unwrap_or_default();
+
+    Progress {
+      streak: streak + 1,
+      uploads_this_month: uploads_this_month + 1,
+    }
+  }
+
+  /// The comments recently posted on any channel. Without a history, none are known.
+  pub fn recent_comments(&self) -> RecentComments {
+    self
+      .connection
+      .as_ref()
+      .map(query_recent_comments)
+      .transpose()
+      .inspect_err(|e| console::error(format!("Failed to read the recent comments from the history: {e}")))
+      .ok()
+      .flatten()
+      .unwrap_or_default()
+  }
+
+  pub fn record(&self, attempt: &Attempt) {
+    let Some(connection) = &self.connection else {
+      return;
+    };
+
+    let result = connection.execute(
+      "INSERT INTO attempts (attempted_at, channel_id, video_id, comment, comment_id, result, latency_ms, error)
+       VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+      params![
+        Utc::now().to_rfc3339(),
+        attempt.channel_id,
+        attempt.video_id,
+        attempt.comment,
+        attempt.comment_id,
+        attempt.result.as_str(),
+        attempt.latency.map(|latency| latency.as_millis() as i64),
+        attempt.error,
+      ],
+    );
+
+    if let Err(e) = result {
+      console::error(format!("Failed to record the attempt in the history: {e}"));
+    }
+  }
+
+  /// Whether a comment on the video was posted and not deleted since, by any watcher. Without a history, none was.
+  pub fn has_posted(&self, video_id: &str) -> bool {
+    let Some(connection) = &self.connection else {
+      return false;
+    };
+
+    connection
+      .query_row(
+        "SELECT EXISTS(SELECT 1 FROM attempts WHERE video_id = ?1 AND result = 'posted' AND deleted_at IS NULL)",
+        params![video_id],
+        |row| row.get(0),
+      )
+      .inspect_err(|e| console::error(format!("Failed to read the history of video {video_id}: {e}")))
+      .unwrap_or(false)
+  }
+
+  /// Records where the comment ended up with --verify-first-for, and how many older comments showed up meanwhile
+  pub fn record_final_rank(&self, comment_id: &str, final_rank: &str, earlier_comments_appeared: usize) {
+    let Some(connection) = &self.connection else {
+      return;
+    };
+
+    let result = connection.execute(
+      "UPDATE attempts SET final_rank = ?1, earlier_comments_appeared = ?2 WHERE comment_id = ?3",
+      params![final_rank, earlier_comments_appeared as i64, comment_id],
+    );
+
+    if let Err(e) = result {
+      console::error(format!("Failed to record the final rank in the history: {e}"));
+    }
+  }
+
+  /// Records that the comment was deleted after being posted
+  pub fn record_deleted(&self, comment_id: &str) {
+    let Some(connection) = &self.connection else {
+      return;
+    };
+
+    let result = connection.execute(
+      "UPDATE attempts SET deleted_at = ?1 WHERE comment_id = ?2 AND deleted_at IS NULL",
+      params![Utc::now().to_rfc3339(), comment_id],
+    );
+
+    if let Err(e) = result {
+      console::error(format!("Failed to record the deletion in the history: {e}"));
+    }
+  }
+}
+
+/// Counts the videos of the streak and of the month before `video_id`
+fn query_progress(connection: &Connection, channel_id: &str, video_id: &str) -> rusqlite::Result<(u32, u32)> {
+  // A video counts as commented on when any of its attempts got posted, e.g. after a failed one
+  let mut statement = connection.prepare(
+    "SELECT MAX(result = 'posted') FROM attempts WHERE channel_id = ?1 AND video_id != ?2
+     GROUP BY video_id ORDER BY MAX(attempted_at) DESC",
+  )?;
+  let mut streak = 0;
+  let mut rows = statement.query(params![channel_id, video_id])?;
+
+  while let Some(row) = rows.next()? {
+    if !row.get::<_, bool>(0)? {
+      break;
+    }
+
+    streak += 1;
+  }
+
+  let today = Utc::now().date_naive();
+  let month_start = today
+    .with_day(1)
+    .unwrap_or(today)
+    .and_time(NaiveTime::MIN)
+    .and_utc()
+    .to_rfc3339();
+  let uploads_this_month = connection.query_row(
+    "SELECT COUNT(DISTINCT video_id) FROM attempts WHERE channel_id = ?1 AND video_id != ?2 AND attempted_at >= ?3",
+    params![channel_id, video_id, month_start],
+    |row| row.get(0),
+  )?;
+
+  Ok((streak, uploads_this_month))
+}
+
+fn query_recent_comments(connection: &Connection) -> rusqlite::Result<RecentComments> {
+  let today = Utc::now().date_naive().and_time(NaiveTime::MIN).and_utc().to_rfc3339();
+  let (today, last_posted_at): (u32, Option<String>) = connection.query_row(
+    "SELECT COALESCE(SUM(attempted_at >= ?1), 0), MAX(attempted_at) FROM attempts WHERE result = 'posted'",
+    params![today],
+    |row| Ok((row.get(0)?, row.get(1)?)),
+  )?;
+
+  Ok(RecentComments {
+    today,
+    last_posted_at: last_posted_at
+      .and_then(|time| DateTime::parse_from_rfc3339(&time).ok())
+      .map(|time| time.with_timezone(&Utc)),
+  })
+}
+
+/// Prints the latest attempts, newest first, optionally only the ones on a channel or more recent than `since`
+pub fn print_history(
+  data_dir: &Path,
+  channel_id: Option<&str>,
+  since: Option<Duration>,
+  limit: usize,
+) -> Result<(), Box<dyn Error>> {
+  if !data_dir.join("yfc.db").exists() {
+    println!("No history yet");
+    return Ok(());
+  }
+
+  let connection = open(data_dir)?;
+  let cutoff = since.map(|since| (Utc::now() - since).to_rfc3339());
+  let mut statement = connection.prepare(
+    "SELECT attempted_at, channel_id, video_id, result, latency_ms, comment_id, comment, error, deleted_at,
+       final_rank, earlier_comments_appeared
+     FROM attempts
+     WHERE (?1 IS NULL OR channel_id = ?1) AND (?2 IS NULL OR attempted_at >= ?2)
+     ORDER BY attempted_at DESC LIMIT ?3",
+  )?;
+  let mut rows = statement.query(params![channel_id, cutoff, limit as i64])?;
+  let mut empty = true;
+
+  while let Some(row) = rows.next()? {
+    let attempted_at: String = row.get(0)?;
+    let attempted_at = DateTime::parse_from_rfc3339(&attempted_at)
+      .map(|time| console::format_time(time.with_timezone(&Utc)))
+      .unwrap_or(attempted_at);
+    let channel_id: String = row.get(1)?;
+    let video_id: String = row.get(2)?;
+    let result: String = row.get(3)?;
+    let latency = row
+      .get::<_, Option<i64>>(4)?
+      .map_or("-".into(), |latency_ms| format!("{latency_ms}ms"));
+    let comment_id: Option<String> = row.get(5)?;
+    let comment: Option<String> = row.get(6)?;
+    let error: Option<String> = row.get(7)?;
+    let deleted_at: Option<String> = row.get(8)?;
+    let final_rank: Option<String> = row.get(9)?;
+    let earlier_comments_appeared: Option<i64> = row.get(10)?;
+
+    println!(
+      "{attempted_at}  {channel_id}  {video_id}  {result:<6}  {latency:>8}  {}",
+      comment_id.as_deref().unwrap_or("-")
+    );
+
+    if let Some(comment) = comment {
+      println!("    {comment}");
+    }
+
+    if let Some(error) = error {
+      println!("    error: {error}");
+    }
+
+    if let Some(deleted_at) = deleted_at {
+      let deleted_at = DateTime::parse_from_rfc3339(&deleted_at)
+        .map(|time| console::format_time(time.with_timezone(&Utc)))
+        .unwrap_or(deleted_at);
+
+      println!("    deleted at {deleted_at}");
+    }
+
+    match (final_rank, earlier_comments_appeared) {
+      (Some(final_rank), Some(appeared @ 1..)) => {
+        println!("    final rank {final_rank}, {appeared} earlier comment(s) showed up after posting")
+      }
+      (Some(final_rank), _) => println!("    final rank {final_rank}"),
+      (None, _) => {}
+    }
+
+    empty = false;
+  }
+
+  if empty {
+    println!("No matching attempts");
+  }
+
+  Ok(())
+}
+
+fn object_exists(connection: &Connection, kind: &str, name: &str) -> rusqlite::Result<bool> {
+  connection.query_row(
+    "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = ?1 AND name = ?2)",
+    [kind, name],
+    |row| row.get(0),
+  )
+}
+
+/// Validates the database and, with `repair`, fixes what it can: pending migrations, missing or corrupted indexes and
+/// entries older than the retention window. Returns whether problems remain.
+pub fn check(data_dir: &Path, repair: bool, retention: Option<Duration>) -> Result<bool, Box<dyn Error>> {
+  let path = data_dir.join("yfc.db");
+
+  if !path.exists() {
+    println!("No database at {} yet, nothing to check", path.display());
+    return Ok(false);
+  }
+
+  let mut connection = Connection::open(&path)?;
+  let mut problems = 0;
+
+  let integrity: String = connection.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+
+  if integrity == "ok" {
+    println!("Integrity: ok");
+  } else {
+    println!("Integrity: {integrity}");
+    problems += 1;
+  }
+
+  let version = schema_version(&connection)?;
+
+  if version > MIGRATIONS.len() {
+    return Err(
+      format!(
+        "The database schema (v{version}) is newer than this yfc version supports (v{}), please upgrade yfc",
+        MIGRATIONS.len()
+      )
+      .into(),
+    );
+  }
+
+  if version < MIGRATIONS.len() {
+    println!("Schema: v{version}, v{} is available", MIGRATIONS.len());
+    problems += 1;
+  } else {
+    println!("Schema: v{version}");
+  }
+
+  for table in TABLES {
+    if !object_exists(&connection, "table", table)? {
+      println!("Missing table: {table}");
+      problems += 1;
+    }
+  }
+
+  for (index, _) in INDEXES {
+    if !object_exists(&connection, "index", index)? {
+      println!("Missing index: {index}");
+      problems += 1;
+    }
+  }
+
+  if !repair {
+    if problems > 0 {
+      println!("Found {problems} problem(s), run again with --repair to fix them");
+    }
+
+    return Ok(problems > 0);
+  }
+
+  // Migrating also recreates missing indexes, and REINDEX rebuilds the existing ones in case they are corrupted
+  migrate(&mut connection)?;
+  connection.execute_batch("REINDEX")?;
+  println!("Schema migrated to v{} and indexes rebuilt", MIGRATIONS.len());
+
+  if let Some(retention) = retention {
+    let cutoff = Utc::now() - retention;
+    let pruned = connection.execute("DELETE FROM attempts WHERE attempted_at < ?1", [cutoff.to_rfc3339()])?;
+
+    println!("Pruned {pruned} entries older than {}", cutoff.to_rfc3339());
+  }
+
+  connection.execute_batch("VACUUM")?;
+
+  let integrity: String = connection.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+
+  if integrity != "ok" {
+    println!("The database is still corrupted ({integrity}), consider moving it away to start over");
+    return Ok(true);
+  }
+
+  println!("The database is healthy");
+
+  Ok(false)
+}
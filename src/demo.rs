@@ -0,0 +1,177 @@
+use crate::console;
+use google_youtube3::{
+  chrono::{DateTime, Utc},
+  hyper::{
+    self,
+    header::{CONTENT_TYPE, IF_NONE_MATCH},
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+  },
+};
+use serde_json::{json, Value};
+use std::{convert::Infallible, net::SocketAddr, time::Duration};
+
+pub const CHANNEL_ID: &str = "UCyfcDemoChannel";
+const UPLOADS_PLAYLIST_ID: &str = "UUyfcDemoChannel";
+const OLD_VIDEO_ID: &str = "yfcDemoOld0";
+const NEW_VIDEO_ID: &str = "yfcDemoNew0";
+const COMMENT_ID: &str = "yfcDemoComment0";
+
+/// How long the scripted upload stays private before going public, to show the wait for scheduled videos
+const PRIVATE_FOR: Duration = Duration::from_secs(3);
+
+/// The script of the fake channel: one old video, then a new one uploaded privately after `upload_after` that goes
+/// public a few seconds later.
+#[derive(Clone, Copy)]
+struct Script {
+  started_at: DateTime<Utc>,
+  upload_after: Duration,
+}
+
+impl Script {
+  fn uploaded_at(&self) -> DateTime<Utc> {
+    self.started_at + self.upload_after
+  }
+
+  fn published_at(&self) -> DateTime<Utc> {
+    self.uploaded_at() + PRIVATE_FOR
+  }
+
+  fn playlist_items(&self) -> Value {
+    let mut items = vec![playlist_item(OLD_VIDEO_ID, "An older video", self.started_at)];
+
+    if Utc::now() >= self.uploaded_at() {
+      items.insert(0, playlist_item(NEW_VIDEO_ID, "A brand new video", self.uploaded_at()));
+    }
+
+    // Enough to tell the two states of the playlist apart
+    json!({ "etag": format!("demo-{}", items.len()), "items": items })
+  }
+
+  fn videos(&self, request: &Request<Body>) -> Value {
+    let is_new = request.uri().query().unwrap_or_default().contains(NEW_VIDEO_ID);
+    let (id, privacy_status, published_at) = if is_new {
+      let privacy_status = if Utc::now() >= self.published_at() {
+        "public"
+      } else {
+        "private"
+      };
+
+      (NEW_VIDEO_ID, privacy_status, self.published_at())
+    } else {
+      (OLD_VIDEO_ID, "public", self.started_at)
+    };
+
+    json!({
+      "items": [{
+        "id": id,
+        "status": { "privacyStatus": privacy_status },
+        "contentDetails": {},
+        "snippet": { "publishedAt": published_at.to_rfc3339() },
+      }]
+    })
+  }
+}
+
+fn playlist_item(video_id: &str, title: &str, published_at: DateTime<Utc>) -> Value {
+  json!({
+    "snippet": {
+      "publishedAt": published_at.to_rfc3339(),
+      "title": title,
+      "description": "",
+      "resourceId": { "kind": "youtube#video", "videoId": video_id },
+    }
+  })
+}
+
+async fn handle(request: Request<Body>, script: Script) -> Result<Response<Body>, Infallible> {
+  let path = request.uri().path().to_string();
+
+  if path == "/youtube/v3/playlistItems" {
+    let etag = script.playlist_items()["etag"].clone();
+
+    if request
+      .headers()
+      .get(IF_NONE_MATCH)
+      .and_then(|value| value.to_str().ok())
+      == etag.as_str()
+    {
+      let mut response = Response::new(Body::empty());
+      *response.status_mut() = StatusCode::NOT_MODIFIED;
+      return Ok(response);
+    }
+  }
+
+  let body = match (request.method(), path.as_str()) {
+    (&Method::GET, "/youtube/v3/channels") => Some(json!({
+      "items": [{ "contentDetails": { "relatedPlaylists": { "uploads": UPLOADS_PLAYLIST_ID } } }]
+    })),
+    // The fake channel has no memberships, so no members-only uploads playlist
+    (&Method::GET, "/youtube/v3/playlistItems")
+      if request
+        .uri()
+        .query()
+        .is_some_and(|query| query.contains("playlistId=UUMO")) =>
+    {
+      None
+    }
+    (&Method::GET, "/youtube/v3/playlistItems") => Some(script.playlist_items()),
+    (&Method::GET, "/youtube/v3/videos") => Some(script.videos(&request)),
+    // Nobody else comments on the fake channel, so the demo comment is always the first one
+    (&Method::GET, "/youtube/v3/commentThreads") => Some(json!({ "items": [{ "id": COMMENT_ID }] })),
+    (&Method::POST, "/youtube/v3/commentThreads") => {
+      // Posting is stubbed: the comment is only printed and a fake ID handed back
+      let body = hyper::body::to_bytes(request.into_body()).await.unwrap_or_default();
+      let thread: Value = serde_json::from_slice(&body).unwrap_or_default();
+      let text = &thread["snippet"]["topLevelComment"]["snippet"]["textOriginal"];
+
+      console::info(format!(
+        "[fake YouTube] Received the comment {text}, nothing was actually posted"
+      ));
+
+      Some(json!({ "id": COMMENT_ID, "snippet": thread["snippet"] }))
+    }
+    _ => None,
+  };
+
+  let mut response = match body {
+    Some(body) => Response::new(Body::from(body.to_string())),
+    None => {
+      let mut response = Response::new(Body::empty());
+      *response.status_mut() = StatusCode::NOT_FOUND;
+      response
+    }
+  };
+
+  response
+    .headers_mut()
+    .insert(CONTENT_TYPE, "application/json".parse().unwrap());
+
+  Ok(response)
+}
+
+/// Starts a fake YouTube API on a random local port, answering the handful of endpoints the watcher uses
+pub fn serve(upload_after: Duration) -> Result<SocketAddr, hyper::Error> {
+  let script = Script {
+    started_at: Utc::now(),
+    upload_after,
+  };
+  let make_service =
+    make_service_fn(move |_| async move { Ok::<_, Infallible>(service_fn(move |request| handle(request, script))) });
+
+  let server = Server::try_bind(&([127, 0, 0, 1], 0).into())?.serve(make_service);
+  let addr = server.local_addr();
+
+  console::info(format!(
+    "[fake YouTube] Listening on http://{addr}, a new video will be uploaded in {}s",
+    upload_after.as_secs()
+  ));
+
+  tokio::spawn(async move {
+    if let Err(e) = server.await {
+      console::error(format!("[fake YouTube] Server error: {e}"));
+    }
+  });
+
+  Ok(addr)
+}
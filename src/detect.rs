@@ -0,0 +1,498 @@
+use crate::{
+  cli::{Args, DetectBackend},
+  console::{self, format_duration},
+  quota,
+  source::VideoSource,
+  youtube::{build_https_client, with_timeout, HttpsClient, LatestVideo, PlaylistPage},
+};
+use async_trait::async_trait;
+use google_youtube3::{
+  chrono::{DateTime, Utc},
+  hyper::{
+    self,
+    header::{ETAG, IF_NONE_MATCH},
+    Body, Request, StatusCode,
+  },
+};
+use std::{
+  collections::HashSet,
+  error::Error,
+  io,
+  sync::Mutex,
+  time::{Duration, Instant},
+};
+
+/// How long a backend that failed or lagged is left out before it gets another chance
+const BENCH_TIME: Duration = Duration::from_secs(10 * 60);
+
+/// A way of finding the latest uploads of the channel
+#[async_trait]
+pub trait DetectionBackend: Send + Sync {
+  /// Named in the logs, like in --detect
+  fn name(&self) -> &'static str;
+
+  /// Quota units spent on every poll
+  fn poll_cost(&self) -> u64;
+
+  /// The latest `count` uploads, newest first. `None` means they didn't change since `etag`, or since the last poll
+  /// for the backends keeping their own.
+  async fn latest_videos(
+    &self,
+    source: &dyn VideoSource,
+    count: u32,
+    api_key: Option<&str>,
+    etag: Option<&str>,
+  ) -> google_youtube3::Result<Option<PlaylistPage>>;
+}
+
+/// The uploads playlist, through the Data API
+struct Api {
+  playlist_id: String,
+}
+
+#[async_trait]
+impl DetectionBackend for Api {
+  fn name(&self) -> &'static str {
+    "api"
+  }
+
+  fn poll_cost(&self) -> u64 {
+    quota::LIST_COST
+  }
+
+  async fn latest_videos(
+    &self,
+    source: &dyn VideoSource,
+    count: u32,
+    api_key: Option<&str>,
+    etag: Option<&str>,
+  ) -> google_youtube3::Result<Option<PlaylistPage>> {
+    source.playlist_videos(&self.playlist_id, count, api_key, etag).await
+  }
+}
+
+fn feed_error(message: impl Into<String>) -> google_youtube3::Error {
+  google_youtube3::Error::Io(io::Error::other(message.into()))
+}
+
+/// The text of an XML element, with the entities the feed uses decoded
+fn xml_text(entry: &str, tag: &str) -> Option<String> {
+  let start = entry.find(&format!("<{tag}>"))? + tag.len() + 2;
+  let end = start + entry[start..].find(&format!("</{tag}>"))?;
+
+  Some(
+    entry[start..end]
+      .replace("&lt;", "<")
+      .replace("&gt;", ">")
+      .replace("&quot;", "\"")
+      .replace("&#39;", "'")
+      .replace("&amp;", "&"),
+  )
+}
+
+/// The latest uploads (up to 15) listed by the RSS feed of the channel, newest first. `None` when it didn't change
+/// since `etag`, which is updated.
+pub async fn rss_videos(
+  client: &HttpsClient,
+  channel_id: &str,
+  etag: &mut Option<String>,
+) -> google_youtube3::Result<Option<Vec<LatestVideo>>> {
+  let mut request = Request::get(format!(
+    "https://www.youtube.com/feeds/videos.xml?channel_id={channel_id}"
+  ));
+
+  if let Some(etag) = etag.as_deref() {
+    request = request.header(IF_NONE_MATCH, etag);
+  }
+
+  let request = request.body(Body::empty()).map_err(|e| feed_error(e.to_string()))?;
+  let response =
+    with_timeout(async { client.request(request).await.map_err(google_youtube3::Error::HttpError) }).await?;
+
+  if response.status() == StatusCode::NOT_MODIFIED {
+    return Ok(None);
+  }
+
+  if !response.status().is_success() {
+    return Err(feed_error(format!("the RSS feed returned {}", response.status())));
+  }
+
+  *etag = response
+    .headers()
+    .get(ETAG)
+    .and_then(|value| value.to_str().ok())
+    .map(String::from);
+
+  let body = hyper::body::to_bytes(response.into_body())
+    .await
+    .map_err(google_youtube3::Error::HttpError)?;
+
+  Ok(Some(parse_feed(&String::from_utf8_lossy(&body))))
+}
+
+/// The videos of an RSS feed, in its order
+fn parse_feed(feed: &str) -> Vec<LatestVideo> {
+  // The feed itself has a <title> and a <published> tag too, so only look inside the entries
+  feed
+    .split("<entry>")
+    .skip(1)
+    .filter_map(|entry| {
+      let is_short = xml_text(entry, "media:description").is_some_and(|description| description.contains("#shorts"));
+
+      Some(LatestVideo {
+        id: xml_text(entry, "yt:videoId")?,
+        title: xml_text(entry, "title").unwrap_or_default(),
+        is_short,
+        // The feed mixes the untagged Shorts in without telling them apart
+        may_be_short: !is_short,
+        published_at: xml_text(entry, "published")
+          .and_then(|published| DateTime::parse_from_rfc3339(&published).ok())
+          .map(|published| published.with_timezone(&Utc)),
+        pushed: false,
+      })
+    })
+    .collect()
+}
+
+/// The RSS feed of the channel, which costs no quota but often lags behind the API
+struct Rss {
+  client: HttpsClient,
+  channel_id: String,
+  etag: Mutex<Option<String>>,
+}
+
+#[async_trait]
+impl DetectionBackend for Rss {
+  fn name(&self) -> &'static str {
+    "rss"
+  }
+
+  fn poll_cost(&self) -> u64 {
+    0
+  }
+
+  async fn latest_videos(
+    &self,
+    _source: &dyn VideoSource,
+    count: u32,
+    _api_key: Option<&str>,
+    _etag: Option<&str>,
+  ) -> google_youtube3::Result<Option<PlaylistPage>> {
+    let mut etag = self.etag.lock().unwrap().clone();
+    let videos = rss_videos(&self.client, &self.channel_id, &mut etag).await?;
+
+    *self.etag.lock().unwrap() = etag;
+
+    Ok(videos.map(|mut videos| {
+      videos.truncate(count as usize);
+
+      PlaylistPage {
+        videos,
+        unavailable: Vec::new(),
+        etag: None,
+      }
+    }))
+  }
+}
+
+/// The Videos tab of the channel on the website. Unsupported by YouTube, it may break at any time.
+#[cfg(feature = "web-detect")]
+struct Web {
+  client: HttpsClient,
+  channel_id: String,
+}
+
+#[cfg(feature = "web-detect")]
+#[async_trait]
+impl DetectionBackend for Web {
+  fn name(&self) -> &'static str {
+    "web"
+  }
+
+  fn poll_cost(&self) -> u64 {
+    0
+  }
+
+  async fn latest_videos(
+    &self,
+    _source: &dyn VideoSource,
+    count: u32,
+    _api_key: Option<&str>,
+    _etag: Option<&str>,
+  ) -> google_youtube3::Result<Option<PlaylistPage>> {
+    Ok(Some(PlaylistPage {
+      videos: crate::web::channel_videos(&self.client, &self.channel_id, count).await?,
+      unavailable: Vec::new(),
+      etag: None,
+    }))
+  }
+}
+
+/// How a backend of the chain is doing
+#[derive(Default)]
+struct Health {
+  consecutive_failures: u32,
+  /// Set when it failed or lagged too much, it's left out until then
+  benched_until: Option<Instant>,
+}
+
+impl Health {
+  fn is_benched(&self) -> bool {
+    self.benched_until.is_some_and(|until| Instant::now() < until)
+  }
+}
+
+struct ChainState {
+  health: Vec<Health>,
+  /// The backend the last poll went through
+  active: usize,
+  /// The videos listed so far, to measure how late a backend lists the new ones
+  seen_video_ids: HashSet<String>,
+  /// Videos published earlier were out before watching, they tell nothing about the lag
+  watching_since: DateTime<Utc>,
+}
+
+/// The backends of --detect in order of preference, the API always being the last resort. A poll goes through the
+/// first one that isn't benched, and on to the next ones when it fails. A backend failing --failover-after polls in a
+/// row, or listing a new video more than --max-detection-lag after its publication, is benched for a while.
+pub struct DetectionChain {
+  backends: Vec<Box<dyn DetectionBackend>>,
+  failover_after: u32,
+  max_lag: Option<Duration>,
+  state: Mutex<ChainState>,
+}
+
+impl DetectionChain {
+  pub fn new(args: &Args, playlist_id: String) -> Result<Self, Box<dyn Error>> {
+    let mut backends: Vec<Box<dyn DetectionBackend>> = Vec::new();
+
+    for backend in &args.detect {
+      backends.push(match backend {
+        DetectBackend::Api => Box::new(Api {
+          playlist_id: playlist_id.clone(),
+        }),
+        DetectBackend::Rss => Box::new(Rss {
+          client: build_https_client()?,
+          channel_id: args.channel_id.clone(),
+          etag: Mutex::new(None),
+        }),
+        #[cfg(feature = "web-detect")]
+        DetectBackend::Web => {
+          console::warning(
+            "Detecting the uploads through the website, which is experimental and unsupported by YouTube",
+          );
+
+          Box::new(Web {
+            client: build_https_client()?,
+            channel_id: args.channel_id.clone(),
+          })
+        }
+      });
+    }
+
+    if !args.detect.contains(&DetectBackend::Api) {
+      backends.push(Box::new(Api { playlist_id }));
+    }
+
+    console::info(format!(
+      "Detecting the uploads through {}",
+      backends
+        .iter()
+        .map(|backend| backend.name())
+        .collect::<Vec<_>>()
+        .join(", then ")
+    ));
+
+    Ok(Self {
+      state: Mutex::new(ChainState {
+        health: backends.iter().map(|_| Health::default()).collect(),
+        active: 0,
+        seen_video_ids: HashSet::new(),
+        watching_since: Utc::now(),
+      }),
+      backends,
+      failover_after: args.failover_after,
+      max_lag: args.max_detection_lag,
+    })
+  }
+
+  /// Quota units spent on a poll through the backend in use
+  pub fn poll_cost(&self) -> u64 {
+    self.backends[self.state.lock().unwrap().active].poll_cost()
+  }
+
+  /// The backends to try in turn, the benched ones left out unless they all are
+  fn order(&self) -> Vec<usize> {
+    let state = self.state.lock().unwrap();
+    let available: Vec<usize> = (0..self.backends.len())
+      .filter(|&index| !state.health[index].is_benched())
+      .collect();
+
+    if available.is_empty() {
+      (0..self.backends.len()).collect()
+    } else {
+      available
+    }
+  }
+
+  /// The name of the first backend after `index` that isn't benched
+  fn next_name(&self, state: &ChainState, index: usize) -> &'static str {
+    (index + 1..self.backends.len())
+      .find(|&next| !state.health[next].is_benched())
+      .map_or("the remaining backends", |next| self.backends[next].name())
+  }
+
+  pub async fn latest_videos(
+    &self,
+    source: &dyn VideoSource,
+    count: u32,
+    api_key: Option<&str>,
+    etag: Option<&str>,
+  ) -> google_youtube3::Result<Option<PlaylistPage>> {
+    let order = self.order();
+    let mut last_error = None;
+
+    for (attempt, &index) in order.iter().enumerate() {
+      let backend = &self.backends[index];
+      // The ETag is of the page of the backend that was in use
+      let etag = etag.filter(|_| index == self.state.lock().unwrap().active);
+
+      match backend.latest_videos(source, count, api_key, etag).await {
+        Ok(page) => {
+          self.succeeded(index, page.as_ref());
+          return Ok(page);
+        }
+        Err(e) => {
+          if attempt + 1 < order.len() {
+            console::warning(format!(
+              "Failed to detect the uploads through {}, trying {} for this poll: {e}",
+              backend.name(),
+              self.backends[order[attempt + 1]].name()
+            ));
+          }
+
+          self.failed(index, &e);
+          last_error = Some(e);
+        }
+      }
+    }
+
+    Err(last_error.expect("the chain has at least the API"))
+  }
+
+  fn failed(&self, index: usize, error: &google_youtube3::Error) {
+    let mut state = self.state.lock().unwrap();
+    let health = &mut state.health[index];
+
+    health.consecutive_failures += 1;
+
+    if health.consecutive_failures >= self.failover_after && !health.is_benched() && index + 1 < self.backends.len() {
+      let failures = health.consecutive_failures;
+
+      health.benched_until = Some(Instant::now() + BENCH_TIME);
+      health.consecutive_failures = 0;
+      console::warning(format!(
+        "Detecting through {} failed {failures} polls in a row ({error}), switching to {} for {}",
+        self.backends[index].name(),
+        self.next_name(&state, index),
+        format_duration(BENCH_TIME)
+      ));
+    }
+  }
+
+  fn succeeded(&self, index: usize, page: Option<&PlaylistPage>) {
+    let mut state = self.state.lock().unwrap();
+    let previous = state.active;
+
+    state.health[index].consecutive_failures = 0;
+    state.active = index;
+
+    // Switching down is reported when the backend gets benched, a single poll falling through isn't a switch
+    if index < previous {
+      console::info(format!(
+        "Detecting through {} again, rather than {}",
+        self.backends[index].name(),
+        self.backends[previous].name()
+      ));
+    }
+
+    let (Some(page), Some(max_lag)) = (page, self.max_lag) else {
+      return;
+    };
+    let watching_since = state.watching_since;
+    let lag = page
+      .videos
+      .iter()
+      .filter(|video| state.seen_video_ids.insert(video.id.clone()))
+      .filter_map(|video| video.published_at.filter(|published_at| *published_at > watching_since))
+      .filter_map(|published_at| (Utc::now() - published_at).to_std().ok())
+      .max();
+
+    if let Some(lag) = lag.filter(|lag| *lag > max_lag) {
+      if index + 1 < self.backends.len() {
+        state.health[index].benched_until = Some(Instant::now() + BENCH_TIME);
+        console::warning(format!(
+          "Detecting through {} lags {} behind the publication, switching to {} for {}",
+          self.backends[index].name(),
+          format_duration(lag),
+          self.next_name(&state, index),
+          format_duration(BENCH_TIME)
+        ));
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const FEED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns:yt="http://www.youtube.com/xml/schemas/2015" xmlns:media="http://search.yahoo.com/mrss/">
+ <title>Channel</title>
+ <published>2020-01-01T00:00:00+00:00</published>
+ <entry>
+  <yt:videoId>short000001</yt:videoId>
+  <title>Quick tip</title>
+  <published>2024-01-02T12:00:00+00:00</published>
+  <media:group>
+   <media:title>Quick tip</media:title>
+   <media:description>Subscribe! #shorts</media:description>
+  </media:group>
+ </entry>
+ <entry>
+  <yt:videoId>video000001</yt:videoId>
+  <title>Tom &amp; Jerry</title>
+  <published>2024-01-01T12:00:00+00:00</published>
+  <media:group>
+   <media:title>Tom &amp; Jerry</media:title>
+   <media:description>The full episode</media:description>
+  </media:group>
+ </entry>
+</feed>"#;
+
+  #[test]
+  fn parses_the_entries_of_the_feed() {
+    let videos = parse_feed(FEED);
+
+    assert_eq!(videos.len(), 2);
+    assert_eq!(videos[1].id, "video000001");
+    assert_eq!(videos[1].title, "Tom & Jerry");
+    assert_eq!(
+      videos[1]
+        .published_at
+        .map(|published_at| published_at.to_rfc3339())
+        .as_deref(),
+      Some("2024-01-01T12:00:00+00:00")
+    );
+  }
+
+  #[test]
+  fn tells_the_tagged_shorts_apart() {
+    let videos = parse_feed(FEED);
+
+    assert!(videos[0].is_short && !videos[0].may_be_short);
+    // Not tagged, it's left to YouTube to tell
+    assert!(!videos[1].is_short && videos[1].may_be_short);
+  }
+}
@@ -0,0 +1,77 @@
+use crate::{
+  config::{EmailConfig, SmtpSecurity},
+  console,
+  notify::Event,
+  quota,
+};
+use lettre::{
+  message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport, AsyncTransport, Message,
+  Tokio1Executor,
+};
+use std::error::Error;
+
+/// Sends a summary email for the events that end a watch: the comment being posted, posting failing, or the wait limit
+/// being reached.
+pub struct EmailNotifier {
+  transport: AsyncSmtpTransport<Tokio1Executor>,
+  from: Mailbox,
+  to: Mailbox,
+}
+
+impl EmailNotifier {
+  pub fn new(config: &EmailConfig) -> Result<Self, Box<dyn Error>> {
+    let mut builder = match config.security {
+      SmtpSecurity::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?,
+      SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?,
+      SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+    };
+
+    if let Some(port) = config.port {
+      builder = builder.port(port);
+    }
+
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+      builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+
+    Ok(Self {
+      transport: builder.build(),
+      from: config.from.parse().map_err(|e| format!("Invalid email sender: {e}"))?,
+      to: config.to.parse().map_err(|e| format!("Invalid email recipient: {e}"))?,
+    })
+  }
+
+  pub async fn send(&self, event: &Event<'_>) {
+    if matches!(event, Event::VideoDetected { .. }) {
+      return;
+    }
+
+    let mut body = event.description();
+
+    if let Some(url) = event.url() {
+      body.push_str(&format!("\n\n{url}"));
+    }
+
+    body.push_str(&format!("\n\nEstimated quota used: {} units", quota::used()));
+
+    let message = Message::builder()
+      .from(self.from.clone())
+      .to(self.to.clone())
+      .subject(format!("yfc: {}", event.title()))
+      .body(body);
+
+    let result = match message {
+      Ok(message) => self
+        .transport
+        .send(message)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string()),
+      Err(e) => Err(e.to_string()),
+    };
+
+    if let Err(e) = result {
+      console::error(format!("Failed to send the notification email: {e}"));
+    }
+  }
+}
@@ -0,0 +1,215 @@
+use crate::{
+  console,
+  notify::{Event, Notifier},
+  summary::RunSummary,
+  youtube::CommentRank,
+};
+use google_youtube3::chrono::{DateTime, Utc};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Something that happened while watching. The watcher only reports these, the frontends (the console, the
+/// notification channels) decide what to make of them.
+pub enum WatchEvent {
+  VideoDetected {
+    video_id: String,
+    title: String,
+    /// When YouTube reports it
+    published_at: Option<DateTime<Utc>>,
+  },
+  ShortSkipped {
+    video_id: String,
+  },
+  CommentPosted {
+    video_id: String,
+    comment_id: String,
+    /// Time between detecting the video and the comment being created
+    latency: Duration,
+    /// Time between the video being published and the comment being created, when YouTube reports the publish time
+    publish_latency: Option<Duration>,
+  },
+  PostFailed {
+    video_id: String,
+    error: String,
+  },
+  /// Posting failed with a transient error and is tried again after `delay`
+  RetryScheduled {
+    video_id: String,
+    attempt: u32,
+    retries: u32,
+    delay: Duration,
+    error: String,
+  },
+  /// Polls are spaced out to stay within --quota-budget
+  QuotaWarning {
+    used: u64,
+    budget: u64,
+    next_poll_in: Duration,
+  },
+  WaitLimitReached,
+  /// The API quota ran out, see [`Event::QuotaExceeded`]
+  QuotaExceeded {
+    resumes_in: Option<Duration>,
+  },
+  /// Where the comment ended up after --verify-first-for, and how many older comments showed up meanwhile
+  RankVerified {
+    video_id: String,
+    rank: CommentRank,
+    earlier_appeared: usize,
+  },
+  /// The --follow-up reply to the comment was posted
+  FollowUpPosted {
+    video_id: String,
+    reply_id: String,
+  },
+  /// The comment got new likes or replies, with --monitor-for
+  CommentEngagement {
+    video_id: String,
+    comment_id: String,
+    likes: u32,
+    replies: u32,
+  },
+  /// Observer mode: how fast the video was detected and who got the first comment
+  VideoObserved {
+    video_id: String,
+    detection_latency: Option<Duration>,
+    first_commenter: Option<String>,
+    first_comment_after: Option<Duration>,
+  },
+}
+
+impl WatchEvent {
+  /// The event as sent to the notification channels, `None` for the ones only shown in the terminal
+  pub fn notification(&self) -> Option<Event<'_>> {
+    Some(match self {
+      WatchEvent::VideoDetected {
+        video_id,
+        title,
+        published_at,
+      } => Event::VideoDetected {
+        video_id,
+        title,
+        published_at: *published_at,
+      },
+      WatchEvent::CommentPosted {
+        video_id,
+        comment_id,
+        latency,
+        publish_latency,
+      } => Event::CommentPosted {
+        video_id,
+        comment_id,
+        latency: *latency,
+        publish_latency: *publish_latency,
+      },
+      WatchEvent::PostFailed { video_id, error } => Event::PostFailed { video_id, error },
+      WatchEvent::WaitLimitReached => Event::WaitLimitReached,
+      WatchEvent::QuotaExceeded { resumes_in } => Event::QuotaExceeded {
+        resumes_in: *resumes_in,
+      },
+      WatchEvent::CommentEngagement {
+        video_id,
+        comment_id,
+        likes,
+        replies,
+      } => Event::CommentEngagement {
+        video_id,
+        comment_id,
+        likes: *likes,
+        replies: *replies,
+      },
+      WatchEvent::VideoObserved {
+        video_id,
+        detection_latency,
+        first_commenter,
+        first_comment_after,
+      } => Event::VideoObserved {
+        video_id,
+        detection_latency: *detection_latency,
+        first_commenter: first_commenter.as_deref(),
+        first_comment_after: *first_comment_after,
+      },
+      WatchEvent::ShortSkipped { .. }
+      | WatchEvent::FollowUpPosted { .. }
+      | WatchEvent::RetryScheduled { .. }
+      | WatchEvent::QuotaWarning { .. }
+      | WatchEvent::RankVerified { .. } => return None,
+    })
+  }
+}
+
+/// The receiving end of the events of a watcher, closed once it stops
+pub type EventStream = mpsc::UnboundedReceiver<WatchEvent>;
+
+/// The sending end of the events, held by the watcher
+#[derive(Clone)]
+pub struct EventSender(mpsc::UnboundedSender<WatchEvent>);
+
+impl EventSender {
+  pub fn send(&self, event: WatchEvent) {
+    // Nobody listening is fine, the watcher keeps going without frontends
+    let _ = self.0.send(event);
+  }
+}
+
+pub fn channel() -> (EventSender, EventStream) {
+  let (sender, stream) = mpsc::unbounded_channel();
+
+  (EventSender(sender), stream)
+}
+
+/// Shows the events in the terminal and sends them to the notification channels, until the watcher stops. They're also
+/// recorded in the summary of the run.
+pub async fn forward(mut events: EventStream, notifier: &Notifier, summary: &mut RunSummary) {
+  while let Some(event) = events.recv().await {
+    summary.record(&event);
+
+    match &event {
+      WatchEvent::ShortSkipped { video_id } => console::info(format!("Video {video_id} is a short, skipping it")),
+      WatchEvent::FollowUpPosted { video_id, reply_id } => {
+        console::success(format!("Follow-up reply {reply_id} posted on video {video_id}"))
+      }
+      WatchEvent::RetryScheduled {
+        video_id,
+        attempt,
+        retries,
+        delay,
+        error,
+      } => console::error(format!(
+        "Failed to post the comment on video {video_id} ({error}), retry {attempt}/{retries} in {}",
+        humantime::format_duration(*delay)
+      )),
+      WatchEvent::QuotaWarning {
+        used,
+        budget,
+        next_poll_in,
+      } => console::info(format!(
+        "{used}/{budget} quota units used today, next poll in {} ({}) to stay within the budget",
+        console::format_duration(*next_poll_in),
+        console::format_time(Utc::now() + *next_poll_in)
+      )),
+      WatchEvent::RankVerified {
+        video_id,
+        rank: CommentRank::Exact(1),
+        ..
+      } => console::success(format!("Your comment on video {video_id} is still the first one!")),
+      WatchEvent::RankVerified {
+        video_id,
+        rank,
+        earlier_appeared: 0,
+      } => console::info(format!("Your comment on video {video_id} ended up {rank}")),
+      WatchEvent::RankVerified {
+        video_id,
+        rank,
+        earlier_appeared,
+      } => console::warning(format!(
+        "Your comment on video {video_id} ended up {rank}, {earlier_appeared} earlier comment(s) showed up after posting"
+      )),
+      _ => {}
+    }
+
+    if let Some(notification) = event.notification() {
+      notifier.notify(&notification).await;
+    }
+  }
+}
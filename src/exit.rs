@@ -0,0 +1,73 @@
+use crate::shutdown::{self, Interrupted};
+use std::{error::Error, fmt};
+
+/// Any other error
+pub const FAILURE: i32 = 1;
+pub const AUTH_FAILED: i32 = 2;
+pub const QUOTA_EXCEEDED: i32 = 3;
+pub const COMMENT_REJECTED: i32 = 4;
+pub const WAIT_LIMIT_REACHED: i32 = 5;
+/// Invalid command line options, EX_USAGE of sysexits.h
+pub const USAGE: i32 = 64;
+
+/// Listed in --help, to script around yfc
+pub const EXIT_CODES_HELP: &str = "Exit codes:
+  0    Done, e.g. the comment was posted
+  1    Any other error
+  2    Logging in failed, or the login doesn't post as --post-as
+  3    The quota of the project ran out
+  4    The comment was rejected, or comments are disabled on the video
+  5    The wait limit was reached without posting
+  64   Invalid options
+  130  Stopped by Ctrl+C or SIGTERM";
+
+/// Why the watcher gave up, each with its own exit code
+#[derive(Debug)]
+pub enum Failure {
+  Auth(Box<dyn Error>),
+  QuotaExceeded(Box<dyn Error>),
+  CommentRejected(Box<dyn Error>),
+  WaitLimitReached,
+}
+
+impl fmt::Display for Failure {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Failure::Auth(e) | Failure::QuotaExceeded(e) | Failure::CommentRejected(e) => write!(f, "{e}"),
+      Failure::WaitLimitReached => write!(f, "the wait limit was reached"),
+    }
+  }
+}
+
+impl Error for Failure {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      Failure::Auth(e) | Failure::QuotaExceeded(e) | Failure::CommentRejected(e) => Some(e.as_ref()),
+      Failure::WaitLimitReached => None,
+    }
+  }
+}
+
+/// The exit code yfc ends with after the error
+pub fn code(error: &(dyn Error + 'static)) -> i32 {
+  match error.downcast_ref::<Failure>() {
+    Some(Failure::Auth(_)) => AUTH_FAILED,
+    Some(Failure::QuotaExceeded(_)) => QUOTA_EXCEEDED,
+    Some(Failure::CommentRejected(_)) => COMMENT_REJECTED,
+    Some(Failure::WaitLimitReached) => WAIT_LIMIT_REACHED,
+    None if error.is::<Interrupted>() => shutdown::EXIT_CODE,
+    None => FAILURE,
+  }
+}
+
+/// Why yfc ended with the error, as written in the summary of the run
+pub fn reason(error: &(dyn Error + 'static)) -> &'static str {
+  match error.downcast_ref::<Failure>() {
+    Some(Failure::Auth(_)) => "auth_failed",
+    Some(Failure::QuotaExceeded(_)) => "quota_exceeded",
+    Some(Failure::CommentRejected(_)) => "comment_rejected",
+    Some(Failure::WaitLimitReached) => "wait_limit_reached",
+    None if error.is::<Interrupted>() => "interrupted",
+    None => "error",
+  }
+}
@@ -0,0 +1,373 @@
+use crate::{
+  search::SearchOrder,
+  source::{CommentSink, VideoSource},
+  youtube::{new_client, LatestVideo, PlaylistPage, YoutubeClient, API_BASE_URL},
+};
+use async_trait::async_trait;
+use google_youtube3::{
+  api::{Video, VideoSnippet, VideoStatus},
+  chrono::{DateTime, Duration, TimeZone, Utc},
+  client::NoToken,
+  hyper::{
+    self,
+    header::{CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+  },
+};
+use serde_json::{json, Value};
+use std::{
+  collections::{HashMap, HashSet, VecDeque},
+  convert::Infallible,
+  net::SocketAddr,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc, Mutex, OnceLock,
+  },
+  thread,
+};
+
+/// YouTube in memory, for the tests to drive the watcher without the API: the uploads of a channel, the privacy status
+/// of its videos and how the comment inserts go
+#[derive(Default)]
+pub struct FakeYoutube {
+  /// Newest first, like the uploads playlist
+  uploads: Mutex<Vec<LatestVideo>>,
+  /// The videos not public yet, by ID
+  privacy_statuses: Mutex<HashMap<String, String>>,
+  shorts: Mutex<HashSet<String>>,
+  /// What the next video lookups fail with, in order. Once they're used up, lookups succeed.
+  lookup_errors: Mutex<VecDeque<google_youtube3::Error>>,
+  /// What the next inserts answer, in order. Once they're used up, inserts succeed.
+  post_results: Mutex<VecDeque<google_youtube3::Result<String>>>,
+  /// The comments posted, as (video ID, comment)
+  pub posted: Mutex<Vec<(String, String)>>,
+}
+
+impl FakeYoutube {
+  /// A channel with these videos, oldest first, each one published an hour after the previous one
+  pub fn with_uploads(video_ids: &[&str]) -> Self {
+    let youtube = Self::default();
+
+    for video_id in video_ids {
+      youtube.upload(video_id);
+    }
+
+    youtube
+  }
+
+  /// Publishes a video an hour after the latest one
+  pub fn upload(&self, video_id: &str) {
+    let mut uploads = self.uploads.lock().unwrap();
+    let published_at = uploads
+      .first()
+      .and_then(|video| video.published_at)
+      .map_or_else(start, |published_at| published_at + Duration::hours(1));
+
+    uploads.insert(
+      0,
+      LatestVideo {
+        id: video_id.into(),
+        title: format!("Video {video_id}"),
+        is_short: false,
+        may_be_short: false,
+        published_at: Some(published_at),
+        pushed: false,
+      },
+    );
+  }
+
+  /// Takes the video down, as deleting it or making it private does
+  pub fn remove(&self, video_id: &str) {
+    self.uploads.lock().unwrap().retain(|video| video.id != video_id);
+  }
+
+  /// Makes the video a Short. `tagged` is whether its title or description tells so.
+  pub fn make_short(&self, video_id: &str, tagged: bool) {
+    self.shorts.lock().unwrap().insert(video_id.into());
+
+    for video in self
+      .uploads
+      .lock()
+      .unwrap()
+      .iter_mut()
+      .filter(|video| video.id == video_id)
+    {
+      video.is_short = tagged;
+    }
+  }
+
+  pub fn set_privacy_status(&self, video_id: &str, status: &str) {
+    self
+      .privacy_statuses
+      .lock()
+      .unwrap()
+      .insert(video_id.into(), status.into());
+  }
+
+  /// Has the next video lookups fail with these, before succeeding again
+  pub fn fail_lookups(&self, errors: impl IntoIterator<Item = google_youtube3::Error>) {
+    self.lookup_errors.lock().unwrap().extend(errors);
+  }
+
+  /// Has the next inserts answer these, before succeeding again
+  pub fn fail_posts(&self, errors: impl IntoIterator<Item = google_youtube3::Error>) {
+    self.post_results.lock().unwrap().extend(errors.into_iter().map(Err));
+  }
+}
+
+/// When the first upload of a fake channel was published
+fn start() -> DateTime<Utc> {
+  Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+}
+
+/// An error worth retrying, the API being unavailable for a moment
+pub fn transient_error() -> google_youtube3::Error {
+  google_youtube3::Error::BadRequest(json!({ "error": { "code": 503, "message": "Backend Error" } }))
+}
+
+/// An error not worth retrying, the comments of the video being turned off
+pub fn permanent_error() -> google_youtube3::Error {
+  google_youtube3::Error::BadRequest(json!({
+    "error": { "code": 403, "errors": [{ "reason": "commentsDisabled" }] }
+  }))
+}
+
+#[async_trait]
+impl VideoSource for FakeYoutube {
+  async fn playlist_videos(
+    &self,
+    _playlist_id: &str,
+    count: u32,
+    _api_key: Option<&str>,
+    _etag: Option<&str>,
+  ) -> google_youtube3::Result<Option<PlaylistPage>> {
+    let uploads = self.uploads.lock().unwrap();
+
+    Ok(Some(PlaylistPage {
+      videos: uploads.iter().take(count as usize).cloned().collect(),
+      unavailable: Vec::new(),
+      etag: None,
+    }))
+  }
+
+  async fn search_videos(
+    &self,
+    query: &str,
+    _channel_id: Option<&str>,
+    _order: SearchOrder,
+    count: u32,
+    _api_key: Option<&str>,
+  ) -> google_youtube3::Result<Vec<LatestVideo>> {
+    let uploads = self.uploads.lock().unwrap();
+
+    Ok(
+      uploads
+        .iter()
+        .filter(|video| video.title.contains(query))
+        .take(count as usize)
+        .cloned()
+        .collect(),
+    )
+  }
+
+  async fn video(&self, video_id: &str) -> google_youtube3::Result<Option<Video>> {
+    if let Some(e) = self.lookup_errors.lock().unwrap().pop_front() {
+      return Err(e);
+    }
+
+    let uploads = self.uploads.lock().unwrap();
+    let Some(upload) = uploads.iter().find(|video| video.id == video_id) else {
+      return Ok(None);
+    };
+    let privacy_status = self.privacy_statuses.lock().unwrap().get(video_id).cloned();
+
+    Ok(Some(Video {
+      id: Some(video_id.into()),
+      snippet: Some(VideoSnippet {
+        title: Some(upload.title.clone()),
+        published_at: upload.published_at,
+        ..Default::default()
+      }),
+      status: Some(VideoStatus {
+        privacy_status: Some(privacy_status.unwrap_or_else(|| "public".into())),
+        ..Default::default()
+      }),
+      ..Default::default()
+    }))
+  }
+
+  async fn is_short(&self, video_id: &str) -> google_youtube3::Result<bool> {
+    Ok(self.shorts.lock().unwrap().contains(video_id))
+  }
+}
+
+#[async_trait]
+impl CommentSink for FakeYoutube {
+  async fn post(&self, video_id: &str, comment: &str, _parent_id: Option<&str>) -> google_youtube3::Result<String> {
+    if let Some(result) = self.post_results.lock().unwrap().pop_front() {
+      return result;
+    }
+
+    let mut posted = self.posted.lock().unwrap();
+    posted.push((video_id.into(), comment.into()));
+
+    Ok(format!("comment-{}", posted.len()))
+  }
+}
+
+/// The ETag of the uploads the fake API serves
+pub const UPLOADS_ETAG: &str = "\"uploads-1\"";
+
+/// Where the fake API listens, started once for all the tests
+static API_ADDR: OnceLock<SocketAddr> = OnceLock::new();
+/// Whether the first insert on the `flaky` video already failed
+static FLAKY_FAILED: AtomicBool = AtomicBool::new(false);
+
+fn api_error(status: StatusCode, reason: &str) -> Response<Body> {
+  json_response(
+    status,
+    json!({ "error": { "code": status.as_u16(), "message": reason, "errors": [{ "reason": reason }] } }),
+  )
+}
+
+fn json_response(status: StatusCode, body: Value) -> Response<Body> {
+  let mut response = Response::new(Body::from(body.to_string()));
+  *response.status_mut() = status;
+  response
+    .headers_mut()
+    .insert(CONTENT_TYPE, "application/json".parse().unwrap());
+  response
+}
+
+fn query_param<'a>(request: &'a Request<Body>, name: &str) -> Option<&'a str> {
+  request
+    .uri()
+    .query()?
+    .split('&')
+    .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+}
+
+/// The uploads playlists, by ID: `PLuploads` (a public and a private video), `PLquota`, `PLbusy` (503) and `PLdown`
+/// (502 without a JSON body, as a proxy would answer)
+fn playlist_items(request: &Request<Body>) -> Response<Body> {
+  match query_param(request, "playlistId") {
+    Some("PLuploads") => {
+      let if_none_match = request
+        .headers()
+        .get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok());
+
+      if if_none_match == Some(UPLOADS_ETAG) {
+        let mut response = Response::new(Body::empty());
+        *response.status_mut() = StatusCode::NOT_MODIFIED;
+        return response;
+      }
+
+      let mut response = json_response(
+        StatusCode::OK,
+        json!({
+          "items": [
+            {
+              "snippet": {
+                "publishedAt": "2024-01-01T13:00:00Z",
+                "title": "A new video",
+                "description": "Out now #shorts",
+                "resourceId": { "kind": "youtube#video", "videoId": "new0000001" },
+              },
+              "status": { "privacyStatus": "public" },
+            },
+            {
+              "snippet": {
+                "publishedAt": "2024-01-01T12:00:00Z",
+                "title": "Private video",
+                "resourceId": { "kind": "youtube#video", "videoId": "private001" },
+              },
+              "status": { "privacyStatus": "private" },
+            },
+          ]
+        }),
+      );
+      response.headers_mut().insert(ETAG, UPLOADS_ETAG.parse().unwrap());
+      response
+    }
+    Some("PLquota") => api_error(StatusCode::FORBIDDEN, "quotaExceeded"),
+    Some("PLbusy") => api_error(StatusCode::SERVICE_UNAVAILABLE, "backendError"),
+    Some("PLdown") => {
+      let mut response = Response::new(Body::from("Bad Gateway"));
+      *response.status_mut() = StatusCode::BAD_GATEWAY;
+      response
+    }
+    _ => api_error(StatusCode::NOT_FOUND, "playlistNotFound"),
+  }
+}
+
+/// The comment inserts, by video ID: `quota`, `disabled` (comments turned off), `limited` (429), `flaky` (503 the
+/// first time only). The others succeed.
+async fn insert_comment_thread(request: Request<Body>) -> Response<Body> {
+  let body = hyper::body::to_bytes(request.into_body()).await.unwrap_or_default();
+  let thread: Value = serde_json::from_slice(&body).unwrap_or_default();
+  let video_id = thread["snippet"]["videoId"].as_str().unwrap_or_default();
+
+  match video_id {
+    "quota" => api_error(StatusCode::FORBIDDEN, "quotaExceeded"),
+    "disabled" => api_error(StatusCode::FORBIDDEN, "commentsDisabled"),
+    "limited" => api_error(StatusCode::TOO_MANY_REQUESTS, "rateLimitExceeded"),
+    "flaky" if !FLAKY_FAILED.swap(true, Ordering::Relaxed) => {
+      api_error(StatusCode::SERVICE_UNAVAILABLE, "backendError")
+    }
+    _ => json_response(
+      StatusCode::OK,
+      json!({ "id": format!("thread-{video_id}"), "snippet": thread["snippet"] }),
+    ),
+  }
+}
+
+/// The videos, by ID: `private001` is private, the others don't exist
+fn videos(request: &Request<Body>) -> Response<Body> {
+  let items = match query_param(request, "id") {
+    Some(video_id @ "private001") => vec![json!({
+      "id": video_id,
+      "status": { "privacyStatus": "private" },
+      "snippet": { "title": "Private video", "publishedAt": "2024-01-01T12:00:00Z" },
+    })],
+    _ => Vec::new(),
+  };
+
+  json_response(StatusCode::OK, json!({ "items": items }))
+}
+
+async fn respond(request: Request<Body>) -> Result<Response<Body>, Infallible> {
+  Ok(match (request.method(), request.uri().path()) {
+    (&Method::GET, "/youtube/v3/playlistItems") => playlist_items(&request),
+    (&Method::GET, "/youtube/v3/videos") => videos(&request),
+    (&Method::POST, "/youtube/v3/commentThreads") => insert_comment_thread(request).await,
+    _ => api_error(StatusCode::NOT_FOUND, "notFound"),
+  })
+}
+
+/// A client of YouTube served locally over HTTP, for the tests to go through the real requests and responses. The
+/// server runs on its own thread, each test having its own runtime.
+pub fn api_client() -> YoutubeClient {
+  let addr = *API_ADDR.get_or_init(|| {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+      let runtime = tokio::runtime::Runtime::new().unwrap();
+
+      runtime.block_on(async {
+        let make_service = make_service_fn(|_| async { Ok::<_, Infallible>(service_fn(respond)) });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+
+        sender.send(server.local_addr()).unwrap();
+        server.await.unwrap();
+      });
+    });
+
+    receiver.recv().unwrap()
+  });
+
+  API_BASE_URL.get_or_init(|| format!("http://{addr}/"));
+
+  new_client(NoToken).unwrap()
+}
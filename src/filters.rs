@@ -0,0 +1,96 @@
+use crate::{cli::Args, youtube::is_stream_recording};
+use google_youtube3::api::Video;
+
+/// The video categories of YouTube, so they can be given by name
+const CATEGORIES: &[(&str, &str)] = &[
+  ("1", "Film & Animation"),
+  ("2", "Autos & Vehicles"),
+  ("10", "Music"),
+  ("15", "Pets & Animals"),
+  ("17", "Sports"),
+  ("19", "Travel & Events"),
+  ("20", "Gaming"),
+  ("22", "People & Blogs"),
+  ("23", "Comedy"),
+  ("24", "Entertainment"),
+  ("25", "News & Politics"),
+  ("26", "Howto & Style"),
+  ("27", "Education"),
+  ("28", "Science & Technology"),
+  ("29", "Nonprofits & Activism"),
+];
+
+fn normalize(name: &str) -> String {
+  name
+    .chars()
+    .filter(char::is_ascii_alphanumeric)
+    .map(|c| c.to_ascii_lowercase())
+    .collect()
+}
+
+/// A category ID, or the name of a category: in full (e.g. "Science & Technology") or its first word (e.g. science)
+pub fn parse_category(value: &str) -> Result<String, String> {
+  if !value.is_empty() && value.chars().all(|c| c.is_ascii_digit()) {
+    return Ok(value.into());
+  }
+
+  let value = normalize(value);
+
+  CATEGORIES
+    .iter()
+    .find(|(_, name)| {
+      normalize(name) == value
+        || name
+          .split_whitespace()
+          .next()
+          .is_some_and(|word| normalize(word) == value)
+    })
+    .map(|(id, _)| id.to_string())
+    .ok_or_else(|| {
+      format!(
+        "unknown category, expected an ID or one of {}",
+        CATEGORIES.iter().map(|(_, name)| *name).collect::<Vec<_>>().join(", ")
+      )
+    })
+}
+
+fn category_name(id: &str) -> String {
+  CATEGORIES
+    .iter()
+    .find(|(category_id, _)| *category_id == id)
+    .map_or_else(|| format!("category {id}"), |(_, name)| name.to_string())
+}
+
+/// Why the video is left out by --skip-vods, --category, --exclude-category, --tag and --exclude-tag, `None` when
+/// it's not
+pub fn skip_reason(args: &Args, video: &Video) -> Option<String> {
+  if args.skip_vods && is_stream_recording(video) {
+    return Some("it's the recording of a finished live stream".into());
+  }
+
+  let snippet = video.snippet.as_ref();
+  let category_id = snippet.and_then(|snippet| snippet.category_id.as_deref());
+  let tags = snippet.and_then(|snippet| snippet.tags.as_deref()).unwrap_or_default();
+  let has_tag = |wanted: &String| tags.iter().any(|tag| tag.eq_ignore_ascii_case(wanted));
+
+  if !args.category.is_empty() && !category_id.is_some_and(|id| args.category.iter().any(|wanted| wanted == id)) {
+    return Some(match category_id {
+      Some(id) => format!("it's in {}, not a category of --category", category_name(id)),
+      None => "its category is unknown".into(),
+    });
+  }
+
+  if let Some(id) = category_id.filter(|id| args.exclude_category.iter().any(|excluded| excluded == id)) {
+    return Some(format!("it's in {}", category_name(id)));
+  }
+
+  if !args.tag.is_empty() && !args.tag.iter().any(has_tag) {
+    return Some("it has none of the tags of --tag".into());
+  }
+
+  args
+    .exclude_tag
+    .iter()
+    .find(|excluded| has_tag(excluded))
+    .map(|excluded| format!("it's tagged {excluded}"))
+}
@@ -0,0 +1,296 @@
+use crate::youtube::{build_https_client, HttpsClient};
+use google_youtube3::hyper::{
+  self,
+  header::{ACCEPT_ENCODING, CONTENT_TYPE, HOST, RETRY_AFTER},
+  service::{make_service_fn, service_fn},
+  Body, Method, Request, Response, Server, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+  collections::HashMap,
+  convert::Infallible,
+  error::Error,
+  fs, io,
+  net::SocketAddr,
+  path::{Path, PathBuf},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+  },
+  time::Instant,
+};
+
+/// Where the recorded calls really go
+const API_URL: &str = "https://youtube.googleapis.com";
+
+/// Opening a connection ahead of posting requests the root, which isn't an API call
+const WARM_UP_PATH: &str = "/";
+
+/// A call to the API and its answer, as saved in the fixtures file
+#[derive(Serialize, Deserialize)]
+struct Exchange {
+  /// Milliseconds since the recording started
+  at_ms: u64,
+  method: String,
+  /// Path and query, without the API key
+  uri: String,
+  status: u16,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  retry_after: Option<String>,
+  /// The JSON answer, or its raw text when it isn't JSON
+  #[serde(default)]
+  body: Value,
+}
+
+/// The path and query of the request, without the API key so it's neither saved nor needed to replay
+fn normalize(request: &Request<Body>) -> String {
+  let uri = request.uri();
+  let query: Vec<&str> = uri
+    .query()
+    .unwrap_or_default()
+    .split('&')
+    .filter(|pair| !pair.is_empty() && !pair.starts_with("key="))
+    .collect();
+
+  match query.as_slice() {
+    [] => uri.path().into(),
+    query => format!("{}?{}", uri.path(), query.join("&")),
+  }
+}
+
+fn json_response(status: StatusCode, body: &Value) -> Response<Body> {
+  let body = match body {
+    Value::Null => Body::empty(),
+    Value::String(text) => Body::from(text.clone()),
+    body => Body::from(body.to_string()),
+  };
+  let mut response = Response::new(body);
+
+  *response.status_mut() = status;
+  response
+    .headers_mut()
+    .insert(CONTENT_TYPE, "application/json".parse().unwrap());
+
+  response
+}
+
+struct Recorder {
+  client: HttpsClient,
+  path: PathBuf,
+  started_at: Instant,
+  exchanges: Mutex<Vec<Exchange>>,
+}
+
+impl Recorder {
+  async fn forward(&self, request: Request<Body>) -> Result<Response<Body>, Box<dyn Error + Send + Sync>> {
+    let uri = normalize(&request);
+    let method = request.method().to_string();
+    let (mut parts, body) = request.into_parts();
+    let warm_up = parts.uri.path() == WARM_UP_PATH;
+
+    parts.uri = format!(
+      "{API_URL}{}",
+      parts.uri.path_and_query().map_or("/", |path| path.as_str())
+    )
+    .parse()?;
+    parts.headers.remove(HOST);
+    // Saved as is, the answer has to be readable
+    parts.headers.remove(ACCEPT_ENCODING);
+
+    let response = self.client.request(Request::from_parts(parts, body)).await?;
+
+    if warm_up {
+      return Ok(response);
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = hyper::body::to_bytes(body).await?;
+    let exchange = Exchange {
+      at_ms: self.started_at.elapsed().as_millis() as u64,
+      method,
+      uri,
+      status: parts.status.as_u16(),
+      retry_after: parts
+        .headers
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from),
+      body: match body.as_ref() {
+        [] => Value::Null,
+        body => serde_json::from_slice(body).unwrap_or_else(|_| String::from_utf8_lossy(body).into()),
+      },
+    };
+
+    // Saved on every call, so an interrupted run still leaves its fixtures behind
+    let mut exchanges = self.exchanges.lock().unwrap();
+    exchanges.push(exchange);
+    fs::write(&self.path, serde_json::to_string_pretty(&*exchanges)?)?;
+
+    Ok(Response::from_parts(parts, Body::from(body)))
+  }
+
+  async fn handle(self: Arc<Self>, request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    Ok(self.forward(request).await.unwrap_or_else(|e| {
+      eprintln!("[record] Failed to reach the API: {e}");
+      json_response(
+        StatusCode::BAD_GATEWAY,
+        &json!({ "error": { "code": 502, "message": e.to_string() } }),
+      )
+    }))
+  }
+}
+
+/// Starts a local proxy to the API on a random port, saving every call going through it in the fixtures file
+pub fn record(path: &Path) -> Result<SocketAddr, Box<dyn Error>> {
+  let recorder = Arc::new(Recorder {
+    client: build_https_client()?,
+    path: path.into(),
+    started_at: Instant::now(),
+    exchanges: Mutex::new(Vec::new()),
+  });
+  let make_service = make_service_fn(move |_| {
+    let recorder = recorder.clone();
+    async move { Ok::<_, Infallible>(service_fn(move |request| recorder.clone().handle(request))) }
+  });
+  let server = Server::try_bind(&([127, 0, 0, 1], 0).into())?.serve(make_service);
+  let addr = server.local_addr();
+
+  println!("[record] Saving the API calls in {}", path.display());
+
+  tokio::spawn(async move {
+    if let Err(e) = server.await {
+      eprintln!("[record] Server error: {e}");
+    }
+  });
+
+  Ok(addr)
+}
+
+struct Player {
+  exchanges: Vec<Exchange>,
+  started_at: Instant,
+  speed: u32,
+  /// The next answer of the calls other than reads, by call
+  cursors: Mutex<HashMap<String, usize>>,
+  over: AtomicBool,
+}
+
+impl Player {
+  /// Where the recording is at, sped up
+  fn clock_ms(&self) -> u64 {
+    self.started_at.elapsed().as_millis() as u64 * u64::from(self.speed)
+  }
+
+  /// Reads get the latest answer recorded by now, as the API would have given it then. A 304 means it didn't change,
+  /// so the answer before it stands, given in full whatever the ETag of the call. Other calls (posting, deleting) get
+  /// the recorded answers one after the other, the last one repeating.
+  fn answer(&self, method: &Method, uri: &str) -> Option<&Exchange> {
+    let key = format!("{method} {uri}");
+    let recorded = self
+      .exchanges
+      .iter()
+      .filter(|exchange| exchange.method == method.as_str() && exchange.uri == uri);
+
+    if method != Method::GET {
+      let recorded: Vec<&Exchange> = recorded.collect();
+      let mut cursors = self.cursors.lock().unwrap();
+      let cursor = cursors.entry(key).or_default();
+      let exchange = recorded.get(*cursor).or(recorded.last()).copied();
+
+      *cursor += 1;
+
+      return exchange;
+    }
+
+    let clock_ms = self.clock_ms();
+    let answers: Vec<&Exchange> = recorded
+      .filter(|exchange| exchange.status != StatusCode::NOT_MODIFIED.as_u16())
+      .collect();
+
+    answers
+      .iter()
+      .rev()
+      .find(|exchange| exchange.at_ms <= clock_ms)
+      .or(answers.first())
+      .copied()
+  }
+
+  async fn handle(self: Arc<Self>, request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let uri = normalize(&request);
+    let method = request.method().clone();
+
+    if !self.over.load(Ordering::Relaxed) && self.exchanges.last().is_some_and(|last| self.clock_ms() > last.at_ms) {
+      self.over.store(true, Ordering::Relaxed);
+      println!("[replay] The recording is over, the last answers keep being replayed");
+    }
+
+    if method == Method::POST && uri.contains("/comment") {
+      let body = hyper::body::to_bytes(request.into_body()).await.unwrap_or_default();
+      let comment: Value = serde_json::from_slice(&body).unwrap_or_default();
+      let snippet = &comment["snippet"];
+      let text = snippet["topLevelComment"]["snippet"]["textOriginal"]
+        .as_str()
+        .or(snippet["textOriginal"].as_str())
+        .unwrap_or_default();
+
+      println!("[replay] Received the comment {text:?}, nothing was actually posted");
+    }
+
+    let Some(exchange) = self.answer(&method, &uri) else {
+      if uri != WARM_UP_PATH {
+        println!("[replay] No answer was recorded for {method} {uri}");
+      }
+
+      return Ok(json_response(
+        StatusCode::NOT_FOUND,
+        &json!({ "error": { "code": 404, "message": "No answer was recorded for this call" } }),
+      ));
+    };
+
+    let mut response = json_response(
+      StatusCode::from_u16(exchange.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+      &exchange.body,
+    );
+
+    if let Some(retry_after) = exchange.retry_after.as_ref().and_then(|value| value.parse().ok()) {
+      response.headers_mut().insert(RETRY_AFTER, retry_after);
+    }
+
+    Ok(response)
+  }
+}
+
+/// Starts a fake API on a random local port, answering from the fixtures file `speed` times faster than recorded
+pub fn replay(path: &Path, speed: u32) -> Result<SocketAddr, Box<dyn Error>> {
+  let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+  let exchanges: Vec<Exchange> = serde_json::from_str(&contents)
+    .map_err(|e| io::Error::other(format!("Invalid fixtures {}: {e}", path.display())))?;
+  let count = exchanges.len();
+  let player = Arc::new(Player {
+    exchanges,
+    started_at: Instant::now(),
+    speed,
+    cursors: Mutex::new(HashMap::new()),
+    over: AtomicBool::new(false),
+  });
+  let make_service = make_service_fn(move |_| {
+    let player = player.clone();
+    async move { Ok::<_, Infallible>(service_fn(move |request| player.clone().handle(request))) }
+  });
+  let server = Server::try_bind(&([127, 0, 0, 1], 0).into())?.serve(make_service);
+  let addr = server.local_addr();
+
+  println!(
+    "[replay] Replaying {count} API calls from {} at {speed}x speed",
+    path.display()
+  );
+
+  tokio::spawn(async move {
+    if let Err(e) = server.await {
+      eprintln!("[replay] Server error: {e}");
+    }
+  });
+
+  Ok(addr)
+}
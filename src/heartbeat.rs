@@ -0,0 +1,48 @@
+use crate::{console, youtube::HttpsClient};
+use google_youtube3::hyper::{Body, Method, Request};
+
+/// Pings a dead-man's-switch service (healthchecks.io and friends) so a watcher that silently died gets noticed.
+pub struct Heartbeat {
+  url: String,
+  client: HttpsClient,
+}
+
+impl Heartbeat {
+  pub fn new(url: &str, client: HttpsClient) -> Self {
+    Self {
+      url: url.trim_end_matches('/').into(),
+      client,
+    }
+  }
+
+  /// Signals a successful poll cycle.
+  pub async fn ping(&self) {
+    self.send(&self.url, Body::empty()).await;
+  }
+
+  /// Signals a fatal error, sending the error message as the request body.
+  pub async fn fail(&self, error: &str) {
+    self
+      .send(&format!("{}/fail", self.url), Body::from(error.to_owned()))
+      .await;
+  }
+
+  async fn send(&self, url: &str, body: Body) {
+    let request = match Request::builder().method(Method::POST).uri(url).body(body) {
+      Ok(request) => request,
+      Err(e) => {
+        console::info(format!("Invalid heartbeat URL {url}: {e}"));
+        return;
+      }
+    };
+
+    // A failing heartbeat must never take the watcher down with it
+    match self.client.request(request).await {
+      Ok(response) if !response.status().is_success() => {
+        console::info(format!("Heartbeat ping to {url} returned {}", response.status()))
+      }
+      Ok(_) => {}
+      Err(e) => console::error(format!("Failed to ping heartbeat URL {url}: {e}")),
+    }
+  }
+}
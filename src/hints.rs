@@ -0,0 +1,132 @@
+use crate::{
+  cli::{Args, HintSource},
+  console, detect,
+  status::SharedWatchState,
+  youtube::{build_https_client, HttpsClient},
+};
+use std::{
+  collections::HashSet,
+  error::Error,
+  time::{Duration, Instant},
+};
+use tokio::{task::JoinHandle, time::sleep};
+
+/// Starts a burst of API polls: the watcher wakes up right away, then polls at --burst-interval until --hint-burst is
+/// over. A hint coming during a burst extends it.
+pub fn hint(state: &SharedWatchState, reason: &str) {
+  console::info(format!("Hint: {reason}, confirming through the API"));
+  state.lock().unwrap().hinted_at = Some(Instant::now());
+}
+
+/// Whether a hint came less than `burst` ago
+pub fn is_bursting(state: &SharedWatchState, burst: Duration) -> bool {
+  state
+    .lock()
+    .unwrap()
+    .hinted_at
+    .is_some_and(|hinted_at| hinted_at.elapsed() < burst)
+}
+
+/// The video IDs the source lists now, newest first. `None` when it didn't change since the last check.
+async fn check(
+  source: HintSource,
+  client: &HttpsClient,
+  channel_id: &str,
+  etag: &mut Option<String>,
+) -> Result<Option<Vec<String>>, Box<dyn Error>> {
+  match source {
+    HintSource::Rss => Ok(
+      detect::rss_videos(client, channel_id, etag)
+        .await?
+        .map(|videos| videos.into_iter().map(|video| video.id).collect()),
+    ),
+    #[cfg(feature = "web-detect")]
+    HintSource::Web => Ok(Some(
+      crate::web::channel_videos(client, channel_id, 5)
+        .await?
+        .into_iter()
+        .map(|video| video.id)
+        .collect(),
+    )),
+  }
+}
+
+fn source_name(source: HintSource) -> &'static str {
+  match source {
+    HintSource::Rss => "the RSS feed",
+    #[cfg(feature = "web-detect")]
+    HintSource::Web => "the Videos tab",
+  }
+}
+
+/// Checks a source every --hint-interval, hinting when it lists a video it didn't list before. What it lists the
+/// first time is known already.
+async fn watch_source(
+  source: HintSource,
+  client: HttpsClient,
+  channel_id: String,
+  interval: Duration,
+  state: SharedWatchState,
+) {
+  let mut known: Option<HashSet<String>> = None;
+  let mut etag = None;
+  let mut failing = false;
+
+  loop {
+    match check(source, &client, &channel_id, &mut etag).await {
+      Ok(Some(video_ids)) => {
+        failing = false;
+
+        if let Some(known) = &mut known {
+          if let Some(video_id) = video_ids.iter().find(|video_id| !known.contains(*video_id)) {
+            hint(&state, &format!("{} lists video {video_id}", source_name(source)));
+          }
+
+          known.extend(video_ids);
+        } else {
+          known = Some(video_ids.into_iter().collect());
+        }
+      }
+      Ok(None) => failing = false,
+      // Reported once until it works again, it's only a hint
+      Err(e) if !failing => {
+        failing = true;
+        console::warning(format!("Failed to check {} for a hint: {e}", source_name(source)));
+      }
+      Err(_) => {}
+    }
+
+    sleep(interval).await;
+  }
+}
+
+/// The tasks checking the sources of --hint, stopped when dropped
+pub struct Hints {
+  tasks: Vec<JoinHandle<()>>,
+}
+
+impl Hints {
+  pub fn start(args: &Args, state: &SharedWatchState) -> Result<Self, Box<dyn Error>> {
+    let mut tasks = Vec::new();
+
+    for &source in &args.hint {
+      tasks.push(tokio::spawn(watch_source(
+        source,
+        build_https_client()?,
+        args.channel_id.clone(),
+        args.hint_interval,
+        state.clone(),
+      )));
+    }
+
+    Ok(Self { tasks })
+  }
+}
+
+impl Drop for Hints {
+  fn drop(&mut self) {
+    for task in &self.tasks {
+      task.abort();
+    }
+  }
+}
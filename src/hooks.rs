@@ -0,0 +1,120 @@
+use crate::{console, notify::Event};
+use std::{
+  process::Stdio,
+  sync::atomic::{AtomicBool, Ordering},
+};
+use tokio::process::Command;
+
+/// User commands spawned on lifecycle events, the escape hatch for integrations yfc doesn't ship.
+pub struct Hooks {
+  on_new_video: Option<String>,
+  on_comment_posted: Option<String>,
+  on_error: Option<String>,
+  /// Whether the error hook already ran for a failed post, which then also stops the watcher
+  error_reported: AtomicBool,
+}
+
+impl Hooks {
+  pub fn new(on_new_video: Option<String>, on_comment_posted: Option<String>, on_error: Option<String>) -> Self {
+    Self {
+      on_new_video,
+      on_comment_posted,
+      on_error,
+      error_reported: AtomicBool::new(false),
+    }
+  }
+
+  pub fn run(&self, event: &Event) {
+    let hook = match event {
+      Event::VideoDetected { .. } => &self.on_new_video,
+      Event::CommentPosted { .. } => &self.on_comment_posted,
+      Event::PostFailed { .. } => &self.on_error,
+      Event::WaitLimitReached
+      | Event::QuotaExceeded { .. }
+      | Event::CommentEngagement { .. }
+      | Event::VideoObserved { .. } => &None,
+    };
+
+    let Some(command) = hook else {
+      return;
+    };
+
+    if let Event::PostFailed { .. } = event {
+      self.error_reported.store(true, Ordering::Relaxed);
+    }
+
+    let mut env = vec![("YFC_EVENT", event.name().to_string())];
+
+    if let Some(video_id) = event.video_id() {
+      env.push(("YFC_VIDEO_ID", video_id.into()));
+    }
+
+    match event {
+      Event::VideoDetected {
+        title, published_at, ..
+      } => {
+        env.push(("YFC_VIDEO_TITLE", title.to_string()));
+
+        if let Some(published_at) = published_at {
+          env.push(("YFC_VIDEO_PUBLISHED_AT", published_at.to_rfc3339()));
+        }
+      }
+      Event::CommentPosted {
+        comment_id,
+        latency,
+        publish_latency,
+        ..
+      } => {
+        env.push(("YFC_COMMENT_ID", comment_id.to_string()));
+        env.push(("YFC_LATENCY_MS", latency.as_millis().to_string()));
+
+        if let Some(publish_latency) = publish_latency {
+          env.push(("YFC_PUBLISH_LATENCY_MS", publish_latency.as_millis().to_string()));
+        }
+      }
+      Event::PostFailed { error, .. } => env.push(("YFC_ERROR", error.to_string())),
+      Event::WaitLimitReached
+      | Event::QuotaExceeded { .. }
+      | Event::CommentEngagement { .. }
+      | Event::VideoObserved { .. } => {}
+    }
+
+    spawn(command, env);
+  }
+
+  /// Runs the error hook for errors that stop the watcher, unless it already ran for the same failure
+  pub fn run_fatal(&self, error: &str) {
+    if self.error_reported.load(Ordering::Relaxed) {
+      return;
+    }
+
+    if let Some(command) = &self.on_error {
+      spawn(
+        command,
+        vec![("YFC_EVENT", "fatal_error".into()), ("YFC_ERROR", error.into())],
+      );
+    }
+  }
+}
+
+/// Starts the command without waiting for it, the watcher must not be slowed down by a hook
+fn spawn(command: &str, env: Vec<(&str, String)>) {
+  // The hook writes to the same terminal, not after the countdown line
+  console::clear_countdown();
+
+  #[cfg(unix)]
+  let mut shell = Command::new("sh");
+  #[cfg(unix)]
+  shell.arg("-c").arg(command);
+  // Passed as is, cmd doesn't undo the quoting of a regular argument
+  #[cfg(windows)]
+  let mut shell = Command::new("cmd");
+  #[cfg(windows)]
+  shell.arg("/C").raw_arg(command);
+
+  let result = shell.envs(env).stdin(Stdio::null()).spawn();
+
+  if let Err(e) = result {
+    console::error(format!("Failed to run the hook `{command}`: {e}"));
+  }
+}
@@ -0,0 +1,104 @@
+use crate::{
+  console,
+  token_storage::{self, StoredToken},
+};
+use async_trait::async_trait;
+use google_youtube3::oauth2::storage::{TokenInfo, TokenStorage};
+use keyring::Entry;
+use std::{fs, io, path::PathBuf, sync::Mutex};
+
+/// Service name the entries are stored under in the keychain
+const SERVICE: &str = "yfc";
+
+fn secret_entry(client_id: &str) -> keyring::Result<Entry> {
+  Entry::new(SERVICE, &format!("client-secret:{client_id}"))
+}
+
+/// The client secret stored for the client ID with `yfc auth store-secret`
+pub fn client_secret(client_id: &str) -> Option<String> {
+  secret_entry(client_id).and_then(|entry| entry.get_password()).ok()
+}
+
+pub fn store_client_secret(client_id: &str, client_secret: &str) -> keyring::Result<()> {
+  secret_entry(client_id)?.set_password(client_secret)
+}
+
+/// Keeps the tokens in the OS keychain, one entry per token file. When the keychain can't be used, the tokens are kept
+/// in the file instead, and a token still in the file (from before the keychain) is picked up until it's renewed. The
+/// tokens are read once and kept in memory, the keychain being slow to ask (D-Bus on Linux).
+pub struct KeychainStorage {
+  path: PathBuf,
+  tokens: Mutex<Option<Vec<StoredToken>>>,
+}
+
+impl KeychainStorage {
+  pub fn new(path: PathBuf) -> Self {
+    Self {
+      path,
+      tokens: Mutex::default(),
+    }
+  }
+
+  fn entry(&self) -> keyring::Result<Entry> {
+    Entry::new(SERVICE, &self.path.to_string_lossy())
+  }
+
+  fn load(&self) -> Vec<StoredToken> {
+    let contents = match self.entry().and_then(|entry| entry.get_password()) {
+      Ok(contents) => Some(contents),
+      Err(_) => fs::read_to_string(&self.path).ok(),
+    };
+
+    contents
+      .and_then(|contents| serde_json::from_str(&contents).ok())
+      .unwrap_or_default()
+  }
+
+  /// Forgets the tokens, in the keychain and in the file alike, for the next login to start over
+  pub fn clear(&self) -> io::Result<()> {
+    *self.tokens.lock().unwrap() = None;
+
+    match self.entry().and_then(|entry| entry.delete_credential()) {
+      Ok(()) | Err(keyring::Error::NoEntry) => {}
+      Err(e) => return Err(io::Error::other(e)),
+    }
+
+    match fs::remove_file(&self.path) {
+      Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+      _ => Ok(()),
+    }
+  }
+}
+
+#[async_trait]
+impl TokenStorage for KeychainStorage {
+  async fn set(&self, scopes: &[&str], token: TokenInfo) -> anyhow::Result<()> {
+    let mut cached = self.tokens.lock().unwrap();
+    let tokens = cached.get_or_insert_with(|| self.load());
+    token_storage::upsert(tokens, scopes, token);
+
+    let contents = serde_json::to_string(tokens)?;
+
+    match self.entry().and_then(|entry| entry.set_password(&contents)) {
+      Ok(()) => {
+        // The keychain has it now, a plain-text copy is what it's meant to avoid
+        let _ = fs::remove_file(&self.path);
+      }
+      Err(e) => {
+        console::info(format!(
+          "Failed to store the token in the keychain, keeping it in {}: {e}",
+          self.path.display()
+        ));
+        fs::write(&self.path, contents)?;
+      }
+    }
+
+    Ok(())
+  }
+
+  async fn get(&self, scopes: &[&str]) -> Option<TokenInfo> {
+    let mut cached = self.tokens.lock().unwrap();
+
+    token_storage::find(cached.get_or_insert_with(|| self.load()), scopes)
+  }
+}
@@ -1,238 +1,941 @@
-use clap::Parser;
-use core::f64;
-use dirs::cache_dir;
-use google_youtube3::{
-  api::{Comment, CommentSnippet, CommentThread, CommentThreadSnippet},
-  hyper::{client::HttpConnector, Client},
-  hyper_rustls::{HttpsConnector, HttpsConnectorBuilder},
-  oauth2::{ApplicationSecret, InstalledFlowAuthenticator, InstalledFlowReturnMethod},
-  YouTube,
+mod accounts;
+mod analyze;
+mod api;
+mod auth;
+mod bench;
+mod channels;
+mod check;
+mod cli;
+mod clock;
+mod comment;
+mod config;
+mod console;
+mod control;
+mod db;
+mod demo;
+mod detect;
+mod email;
+mod events;
+mod exit;
+#[cfg(test)]
+mod fake;
+mod filters;
+mod fixtures;
+mod heartbeat;
+mod hints;
+mod hooks;
+#[cfg(feature = "keyring")]
+mod keychain;
+mod notify;
+#[cfg(feature = "otel")]
+mod otel;
+mod playlists;
+mod projects;
+mod quota;
+mod ratelimit;
+mod reload;
+mod report;
+mod safe_mode;
+mod sanitize;
+mod schedule;
+mod search;
+#[cfg(windows)]
+mod service;
+mod shutdown;
+mod source;
+mod state;
+mod stats;
+mod status;
+mod summary;
+mod systemd;
+mod telemetry;
+mod token_storage;
+mod tui;
+mod uploads;
+mod watcher;
+#[cfg(feature = "web-detect")]
+mod web;
+mod youtube;
+
+use accounts::Accounts;
+use auth::authenticate_account;
+use auth::get_client_secret;
+use auth::list_channels;
+use bench::Bench;
+use clap::{CommandFactory, Parser};
+use clap_mangen::Man;
+use cli::load_config;
+use cli::load_dotenv;
+use cli::parse_cli;
+use cli::replays;
+use cli::subcommand;
+use cli::with_config_options;
+use cli::with_options;
+use cli::with_search_key;
+use cli::AgeRestrictedPolicy;
+use cli::Args;
+use cli::AuthCommand;
+use cli::AuthFlow;
+use cli::Cli;
+use cli::Command;
+use cli::ConfigCommand;
+use cli::DbCommand;
+#[cfg(windows)]
+use cli::ServiceCommand;
+use comment::{
+  CommentContext, CommentSource, CommentSourceConfig, FileSource, LanguageSource, ReloadableSource, StaticText,
 };
+use config::{Config, NotifyConfig};
+use db::History;
+use dirs::cache_dir;
+use exit::Failure;
+use futures::future::join_all;
+use google_youtube3::client::NoToken;
+use heartbeat::Heartbeat;
+use hooks::Hooks;
+use notify::Notifier;
+use projects::ReadProjects;
+use report::Reporter;
+use safe_mode::SafeMode;
+use sanitize::SanitizePolicy;
+use shutdown::Interrupted;
+use status::StatusFormat;
 use std::{
+  cmp::Reverse,
+  collections::BTreeMap,
+  env,
   error::Error,
-  fs, io,
-  path::PathBuf,
-  time::{Duration, Instant},
+  ffi::OsString,
+  fs,
+  io::{self, Read},
+  path::{Path, PathBuf},
+  process,
+  sync::{Arc, OnceLock},
+  time::Duration,
 };
-use tokio::time::sleep;
-
-#[derive(Parser)]
-#[command(
-  name = "yfc",
-  about = "A tool to create a new comment on YouTube when a new video is published for the specified channel"
-)]
-struct Args {
-  /// Google client ID
-  #[arg(long)]
-  google_client_id: String,
-
-  /// Google client secret
-  #[arg(long)]
-  google_client_secret: String,
-
-  /// The comment body
-  #[arg(long)]
-  comment: String,
-
-  /// YouTube channel ID
-  #[arg(long)]
-  channel_id: String,
-
-  /// Pool interval (in seconds)
-  #[arg(long, default_value = "60")]
-  pool_interval: u64,
-
-  /// Max wait time (in minutes)
-  #[arg(long, required = false)]
-  wait_limit: Option<u32>,
-}
-
-type YoutubeClient = YouTube<HttpsConnector<HttpConnector>>;
-
-async fn get_uploads_playlist_id(client: &YoutubeClient, channel_id: &str) -> Option<String> {
-  let response = client
-    .channels()
-    .list(&vec!["contentDetails".into()])
-    .add_id(channel_id)
-    .doit()
-    .await;
-
-  if let Ok((_, result)) = response {
-    result.items.and_then(|items| {
-      items
-        .first()
-        .and_then(|item| item.content_details.as_ref())
-        .and_then(|details| details.related_playlists.as_ref())
-        .and_then(|playlists| playlists.uploads.clone())
-    })
-  } else {
-    None
-  }
-}
-
-async fn get_latest_video_id(client: &YoutubeClient, playlist_id: &str) -> Option<String> {
-  let response = client
-    .playlist_items()
-    .list(&vec!["snippet".into()])
-    .playlist_id(playlist_id)
-    .max_results(1)
-    .doit()
-    .await;
-
-  if let Ok((_, result)) = response {
-    result
-      .items
-      .and_then(|items| items.first().cloned())
-      .and_then(|item| item.snippet)
-      .and_then(|snippet| {
-        // Check for #shorts in the description
-        if snippet.description.unwrap_or_default().contains("#shorts") {
-          println!("Latest video is a short");
-          return None;
+use summary::RunSummary;
+use telemetry::Telemetry;
+use watcher::watch_channel;
+use watcher::Watcher;
+use youtube::build_http_client;
+use youtube::build_https_client;
+use youtube::delete_comment;
+use youtube::new_client;
+use youtube::YoutubeClient;
+use youtube::API_BASE_URL;
+
+/// Overrides the data directory, e.g. to keep the demo away from the real state
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Where yfc keeps its own files (token, reports, database), set when it starts
+fn get_data_dir() -> PathBuf {
+  DATA_DIR
+    .get()
+    .expect("the data directory is set before any command runs")
+    .clone()
+}
+
+#[tokio::main]
+async fn main() {
+  if let Err(e) = run_command().await {
+    // How the watcher stopped is already in the log and the summary
+    if !e.is::<Interrupted>() && !matches!(e.downcast_ref(), Some(Failure::WaitLimitReached)) {
+      console::clear_countdown();
+      eprintln!("Error: {e}");
+    }
+
+    process::exit(exit::code(e.as_ref()));
+  }
+}
+
+async fn run_command() -> Result<(), Box<dyn Error>> {
+  load_dotenv()?;
+
+  // --poll-interval used to be --pool-interval
+  if let (None, Some(value)) = (env::var_os("YFC_POLL_INTERVAL"), env::var_os("YFC_POOL_INTERVAL")) {
+    env::set_var("YFC_POLL_INTERVAL", value);
+  }
+
+  let mut command_line: Vec<OsString> = env::args_os().collect();
+
+  // Replaying doesn't log in, but the client is still required
+  if replays(&command_line) {
+    command_line = with_options(
+      command_line,
+      vec![
+        "--google-client-id=replay".into(),
+        "--google-client-secret=replay".into(),
+      ],
+    );
+  }
+
+  if subcommand(&command_line).is_none() {
+    let config = load_config(&command_line)?;
+
+    if !config.channels.is_empty() {
+      return run_channels(config, command_line).await;
+    }
+  }
+
+  let cli = parse_cli(with_config_options(command_line.clone())?);
+
+  // The demo and --replay always use a throwaway directory, they wipe it
+  let throwaway =
+    matches!(cli.command, Some(Command::Demo { .. })) || cli.args.as_ref().is_some_and(|args| args.replay.is_some());
+
+  if !throwaway {
+    set_data_dir(cli.data_dir)?;
+  }
+
+  match cli.command {
+    Some(Command::Status { addr, format }) => print_status(&addr, format).await,
+    Some(Command::Push { video_id, addr, token }) => {
+      api::push(&build_http_client()?, &addr, &token, &video_id).await?;
+      println!("Video {video_id} was pushed, the watcher posts on it right away");
+      Ok(())
+    }
+    Some(Command::History {
+      channel_id,
+      since,
+      limit,
+    }) => db::print_history(&get_data_dir(), channel_id.as_deref(), since, limit),
+    Some(Command::Stats { since, format }) => stats::print(&get_data_dir(), since, format),
+    Some(Command::Demo { upload_after }) => run_demo(upload_after).await,
+    Some(Command::Ctl { socket, command }) => {
+      println!("{}", control::send(&socket, &command).await?);
+      Ok(())
+    }
+    Some(Command::Config {
+      command: ConfigCommand::Migrate { line },
+    }) => migrate_config(line),
+    Some(Command::Db {
+      command: DbCommand::Check { repair, retention },
+    }) => {
+      if db::check(&get_data_dir(), repair, retention)? {
+        process::exit(1);
+      }
+
+      Ok(())
+    }
+    Some(Command::Auth {
+      command:
+        AuthCommand::ListChannels {
+          google_client_id,
+          google_client_secret,
+          account,
+          auth_flow,
+        },
+    }) => {
+      let client_secret = get_client_secret(&google_client_id, google_client_secret.as_deref())?;
+
+      list_channels(&google_client_id, &client_secret, account, auth_flow).await
+    }
+    #[cfg(feature = "keyring")]
+    Some(Command::Auth {
+      command: AuthCommand::StoreSecret { google_client_id },
+    }) => {
+      let mut client_secret = String::new();
+      io::stdin().read_line(&mut client_secret)?;
+      keychain::store_client_secret(&google_client_id, client_secret.trim())
+        .map_err(|e| format!("Failed to store the client secret in the keychain: {e}"))?;
+      println!("The client secret of {google_client_id} is in the keychain now");
+      Ok(())
+    }
+    Some(Command::InstallService {
+      config,
+      name,
+      watchdog,
+      force,
+    }) => {
+      let config = config
+        .or_else(config::get_default_config_path)
+        .ok_or("Could not find the config directory")?;
+
+      systemd::install(&name, &config, &get_data_dir(), watchdog, force)
+    }
+    Some(Command::Check { args }) => check::run(&with_search_key(*args)).await,
+    Some(Command::ListUploads {
+      channel,
+      limit,
+      api_key,
+      google_client_id,
+      google_client_secret,
+      account,
+      auth_flow,
+    }) => {
+      let client = read_client(
+        api_key.as_deref(),
+        google_client_id,
+        google_client_secret.as_deref(),
+        account.as_deref(),
+        auth_flow,
+      )
+      .await?;
+
+      uploads::list(&client, &channel, limit, api_key.as_deref()).await
+    }
+    Some(Command::Analyze {
+      channel,
+      limit,
+      burst_window,
+      api_key,
+      google_client_id,
+      google_client_secret,
+      account,
+      auth_flow,
+    }) => {
+      let client = read_client(
+        api_key.as_deref(),
+        google_client_id,
+        google_client_secret.as_deref(),
+        account.as_deref(),
+        auth_flow,
+      )
+      .await?;
+
+      analyze::run(
+        &client,
+        &get_data_dir(),
+        &channel,
+        limit,
+        api_key.as_deref(),
+        burst_window,
+      )
+      .await
+    }
+    Some(Command::Bench {
+      channel,
+      iterations,
+      spacing,
+      video_id,
+      post,
+      comment,
+      api_key,
+      google_client_id,
+      google_client_secret,
+      account,
+      auth_flow,
+    }) => {
+      let poster = match google_client_id {
+        Some(google_client_id) => {
+          let client_secret = get_client_secret(&google_client_id, google_client_secret.as_deref())?;
+
+          Some(
+            authenticate_account(
+              &google_client_id,
+              &client_secret,
+              account.as_deref().unwrap_or_default(),
+              auth_flow,
+            )
+            .await?,
+          )
         }
+        None => None,
+      };
+      let anonymous_client = match &api_key {
+        Some(_) => Some(new_client(NoToken)?),
+        None => None,
+      };
+      let reader = anonymous_client
+        .as_ref()
+        .or(poster.as_ref())
+        .ok_or("--api-key or --google-client-id is needed to poll")?;
+      let bench = Bench {
+        reader,
+        api_key: api_key.as_deref(),
+        poster: poster.as_ref().zip(video_id.as_deref()),
+        comment: post.then_some(comment.as_str()),
+        iterations,
+        spacing,
+      };
+
+      bench::run(bench, &channel).await
+    }
+    Some(Command::DeleteComment {
+      comment_id,
+      google_client_id,
+      google_client_secret,
+      account,
+      auth_flow,
+    }) => {
+      let client_secret = get_client_secret(&google_client_id, google_client_secret.as_deref())?;
+      let client = authenticate_account(
+        &google_client_id,
+        &client_secret,
+        account.as_deref().unwrap_or_default(),
+        auth_flow,
+      )
+      .await?;
+
+      delete_comment(&client, &comment_id).await?;
+      History::open(&get_data_dir()).record_deleted(&comment_id);
+      console::success(format!("Comment {comment_id} deleted"));
+      Ok(())
+    }
+    Some(Command::Completions { shell }) => {
+      clap_complete::generate(shell, &mut Cli::command(), "yfc", &mut io::stdout());
+      Ok(())
+    }
+    Some(Command::Manpage) => {
+      Man::new(Cli::command().version(env!("CARGO_PKG_VERSION"))).render(&mut io::stdout())?;
+      Ok(())
+    }
+    #[cfg(windows)]
+    Some(Command::Service {
+      command: ServiceCommand::Install { config, name },
+    }) => {
+      let config = config
+        .or_else(config::get_default_config_path)
+        .ok_or("Could not find the config directory")?;
+
+      service::install(&name, &config, &get_data_dir())
+    }
+    #[cfg(windows)]
+    Some(Command::Service {
+      command: ServiceCommand::Uninstall { name },
+    }) => service::uninstall(&name),
+    #[cfg(windows)]
+    Some(Command::Service {
+      command: ServiceCommand::Run { config, name },
+    }) => service::run(name, vec!["yfc".into(), "--config".into(), config.into()]),
+    // Clap only lets the watcher arguments be omitted when a subcommand is given
+    None => {
+      run(
+        with_search_key(cli.args.expect("missing watcher arguments")),
+        command_line,
+      )
+      .await
+    }
+  }
+}
 
-        snippet
-          .resource_id
-          .as_ref()
-          .map(|resource_id| resource_id.video_id.clone())
-          .unwrap_or_default()
-      })
-  } else {
-    None
-  }
-}
-
-async fn post_comment(client: &YoutubeClient, video_id: &str, comment: &str) -> google_youtube3::Result<()> {
-  let comment_thread = CommentThread {
-    snippet: Some(CommentThreadSnippet {
-      video_id: Some(video_id.into()),
-      top_level_comment: Some(Comment {
-        snippet: Some(CommentSnippet {
-          text_original: Some(comment.into()),
-          ..Default::default()
-        }),
-        ..Default::default()
-      }),
-      ..Default::default()
-    }),
-    ..Default::default()
+fn set_data_dir(data_dir: Option<PathBuf>) -> Result<(), Box<dyn Error>> {
+  let data_dir = match data_dir {
+    Some(data_dir) => data_dir,
+    None => cache_dir()
+      .ok_or("Could not find the cache directory, set one with --data-dir")?
+      .join("yfc"),
   };
 
-  client.comment_threads().insert(comment_thread).doit().await.map(|_| ())
+  let _ = DATA_DIR.set(data_dir);
+
+  Ok(())
 }
 
-fn get_token_storage_path() -> PathBuf {
-  cache_dir()
-    .expect("Could not find the cache directory")
-    .join("yfc")
-    .join("token.json")
+fn migrate_config(line: Option<String>) -> Result<(), Box<dyn Error>> {
+  let line = match line {
+    Some(line) => line,
+    None => io::read_to_string(io::stdin())?,
+  };
+
+  print!("{}", config::migrate(&line, &Cli::command())?);
+
+  Ok(())
 }
 
-async fn get_youtube_client(client_id: &str, client_secret: &str) -> io::Result<YoutubeClient> {
-  let secret = ApplicationSecret {
-    client_id: client_id.into(),
-    client_secret: client_secret.into(),
-    auth_uri: "https://accounts.google.com/o/oauth2/auth".into(),
-    token_uri: "https://oauth2.googleapis.com/token".into(),
-    ..Default::default()
+/// Runs the real watcher against the fake YouTube API of the demo module, with its own throwaway data directory
+async fn run_demo(upload_after: Duration) -> Result<(), Box<dyn Error>> {
+  let addr = demo::serve(upload_after)?;
+
+  DATA_DIR.get_or_init(|| env::temp_dir().join("yfc-demo"));
+  API_BASE_URL.get_or_init(|| format!("http://{addr}/"));
+
+  // Every demo starts from scratch, otherwise the previous one would be resumed
+  let _ = fs::remove_dir_all(get_data_dir());
+
+  let Cli { args: Some(args), .. } = Cli::parse_from([
+    "yfc",
+    "--google-client-id=demo",
+    "--google-client-secret=demo",
+    &format!("--channel-id={}", demo::CHANNEL_ID),
+    "--comment=First! (sent by the yfc demo)",
+    "--poll-interval=2s",
+    "--privacy-poll-interval=1s",
+    "--wait-limit=5m",
+    "--verify-rank",
+  ]) else {
+    unreachable!("the demo passes every required argument");
   };
 
-  let token_path = get_token_storage_path();
-  let app_cache_path = token_path.parent().unwrap();
+  watch_locally(&args, &StaticText(args.comment.clone().unwrap_or_default())).await?;
+
+  println!("Demo over! Run yfc with your own --google-client-id, --google-client-secret and --channel-id to go live");
+
+  Ok(())
+}
+
+/// Runs the watcher against the API calls saved by --record, with its own throwaway data directory
+async fn run_replay(args: &Args, path: &Path) -> Result<(), Box<dyn Error>> {
+  let addr = fixtures::replay(path, args.replay_speed)?;
+
+  DATA_DIR.get_or_init(|| env::temp_dir().join("yfc-replay"));
+  API_BASE_URL.get_or_init(|| format!("http://{addr}/"));
+
+  // Every replay starts from scratch, otherwise the previous one would be resumed
+  let _ = fs::remove_dir_all(get_data_dir());
+
+  let config = Config::load(args.config.as_deref())?;
+  let comment_source = build_comment_source(args, config.comment.as_ref(), &config.comments)?;
+
+  preview_comment(args, comment_source.as_ref())?;
+  watch_locally(args, comment_source.as_ref()).await?;
+
+  println!("Replay over");
+
+  Ok(())
+}
+
+/// Runs the watcher against the local API of the demo or of --replay, without logging in nor notifying
+async fn watch_locally(args: &Args, comment_source: &dyn CommentSource) -> Result<(), Box<dyn Error>> {
+  let client = new_client(NoToken)?;
+  let notifier = Notifier::new(&NotifyConfig::default(), None, build_http_client()?)?;
+  let mut safe_mode = SafeMode::load(&get_data_dir());
+  let (events, stream) = events::channel();
+  let recent_events = notifier.recent_events();
+  let watching = async move {
+    watch_channel(
+      &Accounts::new(vec![(String::new(), client)]),
+      ReadProjects::default(),
+      args,
+      comment_source,
+      None,
+      &events,
+      recent_events,
+      &mut safe_mode,
+    )
+    .await
+  };
+  let mut summary = RunSummary::new(&args.channel_id);
+  let (result, ()) = tokio::join!(watching, events::forward(stream, &notifier, &mut summary));
 
-  if !fs::exists(app_cache_path)? {
-    fs::create_dir(app_cache_path)?;
+  write_summary(args, summary, &result);
+
+  result.inspect_err(|e| {
+    if e.is::<Interrupted>() {
+      process::exit(shutdown::EXIT_CODE);
+    }
+  })
+}
+
+/// A client reading with the API key when there's one, with the login of the account otherwise
+async fn read_client(
+  api_key: Option<&str>,
+  google_client_id: Option<String>,
+  google_client_secret: Option<&str>,
+  account: Option<&str>,
+  auth_flow: AuthFlow,
+) -> Result<YoutubeClient, Box<dyn Error>> {
+  Ok(match google_client_id.filter(|_| api_key.is_none()) {
+    Some(google_client_id) => {
+      let client_secret = get_client_secret(&google_client_id, google_client_secret)?;
+
+      authenticate_account(
+        &google_client_id,
+        &client_secret,
+        account.unwrap_or_default(),
+        auth_flow,
+      )
+      .await?
+    }
+    None => new_client(NoToken)?,
+  })
+}
+
+async fn print_status(addr: &str, format: StatusFormat) -> Result<(), Box<dyn Error>> {
+  // An unreachable watcher is a state worth showing in a status bar, not an error
+  let status = status::query(&build_http_client()?, addr).await.ok();
+
+  println!("{}", status::format(status.as_ref(), format));
+
+  Ok(())
+}
+
+/// The comment of --comment, or else the one of the config file. With comments by language, they're picked first and
+/// that one is the default.
+fn build_comment_source(
+  args: &Args,
+  source: Option<&CommentSourceConfig>,
+  languages: &BTreeMap<String, CommentSourceConfig>,
+) -> Result<Box<dyn CommentSource>, Box<dyn Error>> {
+  let default: Option<Box<dyn CommentSource>> = match (&args.comment, &args.comment_file, source) {
+    (Some(text), ..) if text == "-" => Some(Box::new(StaticText(read_stdin_comment()?))),
+    (Some(text), ..) => Some(Box::new(StaticText(text.clone()))),
+    (None, Some(path), _) => Some(Box::new(FileSource(path.clone()))),
+    (None, None, Some(source)) => Some(source.build(&build_https_client()?)),
+    // Nothing gets posted
+    (None, None, None) if args.observe => Some(Box::new(StaticText(String::new()))),
+    (None, None, None) => None,
+  };
+
+  if languages.is_empty() || args.observe {
+    return default
+      .ok_or_else(|| "A comment is required, either with --comment, --comment-file or in the config file".into());
   }
 
-  let auth = InstalledFlowAuthenticator::builder(secret, InstalledFlowReturnMethod::HTTPRedirect)
-    .persist_tokens_to_disk(token_path)
-    .build()
-    .await?;
+  let client = build_https_client()?;
 
-  let scopes = [
-    "https://www.googleapis.com/auth/youtube.readonly",  // To read data
-    "https://www.googleapis.com/auth/youtube.force-ssl", // To create comments
-  ];
+  Ok(Box::new(LanguageSource {
+    languages: languages
+      .iter()
+      .map(|(language, source)| (language.clone(), source.build(&client)))
+      .collect(),
+    default,
+  }))
+}
 
-  // This will request both scopes at once instead of having to wait for a comment creation to log in again and give the
-  // other scope
-  auth.token(&scopes).await.map_err(io::Error::other)?;
+/// The comment given on stdin with `--comment -`, kept for when the config file changes and the comment is built again
+static STDIN_COMMENT: OnceLock<String> = OnceLock::new();
 
-  let https_connector = HttpsConnectorBuilder::new()
-    .with_native_roots()?
-    .https_only()
-    .enable_http2()
-    .build();
+fn read_stdin_comment() -> io::Result<String> {
+  if let Some(comment) = STDIN_COMMENT.get() {
+    return Ok(comment.clone());
+  }
 
-  let https_client = Client::builder().build(https_connector);
+  let mut comment = String::new();
+  io::stdin().read_to_string(&mut comment)?;
 
-  Ok(YouTube::new(https_client, auth))
+  Ok(STDIN_COMMENT.get_or_init(|| comment.trim_end().into()).clone())
 }
 
-fn format_duration(seconds: u64) -> String {
-  let hours = seconds / 3600;
-  let minutes = (seconds % 3600) / 60;
-  let seconds = seconds % 60;
+/// Made-up video the comment is previewed for
+const PREVIEW_VIDEO_ID: &str = "xxxxxxxxxxx";
+const PREVIEW_TITLE: &str = "Title of the new video";
 
-  let mut parts = Vec::new();
+/// Prints the comment as it would be posted on a new video and fails when YouTube would reject it, rather than finding
+/// out once the video is out
+fn preview_comment(args: &Args, comment_source: &dyn CommentSource) -> Result<(), Box<dyn Error>> {
+  // Nothing gets posted
+  if args.observe {
+    return Ok(());
+  }
 
-  if hours > 0 {
-    parts.push(format!("{}h", hours));
+  let progress = History::open(&get_data_dir()).progress(&args.channel_id, PREVIEW_VIDEO_ID);
+  let context = CommentContext {
+    channel_id: &args.channel_id,
+    video_id: PREVIEW_VIDEO_ID,
+    title: PREVIEW_TITLE,
+    my_streak: progress.streak,
+    upload_number_this_month: progress.uploads_this_month,
+    language: None,
+  };
+  let comments: Vec<String> = comment_source
+    .preview(&context)
+    .map_err(|e| format!("Failed to preview the comment: {e}"))?
+    .iter()
+    .map(|comment| sanitize_comment(args, comment))
+    .collect();
+
+  match comments.len() {
+    0 => println!("The comment is only known when posting, it will be checked then"),
+    1 => println!("The comment will read:"),
+    count => println!("The comment will be one of these {count}:"),
   }
-  if minutes > 0 {
-    parts.push(format!("{}m", minutes));
+
+  for comment in &comments {
+    show_comment(args, comment)?;
   }
-  if seconds > 0 {
-    parts.push(format!("{}s", seconds));
+
+  if args.age_restricted_policy == AgeRestrictedPolicy::Alternate {
+    if let Some(alternate) = &args.age_restricted_comment {
+      let alternate = sanitize_comment(args, alternate);
+
+      println!("On age-restricted videos, it will read:");
+      show_comment(args, &alternate)?;
+    }
   }
 
-  parts.join(" ")
+  Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-  let args = Args::parse();
-  let client = get_youtube_client(&args.google_client_id, &args.google_client_secret).await?;
-  let uploads_playlist_id = get_uploads_playlist_id(&client, &args.channel_id)
-    .await
-    .ok_or("Failed to get uploads playlist ID")?;
+fn show_comment(args: &Args, comment: &str) -> Result<(), String> {
+  for line in comment.lines() {
+    println!("    {line}");
+  }
+
+  comment::validate(comment)?;
+
+  // The sanitizer already warned about it
+  if args.sanitize.is_none() && comment::has_link(comment) {
+    console::warning("The comment has a link, YouTube often holds those for review as spam");
+  }
+
+  Ok(())
+}
 
-  println!("Uploads Playlist ID: {uploads_playlist_id}");
+/// Applies --sanitize to the comment, unless --force
+fn sanitize_comment(args: &Args, comment: &str) -> String {
+  let Some(policy) = args.sanitize.filter(|_| !args.force) else {
+    return comment.into();
+  };
 
-  let latest_video_id = get_latest_video_id(&client, &uploads_playlist_id).await;
-  let started_at = Instant::now();
-  let wait_limit = args.wait_limit.map_or(f64::INFINITY, |value| value as f64);
+  let findings = sanitize::findings(comment);
 
-  let result = loop {
-    sleep(Duration::from_secs(args.pool_interval)).await;
+  if findings.is_empty() {
+    return comment.into();
+  }
 
-    let elapsed_minutes = started_at.elapsed().as_secs() as f64 / 60.0;
+  let findings = findings.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
 
-    if elapsed_minutes >= wait_limit {
-      println!("The wait limit of {} minutes was reached", wait_limit);
-      break Ok(());
+  match policy {
+    SanitizePolicy::Warn => {
+      console::warning(format!(
+        "The comment has {findings}, YouTube may hold it for review as spam (--sanitize strip removes them)"
+      ));
+      comment.into()
+    }
+    SanitizePolicy::Strip => {
+      console::warning(format!(
+        "Stripped {findings} from the comment, YouTube may hold it for review as spam"
+      ));
+      sanitize::strip(comment)
     }
+  }
+}
 
-    if let Some(new_video_id) = get_latest_video_id(&client, &uploads_playlist_id).await {
-      println!("Latest Video ID: {new_video_id}");
+/// The notification channels of the watcher, with its hooks, report and telemetry
+fn build_notifier(args: &Args, config: &Config) -> Result<Arc<Notifier>, Box<dyn Error>> {
+  let notifier = Notifier::new(&config.notify, args.webhook_url.clone(), build_http_client()?)?
+    .with_reporter(args.report.map(|format| Reporter::new(&get_data_dir(), format)))
+    .with_hooks(Hooks::new(
+      args.on_new_video.clone(),
+      args.on_comment_posted.clone(),
+      args.on_error.clone(),
+    ))
+    .with_telemetry(args.telemetry.then(|| {
+      Telemetry::new(
+        &get_data_dir(),
+        args.telemetry_placement.clone(),
+        args.telemetry_endpoint.clone(),
+      )
+    }))
+    .with_bell(args.bell);
+  #[cfg(feature = "desktop-notify")]
+  let notifier = notifier.with_desktop(args.notify);
+  #[cfg(feature = "sound")]
+  let notifier = notifier.with_sound(args.sound.as_deref())?;
+
+  Ok(Arc::new(notifier))
+}
+
+fn build_heartbeat(args: &Args) -> Result<Option<Heartbeat>, Box<dyn Error>> {
+  Ok(match &args.heartbeat_url {
+    Some(url) => Some(Heartbeat::new(url, build_http_client()?)),
+    None => None,
+  })
+}
 
-      if Some(new_video_id.clone()) != latest_video_id {
-        println!("New Video Published: {new_video_id}");
-        break match post_comment(&client, &new_video_id, &args.comment).await {
-          Ok(_) => {
-            println!("Comment created successfuly!");
-            Ok(())
-          }
-          Err(e) => Err(Box::new(e) as Box<dyn Error>),
-        };
+/// Notifies of the error the watcher stopped with, unless it already did
+async fn report_failure(result: &Result<(), Box<dyn Error>>, notifier: &Notifier, heartbeat: Option<&Heartbeat>) {
+  match result {
+    // Already notified
+    Err(e) if e.is::<Interrupted>() || matches!(e.downcast_ref(), Some(Failure::WaitLimitReached)) => {}
+    Err(e) => {
+      notifier.fatal(&e.to_string());
+
+      if let Some(heartbeat) = heartbeat {
+        heartbeat.fail(&e.to_string()).await;
       }
     }
-  };
+    Ok(()) => {}
+  }
+}
+
+/// Runs the watcher. Changes to the config file are applied while it runs, `command_line` (the one it was started with)
+/// is parsed again with them.
+async fn run(args: Args, command_line: Vec<OsString>) -> Result<(), Box<dyn Error>> {
+  if args.headless {
+    console::set_headless();
+  }
+
+  if let Some(path) = &args.replay {
+    return run_replay(&args, path).await;
+  }
+
+  if let Some(path) = &args.record {
+    let addr = fixtures::record(path)?;
+
+    API_BASE_URL.get_or_init(|| format!("http://{addr}/"));
+  }
+
+  let config = Config::load(args.config.as_deref())?;
+  let notifier = build_notifier(&args, &config)?;
+  let comment_source = build_comment_source(&args, config.comment.as_ref(), &config.comments)?;
 
-  println!(
-    "The elapsed time was {}",
-    format_duration(started_at.elapsed().as_secs())
-  );
+  preview_comment(&args, comment_source.as_ref())?;
+
+  let comment_source = Arc::new(ReloadableSource::new(comment_source));
+
+  if let Some(path) = Config::path(args.config.as_deref()) {
+    if let Err(e) = reload::watch(path, command_line, comment_source.clone(), notifier.clone()) {
+      console::error(format!("Failed to watch the config file for changes: {e}"));
+    }
+  }
+  let heartbeat = build_heartbeat(&args)?;
+  let mut safe_mode = SafeMode::load(&get_data_dir());
+
+  safe_mode.check(args.safe_mode_after, args.force)?;
+
+  let result = watch_and_notify(
+    Watcher {
+      args: &args,
+      comment_source: comment_source.as_ref(),
+      heartbeat: heartbeat.as_ref(),
+      recent_events: notifier.recent_events(),
+      safe_mode: &mut safe_mode,
+    },
+    &notifier,
+  )
+  .await;
+
+  report_failure(&result, &notifier, heartbeat.as_ref()).await;
 
   result
 }
+
+/// Watches every channel of the `[channels]` section of the config file at once. Each one takes the `[watch]` options,
+/// overridden by its own, themselves overridden by the command line.
+async fn run_channels(config: Config, command_line: Vec<OsString>) -> Result<(), Box<dyn Error>> {
+  let mut watchers: Vec<(&String, Args, Box<dyn CommentSource>)> = Vec::new();
+
+  for (name, channel) in &config.channels {
+    let cli = parse_cli(with_options(
+      command_line.clone(),
+      [config.watch_args(), channel.watch_args()].concat(),
+    ));
+    let args = with_search_key(
+      cli
+        .args
+        .ok_or_else(|| format!("No watcher options were given for channel {name}"))?,
+    );
+
+    if watchers.is_empty() {
+      set_data_dir(cli.data_dir)?;
+    }
+
+    if args.tui {
+      return Err("The dashboard only shows a single channel, --tui can't be used with [channels]".into());
+    }
+
+    if args.headless {
+      console::set_headless();
+    }
+
+    if args.record.is_some() || args.replay.is_some() {
+      return Err("--record and --replay only work with a single channel, they can't be used with [channels]".into());
+    }
+
+    if let Some((other, ..)) = watchers
+      .iter()
+      .find(|(_, other, _)| other.channel_id == args.channel_id)
+    {
+      return Err(format!("Channels {other} and {name} both watch {}", args.channel_id).into());
+    }
+
+    if let Some(path) = &args.summary_out {
+      if let Some((other, ..)) = watchers
+        .iter()
+        .find(|(_, other, _)| other.summary_out.as_ref() == Some(path))
+      {
+        return Err(
+          format!(
+            "Channels {other} and {name} both write their summary to {}",
+            path.display()
+          )
+          .into(),
+        );
+      }
+    }
+
+    console::info(format!("Channel {name}"));
+
+    let comment_source = build_comment_source(
+      &args,
+      channel.comment.as_ref().or(config.comment.as_ref()),
+      if channel.comments.is_empty() {
+        &config.comments
+      } else {
+        &channel.comments
+      },
+    )?;
+
+    preview_comment(&args, comment_source.as_ref())?;
+    watchers.push((name, args, comment_source));
+  }
+
+  watchers.sort_by_key(|(_, args, _)| Reverse(args.priority));
+
+  let (_, first, _) = &watchers[0];
+
+  SafeMode::load(&get_data_dir()).check(first.safe_mode_after, first.force)?;
+
+  // Their countdowns would overwrite each other
+  console::hide_countdown();
+  channels::enable(watchers.iter().map(|(_, args, _)| args.priority));
+
+  let results = join_all(
+    watchers
+      .iter()
+      .map(|(_, args, comment_source)| watch_with_notifications(args, &config, comment_source.as_ref())),
+  )
+  .await;
+  let mut first_error = None;
+
+  for ((name, ..), result) in watchers.iter().zip(results) {
+    match result {
+      Ok(()) => {}
+      Err(e) if first_error.is_none() => first_error = Some(e),
+      // Only the first error is printed on exit and sets the exit code
+      Err(e) if e.is::<Interrupted>() || matches!(e.downcast_ref(), Some(Failure::WaitLimitReached)) => {}
+      Err(e) => console::error(format!("Watching channel {name} failed: {e}")),
+    }
+  }
+
+  first_error.map_or(Ok(()), Err)
+}
+
+/// Watches a channel of the config file, with notifications like a single watcher
+async fn watch_with_notifications(
+  args: &Args,
+  config: &Config,
+  comment_source: &dyn CommentSource,
+) -> Result<(), Box<dyn Error>> {
+  let notifier = build_notifier(args, config)?;
+  let heartbeat = build_heartbeat(args)?;
+  let mut safe_mode = SafeMode::load(&get_data_dir());
+  let result = watch_and_notify(
+    Watcher {
+      args,
+      comment_source,
+      heartbeat: heartbeat.as_ref(),
+      recent_events: notifier.recent_events(),
+      safe_mode: &mut safe_mode,
+    },
+    &notifier,
+  )
+  .await;
+
+  report_failure(&result, &notifier, heartbeat.as_ref()).await;
+
+  result
+}
+
+/// Runs the watcher, its events shown and sent to the notification channels as they come
+async fn watch_and_notify(watcher: Watcher<'_>, notifier: &Notifier) -> Result<(), Box<dyn Error>> {
+  let args = watcher.args;
+  let mut summary = RunSummary::new(&args.channel_id);
+  let (events, watching) = watcher.run();
+  let (result, ()) = tokio::join!(watching, events::forward(events, notifier, &mut summary));
+
+  write_summary(args, summary, &result);
+
+  result
+}
+
+/// Writes how the run ended to --summary-out, if given
+fn write_summary(args: &Args, summary: RunSummary, result: &Result<(), Box<dyn Error>>) {
+  if let Some(path) = &args.summary_out {
+    if let Err(e) = summary.write(path, result) {
+      console::error(format!("Failed to write the summary to {}: {e}", path.display()));
+    }
+  }
+}
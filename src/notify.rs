@@ -0,0 +1,506 @@
+use crate::{
+  config::NotifyConfig, console, email::EmailNotifier, hooks::Hooks, report::Reporter, telemetry::Telemetry,
+  youtube::HttpsClient,
+};
+use google_youtube3::{
+  chrono::{DateTime, Utc},
+  hyper::{header::CONTENT_TYPE, Body, Method, Request},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+  collections::VecDeque,
+  error::Error,
+  sync::{Arc, Mutex, RwLock},
+  time::Duration,
+};
+#[cfg(feature = "sound")]
+use std::{fs, io, path::Path};
+
+/// How many events are kept in memory for the status endpoint
+const RECENT_EVENTS: usize = 50;
+
+/// Something worth telling the user about while they aren't looking at the terminal.
+pub enum Event<'a> {
+  VideoDetected {
+    video_id: &'a str,
+    title: &'a str,
+    published_at: Option<DateTime<Utc>>,
+  },
+  CommentPosted {
+    video_id: &'a str,
+    comment_id: &'a str,
+    /// Time between detecting the video and the comment being created
+    latency: Duration,
+    /// Time between the video being published and the comment being created, when YouTube reports the publish time
+    publish_latency: Option<Duration>,
+  },
+  PostFailed {
+    video_id: &'a str,
+    error: &'a str,
+  },
+  WaitLimitReached,
+  /// The API quota ran out. The watcher resumes when it resets in `resumes_in`, or stops when it's `None`.
+  QuotaExceeded {
+    resumes_in: Option<Duration>,
+  },
+  /// The comment got new likes or replies since it was last checked
+  CommentEngagement {
+    video_id: &'a str,
+    comment_id: &'a str,
+    likes: u32,
+    replies: u32,
+  },
+  /// Observer mode: how fast the video was detected and who got the first comment
+  VideoObserved {
+    video_id: &'a str,
+    /// Time between the video being published and it being detected
+    detection_latency: Option<Duration>,
+    /// Display name of the author of the first comment, `None` when it couldn't be found
+    first_commenter: Option<&'a str>,
+    /// Time between the video being published and the first comment
+    first_comment_after: Option<Duration>,
+  },
+}
+
+fn video_url(video_id: &str) -> String {
+  format!("https://www.youtube.com/watch?v={video_id}")
+}
+
+impl Event<'_> {
+  /// Machine-friendly event name used by the JSON webhook
+  pub fn name(&self) -> &'static str {
+    match self {
+      Event::VideoDetected { .. } => "video_detected",
+      Event::CommentPosted { .. } => "comment_posted",
+      Event::PostFailed { .. } => "post_failed",
+      Event::WaitLimitReached => "wait_limit_reached",
+      Event::QuotaExceeded { .. } => "quota_exceeded",
+      Event::CommentEngagement { .. } => "comment_engagement",
+      Event::VideoObserved { .. } => "video_observed",
+    }
+  }
+
+  pub fn title(&self) -> &'static str {
+    match self {
+      Event::VideoDetected { .. } => "New video detected",
+      Event::CommentPosted { .. } => "Comment posted",
+      Event::PostFailed { .. } => "Failed to post the comment",
+      Event::WaitLimitReached => "Wait limit reached",
+      Event::QuotaExceeded { .. } => "Quota exceeded",
+      Event::CommentEngagement { .. } => "New likes or replies",
+      Event::VideoObserved { .. } => "Video observed",
+    }
+  }
+
+  pub fn description(&self) -> String {
+    match self {
+      Event::VideoDetected { title, .. } => title.to_string(),
+      Event::CommentPosted {
+        video_id,
+        latency,
+        publish_latency,
+        ..
+      } => match publish_latency {
+        Some(publish_latency) => format!(
+          "Your comment is live on video {video_id}, {}ms after detection and {}ms after publication",
+          latency.as_millis(),
+          publish_latency.as_millis()
+        ),
+        None => format!(
+          "Your comment is live on video {video_id}, {}ms after detection",
+          latency.as_millis()
+        ),
+      },
+      Event::PostFailed { error, .. } => error.to_string(),
+      Event::WaitLimitReached => "No new video was published in time".into(),
+      Event::QuotaExceeded {
+        resumes_in: Some(resumes_in),
+      } => format!(
+        "The API quota ran out, watching resumes when it resets in {}",
+        console::format_duration(*resumes_in)
+      ),
+      Event::QuotaExceeded { resumes_in: None } => "The API quota ran out, yfc stopped watching".into(),
+      Event::CommentEngagement {
+        video_id,
+        likes,
+        replies,
+        ..
+      } => format!("Your comment on video {video_id} has {likes} likes and {replies} replies"),
+      Event::VideoObserved {
+        video_id,
+        detection_latency,
+        first_commenter,
+        first_comment_after,
+      } => {
+        let mut description = match first_commenter {
+          Some(author) => format!("The first comment on video {video_id} is by {author}"),
+          None => format!("The first comment on video {video_id} couldn't be found"),
+        };
+
+        if let Some(after) = first_comment_after {
+          description.push_str(&format!(", {}ms after publication", after.as_millis()));
+        }
+
+        if let Some(latency) = detection_latency {
+          description.push_str(&format!(
+            ". The video was detected {}ms after publication",
+            latency.as_millis()
+          ));
+        }
+
+        description
+      }
+    }
+  }
+
+  pub fn video_id(&self) -> Option<&str> {
+    match self {
+      Event::VideoDetected { video_id, .. }
+      | Event::CommentPosted { video_id, .. }
+      | Event::PostFailed { video_id, .. }
+      | Event::CommentEngagement { video_id, .. }
+      | Event::VideoObserved { video_id, .. } => Some(video_id),
+      Event::WaitLimitReached | Event::QuotaExceeded { .. } => None,
+    }
+  }
+
+  pub fn url(&self) -> Option<String> {
+    match self {
+      Event::CommentPosted {
+        video_id, comment_id, ..
+      }
+      | Event::CommentEngagement {
+        video_id, comment_id, ..
+      } => Some(format!("{}&lc={comment_id}", video_url(video_id))),
+      _ => self.video_id().map(video_url),
+    }
+  }
+
+  /// Embed accent color: blue for information, green for success and red for failure
+  fn color(&self) -> u32 {
+    match self {
+      Event::VideoDetected { .. } | Event::VideoObserved { .. } => 0x3498db,
+      Event::CommentPosted { .. } | Event::CommentEngagement { .. } => 0x2ecc71,
+      Event::PostFailed { .. } | Event::WaitLimitReached | Event::QuotaExceeded { .. } => 0xe74c3c,
+    }
+  }
+
+  fn to_json(&self) -> serde_json::Value {
+    let (comment_id, latency_ms, publish_latency_ms, error) = match self {
+      Event::CommentPosted {
+        comment_id,
+        latency,
+        publish_latency,
+        ..
+      } => (
+        Some(*comment_id),
+        Some(latency.as_millis() as u64),
+        publish_latency.map(|latency| latency.as_millis() as u64),
+        None,
+      ),
+      Event::PostFailed { error, .. } => (None, None, None, Some(*error)),
+      Event::CommentEngagement { comment_id, .. } => (Some(*comment_id), None, None, None),
+      _ => (None, None, None, None),
+    };
+
+    let mut json = json!({
+      "event": self.name(),
+      "video_id": self.video_id(),
+      "comment_id": comment_id,
+      "latency_ms": latency_ms,
+      "publish_latency_ms": publish_latency_ms,
+      "error": error,
+    });
+
+    if let Event::VideoDetected { published_at, .. } = self {
+      json["published_at"] = json!(published_at);
+    }
+
+    if let Event::QuotaExceeded { resumes_in } = self {
+      json["resumes_in_secs"] = json!(resumes_in.map(|resumes_in| resumes_in.as_secs()));
+      json["resumes_at"] = json!(resumes_in.map(|resumes_in| Utc::now() + resumes_in));
+    }
+
+    if let Event::CommentEngagement { likes, replies, .. } = self {
+      json["likes"] = json!(likes);
+      json["replies"] = json!(replies);
+    }
+
+    if let Event::VideoObserved {
+      detection_latency,
+      first_commenter,
+      first_comment_after,
+      ..
+    } = self
+    {
+      json["detection_latency_ms"] = json!(detection_latency.map(|latency| latency.as_millis() as u64));
+      json["first_commenter"] = json!(first_commenter);
+      json["first_comment_after_ms"] = json!(first_comment_after.map(|after| after.as_millis() as u64));
+    }
+
+    json
+  }
+}
+
+/// An event as remembered in the recent events ring.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RecentEvent {
+  pub at: DateTime<Utc>,
+  pub event: String,
+  pub video_id: Option<String>,
+  pub detail: String,
+}
+
+/// The last events, oldest first, shared with the status server. It's always kept, unlike the SQLite history.
+pub type EventRing = Arc<Mutex<VecDeque<RecentEvent>>>;
+
+/// The channels set up by the `[notify]` section of the config and --webhook-url, which can be reloaded while watching
+struct Channels {
+  discord_webhook_url: Option<String>,
+  webhook_url: Option<String>,
+  email: Option<EmailNotifier>,
+}
+
+impl Channels {
+  fn new(config: &NotifyConfig, webhook_url: Option<String>) -> Result<Self, Box<dyn Error>> {
+    Ok(Self {
+      discord_webhook_url: config.discord.as_ref().map(|discord| discord.webhook_url.clone()),
+      webhook_url,
+      email: config.email.as_ref().map(EmailNotifier::new).transpose()?,
+    })
+  }
+}
+
+/// Fans events out to every notification channel enabled in the config.
+pub struct Notifier {
+  client: HttpsClient,
+  channels: RwLock<Arc<Channels>>,
+  desktop: bool,
+  bell: bool,
+  /// The audio file of --sound, read when starting so a missing or unsupported one is reported right away
+  #[cfg(feature = "sound")]
+  sound: Option<Arc<[u8]>>,
+  reporter: Option<Reporter>,
+  hooks: Option<Hooks>,
+  telemetry: Option<Telemetry>,
+  recent_events: EventRing,
+}
+
+impl Notifier {
+  pub fn new(config: &NotifyConfig, webhook_url: Option<String>, client: HttpsClient) -> Result<Self, Box<dyn Error>> {
+    Ok(Self {
+      client,
+      channels: RwLock::new(Arc::new(Channels::new(config, webhook_url)?)),
+      desktop: false,
+      bell: false,
+      #[cfg(feature = "sound")]
+      sound: None,
+      reporter: None,
+      hooks: None,
+      telemetry: None,
+      recent_events: EventRing::default(),
+    })
+  }
+
+  /// Replaces the channels of the config and --webhook-url, e.g. when the config file changes. An event being sent
+  /// still goes to the old ones.
+  pub fn reload(&self, config: &NotifyConfig, webhook_url: Option<String>) -> Result<(), Box<dyn Error>> {
+    *self.channels.write().unwrap() = Arc::new(Channels::new(config, webhook_url)?);
+
+    Ok(())
+  }
+
+  /// Also spawns the user commands hooked to the events
+  pub fn with_hooks(mut self, hooks: Hooks) -> Self {
+    self.hooks = Some(hooks);
+    self
+  }
+
+  /// Reports an error that stops the watcher. Only hooks get it, other channels already hear about failed posts.
+  pub fn fatal(&self, error: &str) {
+    if let Some(hooks) = &self.hooks {
+      hooks.run_fatal(error);
+    }
+  }
+
+  /// Also records every event in the weekly activity report
+  pub fn with_reporter(mut self, reporter: Option<Reporter>) -> Self {
+    self.reporter = reporter;
+    self
+  }
+
+  /// Also samples the race telemetry when comments get posted
+  pub fn with_telemetry(mut self, telemetry: Option<Telemetry>) -> Self {
+    self.telemetry = telemetry;
+    self
+  }
+
+  /// Also shows OS notifications for the final outcome (comment posted or failed)
+  #[cfg(feature = "desktop-notify")]
+  pub fn with_desktop(mut self, enabled: bool) -> Self {
+    self.desktop = enabled;
+    self
+  }
+
+  /// Also rings the terminal bell for the final outcome
+  pub fn with_bell(mut self, enabled: bool) -> Self {
+    self.bell = enabled;
+    self
+  }
+
+  /// Also plays an audio file for the final outcome
+  #[cfg(feature = "sound")]
+  pub fn with_sound(mut self, path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+    if let Some(path) = path {
+      let sound: Arc<[u8]> = fs::read(path)
+        .map_err(|e| format!("Failed to read the sound {}: {e}", path.display()))?
+        .into();
+
+      rodio::Decoder::new(io::Cursor::new(sound.clone()))
+        .map_err(|e| format!("Unsupported sound {}: {e}", path.display()))?;
+      self.sound = Some(sound);
+    }
+
+    Ok(self)
+  }
+
+  pub fn recent_events(&self) -> EventRing {
+    self.recent_events.clone()
+  }
+
+  pub async fn notify(&self, event: &Event<'_>) {
+    {
+      let mut recent_events = self.recent_events.lock().unwrap();
+
+      if recent_events.len() == RECENT_EVENTS {
+        recent_events.pop_front();
+      }
+
+      recent_events.push_back(RecentEvent {
+        at: Utc::now(),
+        event: event.name().into(),
+        video_id: event.video_id().map(String::from),
+        detail: event.description(),
+      });
+    }
+
+    if let Some(reporter) = &self.reporter {
+      reporter.record(event);
+    }
+
+    if let Some(hooks) = &self.hooks {
+      hooks.run(event);
+    }
+
+    if let Some(telemetry) = &self.telemetry {
+      if let (Some(sample), Some(url)) = (telemetry.record(event), telemetry.endpoint()) {
+        self.post_json(url, &sample).await;
+      }
+    }
+
+    let final_outcome = matches!(event, Event::CommentPosted { .. } | Event::PostFailed { .. });
+
+    if self.bell && final_outcome {
+      console::bell();
+    }
+
+    // Played alongside the other channels, rather than holding them up until it ends
+    #[cfg(feature = "sound")]
+    let sound = self.sound.clone().filter(|_| final_outcome).map(play_sound);
+
+    if self.desktop && final_outcome {
+      show_desktop_notification(event.title(), &event.description()).await;
+    }
+
+    let channels = self.channels.read().unwrap().clone();
+
+    if let Some(url) = &channels.webhook_url {
+      self.post_json(url, &event.to_json()).await;
+    }
+
+    if let Some(email) = &channels.email {
+      email.send(event).await;
+    }
+
+    if let Some(url) = &channels.discord_webhook_url {
+      let mut embed = json!({
+        "title": event.title(),
+        "description": event.description(),
+        "color": event.color(),
+      });
+
+      if let Some(url) = event.url() {
+        embed["url"] = url.into();
+      }
+
+      let payload = json!({ "embeds": [embed] });
+
+      self.post_json(url, &payload).await;
+    }
+
+    // Waited for, so exiting after the comment is posted doesn't cut it off
+    #[cfg(feature = "sound")]
+    if let Some(sound) = sound {
+      let _ = sound.await;
+    }
+  }
+
+  async fn post_json(&self, url: &str, payload: &serde_json::Value) {
+    let request = match Request::builder()
+      .method(Method::POST)
+      .uri(url)
+      .header(CONTENT_TYPE, "application/json")
+      .body(Body::from(payload.to_string()))
+    {
+      Ok(request) => request,
+      Err(e) => {
+        console::info(format!("Invalid notification URL {url}: {e}"));
+        return;
+      }
+    };
+
+    // Notifications are best effort, the watcher keeps going whatever happens to them
+    match self.client.request(request).await {
+      Ok(response) if !response.status().is_success() => {
+        console::info(format!("Notification to {url} returned {}", response.status()))
+      }
+      Ok(_) => {}
+      Err(e) => console::error(format!("Failed to send notification to {url}: {e}")),
+    }
+  }
+}
+
+#[cfg(feature = "desktop-notify")]
+async fn show_desktop_notification(summary: &str, body: &str) {
+  let mut notification = notify_rust::Notification::new();
+  notification.appname("yfc").summary(summary).body(body);
+
+  // Showing a notification talks to the desktop session synchronously (D-Bus on Linux)
+  if let Ok(Err(e)) = tokio::task::spawn_blocking(move || notification.show().map(|_| ())).await {
+    console::error(format!("Failed to show the desktop notification: {e}"));
+  }
+}
+
+#[cfg(not(feature = "desktop-notify"))]
+async fn show_desktop_notification(_summary: &str, _body: &str) {}
+
+#[cfg(feature = "sound")]
+fn play_sound(sound: Arc<[u8]>) -> tokio::task::JoinHandle<()> {
+  // Playing blocks until the end, on the default output device
+  tokio::task::spawn_blocking(move || {
+    let play = || -> Result<(), Box<dyn Error>> {
+      let (_stream, handle) = rodio::OutputStream::try_default()?;
+      let sink = rodio::Sink::try_new(&handle)?;
+
+      sink.append(rodio::Decoder::new(io::Cursor::new(sound))?);
+      sink.sleep_until_end();
+
+      Ok(())
+    };
+
+    if let Err(e) = play() {
+      console::error(format!("Failed to play the sound: {e}"));
+    }
+  })
+}
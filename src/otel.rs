@@ -0,0 +1,234 @@
+use crate::{
+  console,
+  youtube::{build_http_client, HttpsClient},
+};
+use google_youtube3::hyper::{self, header::CONTENT_TYPE, Body, Method, Request};
+use rand::RngCore;
+use serde_json::{json, Value};
+use std::{
+  mem,
+  sync::{Mutex, OnceLock},
+  time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::time::sleep;
+
+/// How often the finished spans are sent
+const EXPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Spans kept at most between two exports, the oldest ones are dropped first
+const MAX_BUFFERED_SPANS: usize = 2048;
+
+/// Sends the spans to an OTLP collector, over HTTP with the JSON encoding so no gRPC or protobuf stack is needed
+struct Exporter {
+  client: HttpsClient,
+  url: String,
+  spans: Mutex<Vec<Value>>,
+}
+
+static EXPORTER: OnceLock<Exporter> = OnceLock::new();
+
+/// Starts exporting the spans to the collector at `endpoint` (e.g. `http://localhost:4318`). Watching several
+/// channels, the first one to start sets it up for all of them.
+pub fn init(endpoint: &str) {
+  let Ok(client) = build_http_client() else {
+    console::error("Failed to set up the OpenTelemetry exporter");
+    return;
+  };
+  let endpoint = endpoint.trim_end_matches('/');
+  let url = if endpoint.ends_with("/v1/traces") {
+    endpoint.into()
+  } else {
+    format!("{endpoint}/v1/traces")
+  };
+
+  if EXPORTER
+    .set(Exporter {
+      client,
+      url,
+      spans: Mutex::new(Vec::new()),
+    })
+    .is_ok()
+  {
+    tokio::spawn(async {
+      loop {
+        sleep(EXPORT_INTERVAL).await;
+        flush().await;
+      }
+    });
+  }
+}
+
+/// Sends the spans finished so far, e.g. before exiting
+pub async fn flush() {
+  let Some(exporter) = EXPORTER.get() else {
+    return;
+  };
+  let spans = mem::take(&mut *exporter.spans.lock().unwrap());
+
+  if spans.is_empty() {
+    return;
+  }
+
+  let body = json!({
+    "resourceSpans": [{
+      "resource": { "attributes": attributes(&[
+        ("service.name", "yfc".into()),
+        ("service.version", env!("CARGO_PKG_VERSION").into()),
+      ]) },
+      "scopeSpans": [{ "scope": { "name": "yfc" }, "spans": spans }],
+    }]
+  });
+  let request = Request::builder()
+    .method(Method::POST)
+    .uri(&exporter.url)
+    .header(CONTENT_TYPE, "application/json")
+    .body(Body::from(body.to_string()));
+  let result = match request {
+    Ok(request) => exporter.client.request(request).await.map_err(|e| e.to_string()),
+    Err(e) => Err(e.to_string()),
+  };
+
+  match result {
+    Ok(response) if response.status().is_success() => {}
+    Ok(response) => {
+      let status = response.status();
+      let body = hyper::body::to_bytes(response.into_body()).await.unwrap_or_default();
+
+      console::error(format!(
+        "The OpenTelemetry collector refused the spans ({status}): {}",
+        String::from_utf8_lossy(&body)
+      ));
+    }
+    Err(e) => console::error(format!("Failed to send the spans to the OpenTelemetry collector: {e}")),
+  }
+}
+
+fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+  time
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_nanos()
+    .to_string()
+}
+
+/// The attributes in the OTLP encoding, strings, integers and booleans being the only ones used
+fn attributes(attributes: &[(&str, Value)]) -> Value {
+  attributes
+    .iter()
+    .filter(|(_, value)| !value.is_null())
+    .map(|(key, value)| {
+      let value = match value {
+        Value::Bool(value) => json!({ "boolValue": value }),
+        // 64-bit integers are strings in the JSON encoding
+        Value::Number(value) if value.is_i64() || value.is_u64() => json!({ "intValue": value.to_string() }),
+        Value::String(value) => json!({ "stringValue": value }),
+        value => json!({ "stringValue": value.to_string() }),
+      };
+
+      json!({ "key": key, "value": value })
+    })
+    .collect()
+}
+
+/// The spans of one operation, e.g. the race from detecting a video to posting the comment, under a root span. They're
+/// only exported once it ends, a dropped trace (e.g. of a video skipped halfway) leaves nothing behind.
+pub struct Trace {
+  trace_id: [u8; 16],
+  root_id: [u8; 8],
+  started_at: SystemTime,
+  spans: Vec<Value>,
+}
+
+fn random_id<const N: usize>() -> [u8; N] {
+  let mut id = [0; N];
+  rand::thread_rng().fill_bytes(&mut id);
+  id
+}
+
+impl Trace {
+  pub fn new(started_at: SystemTime) -> Self {
+    Self {
+      trace_id: random_id(),
+      root_id: random_id(),
+      started_at,
+      spans: Vec::new(),
+    }
+  }
+
+  fn encode(
+    &self,
+    span_id: [u8; 8],
+    parent_id: Option<[u8; 8]>,
+    name: &str,
+    (started_at, ended_at): (SystemTime, SystemTime),
+    attrs: &[(&str, Value)],
+  ) -> Value {
+    json!({
+      "traceId": hex(&self.trace_id),
+      "spanId": hex(&span_id),
+      "parentSpanId": parent_id.map(|id| hex(&id)).unwrap_or_default(),
+      "name": name,
+      // Internal
+      "kind": 1,
+      "startTimeUnixNano": unix_nanos(started_at),
+      "endTimeUnixNano": unix_nanos(ended_at.max(started_at)),
+      "attributes": attributes(attrs),
+    })
+  }
+
+  /// A step of the operation, from `started_at` until now
+  pub fn span(&mut self, name: &str, started_at: SystemTime, attributes: &[(&str, Value)]) {
+    self.span_between(name, started_at, SystemTime::now(), attributes);
+  }
+
+  /// A step of the operation that ended earlier
+  pub fn span_between(
+    &mut self,
+    name: &str,
+    started_at: SystemTime,
+    ended_at: SystemTime,
+    attributes: &[(&str, Value)],
+  ) {
+    if EXPORTER.get().is_some() {
+      let span = self.encode(
+        random_id(),
+        Some(self.root_id),
+        name,
+        (started_at, ended_at),
+        attributes,
+      );
+      self.spans.push(span);
+    }
+  }
+
+  /// Ends the operation now and queues its spans for export
+  pub fn end(mut self, name: &str, attributes: &[(&str, Value)]) {
+    let Some(exporter) = EXPORTER.get() else {
+      return;
+    };
+    let root = self.encode(
+      self.root_id,
+      None,
+      name,
+      (self.started_at, SystemTime::now()),
+      attributes,
+    );
+
+    self.spans.push(root);
+
+    let mut spans = exporter.spans.lock().unwrap();
+    spans.append(&mut self.spans);
+
+    let overflow = spans.len().saturating_sub(MAX_BUFFERED_SPANS);
+    spans.drain(..overflow);
+  }
+}
+
+/// An operation without steps, from `started_at` until now, in its own trace
+pub fn span(name: &str, started_at: SystemTime, attributes: &[(&str, Value)]) {
+  Trace::new(started_at).end(name, attributes);
+}
@@ -0,0 +1,68 @@
+use crate::{
+  console,
+  youtube::{get_uploads_playlist_id, YoutubeClient},
+};
+use std::{collections::HashMap, fs, io, path::Path};
+
+/// The uploads playlist of a `UC…` channel is the same ID with `UU` in front
+pub fn derive_uploads_playlist_id(channel_id: &str) -> Option<String> {
+  channel_id
+    .strip_prefix("UC")
+    .filter(|rest| !rest.is_empty())
+    .map(|rest| format!("UU{rest}"))
+}
+
+fn load(path: &Path) -> io::Result<HashMap<String, String>> {
+  match fs::read_to_string(path) {
+    Ok(contents) => serde_json::from_str(&contents).map_err(io::Error::other),
+    Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(HashMap::new()),
+    Err(e) => Err(e),
+  }
+}
+
+fn save(path: &Path, channel_id: &str, playlist_id: &str) -> io::Result<()> {
+  // Another watcher sharing the data directory may have cached its channel meanwhile
+  let mut playlists = load(path).unwrap_or_default();
+  playlists.insert(channel_id.into(), playlist_id.into());
+
+  if let Some(dir) = path.parent() {
+    fs::create_dir_all(dir)?;
+  }
+
+  fs::write(
+    path,
+    serde_json::to_string_pretty(&playlists).map_err(io::Error::other)?,
+  )
+}
+
+/// The uploads playlist of the channel, cached in `<data dir>/playlists.json` as it never changes. It's derived from
+/// the channel ID when possible, and only asked to the API (for a quota unit) otherwise or when `refresh` is set.
+pub async fn uploads_playlist_id(
+  client: &YoutubeClient,
+  data_dir: &Path,
+  channel_id: &str,
+  api_key: Option<&str>,
+  refresh: bool,
+) -> Option<String> {
+  let path = data_dir.join("playlists.json");
+
+  if !refresh {
+    if let Some(playlist_id) = load(&path).ok().and_then(|mut playlists| playlists.remove(channel_id)) {
+      return Some(playlist_id);
+    }
+  }
+
+  let playlist_id = match derive_uploads_playlist_id(channel_id).filter(|_| !refresh) {
+    Some(playlist_id) => playlist_id,
+    None => get_uploads_playlist_id(client, channel_id, api_key).await?,
+  };
+
+  // A lost cache only costs a lookup on the next run
+  if let Err(e) = save(&path, channel_id, &playlist_id) {
+    console::error(format!(
+      "Failed to cache the uploads playlist of channel {channel_id}: {e}"
+    ));
+  }
+
+  Some(playlist_id)
+}
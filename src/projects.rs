@@ -0,0 +1,97 @@
+use crate::{quota, youtube::YoutubeClient};
+use std::{str::FromStr, time::Instant};
+
+/// OAuth client of a Google Cloud project, written as `<client id>:<client secret>`
+#[derive(Clone)]
+pub struct ProjectCredentials {
+  pub client_id: String,
+  pub client_secret: String,
+}
+
+impl FromStr for ProjectCredentials {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value.split_once(':') {
+      Some((client_id, client_secret)) if !client_id.is_empty() && !client_secret.is_empty() => Ok(Self {
+        client_id: client_id.into(),
+        client_secret: client_secret.into(),
+      }),
+      _ => Err("expected <client id>:<client secret>".into()),
+    }
+  }
+}
+
+/// Extra projects to poll with, each with its own daily quota. Polling sticks to one of them until it runs out of quota
+/// and then moves on to the next, so the main project keeps its quota for posting the comment.
+#[derive(Default)]
+pub struct ReadProjects {
+  /// Client ID (to tell them apart in the logs) and client of every project
+  projects: Vec<(String, YoutubeClient)>,
+  /// When the quota of each project resets, `None` while it has some left
+  exhausted_until: Vec<Option<Instant>>,
+  current: usize,
+}
+
+impl ReadProjects {
+  pub fn new(projects: Vec<(String, YoutubeClient)>) -> Self {
+    Self {
+      exhausted_until: vec![None; projects.len()],
+      projects,
+      current: 0,
+    }
+  }
+
+  /// The client to poll with: the current project if it has quota left, else the next one that does. Once they are
+  /// all out of quota, it's the `main` one.
+  pub fn client<'a>(&'a mut self, main: &'a YoutubeClient) -> &'a YoutubeClient {
+    let now = Instant::now();
+
+    for exhausted_until in &mut self.exhausted_until {
+      if exhausted_until.is_some_and(|until| until <= now) {
+        *exhausted_until = None;
+      }
+    }
+
+    let available = (0..self.projects.len())
+      .map(|offset| (self.current + offset) % self.projects.len())
+      .find(|&index| self.exhausted_until[index].is_none());
+
+    match available {
+      Some(index) => {
+        self.current = index;
+        &self.projects[index].1
+      }
+      None => main,
+    }
+  }
+
+  /// Takes the current project out of the rotation until its quota resets, after it answered with quotaExceeded.
+  /// `false` when it was the main project answering, which has no other one to fall back to.
+  pub fn exhausted(&mut self) -> bool {
+    let Some(exhausted_until) = self.exhausted_until.get_mut(self.current) else {
+      return false;
+    };
+
+    // Already out of the rotation, the main project was the one answering
+    if exhausted_until.is_some() {
+      return false;
+    }
+
+    *exhausted_until = Some(Instant::now() + quota::until_reset());
+
+    if self.exhausted_until.iter().all(Option::is_some) {
+      println!(
+        "Read project {} is out of quota and so are the others, polling with the main project until the quota resets",
+        self.projects[self.current].0
+      );
+    } else {
+      println!(
+        "Read project {} is out of quota, switching to the next one",
+        self.projects[self.current].0
+      );
+    }
+
+    true
+  }
+}
@@ -0,0 +1,125 @@
+use crate::console;
+use chrono_tz::{America::Los_Angeles, Tz};
+use google_youtube3::chrono::{Days, NaiveDate, NaiveTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+  fs, io,
+  path::{Path, PathBuf},
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    OnceLock,
+  },
+  time::Duration,
+};
+
+/// Estimated quota cost of the API calls made by the watcher, see
+/// https://developers.google.com/youtube/v3/determine_quota_cost
+pub const LIST_COST: u64 = 1;
+pub const INSERT_COST: u64 = 50;
+pub const DELETE_COST: u64 = 50;
+pub const RATE_COST: u64 = 50;
+pub const SEARCH_COST: u64 = 100;
+
+/// Daily quota of a Google Cloud project, unless more was granted
+pub const DEFAULT_DAILY_QUOTA: u64 = 10_000;
+
+/// Units kept aside by the budget for when a video is detected: the insert and the lookups around it
+pub const POST_RESERVE: u64 = INSERT_COST + 10 * LIST_COST;
+
+/// The daily quota of a Google Cloud project resets at midnight Pacific time
+const RESET_TIME_ZONE: Tz = Los_Angeles;
+
+static USED: AtomicU64 = AtomicU64::new(0);
+
+/// Where the usage of the day is kept, see `track`
+static DAILY_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Units spent on the quota day `day`, by every yfc run sharing the data directory
+#[derive(Serialize, Deserialize)]
+struct DailyUsage {
+  day: NaiveDate,
+  used: u64,
+}
+
+fn quota_day() -> NaiveDate {
+  Utc::now().with_timezone(&RESET_TIME_ZONE).date_naive()
+}
+
+fn load_daily(path: &Path) -> io::Result<u64> {
+  let usage: DailyUsage = match fs::read_to_string(path) {
+    Ok(contents) => serde_json::from_str(&contents).map_err(io::Error::other)?,
+    Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+    Err(e) => return Err(e),
+  };
+
+  Ok(if usage.day == quota_day() { usage.used } else { 0 })
+}
+
+fn save_daily(path: &Path, used: u64) -> io::Result<()> {
+  let usage = DailyUsage { day: quota_day(), used };
+
+  fs::write(path, serde_json::to_string(&usage).map_err(io::Error::other)?)
+}
+
+/// Keeps the usage of the day in `<data dir>/quota.json`, so it adds up across runs (and watchers) sharing a project
+pub fn track(data_dir: &Path) {
+  if let Err(e) = fs::create_dir_all(data_dir) {
+    console::error(format!(
+      "Failed to create the data directory, the daily quota usage won't be kept: {e}"
+    ));
+    return;
+  }
+
+  let _ = DAILY_PATH.set(data_dir.join("quota.json"));
+}
+
+/// Records the estimated cost of an API call. Failed calls are charged too, as the API does.
+pub fn spend(units: u64) {
+  USED.fetch_add(units, Ordering::Relaxed);
+
+  if let Some(path) = DAILY_PATH.get() {
+    // Read again every time, another watcher may have spent some meanwhile
+    if let Err(e) = load_daily(path).and_then(|used| save_daily(path, used + units)) {
+      console::error(format!("Failed to update the daily quota usage: {e}"));
+    }
+  }
+}
+
+/// Estimated units spent since the program started
+pub fn used() -> u64 {
+  USED.load(Ordering::Relaxed)
+}
+
+/// Estimated units spent since the quota last reset, only counting this run when the usage isn't kept
+pub fn used_today() -> u64 {
+  DAILY_PATH
+    .get()
+    .and_then(|path| load_daily(path).ok())
+    .unwrap_or_else(used)
+}
+
+/// Time left until the quota resets
+pub fn until_reset() -> Duration {
+  let reset = quota_day().checked_add_days(Days::new(1)).and_then(|day| {
+    RESET_TIME_ZONE
+      .from_local_datetime(&day.and_time(NaiveTime::MIN))
+      .earliest()
+  });
+
+  reset
+    .and_then(|reset| (reset.with_timezone(&Utc) - Utc::now()).to_std().ok())
+    .unwrap_or_default()
+}
+
+/// Stretches the poll interval so the polls left in the daily `budget`, minus the reserve for posting, last until the
+/// quota resets. When nothing is left, the interval runs until the reset.
+pub fn pace(interval: Duration, budget: u64, poll_cost: u64) -> Duration {
+  let until_reset = until_reset();
+  let polls_left = budget.saturating_sub(used_today() + POST_RESERVE) / poll_cost;
+
+  if polls_left == 0 {
+    return until_reset;
+  }
+
+  interval.max(Duration::from_secs_f64(until_reset.as_secs_f64() / polls_left as f64))
+}
@@ -0,0 +1,102 @@
+use google_youtube3::{
+  chrono::{DateTime, Utc},
+  client::{Delegate, Retry},
+  hyper::{
+    header::{HeaderMap, RETRY_AFTER},
+    Body, Response, StatusCode,
+  },
+};
+use serde_json::Value;
+use std::{
+  sync::{
+    atomic::{AtomicU32, Ordering},
+    Mutex,
+  },
+  time::{Duration, Instant},
+};
+
+/// Back-off after the first rate limited call without a Retry-After header, doubled on every one in a row
+const FIRST_BACKOFF: Duration = Duration::from_secs(10);
+const MAX_BACKOFF: Duration = Duration::from_secs(10 * 60);
+
+/// The 403 reasons telling to slow down, the same as a 429
+const RATE_LIMIT_REASONS: &[&str] = &["rateLimitExceeded", "userRateLimitExceeded"];
+
+/// Until when the API asked to be left alone. It's shared by every watcher, they poll with the same projects.
+static BACKOFF_UNTIL: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Rate limited calls in a row, without a successful one in between
+static STRIKES: AtomicU32 = AtomicU32::new(0);
+
+/// Whether the API answered to slow down: a 429, or a 403 with a rate limit reason
+pub fn is_rate_limit(status: StatusCode, body: Option<&Value>) -> bool {
+  status == StatusCode::TOO_MANY_REQUESTS
+    || (status == StatusCode::FORBIDDEN
+      && body
+        .and_then(|body| body["error"]["errors"].as_array())
+        .is_some_and(|errors| {
+          errors
+            .iter()
+            .any(|error| RATE_LIMIT_REASONS.iter().any(|reason| error["reason"] == *reason))
+        }))
+}
+
+/// The Retry-After header, either in seconds or as an HTTP date
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+  let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+  match value.parse() {
+    Ok(seconds) => Some(Duration::from_secs(seconds)),
+    Err(_) => (DateTime::parse_from_rfc2822(value).ok()? - Utc::now().fixed_offset())
+      .to_std()
+      .ok(),
+  }
+}
+
+/// Takes note of a failed response, backing off when it's a rate limit
+pub fn record(status: StatusCode, headers: &HeaderMap, body: Option<&Value>) {
+  if !is_rate_limit(status, body) {
+    return;
+  }
+
+  let strikes = STRIKES.fetch_add(1, Ordering::Relaxed);
+  let backoff =
+    retry_after(headers).unwrap_or_else(|| FIRST_BACKOFF.saturating_mul(1 << strikes.min(16)).min(MAX_BACKOFF));
+  let until = Instant::now() + backoff;
+  let mut backoff_until = BACKOFF_UNTIL.lock().unwrap();
+
+  if backoff_until.is_none_or(|backoff_until| backoff_until < until) {
+    *backoff_until = Some(until);
+  }
+}
+
+/// A call went through, the next rate limit starts backing off from the beginning again
+pub fn cleared() {
+  STRIKES.store(0, Ordering::Relaxed);
+}
+
+/// How long to wait before calling the API again, `None` when it didn't ask to slow down
+pub fn remaining() -> Option<Duration> {
+  BACKOFF_UNTIL
+    .lock()
+    .unwrap()
+    .map(|until| until.saturating_duration_since(Instant::now()))
+    .filter(|remaining| !remaining.is_zero())
+}
+
+/// Records the rate limits of the calls made through the generated API, which otherwise drops the headers
+pub struct RateLimitHint;
+
+impl Delegate for RateLimitHint {
+  fn http_failure(&mut self, response: &Response<Body>, body: Option<Value>) -> Retry {
+    record(response.status(), response.headers(), body.as_ref());
+
+    Retry::Abort
+  }
+
+  fn finished(&mut self, is_success: bool) {
+    if is_success {
+      cleared();
+    }
+  }
+}
@@ -0,0 +1,261 @@
+use crate::{cli::Args, comment::ReloadableSource, config::Config, console, notify::Notifier};
+use fs_notify::{recommended_watcher, RecursiveMode, Watcher};
+use std::{
+  error::Error,
+  ffi::OsString,
+  fs,
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex},
+  time::Duration,
+};
+use tokio::{sync::mpsc, time::sleep};
+use toml::{Table, Value};
+
+/// Watcher options that only take effect when it starts. A change to them is reported, but not applied.
+const STARTUP_OPTIONS: &[&str] = &[
+  "config",
+  "google-client-id",
+  "google-client-secret",
+  "auth-flow",
+  "headless",
+  "api-key",
+  "viewer-api-key",
+  "read-project",
+  "account",
+  "rotate-accounts",
+  "post-as",
+  "channel-id",
+  "playlist-id",
+  "query",
+  "query-channel",
+  "order",
+  "detect",
+  "failover-after",
+  "max-detection-lag",
+  "hint",
+  "hint-interval",
+  "wait-limit",
+  "request-timeout",
+  "start-at",
+  "start-in",
+  "tui",
+  "status-addr",
+  "control-socket",
+  "api-addr",
+  "api-token",
+  "heartbeat-url",
+  "notify",
+  "bell",
+  "sound",
+  "on-new-video",
+  "on-comment-posted",
+  "on-error",
+  "fresh",
+  "refresh-cache",
+  "allow-multiple",
+  "comment-on-current",
+  "check-own-comments",
+  "safe-mode-after",
+  "force",
+  "report",
+  "summary-out",
+  "telemetry",
+  "telemetry-placement",
+  "telemetry-endpoint",
+  "otel-endpoint",
+  "auto-schedule",
+  "record",
+  "replay",
+  "replay-speed",
+];
+
+/// How long an editor gets to finish saving the file before it's read
+const SETTLE_DELAY: Duration = Duration::from_millis(500);
+
+/// Watcher options reloaded from the config file and not picked up by the watcher yet
+static RELOADED_ARGS: Mutex<Option<Args>> = Mutex::new(None);
+
+/// The options of the last reload, if the watcher didn't pick them up yet. It does between polls.
+pub fn take_args() -> Option<Args> {
+  RELOADED_ARGS.lock().unwrap().take()
+}
+
+/// Applies the changes to the config file while watching: the comment, the notification channels and the watcher
+/// options that don't need a restart
+struct Reloader {
+  path: PathBuf,
+  /// The command line the watcher was started with, its options still take precedence over the config file
+  command_line: Vec<OsString>,
+  comment_source: Arc<ReloadableSource>,
+  notifier: Arc<Notifier>,
+  /// The config file as the watcher started with it
+  initial: Table,
+  /// The config file as of the last reload
+  current: Table,
+}
+
+fn read(path: &Path) -> Result<(Table, Config), Box<dyn Error>> {
+  let contents = fs::read_to_string(path)?;
+
+  Ok((toml::from_str(&contents)?, toml::from_str(&contents)?))
+}
+
+fn watch_options(table: &Table) -> Table {
+  match table.get("watch") {
+    Some(Value::Table(watch)) => watch.clone(),
+    _ => Table::new(),
+  }
+}
+
+impl Reloader {
+  /// The watcher options with the reloaded config, the startup ones keeping the values the watcher started with
+  fn parse_args(&self, config: &Config) -> Result<Args, Box<dyn Error>> {
+    let initial = watch_options(&self.initial);
+    let mut watch = config.watch.clone();
+
+    for name in STARTUP_OPTIONS {
+      match initial.get(*name) {
+        Some(value) => watch.insert(name.to_string(), value.clone()),
+        None => watch.remove(*name),
+      };
+    }
+
+    let config = Config {
+      watch,
+      ..Config::default()
+    };
+
+    crate::cli::parse_watcher_args(crate::cli::with_watch_options(self.command_line.clone(), &config))
+  }
+
+  fn reload(&mut self) {
+    let (table, config) = match read(&self.path) {
+      Ok(config) => config,
+      Err(e) => {
+        console::warning(format!(
+          "Ignoring the change to the config file {}: {e}",
+          self.path.display()
+        ));
+        return;
+      }
+    };
+
+    // Editors often write the file more than once per save
+    if table == self.current {
+      return;
+    }
+
+    let args = match self.parse_args(&config) {
+      Ok(args) => args,
+      Err(e) => {
+        console::warning(format!(
+          "Ignoring the change to the config file {}: {e}",
+          self.path.display()
+        ));
+        return;
+      }
+    };
+
+    let previous = watch_options(&self.current);
+    let watch = watch_options(&table);
+    let changed_options: Vec<&String> = previous
+      .keys()
+      .chain(watch.keys().filter(|name| !previous.contains_key(*name)))
+      .filter(|name| previous.get(*name) != watch.get(*name))
+      .collect();
+    let is_changed = |section: &str| self.current.get(section) != table.get(section);
+
+    if is_changed("comment")
+      || is_changed("comments")
+      || changed_options
+        .iter()
+        .any(|name| *name == "comment" || *name == "comment-file")
+    {
+      let source = crate::build_comment_source(&args, config.comment.as_ref(), &config.comments)
+        .and_then(|source| crate::preview_comment(&args, source.as_ref()).map(|_| source));
+
+      match source {
+        Ok(source) => {
+          self.comment_source.replace(source);
+          console::success("Reloaded the comment");
+        }
+        Err(e) => console::error(format!("Failed to reload the comment, keeping the previous one: {e}")),
+      }
+    }
+
+    if is_changed("notify") || changed_options.iter().any(|name| *name == "webhook-url") {
+      match self.notifier.reload(&config.notify, args.webhook_url.clone()) {
+        Ok(()) => console::success("Reloaded the notification settings"),
+        Err(e) => console::error(format!(
+          "Failed to reload the notification settings, keeping the previous ones: {e}"
+        )),
+      }
+    }
+
+    let (startup_options, live_options): (Vec<&String>, Vec<&String>) = changed_options
+      .into_iter()
+      .partition(|name| STARTUP_OPTIONS.contains(&name.as_str()));
+
+    for name in startup_options {
+      console::warning(format!("{name} changed in the config file, restart yfc to apply it"));
+    }
+
+    if !live_options.is_empty() {
+      let names: Vec<&str> = live_options.iter().map(|name| name.as_str()).collect();
+      console::info(format!("Reloaded {} from the config file", names.join(", ")));
+    }
+
+    *RELOADED_ARGS.lock().unwrap() = Some(args);
+    self.current = table;
+  }
+}
+
+/// Watches the config file, applying its changes to the running watcher. Options given on the command line still take
+/// precedence.
+pub fn watch(
+  path: PathBuf,
+  command_line: Vec<OsString>,
+  comment_source: Arc<ReloadableSource>,
+  notifier: Arc<Notifier>,
+) -> Result<(), Box<dyn Error>> {
+  let (table, _) = read(&path)?;
+  let mut reloader = Reloader {
+    path,
+    command_line,
+    comment_source,
+    notifier,
+    initial: table.clone(),
+    current: table,
+  };
+
+  // Editors often save by replacing the file, so its directory is watched rather than the file itself
+  let dir = match reloader.path.parent() {
+    Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+    _ => PathBuf::from("."),
+  };
+  let file_name = reloader.path.file_name().map(OsString::from);
+  let (sender, mut receiver) = mpsc::unbounded_channel();
+  let mut watcher = recommended_watcher(move |event: fs_notify::Result<fs_notify::Event>| {
+    if let Ok(event) = event {
+      if !event.kind.is_access() && event.paths.iter().any(|path| path.file_name() == file_name.as_deref()) {
+        let _ = sender.send(());
+      }
+    }
+  })?;
+
+  watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+  tokio::spawn(async move {
+    // Dropping the watcher stops it
+    let _watcher = watcher;
+
+    while receiver.recv().await.is_some() {
+      sleep(SETTLE_DELAY).await;
+      while receiver.try_recv().is_ok() {}
+
+      reloader.reload();
+    }
+  });
+
+  Ok(())
+}
@@ -0,0 +1,173 @@
+use crate::{console, notify::Event, quota};
+use clap::ValueEnum;
+use google_youtube3::chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+  fmt::Write,
+  fs, io,
+  path::{Path, PathBuf},
+  sync::atomic::{AtomicU64, Ordering},
+};
+
+/// How many weekly reports are kept before the oldest ones get deleted
+const KEPT_WEEKS: usize = 12;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ReportFormat {
+  Json,
+  Markdown,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct WeeklyReport {
+  week: String,
+  uploads: u32,
+  posts: u32,
+  failures: u32,
+  wait_limits_reached: u32,
+  quota_units: u64,
+  activity: Vec<Activity>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Activity {
+  at: DateTime<Utc>,
+  event: String,
+  video_id: Option<String>,
+  detail: String,
+}
+
+/// Keeps a report of the current ISO week up to date under `<data dir>/reports`, starting a new file every week.
+pub struct Reporter {
+  dir: PathBuf,
+  format: ReportFormat,
+  /// Quota units already accounted for in the report
+  quota_reported: AtomicU64,
+}
+
+impl WeeklyReport {
+  fn to_markdown(&self) -> String {
+    let mut markdown = format!("# yfc weekly report: {}\n\n", self.week);
+
+    markdown.push_str("| Uploads | Posts | Failures | Wait limits reached | Quota units |\n");
+    markdown.push_str("|---------|-------|----------|---------------------|-------------|\n");
+    let _ = writeln!(
+      markdown,
+      "| {} | {} | {} | {} | {} |",
+      self.uploads, self.posts, self.failures, self.wait_limits_reached, self.quota_units
+    );
+
+    if !self.activity.is_empty() {
+      markdown.push_str("\n## Activity\n\n");
+
+      for activity in &self.activity {
+        let _ = writeln!(
+          markdown,
+          "- {} `{}` {}: {}",
+          console::format_time(activity.at),
+          activity.event,
+          activity.video_id.as_deref().unwrap_or("-"),
+          activity.detail
+        );
+      }
+    }
+
+    markdown
+  }
+}
+
+impl Reporter {
+  pub fn new(data_dir: &Path, format: ReportFormat) -> Self {
+    Self {
+      dir: data_dir.join("reports"),
+      format,
+      quota_reported: AtomicU64::new(0),
+    }
+  }
+
+  /// Adds the event to this week's report. Reports are a convenience, so failures are only logged.
+  pub fn record(&self, event: &Event) {
+    if let Err(e) = self.update(event) {
+      console::error(format!("Failed to update the weekly report: {e}"));
+    }
+  }
+
+  fn update(&self, event: &Event) -> io::Result<()> {
+    let now = Utc::now();
+    let week = now.iso_week();
+    let name = format!("{}-W{:02}", week.year(), week.week());
+    let json_path = self.dir.join(format!("{name}.json"));
+
+    fs::create_dir_all(&self.dir)?;
+
+    let mut report = match fs::read_to_string(&json_path) {
+      Ok(contents) => serde_json::from_str(&contents).map_err(io::Error::other)?,
+      Err(e) if e.kind() == io::ErrorKind::NotFound => {
+        self.prune()?;
+
+        WeeklyReport {
+          week: name.clone(),
+          ..Default::default()
+        }
+      }
+      Err(e) => return Err(e),
+    };
+
+    match event {
+      Event::VideoDetected { .. } => report.uploads += 1,
+      Event::CommentPosted { .. } => report.posts += 1,
+      Event::PostFailed { .. } => report.failures += 1,
+      Event::WaitLimitReached => report.wait_limits_reached += 1,
+      Event::QuotaExceeded { .. } | Event::CommentEngagement { .. } | Event::VideoObserved { .. } => {}
+    }
+
+    let used = quota::used();
+    report.quota_units += used - self.quota_reported.swap(used, Ordering::Relaxed);
+    report.activity.push(Activity {
+      at: now,
+      event: event.name().into(),
+      video_id: event.video_id().map(String::from),
+      detail: event.description(),
+    });
+
+    fs::write(
+      &json_path,
+      serde_json::to_string_pretty(&report).map_err(io::Error::other)?,
+    )?;
+
+    if let ReportFormat::Markdown = self.format {
+      fs::write(self.dir.join(format!("{name}.md")), report.to_markdown())?;
+    }
+
+    Ok(())
+  }
+
+  /// Deletes the reports of the oldest weeks, keeping the last `KEPT_WEEKS` ones
+  fn prune(&self) -> io::Result<()> {
+    let mut weeks: Vec<String> = fs::read_dir(&self.dir)?
+      .filter_map(|entry| entry.ok())
+      .filter_map(|entry| {
+        let path = entry.path();
+        (path.extension()? == "json").then(|| path.file_stem()?.to_str().map(String::from))?
+      })
+      .collect();
+
+    // Week names (e.g. 2024-W07) sort chronologically
+    weeks.sort();
+
+    // Make room for the report that is about to be created
+    let excess = (weeks.len() + 1).saturating_sub(KEPT_WEEKS);
+
+    for week in &weeks[..excess] {
+      for extension in ["json", "md"] {
+        let path = self.dir.join(format!("{week}.{extension}"));
+
+        if path.exists() {
+          fs::remove_file(path)?;
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
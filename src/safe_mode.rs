@@ -0,0 +1,115 @@
+use crate::console;
+use google_youtube3::chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+  error::Error,
+  fs,
+  io::{self, BufRead, Write},
+  path::{Path, PathBuf},
+};
+
+/// How many run outcomes are kept in the log
+const KEPT_RUNS: usize = 20;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunOutcome {
+  Posted,
+  CommentRejected,
+  AuthFailed,
+  WaitLimitReached,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RunRecord {
+  at: DateTime<Utc>,
+  outcome: RunOutcome,
+}
+
+/// Remembers how the last runs ended, so a streak of rejected comments or failed logins doesn't keep hammering the
+/// account with more attempts that look like spam.
+pub struct SafeMode {
+  path: PathBuf,
+  runs: Vec<RunRecord>,
+}
+
+impl RunOutcome {
+  fn is_failure(self) -> bool {
+    matches!(self, RunOutcome::CommentRejected | RunOutcome::AuthFailed)
+  }
+}
+
+impl SafeMode {
+  pub fn load(data_dir: &Path) -> Self {
+    let path = data_dir.join("runs.json");
+    let runs = fs::read_to_string(&path)
+      .ok()
+      .and_then(|contents| serde_json::from_str(&contents).ok())
+      .unwrap_or_default();
+
+    Self { path, runs }
+  }
+
+  /// Number of runs in a row, counting back from the last one, that ended in a rejection or auth failure
+  fn failure_streak(&self) -> usize {
+    self
+      .runs
+      .iter()
+      .rev()
+      .take_while(|run| run.outcome.is_failure())
+      .count()
+  }
+
+  /// Refuses to go on when the last `threshold` runs all failed, unless forced or confirmed interactively
+  pub fn check(&self, threshold: usize, force: bool) -> Result<(), Box<dyn Error>> {
+    let streak = self.failure_streak();
+
+    if threshold == 0 || streak < threshold || force {
+      return Ok(());
+    }
+
+    let message = format!("Safe mode: the last {streak} runs ended in a rejected comment or an auth failure");
+
+    if !console::is_interactive() {
+      return Err(format!("{message}, pass --force to post again").into());
+    }
+
+    print!("{message}. Post again anyway? [y/N] ");
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+
+    if matches!(answer.trim(), "y" | "Y" | "yes") {
+      Ok(())
+    } else {
+      Err("Aborted by the user".into())
+    }
+  }
+
+  /// Appends the outcome of this run to the log. Failing to save it must not fail the run itself.
+  pub fn record(&mut self, outcome: RunOutcome) {
+    // Other watchers sharing the data directory may have recorded their runs meanwhile
+    if let Some(data_dir) = self.path.parent() {
+      self.runs = Self::load(data_dir).runs;
+    }
+
+    self.runs.push(RunRecord {
+      at: Utc::now(),
+      outcome,
+    });
+
+    let excess = self.runs.len().saturating_sub(KEPT_RUNS);
+    self.runs.drain(..excess);
+
+    let result = self
+      .path
+      .parent()
+      .map_or(Ok(()), fs::create_dir_all)
+      .and_then(|_| fs::write(&self.path, serde_json::to_string_pretty(&self.runs).unwrap()));
+
+    if let Err(e) = result {
+      console::error(format!("Failed to save the run log: {e}"));
+    }
+  }
+}
@@ -0,0 +1,183 @@
+use crate::comment;
+use clap::ValueEnum;
+use std::{fmt, ops::Range};
+
+/// Least letters for a comment to be judged on its capital letters
+const CAPS_MIN_LETTERS: usize = 10;
+/// Share of capital letters above which a comment is shouting
+const CAPS_MAX_RATIO: f64 = 0.5;
+/// Most emoji a comment keeps
+const MAX_EMOJI: usize = 3;
+/// Least digits in a run (with spaces, dashes, dots, parentheses or + in between) for it to look like a phone number
+const PHONE_MIN_DIGITS: usize = 7;
+
+/// What to do with the parts of the comment known to get it held for review as spam
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SanitizePolicy {
+  /// Warn about them and post the comment as is
+  Warn,
+  /// Remove them from the comment
+  Strip,
+}
+
+/// A pattern known to get comments held for review as spam
+#[derive(Clone, Copy)]
+pub enum Finding {
+  Link,
+  PhoneNumber,
+  Caps,
+  Emoji,
+}
+
+impl fmt::Display for Finding {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Finding::Link => write!(f, "a link"),
+      Finding::PhoneNumber => write!(f, "a phone number"),
+      Finding::Caps => write!(f, "too many capital letters"),
+      Finding::Emoji => write!(f, "more than {MAX_EMOJI} emoji"),
+    }
+  }
+}
+
+/// Close enough to tell emoji from text: the pictographs, symbols and dingbats blocks
+fn is_emoji(c: char) -> bool {
+  matches!(c as u32, 0x1F000..=0x1FAFF | 0x2600..=0x27BF | 0x2B00..=0x2BFF)
+}
+
+/// The byte ranges of the whitespace-separated words of the text
+fn words(text: &str) -> impl Iterator<Item = (Range<usize>, &str)> {
+  text.split_whitespace().map(move |word| {
+    let start = word.as_ptr() as usize - text.as_ptr() as usize;
+    (start..start + word.len(), word)
+  })
+}
+
+fn link_ranges(text: &str) -> Vec<Range<usize>> {
+  words(text)
+    .filter(|(_, word)| comment::has_link(word))
+    .map(|(range, _)| range)
+    .collect()
+}
+
+fn phone_number_ranges(text: &str) -> Vec<Range<usize>> {
+  let mut ranges = Vec::new();
+  // Where the run started, where its last digit ends and how many digits it has
+  let mut run: Option<(usize, usize, usize)> = None;
+
+  for (index, c) in text.char_indices().chain([(text.len(), '\0')]) {
+    match (c, &mut run) {
+      ('0'..='9', Some((_, end, digits))) => {
+        *end = index + 1;
+        *digits += 1;
+      }
+      ('0'..='9', None) => {
+        // A leading + or ( belongs to the number
+        let start = if text[..index].ends_with(['+', '(']) {
+          index - 1
+        } else {
+          index
+        };
+
+        run = Some((start, index + 1, 1));
+      }
+      (' ' | '-' | '.' | '(' | ')' | '+', _) => {}
+      _ => {
+        if let Some((start, end, digits)) = run.take() {
+          if digits >= PHONE_MIN_DIGITS {
+            ranges.push(start..end);
+          }
+        }
+      }
+    }
+  }
+
+  ranges
+}
+
+fn is_shouting(text: &str) -> bool {
+  let letters = text.chars().filter(|c| c.is_alphabetic()).count();
+  let capitals = text.chars().filter(|c| c.is_uppercase()).count();
+
+  letters >= CAPS_MIN_LETTERS && capitals as f64 / letters as f64 > CAPS_MAX_RATIO
+}
+
+/// The patterns of the comment known to get it held for review as spam
+pub fn findings(text: &str) -> Vec<Finding> {
+  let mut findings = Vec::new();
+
+  if !link_ranges(text).is_empty() {
+    findings.push(Finding::Link);
+  }
+
+  if !phone_number_ranges(text).is_empty() {
+    findings.push(Finding::PhoneNumber);
+  }
+
+  if is_shouting(text) {
+    findings.push(Finding::Caps);
+  }
+
+  if text.chars().filter(|c| is_emoji(*c)).count() > MAX_EMOJI {
+    findings.push(Finding::Emoji);
+  }
+
+  findings
+}
+
+/// Removes the links and phone numbers, lowers the words in capitals when the comment is shouting and drops the emoji
+/// past the first few
+pub fn strip(text: &str) -> String {
+  let mut removed = link_ranges(text);
+  removed.extend(phone_number_ranges(text));
+
+  let mut stripped = String::with_capacity(text.len());
+  let mut emoji = 0;
+  // Joiners and variation selectors of a dropped emoji go with it
+  let mut dropping_emoji = false;
+
+  for (index, c) in text.char_indices() {
+    if removed.iter().any(|range| range.contains(&index)) {
+      continue;
+    }
+
+    if is_emoji(c) {
+      emoji += 1;
+      dropping_emoji = emoji > MAX_EMOJI;
+    } else if !matches!(c, '\u{200D}' | '\u{FE0F}') {
+      dropping_emoji = false;
+    }
+
+    if !dropping_emoji {
+      stripped.push(c);
+    }
+  }
+
+  if is_shouting(&stripped) {
+    let mut lowered = String::with_capacity(stripped.len());
+    let mut copied = 0;
+
+    for (range, word) in words(&stripped) {
+      lowered.push_str(&stripped[copied..range.start]);
+      copied = range.end;
+
+      // Single letters like "I" are capitals anyway
+      if word.chars().filter(|c| c.is_alphabetic()).count() > 1 && !word.chars().any(char::is_lowercase) {
+        lowered.push_str(&word.to_lowercase());
+      } else {
+        lowered.push_str(word);
+      }
+    }
+
+    stripped = lowered;
+  }
+
+  // What was removed leaves its spaces behind
+  stripped
+    .lines()
+    .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+    .collect::<Vec<_>>()
+    .join("\n")
+    .trim()
+    .into()
+}
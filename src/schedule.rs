@@ -0,0 +1,439 @@
+use crate::youtube::HttpsClient;
+use chrono_tz::Tz;
+use google_youtube3::{
+  chrono::{DateTime, Datelike, Days, NaiveTime, TimeZone, Timelike, Utc, Weekday},
+  hyper,
+};
+use std::{collections::HashSet, error::Error, fmt, str::FromStr, time::Duration};
+
+const MINUTES_PER_DAY: i64 = 24 * 60;
+
+/// When a channel usually uploads, learned from the publish times of its past videos. It drives the burst scheduling:
+/// polling fast around the usual upload times and slowly otherwise.
+pub struct UploadPattern {
+  /// Weekday and minute of the day (UTC) of each known upload
+  uploads: Vec<(Weekday, i64)>,
+}
+
+/// A daily time range (UTC) to poll fast in, e.g. 17:30-19:00. It may wrap around midnight.
+#[derive(Clone, Copy)]
+pub struct HotWindow {
+  start: i64,
+  end: i64,
+}
+
+/// A weekly time range to watch in, e.g. "Mon-Fri 16:45-18:00 Europe/Berlin". The days default to every day and the
+/// time zone to UTC. A range wrapping around midnight belongs to the day it starts on.
+#[derive(Clone)]
+pub struct WatchSchedule {
+  weekdays: HashSet<Weekday>,
+  start: i64,
+  end: i64,
+  time_zone: Tz,
+}
+
+fn minute_of_day<T: TimeZone>(time: &DateTime<T>) -> i64 {
+  (time.hour() * 60 + time.minute()) as i64
+}
+
+impl UploadPattern {
+  pub fn new(published: &[DateTime<Utc>]) -> Self {
+    Self {
+      uploads: published
+        .iter()
+        .map(|time| (time.weekday(), minute_of_day(time)))
+        .collect(),
+    }
+  }
+
+  /// Whether `now` is within `window` of the time of day of a past upload, on a weekday the channel uploads on
+  pub fn is_hot(&self, now: DateTime<Utc>, window: Duration) -> bool {
+    let window = (window.as_secs() / 60) as i64;
+    let weekdays: HashSet<Weekday> = self.uploads.iter().map(|(weekday, _)| *weekday).collect();
+
+    if !weekdays.contains(&now.weekday()) {
+      return false;
+    }
+
+    let now = minute_of_day(&now);
+
+    self.uploads.iter().any(|(_, minute)| {
+      let distance = (now - minute).rem_euclid(MINUTES_PER_DAY);
+
+      distance.min(MINUTES_PER_DAY - distance) <= window
+    })
+  }
+
+  pub fn len(&self) -> usize {
+    self.uploads.len()
+  }
+
+  /// Number of uploads per weekday, Monday first
+  pub fn weekdays(&self) -> [usize; 7] {
+    let mut counts = [0; 7];
+
+    for (weekday, _) in &self.uploads {
+      counts[weekday.num_days_from_monday() as usize] += 1;
+    }
+
+    counts
+  }
+
+  /// The times of day the uploads cluster around, with the number of uploads in each, busiest first. Uploads less than
+  /// `window` apart are in the same cluster, which is widened by `window` on both sides like `is_hot` does.
+  pub fn hot_windows(&self, window: Duration) -> Vec<(HotWindow, usize)> {
+    let window = (window.as_secs() / 60) as i64;
+    let mut minutes: Vec<i64> = self.uploads.iter().map(|(_, minute)| *minute).collect();
+    minutes.sort_unstable();
+
+    // (first minute, last minute, uploads)
+    let mut clusters: Vec<(i64, i64, usize)> = Vec::new();
+
+    for minute in minutes {
+      match clusters.last_mut() {
+        Some((_, last, count)) if minute - *last <= window => {
+          *last = minute;
+          *count += 1;
+        }
+        _ => clusters.push((minute, minute, 1)),
+      }
+    }
+
+    // The last cluster of the day may go on past midnight into the first one
+    if clusters.len() > 1 {
+      let (first, _, count) = clusters[0];
+      let (_, last, _) = clusters[clusters.len() - 1];
+
+      if first + MINUTES_PER_DAY - last <= window {
+        clusters.remove(0);
+        let wrapping = clusters.last_mut().unwrap();
+        wrapping.1 = first + MINUTES_PER_DAY;
+        wrapping.2 += count;
+      }
+    }
+
+    let mut windows: Vec<(HotWindow, usize)> = clusters
+      .into_iter()
+      .map(|(first, last, count)| {
+        (
+          HotWindow {
+            start: (first - window).rem_euclid(MINUTES_PER_DAY),
+            end: (last + window).rem_euclid(MINUTES_PER_DAY),
+          },
+          count,
+        )
+      })
+      .collect();
+    windows.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+    windows
+  }
+}
+
+fn parse_time_of_day(value: &str) -> Option<i64> {
+  let (hours, minutes) = value.trim().split_once(':')?;
+  let (hours, minutes): (i64, i64) = (hours.parse().ok()?, minutes.parse().ok()?);
+
+  ((0..24).contains(&hours) && (0..60).contains(&minutes)).then_some(hours * 60 + minutes)
+}
+
+impl FromStr for HotWindow {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    let (start, end) = value
+      .split_once('-')
+      .and_then(|(start, end)| Some((parse_time_of_day(start)?, parse_time_of_day(end)?)))
+      .ok_or("expected a time range such as 17:30-19:00")?;
+
+    Ok(Self { start, end })
+  }
+}
+
+impl fmt::Display for HotWindow {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "{:02}:{:02}-{:02}:{:02}",
+      self.start / 60,
+      self.start % 60,
+      self.end / 60,
+      self.end % 60
+    )
+  }
+}
+
+impl HotWindow {
+  pub fn contains(&self, now: DateTime<Utc>) -> bool {
+    let now = minute_of_day(&now);
+
+    if self.start <= self.end {
+      (self.start..self.end).contains(&now)
+    } else {
+      now >= self.start || now < self.end
+    }
+  }
+}
+
+/// Parses a day list such as Mon-Fri or Mon,Wed,Sat-Sun, ranges may wrap around the end of the week
+fn parse_weekdays(value: &str) -> Option<HashSet<Weekday>> {
+  let mut weekdays = HashSet::new();
+
+  for part in value.split(',') {
+    let (first, last) = part.split_once('-').unwrap_or((part, part));
+    let (mut weekday, last): (Weekday, Weekday) = (first.parse().ok()?, last.parse().ok()?);
+
+    weekdays.insert(weekday);
+
+    while weekday != last {
+      weekday = weekday.succ();
+      weekdays.insert(weekday);
+    }
+  }
+
+  Some(weekdays)
+}
+
+impl FromStr for WatchSchedule {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    let mut weekdays = None;
+    let mut range = None;
+    let mut time_zone = None;
+
+    for part in value.split_whitespace() {
+      if range.is_none() && part.contains(':') {
+        range = Some(part.parse::<HotWindow>()?);
+      } else if range.is_none() && weekdays.is_none() {
+        weekdays = Some(parse_weekdays(part).ok_or_else(|| format!("invalid days {part}, expected e.g. Mon-Fri"))?);
+      } else if range.is_some() && time_zone.is_none() {
+        time_zone = Some(
+          part
+            .parse::<Tz>()
+            .map_err(|_| format!("unknown time zone {part}, expected e.g. Europe/Berlin"))?,
+        );
+      } else {
+        return Err("expected a schedule such as \"Mon-Fri 16:45-18:00 Europe/Berlin\"".into());
+      }
+    }
+
+    let range = range.ok_or("missing the time range, e.g. 16:45-18:00")?;
+
+    Ok(Self {
+      weekdays: weekdays.unwrap_or_else(|| (0..7).filter_map(|day| Weekday::try_from(day).ok()).collect()),
+      start: range.start,
+      end: range.end,
+      time_zone: time_zone.unwrap_or(Tz::UTC),
+    })
+  }
+}
+
+impl WatchSchedule {
+  pub fn contains(&self, now: DateTime<Utc>) -> bool {
+    let now = now.with_timezone(&self.time_zone);
+    let minute = minute_of_day(&now);
+
+    if self.start <= self.end {
+      self.weekdays.contains(&now.weekday()) && (self.start..self.end).contains(&minute)
+    } else {
+      (self.weekdays.contains(&now.weekday()) && minute >= self.start)
+        || (self.weekdays.contains(&now.weekday().pred()) && minute < self.end)
+    }
+  }
+
+  /// When the next window after `now` starts, in the time zone of the schedule
+  pub fn next_start(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let today = now.with_timezone(&self.time_zone).date_naive();
+    let start = NaiveTime::from_num_seconds_from_midnight_opt((self.start * 60) as u32, 0)?;
+
+    // Eight days so today's window is found again next week when it's already past
+    (0..8)
+      .filter_map(|offset| today.checked_add_days(Days::new(offset)))
+      .filter(|day| self.weekdays.contains(&day.weekday()))
+      // A start skipped by a daylight saving time change has no local time, that day is left out
+      .filter_map(|day| self.time_zone.from_local_datetime(&day.and_time(start)).earliest())
+      .map(|start| start.with_timezone(&Utc))
+      .find(|start| *start > now)
+  }
+}
+
+/// Fetches the publish times of the latest uploads (up to 15) from the channel RSS feed. It costs no API quota, so it
+/// is a cheap way to seed the upload pattern of a channel that was never watched before.
+pub async fn fetch_rss_publish_times(
+  client: &HttpsClient,
+  channel_id: &str,
+) -> Result<Vec<DateTime<Utc>>, Box<dyn Error>> {
+  let url = format!("https://www.youtube.com/feeds/videos.xml?channel_id={channel_id}");
+  let response = client.get(url.parse()?).await?;
+
+  if !response.status().is_success() {
+    return Err(format!("RSS feed returned {}", response.status()).into());
+  }
+
+  let body = hyper::body::to_bytes(response.into_body()).await?;
+  let feed = String::from_utf8_lossy(&body);
+
+  // The feed itself has a <published> tag too (the channel creation), so only look inside the entries
+  Ok(
+    feed
+      .split("<entry>")
+      .skip(1)
+      .filter_map(|entry| {
+        let start = entry.find("<published>")? + "<published>".len();
+        let end = start + entry[start..].find("</published>")?;
+
+        DateTime::parse_from_rfc3339(&entry[start..end]).ok()
+      })
+      .map(|time| time.with_timezone(&Utc))
+      .collect(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap()
+  }
+
+  fn schedule(value: &str) -> WatchSchedule {
+    value.parse().unwrap()
+  }
+
+  #[test]
+  fn parses_hot_windows() {
+    assert_eq!("17:30-19:00".parse::<HotWindow>().unwrap().to_string(), "17:30-19:00");
+    assert!("17:30".parse::<HotWindow>().is_err());
+    assert!("24:00-01:00".parse::<HotWindow>().is_err());
+  }
+
+  #[test]
+  fn hot_windows_wrap_around_midnight() {
+    let window: HotWindow = "23:00-01:00".parse().unwrap();
+
+    assert!(window.contains(utc(2024, 6, 3, 23, 30)));
+    assert!(window.contains(utc(2024, 6, 4, 0, 30)));
+    assert!(!window.contains(utc(2024, 6, 4, 1, 0)));
+    assert!(!window.contains(utc(2024, 6, 3, 22, 59)));
+  }
+
+  #[test]
+  fn parses_schedules() {
+    assert!("Mon-Fri 16:45-18:00 Europe/Berlin".parse::<WatchSchedule>().is_ok());
+    assert!("16:45-18:00".parse::<WatchSchedule>().is_ok());
+    assert!("Mon-Fri".parse::<WatchSchedule>().is_err());
+    assert!("Someday 16:45-18:00".parse::<WatchSchedule>().is_err());
+    assert!("16:45-18:00 Mars/Olympus".parse::<WatchSchedule>().is_err());
+  }
+
+  #[test]
+  fn schedules_only_contain_their_days() {
+    let schedule = schedule("Mon-Fri 16:45-18:00");
+
+    // Friday, then Saturday
+    assert!(schedule.contains(utc(2024, 6, 7, 17, 0)));
+    assert!(!schedule.contains(utc(2024, 6, 8, 17, 0)));
+  }
+
+  #[test]
+  fn day_ranges_wrap_around_the_end_of_the_week() {
+    let schedule = schedule("Sat-Mon 12:00-13:00");
+
+    // Saturday, Sunday, Monday, then Tuesday
+    assert!(schedule.contains(utc(2024, 6, 8, 12, 30)));
+    assert!(schedule.contains(utc(2024, 6, 9, 12, 30)));
+    assert!(schedule.contains(utc(2024, 6, 10, 12, 30)));
+    assert!(!schedule.contains(utc(2024, 6, 11, 12, 30)));
+  }
+
+  #[test]
+  fn a_window_past_midnight_belongs_to_the_day_it_starts_on() {
+    let schedule = schedule("Sun 23:00-01:00");
+
+    // Sunday night and the small hours of Monday, the start of the week
+    assert!(schedule.contains(utc(2024, 6, 9, 23, 30)));
+    assert!(schedule.contains(utc(2024, 6, 10, 0, 30)));
+    // The small hours of Sunday follow Saturday night, which isn't in the schedule
+    assert!(!schedule.contains(utc(2024, 6, 9, 0, 30)));
+  }
+
+  #[test]
+  fn schedules_follow_daylight_saving_time() {
+    let schedule = schedule("Mon 16:45-18:00 Europe/Berlin");
+
+    // 16:00 UTC is 17:00 in the winter and 18:00 in the summer in Berlin
+    assert!(schedule.contains(utc(2024, 1, 8, 16, 0)));
+    assert!(!schedule.contains(utc(2024, 6, 3, 16, 0)));
+  }
+
+  #[test]
+  fn finds_the_next_start_on_a_later_day() {
+    let schedule = schedule("Mon-Fri 16:45-18:00 Europe/Berlin");
+
+    // From Friday evening to Monday afternoon, in summer time
+    assert_eq!(
+      schedule.next_start(utc(2024, 6, 7, 20, 0)),
+      Some(utc(2024, 6, 10, 14, 45))
+    );
+    // Earlier the same day
+    assert_eq!(
+      schedule.next_start(utc(2024, 6, 7, 8, 0)),
+      Some(utc(2024, 6, 7, 14, 45))
+    );
+  }
+
+  #[test]
+  fn finds_today_again_next_week() {
+    let schedule = schedule("Mon 09:00-10:00");
+
+    assert_eq!(
+      schedule.next_start(utc(2024, 6, 3, 12, 0)),
+      Some(utc(2024, 6, 10, 9, 0))
+    );
+  }
+
+  #[test]
+  fn skips_a_start_lost_to_daylight_saving_time() {
+    let schedule = schedule("02:30-03:30 Europe/Berlin");
+
+    // 02:30 doesn't exist on March 31st 2024 in Berlin, the clocks going from 02:00 to 03:00
+    assert_eq!(
+      schedule.next_start(utc(2024, 3, 30, 12, 0)),
+      Some(utc(2024, 4, 1, 0, 30))
+    );
+  }
+
+  #[test]
+  fn takes_the_first_of_a_repeated_start() {
+    let schedule = schedule("02:30-03:30 Europe/Berlin");
+
+    // 02:30 happens twice on October 27th 2024 in Berlin, first in summer time
+    assert_eq!(
+      schedule.next_start(utc(2024, 10, 26, 12, 0)),
+      Some(utc(2024, 10, 27, 0, 30))
+    );
+  }
+
+  #[test]
+  fn upload_clusters_wrap_around_midnight() {
+    let pattern = UploadPattern::new(&[utc(2024, 6, 3, 23, 50), utc(2024, 6, 4, 0, 10)]);
+    let windows = pattern.hot_windows(Duration::from_secs(30 * 60));
+
+    assert_eq!(windows.len(), 1);
+    assert_eq!(windows[0].0.to_string(), "23:20-00:40");
+    assert_eq!(windows[0].1, 2);
+  }
+
+  #[test]
+  fn uploads_are_hot_across_midnight() {
+    let pattern = UploadPattern::new(&[utc(2024, 6, 3, 23, 50)]);
+
+    // Monday 00:05 is 15 minutes from the time of day of Monday's upload
+    assert!(pattern.is_hot(utc(2024, 6, 10, 0, 5), Duration::from_secs(30 * 60)));
+    assert!(!pattern.is_hot(utc(2024, 6, 10, 1, 0), Duration::from_secs(30 * 60)));
+    // Not a day the channel uploads on
+    assert!(!pattern.is_hot(utc(2024, 6, 11, 23, 50), Duration::from_secs(30 * 60)));
+  }
+}
@@ -0,0 +1,97 @@
+use crate::{
+  quota,
+  ratelimit::RateLimitHint,
+  youtube::{with_timeout, LatestVideo, YoutubeClient},
+};
+use clap::ValueEnum;
+
+/// Parts of the search results actually read
+const SEARCH_RESULT_FIELDS: &str = "items(id/videoId,snippet(publishedAt,title,description))";
+
+/// How the search results are sorted, only the first ones are looked at
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SearchOrder {
+  /// Newest first, to be early on new videos
+  Date,
+  /// Most relevant to the query first
+  Relevance,
+  /// Most viewed first
+  ViewCount,
+  /// Best rated first
+  Rating,
+}
+
+impl SearchOrder {
+  fn as_param(self) -> &'static str {
+    match self {
+      SearchOrder::Date => "date",
+      SearchOrder::Relevance => "relevance",
+      SearchOrder::ViewCount => "viewCount",
+      SearchOrder::Rating => "rating",
+    }
+  }
+}
+
+/// What the watcher keeps its state under when searching, as there is no channel. It names the lock file as well.
+pub fn state_key(query: &str) -> String {
+  let words: Vec<String> = query
+    .split(|c: char| !c.is_alphanumeric())
+    .filter(|word| !word.is_empty())
+    .map(str::to_lowercase)
+    .collect();
+
+  format!("search-{}", words.join("-"))
+}
+
+/// The first `count` videos matching the query, only among the videos of the channel if given. A search costs 100
+/// units whatever the count.
+pub async fn search_videos(
+  client: &YoutubeClient,
+  query: &str,
+  channel_id: Option<&str>,
+  order: SearchOrder,
+  count: u32,
+  api_key: Option<&str>,
+) -> google_youtube3::Result<Vec<LatestVideo>> {
+  quota::spend(quota::SEARCH_COST);
+
+  let mut call = client
+    .search()
+    .list(&vec!["snippet".into()])
+    .q(query)
+    .add_type("video")
+    .order(order.as_param())
+    .max_results(count)
+    .param("fields", SEARCH_RESULT_FIELDS);
+
+  if let Some(channel_id) = channel_id {
+    call = call.channel_id(channel_id);
+  }
+
+  if let Some(api_key) = api_key {
+    call = call.param("key", api_key);
+  }
+
+  let (_, result) = with_timeout(call.delegate(&mut RateLimitHint).doit()).await?;
+
+  Ok(
+    result
+      .items
+      .unwrap_or_default()
+      .into_iter()
+      .filter_map(|item| {
+        let id = item.id?.video_id?;
+        let snippet = item.snippet.unwrap_or_default();
+
+        Some(LatestVideo {
+          id,
+          title: snippet.title.unwrap_or_default(),
+          is_short: snippet.description.unwrap_or_default().contains("#shorts"),
+          may_be_short: false,
+          published_at: snippet.published_at,
+          pushed: false,
+        })
+      })
+      .collect(),
+  )
+}
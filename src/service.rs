@@ -0,0 +1,303 @@
+use crate::{
+  cli::Args,
+  shutdown::{self, Interrupted},
+};
+use google_youtube3::chrono::Local;
+use std::{
+  env,
+  error::Error,
+  ffi::OsString,
+  fs::{self, File, OpenOptions},
+  io::{self, BufRead, BufReader, PipeReader, PipeWriter, Write},
+  os::windows::io::AsRawHandle,
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex, OnceLock},
+  thread,
+  time::Duration,
+};
+use windows_service::{
+  define_windows_service,
+  service::{
+    ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode, ServiceInfo,
+    ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+  },
+  service_control_handler::{self, ServiceControlHandlerResult, ServiceStatusHandle},
+  service_dispatcher,
+  service_manager::{ServiceManager, ServiceManagerAccess},
+};
+use windows_sys::Win32::{
+  Foundation::HANDLE,
+  System::Console::{SetStdHandle, STD_ERROR_HANDLE, STD_OUTPUT_HANDLE},
+};
+
+/// Service name used by `yfc service` when none is given
+pub const DEFAULT_NAME: &str = "yfc";
+
+/// Size the log file is rotated at
+const MAX_LOG_SIZE: u64 = 10 * 1024 * 1024;
+/// Number of rotated log files kept next to the current one
+const ROTATED_LOGS_KEPT: usize = 3;
+/// How long Windows is told to wait for the watcher to stop, it stops between polls
+const STOP_WAIT_HINT: Duration = Duration::from_secs(30);
+
+/// Error Windows gives when `yfc service run` isn't started by the service control manager
+const ERROR_FAILED_SERVICE_CONTROLLER_CONNECT: i32 = 1063;
+
+/// Name, watcher arguments and command line of the service, handed over to the thread the dispatcher runs it on
+static SERVICE: Mutex<Option<(String, Args, Vec<OsString>)>> = Mutex::new(None);
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Registers a service starting the watcher with the config file when Windows starts. It runs as LocalSystem, so the
+/// data directory is given explicitly rather than being the one of that account.
+pub fn install(name: &str, config_path: &Path, data_dir: &Path) -> Result<(), Box<dyn Error>> {
+  let config_path = fs::canonicalize(config_path).map_err(|e| {
+    format!(
+      "Failed to find the config file {}, create it first (e.g. with yfc config migrate): {e}",
+      config_path.display()
+    )
+  })?;
+
+  fs::create_dir_all(data_dir)?;
+  let data_dir = fs::canonicalize(data_dir)?;
+
+  let manager = ServiceManager::local_computer(
+    None::<&str>,
+    ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+  )
+  .map_err(|e| format!("Failed to connect to the service manager, is this an administrator prompt? {e}"))?;
+  let info = ServiceInfo {
+    name: name.into(),
+    display_name: format!("YouTube first comment ({name})").into(),
+    service_type: ServiceType::OWN_PROCESS,
+    start_type: ServiceStartType::AutoStart,
+    error_control: ServiceErrorControl::Normal,
+    executable_path: env::current_exe()?,
+    launch_arguments: vec![
+      "service".into(),
+      "run".into(),
+      "--name".into(),
+      name.into(),
+      "--config".into(),
+      config_path.into(),
+      "--data-dir".into(),
+      data_dir.clone().into(),
+    ],
+    dependencies: Vec::new(),
+    account_name: None,
+    account_password: None,
+  };
+  let service = manager
+    .create_service(&info, ServiceAccess::CHANGE_CONFIG)
+    .map_err(|e| format!("Failed to install the {name} service: {e}"))?;
+
+  service.set_description("Posts the first comment on the new videos of a YouTube channel")?;
+
+  println!("Installed the {name} service, start it with: sc start {name}");
+  println!("It logs to {}", get_log_path(&data_dir).display());
+
+  Ok(())
+}
+
+/// Stops the service if it's running and removes it
+pub fn uninstall(name: &str) -> Result<(), Box<dyn Error>> {
+  let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+    .map_err(|e| format!("Failed to connect to the service manager, is this an administrator prompt? {e}"))?;
+  let service = manager
+    .open_service(
+      name,
+      ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+    )
+    .map_err(|e| format!("Failed to open the {name} service: {e}"))?;
+
+  if service.query_status()?.current_state != ServiceState::Stopped {
+    service.stop()?;
+  }
+
+  // Windows removes it once it's stopped
+  service.delete()?;
+
+  println!("Uninstalled the {name} service");
+
+  Ok(())
+}
+
+/// Runs the watcher as the service, until Windows stops it. Only the service control manager can start it this way.
+pub fn run(name: String, command_line: Vec<OsString>) -> Result<(), Box<dyn Error>> {
+  let args = crate::cli::parse_watcher_args(crate::cli::with_config_options(command_line.clone())?)?;
+
+  *SERVICE.lock().unwrap() = Some((name.clone(), args, command_line));
+
+  // The dispatcher blocks until the service stops, running it on a thread of its own
+  tokio::task::block_in_place(|| service_dispatcher::start(&name, ffi_service_main)).map_err(|e| match e {
+    windows_service::Error::Winapi(e) if e.raw_os_error() == Some(ERROR_FAILED_SERVICE_CONTROLLER_CONNECT) => {
+      "yfc service run is started by Windows, install the service with yfc service install".into()
+    }
+    e => e.into(),
+  })
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+  let (name, args, command_line) = SERVICE
+    .lock()
+    .unwrap()
+    .take()
+    .expect("the service is only started once");
+
+  // There's no console to print to. Without a log file, the output is lost.
+  let logger = Logger::start(get_log_path(&crate::get_data_dir())).ok();
+
+  if let Err(e) = run_service(&name, args, command_line) {
+    println!("The {name} service failed: {e}");
+  }
+
+  if let Some(logger) = logger {
+    logger.stop();
+  }
+}
+
+fn run_service(name: &str, args: Args, command_line: Vec<OsString>) -> Result<(), Box<dyn Error>> {
+  let status_handle: Arc<OnceLock<ServiceStatusHandle>> = Arc::default();
+  let event_handler = {
+    let status_handle = status_handle.clone();
+
+    move |control| match control {
+      ServiceControl::Stop | ServiceControl::Shutdown => {
+        shutdown::request();
+
+        if let Some(status_handle) = status_handle.get() {
+          set_status(status_handle, ServiceState::StopPending, ServiceExitCode::NO_ERROR);
+        }
+
+        ServiceControlHandlerResult::NoError
+      }
+      ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+      _ => ServiceControlHandlerResult::NotImplemented,
+    }
+  };
+  let registered = service_control_handler::register(name, event_handler)?;
+  let status_handle = status_handle.get_or_init(|| registered);
+
+  set_status(status_handle, ServiceState::Running, ServiceExitCode::NO_ERROR);
+
+  // Being stopped by Windows is the usual way for the service to end
+  let result = match tokio::runtime::Runtime::new()?.block_on(crate::run(args, command_line)) {
+    Err(e) if e.is::<Interrupted>() => Ok(()),
+    result => result,
+  };
+  let exit_code = match &result {
+    Ok(()) => ServiceExitCode::NO_ERROR,
+    Err(e) => ServiceExitCode::ServiceSpecific(crate::exit::code(e.as_ref()) as u32),
+  };
+
+  set_status(status_handle, ServiceState::Stopped, exit_code);
+
+  result
+}
+
+fn set_status(status_handle: &ServiceStatusHandle, state: ServiceState, exit_code: ServiceExitCode) {
+  let status = ServiceStatus {
+    service_type: ServiceType::OWN_PROCESS,
+    current_state: state,
+    controls_accepted: match state {
+      ServiceState::Running => ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+      _ => ServiceControlAccept::empty(),
+    },
+    exit_code,
+    checkpoint: 0,
+    wait_hint: match state {
+      ServiceState::StopPending => STOP_WAIT_HINT,
+      _ => Duration::ZERO,
+    },
+    process_id: None,
+  };
+
+  if let Err(e) = status_handle.set_service_status(status) {
+    println!("Failed to report the {state:?} state to Windows: {e}");
+  }
+}
+
+fn get_log_path(data_dir: &Path) -> PathBuf {
+  data_dir.join("logs").join("service.log")
+}
+
+/// Sends what the watcher prints to a log file, with the time of every line. The file is rotated when it grows past
+/// `MAX_LOG_SIZE`.
+struct Logger {
+  writer: PipeWriter,
+  thread: thread::JoinHandle<()>,
+}
+
+fn redirect_output(handle: HANDLE) {
+  for std_handle in [STD_OUTPUT_HANDLE, STD_ERROR_HANDLE] {
+    // SAFETY: the handle is either null or the pipe writer, which the logger keeps open until it's redirected again
+    unsafe { SetStdHandle(std_handle, handle) };
+  }
+}
+
+impl Logger {
+  fn start(path: PathBuf) -> io::Result<Self> {
+    if let Some(dir) = path.parent() {
+      fs::create_dir_all(dir)?;
+    }
+
+    let file = open_log(&path)?;
+    let (reader, writer) = io::pipe()?;
+
+    // The standard streams are looked up on every write, so printing goes to the pipe from now on
+    redirect_output(writer.as_raw_handle() as HANDLE);
+
+    Ok(Self {
+      writer,
+      thread: thread::spawn(move || write_log(reader, file, &path)),
+    })
+  }
+
+  /// Waits for everything printed so far to be written
+  fn stop(self) {
+    let _ = io::stdout().flush();
+    redirect_output(0);
+
+    // The log thread is done once the pipe is closed
+    drop(self.writer);
+    let _ = self.thread.join();
+  }
+}
+
+fn open_log(path: &Path) -> io::Result<File> {
+  OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// `service.log.1` is the most recent rotated file
+fn get_rotated_log_path(path: &Path, index: usize) -> PathBuf {
+  let mut path = path.as_os_str().to_owned();
+  path.push(format!(".{index}"));
+
+  path.into()
+}
+
+fn rotate(path: &Path) -> io::Result<File> {
+  for index in (1..ROTATED_LOGS_KEPT).rev() {
+    let _ = fs::rename(get_rotated_log_path(path, index), get_rotated_log_path(path, index + 1));
+  }
+
+  fs::rename(path, get_rotated_log_path(path, 1))?;
+
+  open_log(path)
+}
+
+fn write_log(reader: PipeReader, mut file: File, path: &Path) {
+  for line in BufReader::new(reader).lines() {
+    let Ok(line) = line else {
+      break;
+    };
+
+    let _ = writeln!(file, "{} {line}", Local::now().format("%Y-%m-%d %H:%M:%S"));
+
+    if file.metadata().is_ok_and(|metadata| metadata.len() >= MAX_LOG_SIZE) {
+      if let Ok(rotated) = rotate(path) {
+        file = rotated;
+      }
+    }
+  }
+}
@@ -0,0 +1,93 @@
+use crate::console;
+use std::{
+  error::Error,
+  fmt,
+  future::Future,
+  process,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Once,
+  },
+};
+use tokio::sync::Notify;
+
+/// Exit code of a watcher stopped by SIGINT or SIGTERM, the usual one for Ctrl+C
+pub const EXIT_CODE: i32 = 130;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Wakes up the waits that end on a stop request
+static REQUESTS: Notify = Notify::const_new();
+
+/// The signals are listened to once, however many watchers run
+static LISTENING: Once = Once::new();
+
+/// The watcher was stopped by SIGINT or SIGTERM
+#[derive(Debug)]
+pub struct Interrupted;
+
+impl fmt::Display for Interrupted {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "interrupted")
+  }
+}
+
+impl Error for Interrupted {}
+
+/// Asks the watcher to stop between polls, which the signals do and Windows does to its service
+pub fn request() {
+  // A second signal means the clean stop takes too long
+  if REQUESTED.swap(true, Ordering::Relaxed) {
+    process::exit(EXIT_CODE);
+  }
+
+  REQUESTS.notify_waiters();
+  console::warning("Stopping, press Ctrl+C again to quit right away");
+}
+
+/// Turns SIGINT (Ctrl+C) and SIGTERM into a stop request rather than killing the process right away
+pub fn listen() {
+  LISTENING.call_once(|| {
+    tokio::spawn(async {
+      while tokio::signal::ctrl_c().await.is_ok() {
+        request();
+      }
+    });
+
+    #[cfg(unix)]
+    tokio::spawn(async {
+      use tokio::signal::unix::{signal, SignalKind};
+
+      if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+        while terminate.recv().await.is_some() {
+          request();
+        }
+      }
+    });
+  });
+}
+
+/// Whether a stop was requested, the watcher checks between polls
+pub fn requested() -> bool {
+  REQUESTED.load(Ordering::Relaxed)
+}
+
+/// Resolves once a stop is requested, right away if it already was
+async fn wait_for_request() {
+  let notified = REQUESTS.notified();
+  tokio::pin!(notified);
+  // Registered before checking, so a request coming in between isn't missed
+  notified.as_mut().enable();
+
+  if !requested() {
+    notified.await;
+  }
+}
+
+/// Runs `future` to its end, unless a stop is requested first
+pub async fn unless_requested<T>(future: impl Future<Output = T>) -> Result<T, Interrupted> {
+  tokio::select! {
+    output = future => Ok(output),
+    () = wait_for_request() => Err(Interrupted),
+  }
+}
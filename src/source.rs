@@ -0,0 +1,81 @@
+use crate::{
+  search::{self, SearchOrder},
+  youtube::{get_latest_videos, get_video, is_short_video, post_comment, LatestVideo, PlaylistPage, YoutubeClient},
+};
+use async_trait::async_trait;
+use google_youtube3::api::Video;
+
+/// Where the watcher finds the videos: the uploads playlist, the search results and the details of a video
+#[async_trait]
+pub trait VideoSource: Sync {
+  /// The latest `count` videos of the playlist, newest first. `None` means it didn't change since `etag`.
+  async fn playlist_videos(
+    &self,
+    playlist_id: &str,
+    count: u32,
+    api_key: Option<&str>,
+    etag: Option<&str>,
+  ) -> google_youtube3::Result<Option<PlaylistPage>>;
+
+  /// The first `count` videos matching the query, only among the videos of the channel if given
+  async fn search_videos(
+    &self,
+    query: &str,
+    channel_id: Option<&str>,
+    order: SearchOrder,
+    count: u32,
+    api_key: Option<&str>,
+  ) -> google_youtube3::Result<Vec<LatestVideo>>;
+
+  /// The video, `None` if it doesn't exist or isn't visible to the client
+  async fn video(&self, video_id: &str) -> google_youtube3::Result<Option<Video>>;
+
+  /// Whether the video is a Short, whatever its title and description say
+  async fn is_short(&self, video_id: &str) -> google_youtube3::Result<bool>;
+}
+
+/// Where the watcher posts its comments
+#[async_trait]
+pub trait CommentSink: Sync {
+  /// Creates the comment thread, or the reply to `parent_id`, and returns the ID of the new comment
+  async fn post(&self, video_id: &str, comment: &str, parent_id: Option<&str>) -> google_youtube3::Result<String>;
+}
+
+#[async_trait]
+impl VideoSource for YoutubeClient {
+  async fn playlist_videos(
+    &self,
+    playlist_id: &str,
+    count: u32,
+    api_key: Option<&str>,
+    etag: Option<&str>,
+  ) -> google_youtube3::Result<Option<PlaylistPage>> {
+    get_latest_videos(self, playlist_id, count, api_key, etag).await
+  }
+
+  async fn search_videos(
+    &self,
+    query: &str,
+    channel_id: Option<&str>,
+    order: SearchOrder,
+    count: u32,
+    api_key: Option<&str>,
+  ) -> google_youtube3::Result<Vec<LatestVideo>> {
+    search::search_videos(self, query, channel_id, order, count, api_key).await
+  }
+
+  async fn video(&self, video_id: &str) -> google_youtube3::Result<Option<Video>> {
+    get_video(self, video_id).await
+  }
+
+  async fn is_short(&self, video_id: &str) -> google_youtube3::Result<bool> {
+    is_short_video(self, video_id).await
+  }
+}
+
+#[async_trait]
+impl CommentSink for YoutubeClient {
+  async fn post(&self, video_id: &str, comment: &str, parent_id: Option<&str>) -> google_youtube3::Result<String> {
+    post_comment(self, video_id, comment, parent_id).await
+  }
+}
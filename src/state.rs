@@ -0,0 +1,161 @@
+use crate::console;
+use google_youtube3::chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+  collections::{HashMap, HashSet},
+  fs::{self, File, TryLockError},
+  io::{self, Write},
+  path::{Path, PathBuf},
+  process,
+  sync::Mutex,
+};
+
+/// How many commented video IDs are remembered per channel
+const KEPT_COMMENTED: usize = 50;
+
+/// Held while saving, for the watchers of a process to write the file one at a time
+static SAVING: Mutex<()> = Mutex::new(());
+
+/// What a watcher needs to pick up where it left off after a crash or a reboot.
+#[derive(Default, Serialize, Deserialize)]
+pub struct ChannelState {
+  /// The latest video when the wait started, anything else showing up is new
+  pub baseline_video_id: Option<String>,
+  /// When the baseline video was published, only videos published after it are new
+  pub baseline_published_at: Option<DateTime<Utc>>,
+  /// When the current wait started, `None` once it ended
+  pub wait_started_at: Option<DateTime<Utc>>,
+  /// Videos already commented on, oldest first
+  pub commented_video_ids: Vec<String>,
+}
+
+/// Held for as long as a watcher runs, so a second one watching the same channel refuses to start. The OS releases the
+/// lock when the process exits, even on a crash.
+pub struct ChannelLock {
+  _file: File,
+}
+
+/// The watcher state of every channel, persisted to `<data dir>/state.json`.
+pub struct StateStore {
+  path: PathBuf,
+  channels: HashMap<String, ChannelState>,
+  /// The channels of this watcher, the others are left as their own watchers saved them
+  changed: HashSet<String>,
+}
+
+impl ChannelState {
+  pub fn has_commented(&self, video_id: &str) -> bool {
+    self.commented_video_ids.iter().any(|id| id == video_id)
+  }
+
+  pub fn start_wait(&mut self, baseline_video_id: Option<String>, baseline_published_at: Option<DateTime<Utc>>) {
+    self.set_baseline(baseline_video_id, baseline_published_at);
+    self.wait_started_at = Some(Utc::now());
+  }
+
+  pub fn set_baseline(&mut self, video_id: Option<String>, published_at: Option<DateTime<Utc>>) {
+    self.baseline_video_id = video_id;
+    self.baseline_published_at = published_at;
+  }
+
+  pub fn end_wait(&mut self) {
+    self.set_baseline(None, None);
+    self.wait_started_at = None;
+  }
+
+  pub fn add_commented(&mut self, video_id: &str) {
+    self.commented_video_ids.push(video_id.into());
+
+    let excess = self.commented_video_ids.len().saturating_sub(KEPT_COMMENTED);
+    self.commented_video_ids.drain(..excess);
+  }
+}
+
+impl StateStore {
+  pub fn load(data_dir: &Path) -> io::Result<Self> {
+    let path = data_dir.join("state.json");
+    let channels = match fs::read_to_string(&path) {
+      Ok(contents) => serde_json::from_str(&contents).map_err(io::Error::other)?,
+      Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+      Err(e) => return Err(e),
+    };
+
+    Ok(Self {
+      path,
+      channels,
+      changed: HashSet::new(),
+    })
+  }
+
+  pub fn channel(&mut self, channel_id: &str) -> &mut ChannelState {
+    self.changed.insert(channel_id.into());
+    self.channels.entry(channel_id.into()).or_default()
+  }
+
+  /// Writes the state to disk. Losing it only costs the ability to resume, so failures are logged and ignored.
+  pub fn save(&self) {
+    let _saving = SAVING.lock().unwrap();
+    // Other watchers sharing the data directory may have saved their channels meanwhile
+    let mut saved: Map<String, Value> = fs::read_to_string(&self.path)
+      .ok()
+      .and_then(|contents| serde_json::from_str(&contents).ok())
+      .unwrap_or_default();
+
+    for channel_id in &self.changed {
+      if let Some(Ok(state)) = self.channels.get(channel_id).map(serde_json::to_value) {
+        saved.insert(channel_id.clone(), state);
+      }
+    }
+
+    let result = serde_json::to_string_pretty(&saved)
+      .map_err(io::Error::other)
+      .and_then(|json| {
+        // Write to a temporary file first so a crash mid-write can't leave a truncated state behind
+        let temporary_path = self.path.with_extension(format!("json.{}.tmp", process::id()));
+
+        if let Some(dir) = self.path.parent() {
+          fs::create_dir_all(dir)?;
+        }
+
+        fs::write(&temporary_path, json)?;
+        fs::rename(&temporary_path, &self.path)
+      });
+
+    if let Err(e) = result {
+      console::error(format!("Failed to save the watcher state: {e}"));
+    }
+  }
+}
+
+impl ChannelLock {
+  /// Locks `<data dir>/locks/<channel id>.lock`. Fails when another watcher holds it, naming its PID.
+  pub fn acquire(data_dir: &Path, channel_id: &str) -> io::Result<Self> {
+    let dir = data_dir.join("locks");
+    fs::create_dir_all(&dir)?;
+
+    let path = dir.join(format!("{channel_id}.lock"));
+    let mut file = File::options().create(true).truncate(false).write(true).open(&path)?;
+
+    match file.try_lock() {
+      Ok(()) => {}
+      Err(TryLockError::WouldBlock) => {
+        let pid = fs::read_to_string(&path).unwrap_or_default();
+
+        return Err(io::Error::new(
+          io::ErrorKind::WouldBlock,
+          format!(
+            "Another yfc (PID {}) is already watching channel {channel_id}, pass --allow-multiple to start anyway",
+            pid.trim()
+          ),
+        ));
+      }
+      Err(TryLockError::Error(e)) => return Err(e),
+    }
+
+    file.set_len(0)?;
+    write!(file, "{}", process::id())?;
+
+    Ok(Self { _file: file })
+  }
+}
@@ -0,0 +1,156 @@
+use crate::{db, quota};
+use clap::ValueEnum;
+use google_youtube3::chrono::Utc;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::json;
+use std::{error::Error, path::Path, time::Duration};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum StatsFormat {
+  /// Aligned columns, for reading in a terminal
+  Table,
+  Json,
+  Csv,
+}
+
+/// Aggregated attempts of one channel, or of every channel for the total
+#[derive(Serialize)]
+struct ChannelStats {
+  channel_id: String,
+  attempts: u64,
+  posted: u64,
+  failed: u64,
+  /// Share of the attempts that got the comment posted, between 0 and 1
+  success_rate: f64,
+  /// Average time between detecting the video and the comment being created, over the posted comments
+  average_latency_ms: Option<u64>,
+  /// Estimated quota units spent on posting, which dwarfs what polling costs
+  quota_units: u64,
+  #[serde(skip)]
+  latency_total_ms: u64,
+  #[serde(skip)]
+  latency_samples: u64,
+}
+
+impl ChannelStats {
+  fn new(channel_id: String, posted: u64, failed: u64, latency_total_ms: u64, latency_samples: u64) -> Self {
+    let attempts = posted + failed;
+
+    Self {
+      channel_id,
+      attempts,
+      posted,
+      failed,
+      success_rate: if attempts == 0 {
+        0.0
+      } else {
+        posted as f64 / attempts as f64
+      },
+      average_latency_ms: (latency_samples > 0).then(|| latency_total_ms / latency_samples),
+      quota_units: attempts * quota::INSERT_COST,
+      latency_total_ms,
+      latency_samples,
+    }
+  }
+
+  fn csv_row(&self) -> String {
+    format!(
+      "{},{},{},{},{:.3},{},{}",
+      self.channel_id,
+      self.attempts,
+      self.posted,
+      self.failed,
+      self.success_rate,
+      self
+        .average_latency_ms
+        .map_or(String::new(), |latency| latency.to_string()),
+      self.quota_units
+    )
+  }
+
+  fn table_row(&self) -> String {
+    format!(
+      "{:<26} {:>8} {:>6} {:>6} {:>7.1}% {:>11} {:>7}",
+      self.channel_id,
+      self.attempts,
+      self.posted,
+      self.failed,
+      self.success_rate * 100.0,
+      self
+        .average_latency_ms
+        .map_or("-".into(), |latency| format!("{latency}ms")),
+      self.quota_units
+    )
+  }
+}
+
+fn query(connection: &Connection, since: Option<Duration>) -> rusqlite::Result<Vec<ChannelStats>> {
+  let cutoff = since.map(|since| (Utc::now() - since).to_rfc3339());
+  let mut statement = connection.prepare(
+    "SELECT channel_id,
+       SUM(result = 'posted'),
+       SUM(result = 'failed'),
+       COALESCE(SUM(CASE WHEN result = 'posted' THEN latency_ms END), 0),
+       COUNT(CASE WHEN result = 'posted' THEN latency_ms END)
+     FROM attempts WHERE ?1 IS NULL OR attempted_at >= ?1
+     GROUP BY channel_id ORDER BY channel_id",
+  )?;
+  let channels = statement
+    .query_map(params![cutoff], |row| {
+      Ok(ChannelStats::new(
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get(3)?,
+        row.get(4)?,
+      ))
+    })?
+    .collect();
+
+  channels
+}
+
+/// Prints the statistics of the attempts in the history, per channel and in total, optionally only the ones more
+/// recent than `since`
+pub fn print(data_dir: &Path, since: Option<Duration>, format: StatsFormat) -> Result<(), Box<dyn Error>> {
+  let channels = if data_dir.join("yfc.db").exists() {
+    query(&db::open(data_dir)?, since)?
+  } else {
+    Vec::new()
+  };
+
+  let (posted, failed) = channels.iter().fold((0, 0), |(posted, failed), stats| {
+    (posted + stats.posted, failed + stats.failed)
+  });
+  let (latency_total_ms, latency_samples) = channels.iter().fold((0, 0), |(total, samples), stats| {
+    (total + stats.latency_total_ms, samples + stats.latency_samples)
+  });
+  let total = ChannelStats::new("total".into(), posted, failed, latency_total_ms, latency_samples);
+
+  match format {
+    StatsFormat::Json => println!(
+      "{}",
+      serde_json::to_string_pretty(&json!({ "channels": channels, "total": total }))?
+    ),
+    StatsFormat::Csv => {
+      println!("channel_id,attempts,posted,failed,success_rate,average_latency_ms,quota_units");
+
+      for stats in channels.iter().chain([&total]) {
+        println!("{}", stats.csv_row());
+      }
+    }
+    StatsFormat::Table => {
+      println!(
+        "{:<26} {:>8} {:>6} {:>6} {:>8} {:>11} {:>7}",
+        "Channel", "Attempts", "Posted", "Failed", "Success", "Avg latency", "Quota"
+      );
+
+      for stats in channels.iter().chain([&total]) {
+        println!("{}", stats.table_row());
+      }
+    }
+  }
+
+  Ok(())
+}
@@ -0,0 +1,287 @@
+use crate::{
+  console,
+  notify::{EventRing, RecentEvent},
+  youtube::HttpsClient,
+};
+use clap::ValueEnum;
+use google_youtube3::{
+  chrono::{DateTime, Local, Utc},
+  client::GetToken,
+  hyper::{
+    self,
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+  },
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+  collections::VecDeque,
+  convert::Infallible,
+  error::Error,
+  net::SocketAddr,
+  sync::{Arc, Mutex},
+  time::{Duration, Instant},
+};
+
+/// What the watcher is busy with.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+  Starting,
+  Watching,
+  WaitingForPublic,
+  Posting,
+  Posted,
+  Failed,
+  /// Paused with `yfc ctl pause`
+  Paused,
+}
+
+/// The watcher state shared between the main loop and the status server.
+pub struct WatchState {
+  pub channel_id: String,
+  pub phase: Phase,
+  pub started_at: Option<Instant>,
+  pub last_poll_at: Option<DateTime<Utc>>,
+  pub next_poll_at: Option<DateTime<Utc>>,
+  pub last_seen_video_id: Option<String>,
+  pub last_seen_video_title: Option<String>,
+  pub last_result: Option<String>,
+  /// The wait limit left once the watcher started, `None` without one
+  pub wait_limit: Option<Duration>,
+  pub recent_events: EventRing,
+  /// Videos pushed with `yfc push`, waiting to be picked up by the main loop
+  pub pushed_video_ids: VecDeque<String>,
+  /// Set with `yfc ctl pause`, nothing is polled nor posted until resumed
+  pub paused: bool,
+  /// Comment set with `yfc ctl set-comment`, posted instead of the configured one
+  pub comment: Option<String>,
+  /// When the last hint of a new upload came (from --hint or `POST /hint`), the API being polled fast for a while
+  pub hinted_at: Option<Instant>,
+}
+
+pub type SharedWatchState = Arc<Mutex<WatchState>>;
+
+#[derive(Serialize, Deserialize)]
+pub struct StatusResponse {
+  pub channel_id: String,
+  pub phase: Phase,
+  pub last_poll_at: Option<DateTime<Utc>>,
+  pub next_poll_at: Option<DateTime<Utc>>,
+  pub last_seen_video_id: Option<String>,
+  pub last_result: Option<String>,
+  pub elapsed_seconds: u64,
+  pub token_valid: bool,
+  #[serde(default)]
+  pub paused: bool,
+  /// The last events, oldest first
+  #[serde(default)]
+  pub recent_events: Vec<RecentEvent>,
+}
+
+impl WatchState {
+  pub fn new(channel_id: &str, recent_events: EventRing) -> SharedWatchState {
+    Arc::new(Mutex::new(Self {
+      channel_id: channel_id.into(),
+      phase: Phase::Starting,
+      started_at: None,
+      last_poll_at: None,
+      next_poll_at: None,
+      last_seen_video_id: None,
+      last_seen_video_title: None,
+      last_result: None,
+      wait_limit: None,
+      recent_events,
+      pushed_video_ids: VecDeque::new(),
+      paused: false,
+      comment: None,
+      hinted_at: None,
+    }))
+  }
+}
+
+const SCOPES: [&str; 1] = ["https://www.googleapis.com/auth/youtube.readonly"];
+
+pub fn json_response(status: StatusCode, body: serde_json::Value) -> Response<Body> {
+  let mut response = Response::new(Body::from(body.to_string()));
+  *response.status_mut() = status;
+
+  response
+    .headers_mut()
+    .insert(CONTENT_TYPE, "application/json".parse().unwrap());
+
+  response
+}
+
+/// The status of the watcher, as served by the status server and the control socket
+pub async fn snapshot(state: &SharedWatchState, auth: &dyn GetToken) -> StatusResponse {
+  // A token that can't be fetched (or refreshed) means the next API call will fail as well
+  let token_valid = matches!(auth.get_token(&SCOPES).await, Ok(Some(_)));
+  let state = state.lock().unwrap();
+  let recent_events = state.recent_events.lock().unwrap().iter().cloned().collect();
+
+  StatusResponse {
+    channel_id: state.channel_id.clone(),
+    phase: state.phase,
+    last_poll_at: state.last_poll_at,
+    next_poll_at: state.next_poll_at,
+    last_seen_video_id: state.last_seen_video_id.clone(),
+    last_result: state.last_result.clone(),
+    elapsed_seconds: state.started_at.map_or(0, |started_at| started_at.elapsed().as_secs()),
+    token_valid,
+    paused: state.paused,
+    recent_events,
+  }
+}
+
+async fn handle(
+  request: Request<Body>,
+  state: SharedWatchState,
+  auth: Box<dyn GetToken>,
+) -> Result<Response<Body>, Infallible> {
+  match (request.method(), request.uri().path()) {
+    (_, "/" | "/status") => {}
+    _ => {
+      let mut response = Response::new(Body::empty());
+      *response.status_mut() = StatusCode::NOT_FOUND;
+      return Ok(response);
+    }
+  }
+
+  let status = snapshot(&state, auth.as_ref()).await;
+  let status_code = if status.token_valid {
+    StatusCode::OK
+  } else {
+    StatusCode::SERVICE_UNAVAILABLE
+  };
+
+  Ok(json_response(status_code, serde_json::to_value(&status).unwrap()))
+}
+
+/// Starts the status server in the background. Every request gets a JSON snapshot of the watcher state, answered with
+/// a 503 when the auth token is no longer valid so healthchecks can rely on the status code alone. It's read-only,
+/// pushing a video or a hint goes through the control API and its token.
+pub fn serve(addr: SocketAddr, state: SharedWatchState, auth: Box<dyn GetToken>) -> Result<(), hyper::Error> {
+  let make_service = make_service_fn(move |_| {
+    let state = state.clone();
+    let auth = auth.clone_box();
+
+    async move {
+      Ok::<_, Infallible>(service_fn(move |request| {
+        handle(request, state.clone(), auth.clone_box())
+      }))
+    }
+  });
+
+  let server = Server::try_bind(&addr)?.serve(make_service);
+
+  console::info(format!("Status server listening on http://{addr}"));
+
+  tokio::spawn(async move {
+    if let Err(e) = server.await {
+      console::error(format!("Status server error: {e}"));
+    }
+  });
+
+  Ok(())
+}
+
+/// Output formats of the `status` command.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum StatusFormat {
+  /// The raw JSON returned by the status endpoint
+  Json,
+  /// The {"text", "tooltip", "class"} object expected by waybar custom modules
+  Waybar,
+  /// A single line of text, as expected by polybar script modules
+  Polybar,
+}
+
+/// Fetches the status of the watcher listening on the given address.
+pub async fn query(client: &HttpsClient, addr: &str) -> Result<StatusResponse, Box<dyn Error>> {
+  let url = if addr.contains("://") {
+    addr.to_string()
+  } else {
+    format!("http://{addr}/status")
+  };
+  let response = client.get(url.parse()?).await?;
+  let body = hyper::body::to_bytes(response.into_body()).await?;
+
+  Ok(serde_json::from_slice(&body)?)
+}
+
+pub fn phase_icon(phase: Phase) -> &'static str {
+  match phase {
+    Phase::Starting => "⏳",
+    Phase::Watching => "👀",
+    Phase::WaitingForPublic => "🔒",
+    Phase::Posting => "✍️",
+    Phase::Posted => "✅",
+    Phase::Failed => "❌",
+    Phase::Paused => "⏸️",
+  }
+}
+
+fn phase_class(phase: Phase) -> &'static str {
+  match phase {
+    Phase::Starting => "starting",
+    Phase::Watching => "watching",
+    Phase::WaitingForPublic => "waiting-for-public",
+    Phase::Posting => "posting",
+    Phase::Posted => "posted",
+    Phase::Failed => "failed",
+    Phase::Paused => "paused",
+  }
+}
+
+/// Seconds until the next poll formatted as m:ss, e.g. "0:37"
+pub fn format_countdown(next_poll_at: DateTime<Utc>) -> String {
+  let seconds = (next_poll_at - Utc::now()).num_seconds().max(0);
+
+  format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Renders a status (or `None` when no watcher is reachable) in the given format.
+pub fn format(status: Option<&StatusResponse>, format: StatusFormat) -> String {
+  let Some(status) = status else {
+    return match format {
+      StatusFormat::Json => "null".into(),
+      StatusFormat::Waybar => json!({ "text": "💤", "tooltip": "yfc is not running", "class": "offline" }).to_string(),
+      StatusFormat::Polybar => "💤".into(),
+    };
+  };
+
+  let mut text = phase_icon(status.phase).to_string();
+
+  if let (Phase::Watching, Some(next_poll_at)) = (status.phase, status.next_poll_at) {
+    text.push(' ');
+    text.push_str(&format_countdown(next_poll_at));
+  }
+
+  let mut tooltip = format!("Watching channel {}", status.channel_id);
+
+  if let Some(video_id) = &status.last_seen_video_id {
+    tooltip.push_str(&format!("\nLast seen video: {video_id}"));
+  }
+  if let Some(result) = &status.last_result {
+    tooltip.push_str(&format!("\nLast result: {result}"));
+  }
+  if let Some(event) = status.recent_events.last() {
+    tooltip.push_str(&format!(
+      "\nLast event: {} ({})",
+      event.event,
+      event.at.with_timezone(&Local).format("%H:%M:%S")
+    ));
+  }
+  if !status.token_valid {
+    tooltip.push_str("\nThe auth token is no longer valid");
+  }
+
+  match format {
+    StatusFormat::Json => serde_json::to_string(status).unwrap(),
+    StatusFormat::Waybar => json!({ "text": text, "tooltip": tooltip, "class": phase_class(status.phase) }).to_string(),
+    StatusFormat::Polybar => text,
+  }
+}
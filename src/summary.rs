@@ -0,0 +1,99 @@
+use crate::{events::WatchEvent, exit, quota};
+use google_youtube3::chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{error::Error, fs, path::Path};
+
+/// What a run came to, written to --summary-out when it ends so schedulers and wrappers don't have to parse the log.
+/// The video fields are about the last video the watcher went for.
+#[derive(Serialize)]
+pub struct RunSummary {
+  channel_id: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  video_id: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  comment_id: Option<String>,
+  /// Between detecting the video and the comment being created
+  #[serde(skip_serializing_if = "Option::is_none")]
+  latency_ms: Option<u64>,
+  /// Between the video being published and the comment being created
+  #[serde(skip_serializing_if = "Option::is_none")]
+  publish_latency_ms: Option<u64>,
+  comments_posted: u32,
+  /// Posts tried again after a transient error
+  retries: u32,
+  /// Estimated units spent by the whole process
+  quota_used: u64,
+  exit_code: i32,
+  exit_reason: &'static str,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  error: Option<String>,
+  started_at: DateTime<Utc>,
+  ended_at: DateTime<Utc>,
+}
+
+impl RunSummary {
+  pub fn new(channel_id: &str) -> Self {
+    Self {
+      channel_id: channel_id.into(),
+      video_id: None,
+      comment_id: None,
+      latency_ms: None,
+      publish_latency_ms: None,
+      comments_posted: 0,
+      retries: 0,
+      quota_used: 0,
+      exit_code: 0,
+      exit_reason: "done",
+      error: None,
+      started_at: Utc::now(),
+      ended_at: Utc::now(),
+    }
+  }
+
+  pub fn record(&mut self, event: &WatchEvent) {
+    match event {
+      WatchEvent::VideoDetected { video_id, .. } => {
+        self.video_id = Some(video_id.clone());
+        self.comment_id = None;
+        self.latency_ms = None;
+        self.publish_latency_ms = None;
+      }
+      WatchEvent::CommentPosted {
+        video_id,
+        comment_id,
+        latency,
+        publish_latency,
+      } => {
+        self.video_id = Some(video_id.clone());
+        self.comment_id = Some(comment_id.clone());
+        self.latency_ms = Some(latency.as_millis() as u64);
+        self.publish_latency_ms = publish_latency.map(|latency| latency.as_millis() as u64);
+        self.comments_posted += 1;
+      }
+      WatchEvent::PostFailed { video_id, .. } => self.video_id = Some(video_id.clone()),
+      WatchEvent::RetryScheduled { .. } => self.retries += 1,
+      _ => {}
+    }
+  }
+
+  /// Writes the summary once the run is over, as TOML when the file ends in `.toml` and as JSON otherwise
+  pub fn write(mut self, path: &Path, result: &Result<(), Box<dyn Error>>) -> Result<(), Box<dyn Error>> {
+    self.quota_used = quota::used();
+    self.ended_at = Utc::now();
+
+    if let Err(e) = result {
+      self.exit_code = exit::code(e.as_ref());
+      self.exit_reason = exit::reason(e.as_ref());
+      self.error = Some(e.to_string());
+    }
+
+    let contents = match path.extension() {
+      Some(extension) if extension == "toml" => toml::to_string(&self)?,
+      _ => serde_json::to_string_pretty(&self)?,
+    };
+
+    fs::write(path, contents)?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,193 @@
+use dirs::config_dir;
+use std::{
+  env,
+  error::Error,
+  fs,
+  path::{Path, PathBuf},
+  process,
+  time::Duration,
+};
+
+/// Unit name used by `yfc install-service` when none is given
+pub const DEFAULT_UNIT_NAME: &str = "yfc";
+
+/// Sends a state (e.g. `READY=1`) to the service manager. A no-op unless started by systemd as a Type=notify service.
+#[cfg(target_os = "linux")]
+fn notify(state: &str) {
+  use crate::console;
+  use std::os::{
+    linux::net::SocketAddrExt,
+    unix::{
+      ffi::OsStrExt,
+      net::{SocketAddr, UnixDatagram},
+    },
+  };
+
+  let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+    return;
+  };
+
+  // A leading @ is a socket in the abstract namespace
+  let result = UnixDatagram::unbound().and_then(|socket| {
+    let addr = match path.as_bytes().strip_prefix(b"@") {
+      Some(name) => SocketAddr::from_abstract_name(name)?,
+      None => SocketAddr::from_pathname(&path)?,
+    };
+
+    socket.send_to_addr(state.as_bytes(), &addr)
+  });
+
+  if let Err(e) = result {
+    console::error(format!("Failed to notify systemd: {e}"));
+  }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn notify(_state: &str) {}
+
+/// How often systemd expects a watchdog ping, if the service has WatchdogSec set
+fn watchdog_interval() -> Option<Duration> {
+  // The variables are inherited by child processes, which aren't the ones being watched
+  if let Ok(pid) = env::var("WATCHDOG_PID") {
+    if pid.parse() != Ok(process::id()) {
+      return None;
+    }
+  }
+
+  env::var("WATCHDOG_USEC")
+    .ok()?
+    .parse()
+    .ok()
+    .filter(|usec| *usec > 0)
+    .map(Duration::from_micros)
+}
+
+/// Tells systemd the watcher is up and starts pinging its watchdog, at half the interval it expects
+pub fn ready(status: &str) {
+  notify(&format!("READY=1\nSTATUS={status}"));
+
+  if let Some(interval) = watchdog_interval() {
+    tokio::spawn(async move {
+      loop {
+        tokio::time::sleep(interval / 2).await;
+        notify("WATCHDOG=1");
+      }
+    });
+  }
+}
+
+/// Tells systemd the watcher is shutting down on its own, so it isn't mistaken for a hang
+pub fn stopping() {
+  notify("STOPPING=1");
+}
+
+/// Where systemd looks for the units of the current user
+fn get_user_unit_dir() -> Result<PathBuf, Box<dyn Error>> {
+  Ok(
+    config_dir()
+      .ok_or("Could not find the config directory")?
+      .join("systemd")
+      .join("user"),
+  )
+}
+
+/// Escapes the `%` starting a specifier in unit files
+fn escape(path: &Path) -> String {
+  path.to_string_lossy().replace('%', "%%")
+}
+
+/// Quotes a path for an ExecStart= or a ReadWritePaths= line, which split on spaces
+fn quote(path: &Path) -> String {
+  format!("\"{}\"", escape(path).replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The unit running the watcher with the given config file, hardened to only write to the data directory
+fn render_unit(executable: &Path, config_path: &Path, data_dir: &Path, watchdog: Duration) -> String {
+  // A .env file next to the config is picked up, as the working directory is where it's looked for
+  let working_dir = config_path.parent().unwrap_or(Path::new("/"));
+
+  format!(
+    "[Unit]
+Description=YouTube first comment watcher
+Wants=network-online.target
+After=network-online.target
+
+[Service]
+Type=notify
+ExecStart={executable} --config {config_path} --data-dir {data_dir}
+WorkingDirectory={working_dir}
+Restart=on-failure
+RestartSec=30
+# Ctrl+C and SIGTERM make yfc exit with 130 after saving its state, reaching the wait limit with 5
+SuccessExitStatus=130 5
+WatchdogSec={watchdog}
+
+NoNewPrivileges=yes
+ProtectSystem=strict
+ProtectHome=read-only
+ReadWritePaths={data_dir}
+PrivateTmp=yes
+PrivateDevices=yes
+ProtectKernelTunables=yes
+ProtectKernelModules=yes
+ProtectControlGroups=yes
+RestrictNamespaces=yes
+RestrictRealtime=yes
+RestrictSUIDSGID=yes
+LockPersonality=yes
+MemoryDenyWriteExecute=yes
+RestrictAddressFamilies=AF_UNIX AF_INET AF_INET6
+SystemCallArchitectures=native
+CapabilityBoundingSet=
+
+[Install]
+WantedBy=default.target
+",
+    executable = quote(executable),
+    config_path = quote(config_path),
+    data_dir = quote(data_dir),
+    working_dir = escape(working_dir),
+    watchdog = watchdog.as_secs().max(1),
+  )
+}
+
+/// Writes a user-level systemd unit running the watcher with the config file, which must hold its options. An existing
+/// unit is only replaced with `force`.
+pub fn install(
+  name: &str,
+  config_path: &Path,
+  data_dir: &Path,
+  watchdog: Duration,
+  force: bool,
+) -> Result<(), Box<dyn Error>> {
+  let config_path = fs::canonicalize(config_path).map_err(|e| {
+    format!(
+      "Failed to find the config file {}, create it first (e.g. with yfc config migrate): {e}",
+      config_path.display()
+    )
+  })?;
+
+  // It's the only place the service can write to, so it has to exist when the unit starts
+  fs::create_dir_all(data_dir)?;
+  let data_dir = fs::canonicalize(data_dir)?;
+
+  let unit_dir = get_user_unit_dir()?;
+  let unit_path = unit_dir.join(format!("{name}.service"));
+
+  if unit_path.exists() && !force {
+    return Err(format!("{} already exists, pass --force to replace it", unit_path.display()).into());
+  }
+
+  let unit = render_unit(&env::current_exe()?, &config_path, &data_dir, watchdog);
+
+  fs::create_dir_all(&unit_dir)?;
+  fs::write(&unit_path, unit)?;
+
+  println!("Wrote {}, start it with:", unit_path.display());
+  println!("  systemctl --user daemon-reload");
+  println!("  systemctl --user enable --now {name}");
+  println!("Follow it with journalctl --user -u {name} -f");
+  println!("Run loginctl enable-linger to keep it running after you log out");
+
+  Ok(())
+}
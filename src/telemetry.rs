@@ -0,0 +1,90 @@
+use crate::{console, notify::Event};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+  collections::HashMap,
+  fs, io,
+  path::{Path, PathBuf},
+};
+
+/// How new videos are detected. Polling the uploads playlist through the API is the only method for now.
+const DETECTION_METHOD: &str = "api_poll";
+
+/// Latency statistics of one detection method, across every run on this machine
+#[derive(Default, Serialize, Deserialize)]
+struct Aggregate {
+  samples: u64,
+  total_ms: u64,
+  min_ms: Option<u64>,
+  max_ms: Option<u64>,
+}
+
+/// Opt-in race telemetry: aggregates how long it took to get the comment live after detecting a video into
+/// `<data dir>/telemetry.json`, and optionally sends each sample to an endpoint run by the user, so several instances
+/// running from different places can be compared. Samples never include the channel, the video or the comment.
+pub struct Telemetry {
+  path: PathBuf,
+  placement: Option<String>,
+  endpoint: Option<String>,
+}
+
+impl Aggregate {
+  fn add(&mut self, latency_ms: u64) {
+    self.samples += 1;
+    self.total_ms += latency_ms;
+    self.min_ms = Some(self.min_ms.map_or(latency_ms, |min| min.min(latency_ms)));
+    self.max_ms = Some(self.max_ms.map_or(latency_ms, |max| max.max(latency_ms)));
+  }
+}
+
+impl Telemetry {
+  pub fn new(data_dir: &Path, placement: Option<String>, endpoint: Option<String>) -> Self {
+    Self {
+      path: data_dir.join("telemetry.json"),
+      placement,
+      endpoint,
+    }
+  }
+
+  pub fn endpoint(&self) -> Option<&str> {
+    self.endpoint.as_deref()
+  }
+
+  /// Adds the latency of a posted comment to the local aggregate and returns the anonymous sample, other events are
+  /// ignored. Telemetry is a convenience, so failing to save it is only logged.
+  pub fn record(&self, event: &Event) -> Option<serde_json::Value> {
+    let Event::CommentPosted { latency, .. } = event else {
+      return None;
+    };
+    let latency_ms = latency.as_millis() as u64;
+
+    if let Err(e) = self.update(latency_ms) {
+      console::error(format!("Failed to update the telemetry aggregate: {e}"));
+    }
+
+    Some(json!({
+      "placement": self.placement,
+      "detection": DETECTION_METHOD,
+      "latency_ms": latency_ms,
+    }))
+  }
+
+  fn update(&self, latency_ms: u64) -> io::Result<()> {
+    let mut aggregates: HashMap<String, Aggregate> = match fs::read_to_string(&self.path) {
+      Ok(contents) => serde_json::from_str(&contents).map_err(io::Error::other)?,
+      Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+      Err(e) => return Err(e),
+    };
+
+    aggregates.entry(DETECTION_METHOD.into()).or_default().add(latency_ms);
+
+    if let Some(dir) = self.path.parent() {
+      fs::create_dir_all(dir)?;
+    }
+
+    fs::write(
+      &self.path,
+      serde_json::to_string_pretty(&aggregates).map_err(io::Error::other)?,
+    )
+  }
+}
@@ -0,0 +1,271 @@
+use crate::console;
+use async_trait::async_trait;
+use google_youtube3::oauth2::storage::{TokenInfo, TokenStorage};
+use ring::{
+  aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN},
+  pbkdf2,
+  rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::{
+  fs::{self, OpenOptions},
+  io::{self, Write},
+  num::NonZeroU32,
+  path::PathBuf,
+  sync::Mutex,
+};
+
+/// Environment variable holding the passphrase the token files are encrypted with
+pub const TOKEN_KEY_VAR: &str = "YFC_TOKEN_KEY";
+
+/// Start of an encrypted token file, a plain-text one is a JSON array
+const MAGIC: &[u8] = b"yfc-encrypted-v1\n";
+const SALT_LEN: usize = 16;
+const KEY_ITERATIONS: NonZeroU32 = match NonZeroU32::new(100_000) {
+  Some(iterations) => iterations,
+  None => unreachable!(),
+};
+
+/// A token and the scopes it was granted for, the same layout as the token.json files
+#[derive(Serialize, Deserialize)]
+pub struct StoredToken {
+  scopes: Vec<String>,
+  token: TokenInfo,
+}
+
+/// Replaces the token stored for the same scopes
+pub fn upsert(tokens: &mut Vec<StoredToken>, scopes: &[&str], token: TokenInfo) {
+  let mut scopes: Vec<String> = scopes.iter().map(|scope| scope.to_string()).collect();
+  scopes.sort();
+
+  tokens.retain(|stored| stored.scopes != scopes);
+  tokens.push(StoredToken { scopes, token });
+}
+
+/// The token for `scopes`. A token granted for more scopes than asked for works too.
+pub fn find(tokens: &[StoredToken], scopes: &[&str]) -> Option<TokenInfo> {
+  tokens
+    .iter()
+    .find(|stored| {
+      scopes
+        .iter()
+        .all(|scope| stored.scopes.iter().any(|stored| stored == scope))
+    })
+    .map(|stored| stored.token.clone())
+}
+
+/// Keeps the tokens in the file encrypted with ChaCha20-Poly1305, with a key derived from a passphrase. A plain-text
+/// file is encrypted when opened. The tokens are decrypted once, deriving the key being slow on purpose, and kept in
+/// memory: the file is only written to when a token changes.
+pub struct EncryptedStorage {
+  path: PathBuf,
+  passphrase: String,
+  tokens: Mutex<Vec<StoredToken>>,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> LessSafeKey {
+  let mut key = [0; 32];
+  pbkdf2::derive(
+    pbkdf2::PBKDF2_HMAC_SHA256,
+    KEY_ITERATIONS,
+    salt,
+    passphrase.as_bytes(),
+    &mut key,
+  );
+
+  LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &key).expect("the key has the length of the algorithm"))
+}
+
+fn crypto_error(message: &str) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl EncryptedStorage {
+  /// Opens the token file, failing when it can't be decrypted (e.g. a wrong passphrase) rather than logging in again
+  pub fn open(path: PathBuf, passphrase: String) -> io::Result<Self> {
+    let storage = Self {
+      path,
+      passphrase,
+      tokens: Mutex::default(),
+    };
+    let plaintext = match fs::read(&storage.path) {
+      Ok(contents) if !contents.starts_with(MAGIC) => {
+        storage.write(&contents)?;
+        console::info(format!("Encrypted the token file {}", storage.path.display()));
+
+        contents
+      }
+      Ok(contents) => storage.decrypt(&contents)?,
+      Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(storage),
+      Err(e) => return Err(e),
+    };
+
+    *storage.tokens.lock().unwrap() = serde_json::from_slice(&plaintext).map_err(io::Error::other)?;
+
+    Ok(storage)
+  }
+
+  fn encrypt(&self, plaintext: &[u8]) -> io::Result<Vec<u8>> {
+    let random = SystemRandom::new();
+    let mut salt = [0; SALT_LEN];
+    let mut nonce = [0; NONCE_LEN];
+    random
+      .fill(&mut salt)
+      .and_then(|_| random.fill(&mut nonce))
+      .map_err(|_| crypto_error("Failed to generate a random salt"))?;
+
+    let mut sealed = plaintext.to_vec();
+    derive_key(&self.passphrase, &salt)
+      .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(MAGIC), &mut sealed)
+      .map_err(|_| crypto_error("Failed to encrypt the tokens"))?;
+
+    Ok([MAGIC, &salt, &nonce, &sealed].concat())
+  }
+
+  fn decrypt(&self, contents: &[u8]) -> io::Result<Vec<u8>> {
+    let invalid = || crypto_error(&format!("Failed to decrypt the token file, is {TOKEN_KEY_VAR} right?"));
+    let contents = contents.strip_prefix(MAGIC).ok_or_else(invalid)?;
+
+    if contents.len() < SALT_LEN + NONCE_LEN {
+      return Err(invalid());
+    }
+
+    let (salt, contents) = contents.split_at(SALT_LEN);
+    let (nonce, sealed) = contents.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| invalid())?;
+    let mut sealed = sealed.to_vec();
+    let plaintext = derive_key(&self.passphrase, salt)
+      .open_in_place(nonce, Aad::from(MAGIC), &mut sealed)
+      .map_err(|_| invalid())?;
+
+    Ok(plaintext.to_vec())
+  }
+
+  /// Writes the file readable by the user only, even when it was created more open before
+  fn write(&self, plaintext: &[u8]) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    {
+      options.mode(0o600);
+
+      if self.path.exists() {
+        fs::set_permissions(&self.path, fs::Permissions::from_mode(0o600))?;
+      }
+    }
+
+    options.open(&self.path)?.write_all(&self.encrypt(plaintext)?)
+  }
+}
+
+#[async_trait]
+impl TokenStorage for EncryptedStorage {
+  async fn set(&self, scopes: &[&str], token: TokenInfo) -> anyhow::Result<()> {
+    let mut tokens = self.tokens.lock().unwrap();
+    upsert(&mut tokens, scopes, token);
+
+    Ok(self.write(&serde_json::to_vec(&*tokens)?)?)
+  }
+
+  async fn get(&self, scopes: &[&str]) -> Option<TokenInfo> {
+    find(&self.tokens.lock().unwrap(), scopes)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::env;
+
+  const SCOPES: [&str; 1] = ["https://www.googleapis.com/auth/youtube.force-ssl"];
+
+  fn token(access_token: &str) -> TokenInfo {
+    TokenInfo {
+      access_token: Some(access_token.into()),
+      refresh_token: Some("refresh".into()),
+      expires_at: None,
+      id_token: None,
+    }
+  }
+
+  fn token_path(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("yfc-test-{}-{name}.json", std::process::id()));
+    let _ = fs::remove_file(&path);
+
+    path
+  }
+
+  #[tokio::test]
+  async fn keeps_the_tokens_encrypted() {
+    let path = token_path("encrypted");
+    let storage = EncryptedStorage::open(path.clone(), "passphrase".into()).unwrap();
+
+    storage.set(&SCOPES, token("first")).await.unwrap();
+    storage.set(&SCOPES, token("second")).await.unwrap();
+
+    let contents = fs::read(&path).unwrap();
+    assert!(contents.starts_with(MAGIC));
+    assert!(!String::from_utf8_lossy(&contents).contains("second"));
+
+    #[cfg(unix)]
+    assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+    let reopened = EncryptedStorage::open(path.clone(), "passphrase".into()).unwrap();
+    assert_eq!(
+      reopened
+        .get(&SCOPES)
+        .await
+        .and_then(|token| token.access_token)
+        .as_deref(),
+      Some("second")
+    );
+
+    assert!(EncryptedStorage::open(path.clone(), "wrong".into()).is_err());
+
+    fs::remove_file(path).unwrap();
+  }
+
+  #[tokio::test]
+  async fn serves_the_tokens_from_memory() {
+    let path = token_path("cached");
+    let storage = EncryptedStorage::open(path.clone(), "passphrase".into()).unwrap();
+
+    storage.set(&SCOPES, token("cached")).await.unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+      storage
+        .get(&SCOPES)
+        .await
+        .and_then(|token| token.access_token)
+        .as_deref(),
+      Some("cached")
+    );
+  }
+
+  #[tokio::test]
+  async fn encrypts_a_plain_text_file() {
+    let path = token_path("plain");
+    let mut tokens = Vec::new();
+
+    upsert(&mut tokens, &SCOPES, token("plain"));
+    fs::write(&path, serde_json::to_vec(&tokens).unwrap()).unwrap();
+
+    let storage = EncryptedStorage::open(path.clone(), "passphrase".into()).unwrap();
+
+    assert!(fs::read(&path).unwrap().starts_with(MAGIC));
+    assert_eq!(
+      storage
+        .get(&SCOPES)
+        .await
+        .and_then(|token| token.access_token)
+        .as_deref(),
+      Some("plain")
+    );
+
+    fs::remove_file(path).unwrap();
+  }
+}
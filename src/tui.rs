@@ -0,0 +1,363 @@
+use crate::{
+  console::format_duration,
+  quota, shutdown,
+  status::{self, Phase, SharedWatchState},
+};
+use google_youtube3::chrono::Utc;
+use ratatui::{
+  backend::CrosstermBackend,
+  crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+  },
+  layout::{Alignment, Constraint, Layout},
+  style::{Color, Style, Stylize},
+  text::Line,
+  widgets::{Block, Gauge, Paragraph},
+  Frame, Terminal,
+};
+use std::{
+  collections::VecDeque,
+  error::Error,
+  fs::File,
+  io::{self, BufRead, BufReader, IsTerminal, PipeReader, PipeWriter, Write},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+  },
+  thread,
+  time::Duration,
+};
+
+#[cfg(unix)]
+use std::os::fd::{AsFd, AsRawFd, OwnedFd as OwnedStream};
+#[cfg(windows)]
+use std::os::windows::io::{AsHandle, AsRawHandle, OwnedHandle as OwnedStream};
+
+/// Log lines kept for the dashboard, printed once it's closed
+const LOG_LINES_KEPT: usize = 1000;
+/// How often the dashboard is redrawn
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+type DashboardTerminal = Terminal<CrosstermBackend<File>>;
+type LogLines = Arc<Mutex<VecDeque<String>>>;
+
+#[cfg(unix)]
+fn duplicate(stream: impl AsFd) -> io::Result<OwnedStream> {
+  stream.as_fd().try_clone_to_owned()
+}
+
+#[cfg(windows)]
+fn duplicate(stream: impl AsHandle) -> io::Result<OwnedStream> {
+  stream.as_handle().try_clone_to_owned()
+}
+
+/// Points stdout and stderr to the given streams, what was printed before going to the previous ones
+#[cfg(unix)]
+fn set_output(stdout: &impl AsFd, stderr: &impl AsFd) {
+  let _ = io::stdout().flush();
+
+  // SAFETY: both are open, dup2 only replaces the standard streams with copies of them
+  unsafe {
+    libc::dup2(stdout.as_fd().as_raw_fd(), libc::STDOUT_FILENO);
+    libc::dup2(stderr.as_fd().as_raw_fd(), libc::STDERR_FILENO);
+  }
+}
+
+/// Points stdout and stderr to the given streams, what was printed before going to the previous ones
+#[cfg(windows)]
+fn set_output(stdout: &impl AsHandle, stderr: &impl AsHandle) {
+  use windows_sys::Win32::System::Console::{SetStdHandle, STD_ERROR_HANDLE, STD_OUTPUT_HANDLE};
+
+  let _ = io::stdout().flush();
+
+  // SAFETY: the dashboard keeps both handles open until the output is set back
+  unsafe {
+    SetStdHandle(STD_OUTPUT_HANDLE, stdout.as_handle().as_raw_handle() as _);
+    SetStdHandle(STD_ERROR_HANDLE, stderr.as_handle().as_raw_handle() as _);
+  }
+}
+
+fn read_log(reader: PipeReader, log: &LogLines) {
+  for line in BufReader::new(reader).lines() {
+    let Ok(line) = line else {
+      break;
+    };
+
+    let mut log = log.lock().unwrap();
+
+    if log.len() == LOG_LINES_KEPT {
+      log.pop_front();
+    }
+
+    log.push_back(line);
+  }
+}
+
+fn restore(terminal: &mut DashboardTerminal) {
+  let _ = execute!(terminal.backend_mut(), LeaveAlternateScreen);
+  let _ = terminal.show_cursor();
+  let _ = terminal::disable_raw_mode();
+}
+
+fn phase_label(phase: Phase) -> &'static str {
+  match phase {
+    Phase::Starting => "Starting",
+    Phase::Watching => "Watching for a new video",
+    Phase::WaitingForPublic => "Waiting for the new video to be public",
+    Phase::Posting => "Posting the comment",
+    Phase::Posted => "COMMENT POSTED",
+    Phase::Failed => "POSTING FAILED",
+    Phase::Paused => "Paused",
+  }
+}
+
+fn draw(frame: &mut Frame, state: &SharedWatchState, log: &LogLines, closing: bool) {
+  let state = state.lock().unwrap();
+  let is_done = matches!(state.phase, Phase::Posted | Phase::Failed);
+  let [header_area, poll_area, wait_area, info_area, log_area, footer_area] = Layout::vertical([
+    Constraint::Length(if is_done { 7 } else { 3 }),
+    Constraint::Length(3),
+    Constraint::Length(3),
+    Constraint::Length(5),
+    Constraint::Min(3),
+    Constraint::Length(1),
+  ])
+  .areas(frame.area());
+
+  let title = format!(" yfc · {} ", state.channel_id);
+  let phase = format!("{} {}", status::phase_icon(state.phase), phase_label(state.phase));
+  let header = if is_done {
+    let color = match state.phase {
+      Phase::Posted => Color::Green,
+      _ => Color::Red,
+    };
+
+    // Hard to miss from across the room
+    Paragraph::new(vec![
+      Line::default(),
+      Line::from(phase).bold(),
+      Line::default(),
+      Line::from(state.last_result.clone().unwrap_or_default()),
+    ])
+    .alignment(Alignment::Center)
+    .block(Block::bordered().title(title))
+    .style(Style::new().fg(Color::Black).bg(color))
+  } else {
+    Paragraph::new(phase).block(Block::bordered().title(title))
+  };
+
+  frame.render_widget(header, header_area);
+
+  let now = Utc::now();
+  let poll = match (state.phase, state.last_poll_at, state.next_poll_at) {
+    (Phase::Watching, Some(last_poll_at), Some(next_poll_at)) if next_poll_at > last_poll_at => {
+      let elapsed = (now - last_poll_at).num_milliseconds() as f64;
+      let total = (next_poll_at - last_poll_at).num_milliseconds() as f64;
+
+      Gauge::default()
+        .ratio((elapsed / total).clamp(0.0, 1.0))
+        .label(format!("next poll in {}", status::format_countdown(next_poll_at)))
+    }
+    (Phase::Watching, ..) => Gauge::default().label("polling"),
+    (phase, ..) => Gauge::default().label(phase_label(phase).to_lowercase()),
+  };
+
+  frame.render_widget(
+    poll
+      .block(Block::bordered().title(" Next poll "))
+      .gauge_style(Color::Cyan),
+    poll_area,
+  );
+
+  let wait = match (state.started_at, state.wait_limit) {
+    (Some(started_at), Some(wait_limit)) if !wait_limit.is_zero() => {
+      let elapsed = started_at.elapsed();
+
+      Gauge::default()
+        .ratio((elapsed.as_secs_f64() / wait_limit.as_secs_f64()).clamp(0.0, 1.0))
+        .label(format!(
+          "{} left of {}",
+          format_duration(wait_limit.saturating_sub(elapsed)),
+          format_duration(wait_limit)
+        ))
+    }
+    (_, Some(_)) => Gauge::default().ratio(1.0).label("reached"),
+    (_, None) => Gauge::default().label("no wait limit"),
+  };
+
+  frame.render_widget(
+    wait
+      .block(Block::bordered().title(" Wait limit "))
+      .gauge_style(Color::Yellow),
+    wait_area,
+  );
+
+  let last_seen = match (&state.last_seen_video_title, &state.last_seen_video_id) {
+    (Some(title), Some(video_id)) => format!("{title} ({video_id})"),
+    (None, Some(video_id)) => video_id.clone(),
+    _ => "none yet".into(),
+  };
+  let info = Paragraph::new(vec![
+    Line::from(format!("Last seen video: {last_seen}")),
+    Line::from(format!(
+      "Quota: {} units used, {} today",
+      quota::used(),
+      quota::used_today()
+    )),
+    Line::from(format!(
+      "Last result: {}",
+      state.last_result.as_deref().unwrap_or("none yet")
+    )),
+  ])
+  .block(Block::bordered());
+
+  frame.render_widget(info, info_area);
+
+  let log = log.lock().unwrap();
+  let visible = (log_area.height as usize).saturating_sub(2);
+  let lines: Vec<Line> = log
+    .iter()
+    .skip(log.len().saturating_sub(visible))
+    .map(|line| Line::from(line.as_str()))
+    .collect();
+
+  frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" Log ")), log_area);
+
+  let footer = if closing {
+    " Press any key to exit"
+  } else {
+    " q or Ctrl+C to stop"
+  };
+
+  frame.render_widget(Paragraph::new(footer).dim(), footer_area);
+}
+
+/// Whether the key asks to stop, raw mode turning Ctrl+C into a key rather than a signal
+fn is_stop_key(event: &Event) -> bool {
+  let Event::Key(key) = event else {
+    return false;
+  };
+
+  key.kind == KeyEventKind::Press
+    && match key.code {
+      KeyCode::Char('q') => true,
+      KeyCode::Char('c') => key.modifiers.contains(KeyModifiers::CONTROL),
+      _ => false,
+    }
+}
+
+fn run(mut terminal: DashboardTerminal, state: SharedWatchState, log: LogLines, closed: Arc<AtomicBool>) {
+  while !closed.load(Ordering::Relaxed) {
+    let _ = terminal.draw(|frame| draw(frame, &state, &log, false));
+
+    if !event::poll(REFRESH_INTERVAL).unwrap_or(false) {
+      continue;
+    }
+
+    if event::read().is_ok_and(|event| is_stop_key(&event)) {
+      // The second stop request quits right away, leaving no chance to clean up after
+      if shutdown::requested() {
+        restore(&mut terminal);
+      }
+
+      shutdown::request();
+    }
+  }
+
+  // The result stays on screen until it's seen, unless the watcher was stopped on purpose
+  let is_done = matches!(state.lock().unwrap().phase, Phase::Posted | Phase::Failed);
+
+  if is_done && !shutdown::requested() {
+    let _ = terminal.draw(|frame| draw(frame, &state, &log, true));
+
+    while let Ok(event) = event::read() {
+      if let Event::Key(key) = event {
+        if key.kind == KeyEventKind::Press {
+          break;
+        }
+      }
+    }
+  }
+
+  restore(&mut terminal);
+}
+
+/// A live dashboard of the watcher replacing the scrolling output, which is shown in it as a log. The output is
+/// printed as usual once the dashboard is dropped.
+pub struct Dashboard {
+  stdout: OwnedStream,
+  stderr: OwnedStream,
+  writer: Option<PipeWriter>,
+  reader: Option<thread::JoinHandle<()>>,
+  drawer: Option<thread::JoinHandle<()>>,
+  closed: Arc<AtomicBool>,
+  log: LogLines,
+}
+
+impl Dashboard {
+  pub fn start(state: SharedWatchState) -> Result<Self, Box<dyn Error>> {
+    if !io::stdout().is_terminal() {
+      return Err("--tui needs a terminal to draw on".into());
+    }
+
+    let stdout = duplicate(io::stdout())?;
+    let stderr = duplicate(io::stderr())?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(File::from(duplicate(io::stdout())?)))?;
+
+    terminal::enable_raw_mode()?;
+
+    if let Err(e) = execute!(terminal.backend_mut(), EnterAlternateScreen).and_then(|_| terminal.hide_cursor()) {
+      restore(&mut terminal);
+      return Err(e.into());
+    }
+
+    let (reader, writer) = io::pipe()?;
+    let log = LogLines::default();
+    let closed = Arc::new(AtomicBool::new(false));
+
+    set_output(&writer, &writer);
+
+    Ok(Self {
+      stdout,
+      stderr,
+      writer: Some(writer),
+      reader: Some(thread::spawn({
+        let log = log.clone();
+        move || read_log(reader, &log)
+      })),
+      drawer: Some(thread::spawn({
+        let log = log.clone();
+        let closed = closed.clone();
+        move || run(terminal, state, log, closed)
+      })),
+      closed,
+      log,
+    })
+  }
+}
+
+impl Drop for Dashboard {
+  fn drop(&mut self) {
+    self.closed.store(true, Ordering::Relaxed);
+
+    if let Some(drawer) = self.drawer.take() {
+      let _ = drawer.join();
+    }
+
+    set_output(&self.stdout, &self.stderr);
+
+    // The log thread is done once the pipe is closed
+    drop(self.writer.take());
+
+    if let Some(reader) = self.reader.take() {
+      let _ = reader.join();
+    }
+
+    // The dashboard went away with the alternate screen, the log is kept in the scrollback
+    for line in self.log.lock().unwrap().iter() {
+      println!("{line}");
+    }
+  }
+}
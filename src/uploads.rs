@@ -0,0 +1,170 @@
+use crate::{
+  console, get_data_dir, playlists, quota,
+  ratelimit::RateLimitHint,
+  state::StateStore,
+  youtube::{get_latest_videos, is_age_restricted, parse_duration, with_timeout, YoutubeClient},
+};
+use google_youtube3::api::Video;
+use std::{collections::HashMap, error::Error};
+
+/// The channel ID of a handle (e.g. `@LinusTechTips`), or the channel ID itself
+pub async fn resolve_channel_id(
+  client: &YoutubeClient,
+  channel: &str,
+  api_key: Option<&str>,
+) -> Result<String, Box<dyn Error>> {
+  if !channel.starts_with('@') {
+    return Ok(channel.into());
+  }
+
+  quota::spend(quota::LIST_COST);
+
+  let mut call = client
+    .channels()
+    .list(&vec!["id".into()])
+    .for_handle(channel)
+    .param("fields", "items(id)");
+
+  if let Some(api_key) = api_key {
+    call = call.param("key", api_key);
+  }
+
+  let (_, result) = with_timeout(call.delegate(&mut RateLimitHint).doit()).await?;
+
+  result
+    .items
+    .and_then(|items| items.into_iter().next())
+    .and_then(|channel| channel.id)
+    .ok_or_else(|| format!("No channel has the handle {channel}").into())
+}
+
+/// What the playlist leaves out about several videos (length, live status, rating), in a single call
+async fn get_videos(
+  client: &YoutubeClient,
+  video_ids: &[&str],
+  api_key: Option<&str>,
+) -> google_youtube3::Result<HashMap<String, Video>> {
+  quota::spend(quota::LIST_COST);
+
+  let mut call = client.videos().list(&vec![
+    "snippet".into(),
+    "contentDetails".into(),
+    "liveStreamingDetails".into(),
+  ]);
+
+  for video_id in video_ids {
+    call = call.add_id(video_id);
+  }
+
+  if let Some(api_key) = api_key {
+    call = call.param("key", api_key);
+  }
+
+  let (_, result) = with_timeout(call.delegate(&mut RateLimitHint).doit()).await?;
+
+  Ok(
+    result
+      .items
+      .unwrap_or_default()
+      .into_iter()
+      .filter_map(|video| Some((video.id.clone()?, video)))
+      .collect(),
+  )
+}
+
+/// h:mm:ss, or m:ss under an hour, as YouTube shows it
+fn format_length(seconds: u64) -> String {
+  match seconds / 3600 {
+    0 => format!("{}:{:02}", seconds / 60, seconds % 60),
+    hours => format!("{hours}:{:02}:{:02}", (seconds % 3600) / 60, seconds % 60),
+  }
+}
+
+/// What kind of upload it is. A premiere is told apart from a live stream by already having a length.
+fn classify(video: &Video, is_short: bool) -> &'static str {
+  if is_short {
+    return "short";
+  }
+
+  let is_live = video.live_streaming_details.is_some();
+  let has_length = video
+    .content_details
+    .as_ref()
+    .and_then(|details| details.duration.as_deref())
+    .and_then(parse_duration)
+    .is_some_and(|seconds| seconds > 0);
+  let broadcast = video
+    .snippet
+    .as_ref()
+    .and_then(|snippet| snippet.live_broadcast_content.as_deref());
+
+  match (broadcast, has_length) {
+    (Some("upcoming"), true) => "premiere",
+    (Some("upcoming"), false) => "upcoming live",
+    (Some("live"), true) => "premiering",
+    (Some("live"), false) => "live",
+    _ if is_live => "past live",
+    _ => "video",
+  }
+}
+
+/// Prints the latest uploads of a channel as the watcher sees them: what it would comment on, and why it would skip
+/// the rest
+pub async fn list(
+  client: &YoutubeClient,
+  channel: &str,
+  limit: u32,
+  api_key: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+  let channel_id = resolve_channel_id(client, channel, api_key).await?;
+  let playlist_id = playlists::uploads_playlist_id(client, &get_data_dir(), &channel_id, api_key, false)
+    .await
+    .ok_or_else(|| format!("Failed to get the uploads playlist of channel {channel_id}"))?;
+  let uploads = get_latest_videos(client, &playlist_id, limit, api_key, None)
+    .await?
+    .map(|page| page.videos)
+    .unwrap_or_default();
+
+  if uploads.is_empty() {
+    println!("Channel {channel_id} has no uploads");
+    return Ok(());
+  }
+
+  let video_ids: Vec<&str> = uploads.iter().map(|video| video.id.as_str()).collect();
+  let videos = get_videos(client, &video_ids, api_key).await?;
+  let mut state_store = StateStore::load(&get_data_dir())?;
+  let channel_state = state_store.channel(&channel_id);
+  // Only a wait in progress has a baseline, the next one starts from the latest upload
+  let baseline = channel_state.wait_started_at.and(channel_state.baseline_published_at);
+
+  println!("Latest uploads of channel {channel_id}, newest first:");
+
+  for upload in &uploads {
+    let video = videos.get(&upload.id);
+    let published_at = upload.published_at.map_or("-".into(), console::format_time);
+    let length = video
+      .and_then(|video| video.content_details.as_ref())
+      .and_then(|details| details.duration.as_deref())
+      .and_then(parse_duration)
+      .map_or("-".into(), format_length);
+    let kind = video.map_or(if upload.is_short { "short" } else { "-" }, |video| {
+      classify(video, upload.is_short)
+    });
+    let verdict = if upload.is_short {
+      "skipped, #shorts in the description"
+    } else if channel_state.has_commented(&upload.id) {
+      "skipped, already commented on"
+    } else if baseline.is_some_and(|baseline| upload.published_at.is_some_and(|time| time <= baseline)) {
+      "skipped, not newer than the current wait"
+    } else if video.is_some_and(is_age_restricted) {
+      "age-restricted, see --age-restricted-policy"
+    } else {
+      "eligible"
+    };
+
+    println!("{published_at}  {}  {length:>8}  {kind:<13}  {verdict}", upload.id);
+    println!("    {}", upload.title);
+  }
+
+  Ok(())
+}
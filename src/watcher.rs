@@ -0,0 +1,3295 @@
+use crate::accounts::Accounts;
+use crate::clock::ClockGuard;
+use crate::comment::{CommentContext, CommentSource};
+use crate::control::ControlledSource;
+use crate::db::{Attempt, AttemptResult, History};
+use crate::detect::DetectionChain;
+use crate::events::{self, EventSender, EventStream, WatchEvent};
+use crate::exit::Failure;
+use crate::heartbeat::Heartbeat;
+use crate::hints::{self, Hints};
+use crate::notify::EventRing;
+use crate::projects::ReadProjects;
+use crate::safe_mode::{RunOutcome, SafeMode};
+use crate::schedule::UploadPattern;
+use crate::search::SearchOrder;
+use crate::shutdown::Interrupted;
+use crate::source::{CommentSink, VideoSource};
+use crate::state::{ChannelLock, ChannelState, StateStore};
+use crate::status::{Phase, SharedWatchState, WatchState};
+use crate::{
+  analyze, api, channels, comment, console, control, filters, playlists, quota, ratelimit, reload, sanitize, schedule,
+  shutdown, status, systemd, tui,
+};
+use crate::{
+  auth::{
+    acts_as, authenticate_account, clear_tokens, get_acting_channel, get_client_secret, get_read_token_storage_path,
+    get_token_storage_path, get_youtube_client, READ_SCOPE,
+  },
+  cli::{
+    AgeRestrictedPolicy, Args, CommentTarget, DetectBackend, DuplicatePolicy, MembersOnlyPolicy, QuotaPolicy,
+    RetryStrategy, MIN_POLL_INTERVAL,
+  },
+  console::{format_countdown, format_duration},
+  get_data_dir, sanitize_comment,
+  youtube::{
+    build_https_client, comments_disabled, delete_comment, find_comment, find_own_comment, find_owner_comment,
+    get_comment_rank, get_engagement, get_first_comment, get_privacy_status, get_publish_latency, get_video_language,
+    is_age_restricted, is_comment_visible, is_duplicate, is_members_only, is_quota_exceeded, is_rejected, is_transient,
+    keep_connection_warm, like_video, new_client, rejection_reason, CommentRank, Engagement, LatestVideo, PlaylistPage,
+    RejectionReason, YoutubeClient, REQUEST_TIMEOUT,
+  },
+};
+use futures::future::join_all;
+use google_youtube3::{
+  api::Video,
+  chrono::{self, DateTime, Utc},
+  client::NoToken,
+};
+use std::{
+  cmp::Reverse,
+  collections::{HashSet, VecDeque},
+  error::Error,
+  fmt,
+  future::Future,
+  mem,
+  time::{Duration, Instant},
+};
+use tokio::time::sleep;
+#[cfg(feature = "otel")]
+use {crate::otel, std::time::SystemTime};
+
+/// How many of the latest uploads each poll looks at to detect videos published at once. Fetching more costs no
+/// extra quota, the playlist page is charged the same.
+pub const BATCH_LOOKBACK: u32 = 10;
+
+/// What the watcher already knows about the uploads. A video is new when it was published after the baseline and wasn't
+/// seen before, so the latest video being deleted or made private doesn't make an older one look new.
+#[derive(Default)]
+pub struct Baseline {
+  pub published_at: Option<DateTime<Utc>>,
+  pub seen_video_ids: HashSet<String>,
+}
+
+impl Baseline {
+  pub fn new(videos: &[LatestVideo]) -> Self {
+    let mut baseline = Self::default();
+
+    for video in videos {
+      baseline.advance(video);
+    }
+
+    baseline
+  }
+
+  pub fn is_new(&self, video: &LatestVideo) -> bool {
+    if self.seen_video_ids.contains(&video.id) {
+      return false;
+    }
+
+    match (video.published_at, self.published_at) {
+      (Some(published_at), Some(baseline)) => published_at > baseline,
+      _ => true,
+    }
+  }
+
+  /// Marks `video` as seen, moving the baseline forward if it's newer
+  pub fn advance(&mut self, video: &LatestVideo) {
+    self.seen_video_ids.insert(video.id.clone());
+    self.published_at = self.published_at.max(video.published_at);
+  }
+}
+
+/// Where the watcher looks for new videos
+pub enum Feed {
+  Playlist(String),
+  Search {
+    query: String,
+    channel_ids: Vec<String>,
+    order: SearchOrder,
+  },
+  /// The uploads of the channel through the backends of --detect, the uploads playlist being the last resort
+  Detect(DetectionChain),
+}
+
+impl Feed {
+  /// The search of --query, if given
+  pub fn search(args: &Args) -> Option<Self> {
+    args.query.as_ref().map(|query| Feed::Search {
+      query: query.clone(),
+      channel_ids: args.query_channel.clone(),
+      order: args.order,
+    })
+  }
+
+  /// Quota units spent on every poll
+  pub fn poll_cost(&self) -> u64 {
+    match self {
+      Feed::Playlist(_) => quota::LIST_COST,
+      Feed::Search { channel_ids, .. } => quota::SEARCH_COST * channel_ids.len().max(1) as u64,
+      Feed::Detect(chain) => chain.poll_cost(),
+    }
+  }
+
+  /// The latest `count` videos, newest first, like `get_latest_videos`. Searches don't take ETags.
+  pub async fn latest_videos(
+    &self,
+    source: &dyn VideoSource,
+    count: u32,
+    api_key: Option<&str>,
+    etag: Option<&str>,
+  ) -> google_youtube3::Result<Option<PlaylistPage>> {
+    let (query, channel_ids, order) = match self {
+      Feed::Playlist(playlist_id) => return source.playlist_videos(playlist_id, count, api_key, etag).await,
+      Feed::Detect(chain) => return chain.latest_videos(source, count, api_key, etag).await,
+      Feed::Search {
+        query,
+        channel_ids,
+        order,
+      } => (query, channel_ids, *order),
+    };
+
+    let mut videos = Vec::new();
+
+    if channel_ids.is_empty() {
+      videos = source.search_videos(query, None, order, count, api_key).await?;
+    }
+
+    for channel_id in channel_ids {
+      videos.extend(
+        source
+          .search_videos(query, Some(channel_id), order, count, api_key)
+          .await?,
+      );
+    }
+
+    // Whatever the order of the search, the newest matches come first like in a playlist
+    videos.sort_by_key(|video| Reverse(video.published_at));
+    videos.dedup_by(|a, b| a.id == b.id);
+    videos.truncate(count as usize);
+
+    Ok(Some(PlaylistPage {
+      videos,
+      unavailable: Vec::new(),
+      etag: None,
+    }))
+  }
+}
+
+/// The uploads of the channel, through the backends of --detect when there are others than the API
+fn uploads_feed(args: &Args, playlist_id: String) -> Result<Feed, Box<dyn Error>> {
+  if args.detect.iter().all(|backend| *backend == DetectBackend::Api) {
+    return Ok(Feed::Playlist(playlist_id));
+  }
+
+  Ok(Feed::Detect(DetectionChain::new(args, playlist_id)?))
+}
+
+/// How many of the latest uploads are looked at to find the newest eligible one, so a Short released at the same time
+/// doesn't hide a regular upload
+pub const ELIGIBLE_LOOKBACK: u32 = 5;
+
+/// The newest upload that isn't a Short
+pub async fn get_latest_eligible_video(
+  source: &dyn VideoSource,
+  feed: &Feed,
+  api_key: Option<&str>,
+) -> google_youtube3::Result<Option<LatestVideo>> {
+  let Some(page) = feed.latest_videos(source, ELIGIBLE_LOOKBACK, api_key, None).await? else {
+    return Ok(None);
+  };
+
+  for video in page.videos {
+    let is_short = video.is_short || (video.may_be_short && source.is_short(&video.id).await?);
+
+    if !is_short {
+      return Ok(Some(video));
+    }
+  }
+
+  Ok(None)
+}
+
+/// Whether the video is a Short. The #shorts tag is enough to skip it, but with --shorts-only the Shorts without it
+/// are found too, and so are those listed by a backend that can't tell them apart.
+async fn is_short(args: &Args, source: &dyn VideoSource, video: &LatestVideo) -> bool {
+  if video.is_short || !(args.shorts_only || video.may_be_short) {
+    return video.is_short;
+  }
+
+  source.is_short(&video.id).await.unwrap_or_else(|e| {
+    console::error(format!("Failed to tell whether video {} is a short: {e}", video.id));
+    false
+  })
+}
+
+/// The polled videos to comment on, newest first: the new ones of the right kind (Shorts with --shorts-only, regular
+/// videos otherwise) that weren't commented on before. The others are remembered as seen and not looked at again.
+pub async fn new_videos(
+  args: &Args,
+  source: &dyn VideoSource,
+  baseline: &mut Baseline,
+  channel_state: &ChannelState,
+  polled_videos: Vec<LatestVideo>,
+  events: &EventSender,
+) -> Vec<LatestVideo> {
+  let mut new_videos = Vec::new();
+
+  for video in polled_videos {
+    if !baseline.is_new(&video) {
+      continue;
+    }
+
+    let is_short = is_short(args, source, &video).await;
+
+    if is_short != args.shorts_only {
+      if is_short {
+        events.send(WatchEvent::ShortSkipped {
+          video_id: video.id.clone(),
+        });
+      } else {
+        console::info(format!("Video {} isn't a short, skipping it", video.id));
+      }
+
+      baseline.seen_video_ids.insert(video.id);
+    } else if channel_state.has_commented(&video.id) {
+      console::info(format!("Video {} was already commented on, skipping it", video.id));
+      baseline.seen_video_ids.insert(video.id);
+    } else {
+      new_videos.push(video);
+    }
+  }
+
+  new_videos
+}
+
+/// Whether polling should be fast right now
+pub fn is_hot(args: &Args, upload_pattern: Option<&UploadPattern>, started_at: Instant) -> bool {
+  let now = Utc::now();
+
+  args
+    .hot_after_start
+    .is_some_and(|duration| started_at.elapsed() < duration)
+    || args.hot_window.iter().any(|window| window.contains(now))
+    || args.schedule.iter().any(|schedule| schedule.contains(now))
+    || upload_pattern.is_some_and(|pattern| pattern.is_hot(now, args.burst_window))
+}
+
+/// When the next watch window starts, `None` without a schedule or when already inside a window
+pub fn next_scheduled_start(args: &Args) -> Option<DateTime<Utc>> {
+  let now = Utc::now();
+
+  if args.schedule.iter().any(|schedule| schedule.contains(now)) {
+    return None;
+  }
+
+  args
+    .schedule
+    .iter()
+    .filter_map(|schedule| schedule.next_start(now))
+    .min()
+}
+
+/// Keeps watching the video until its privacy status flips to public. Returns the public video, or `None` if the wait
+/// limit is reached first. A scheduled video may take hours, so a stop request ends the wait.
+pub async fn wait_until_public(
+  source: &dyn VideoSource,
+  video_id: &str,
+  interval: Duration,
+  started_at: Instant,
+  wait_limit: Duration,
+) -> Result<Option<Video>, Interrupted> {
+  loop {
+    // Only a video known to be public is posted on, a failed lookup is tried again
+    match source.video(video_id).await {
+      Ok(Some(video)) => match get_privacy_status(&video) {
+        Some("public") => return Ok(Some(video)),
+        Some(status) => console::info(format!("Video {video_id} is {status}, waiting for it to become public")),
+        None => console::warning(format!(
+          "The privacy status of video {video_id} is unknown, waiting for it to become public"
+        )),
+      },
+      Ok(None) => console::warning(format!("Video {video_id} isn't found, waiting for it to become public")),
+      Err(e) => console::error(format!("Failed to check whether video {video_id} is public: {e}")),
+    }
+
+    shutdown::unless_requested(sleep(interval)).await?;
+
+    if started_at.elapsed() >= wait_limit {
+      return Ok(None);
+    }
+  }
+}
+
+/// Minimum normalized Levenshtein similarity for two titles to be considered the same upload
+pub const REUPLOAD_TITLE_SIMILARITY: f64 = 0.8;
+
+pub fn is_similar_title(a: &str, b: &str) -> bool {
+  strsim::normalized_levenshtein(&a.trim().to_lowercase(), &b.trim().to_lowercase()) >= REUPLOAD_TITLE_SIMILARITY
+}
+
+/// Keeps watching the channel for a while after commenting, looking for the commented video being taken down and
+/// uploaded again under a new ID (creators often do that to fix a typo or a bad cut). Only with --reupload-window.
+pub async fn watch_for_reuploads(
+  poster: &dyn CommentSink,
+  reader: &dyn VideoSource,
+  feed: &Feed,
+  mut commented: LatestVideo,
+  comment: &str,
+  args: &Args,
+  history: &History,
+) -> google_youtube3::Result<()> {
+  let Some(window) = args.reupload_window else {
+    return Ok(());
+  };
+
+  console::info(format!(
+    "Watching for re-uploads of video {} for {}",
+    commented.id,
+    format_duration(window)
+  ));
+
+  let started_at = Instant::now();
+
+  while started_at.elapsed() < window {
+    sleep(args.poll_interval).await;
+
+    let Ok(Some(latest)) = get_latest_eligible_video(reader, feed, args.api_key.as_deref()).await else {
+      continue;
+    };
+
+    if latest.id == commented.id || !is_similar_title(&latest.title, &commented.title) {
+      continue;
+    }
+
+    // A similar title alone could just be the next part of a series, so the original must be gone as well
+    let original_removed = match reader.video(&commented.id).await {
+      Ok(None) => true,
+      Ok(Some(video)) => get_privacy_status(&video) == Some("private"),
+      Err(_) => false,
+    };
+
+    if !original_removed {
+      continue;
+    }
+
+    console::info(format!(
+      "Video {} looks like a re-upload of {}",
+      latest.id, commented.id
+    ));
+
+    if args.repost_on_reupload {
+      let result = poster.post(&latest.id, comment, None).await;
+      let error = result.as_ref().err().map(|e| e.to_string());
+
+      history.record(&Attempt {
+        channel_id: &args.channel_id,
+        video_id: &latest.id,
+        comment,
+        comment_id: result.as_deref().ok(),
+        result: match result {
+          Ok(_) => AttemptResult::Posted,
+          Err(_) => AttemptResult::Failed,
+        },
+        latency: None,
+        error: error.as_deref(),
+      });
+
+      result?;
+      console::success("Comment re-posted on the replacement video!");
+    }
+
+    commented = latest;
+  }
+
+  Ok(())
+}
+
+/// How many times the comment source is asked for a comment different from the hidden one
+pub const ALTERNATE_COMMENT_TRIES: usize = 5;
+
+/// Resolves a comment other than `rejected`, which the sources producing varying texts can. `None` when the source
+/// fails or keeps producing the same text.
+pub async fn resolve_alternate(
+  comment_source: &dyn CommentSource,
+  context: &CommentContext<'_>,
+  rejected: &str,
+) -> Option<String> {
+  for _ in 0..ALTERNATE_COMMENT_TRIES {
+    match comment_source.resolve(context).await {
+      Ok(comment) if comment != rejected => return Some(comment),
+      Ok(_) => {}
+      Err(e) => {
+        console::error(format!("Failed to resolve an alternate comment: {e}"));
+        return None;
+      }
+    }
+  }
+
+  console::info("The comment source has no alternate comment");
+  None
+}
+
+/// Another comment to post when YouTube rejected `rejected` as spam: another one from the source, or else the same
+/// one without what often gets comments flagged (see --sanitize). `None` when neither differs.
+pub async fn resolve_unflagged(
+  comment_source: &dyn CommentSource,
+  context: &CommentContext<'_>,
+  rejected: &str,
+  args: &Args,
+) -> Option<String> {
+  if let Some(alternate) = resolve_alternate(comment_source, context, rejected).await {
+    return Some(sanitize_comment(args, &alternate));
+  }
+
+  Some(sanitize::strip(rejected)).filter(|stripped| stripped != rejected && comment::validate(stripped).is_ok())
+}
+
+/// Posts another comment from the source when the first one got hidden, as the exact same text is likely to be
+/// filtered again
+#[allow(clippy::too_many_arguments)]
+pub async fn repost_alternate(
+  sink: &dyn CommentSink,
+  comment_source: &dyn CommentSource,
+  context: &CommentContext<'_>,
+  hidden_comment: &str,
+  parent_id: Option<&str>,
+  args: &Args,
+  deadline: Option<Instant>,
+  history: &History,
+  events: &EventSender,
+) {
+  let Some(alternate) = resolve_alternate(comment_source, context, hidden_comment).await else {
+    console::info("Not reposting");
+    return;
+  };
+
+  let result = post_comment_with_retries(sink, context.video_id, &alternate, parent_id, args, deadline, events).await;
+  let error = result.as_ref().err().map(|e| e.to_string());
+
+  match &result {
+    Ok(_) => console::success("Alternate comment posted!"),
+    Err(e) => console::error(format!("Failed to post the alternate comment: {e}")),
+  }
+
+  history.record(&Attempt {
+    channel_id: &args.channel_id,
+    video_id: context.video_id,
+    comment: &alternate,
+    comment_id: result.as_deref().ok(),
+    result: match result {
+      Ok(_) => AttemptResult::Posted,
+      Err(_) => AttemptResult::Failed,
+    },
+    latency: None,
+    error: error.as_deref(),
+  });
+}
+
+/// Polls the comments of the video until the channel owner comments, up to `deadline`
+pub async fn wait_for_owner_comment(
+  client: &YoutubeClient,
+  video_id: &str,
+  owner_channel_id: &str,
+  interval: Duration,
+  deadline: Instant,
+) -> Option<String> {
+  console::info(format!("Waiting for the channel owner to comment on video {video_id}"));
+
+  loop {
+    match find_owner_comment(client, video_id, owner_channel_id).await {
+      Ok(Some(comment_id)) => {
+        console::info(format!("Replying to the comment {comment_id} of the channel owner"));
+        return Some(comment_id);
+      }
+      Ok(None) => {}
+      Err(e) => console::error(format!("Failed to look for the comment of the channel owner: {e}")),
+    }
+
+    if Instant::now() + interval >= deadline || shutdown::requested() {
+      return None;
+    }
+
+    sleep(interval).await;
+  }
+}
+
+/// Observer mode: reports how long the video took to be detected and, after `delay`, who got the first comment
+pub async fn observe_video(
+  client: &YoutubeClient,
+  video_id: &str,
+  video: &Video,
+  detected_at: Instant,
+  delay: Duration,
+  events: &EventSender,
+) {
+  let published_at = video.snippet.as_ref().and_then(|snippet| snippet.published_at);
+  let detection_latency = get_publish_latency(video).map(|latency| latency.saturating_sub(detected_at.elapsed()));
+
+  if let Some(latency) = detection_latency {
+    console::info(format!(
+      "Video {video_id} was detected {}ms after it was published",
+      latency.as_millis()
+    ));
+  }
+
+  console::info(format!(
+    "Looking for the first comment on video {video_id} in {}",
+    format_duration(delay)
+  ));
+  sleep(delay).await;
+
+  let first_comment = get_first_comment(client, video_id)
+    .await
+    .inspect_err(|e| console::error(format!("Failed to list the comments of video {video_id}: {e}")))
+    .ok()
+    .flatten();
+  let first_comment_after = first_comment
+    .as_ref()
+    .and_then(|(_, commented_at)| Some((*commented_at)? - published_at?))
+    .and_then(|after| after.to_std().ok());
+
+  match (&first_comment, first_comment_after) {
+    (Some((author, _)), Some(after)) => console::info(format!(
+      "The first comment is by {author}, {}ms after publication",
+      after.as_millis()
+    )),
+    (Some((author, _)), None) => console::info(format!("The first comment is by {author}")),
+    (None, _) => console::info(format!("No first comment found on video {video_id}")),
+  }
+
+  events.send(WatchEvent::VideoObserved {
+    video_id: video_id.into(),
+    detection_latency,
+    first_commenter: first_comment.map(|(author, _)| author),
+    first_comment_after,
+  });
+}
+
+/// Checks the comment every `interval` for `window` (--monitor-for), reporting when it gets more likes or replies. It
+/// runs alongside the watcher, the events only end once it's done.
+pub async fn monitor_engagement(
+  client: YoutubeClient,
+  video_id: String,
+  comment_id: String,
+  window: Duration,
+  interval: Duration,
+  events: EventSender,
+) {
+  let started_at = Instant::now();
+  let mut last = Engagement::default();
+
+  while started_at.elapsed() + interval <= window && !shutdown::requested() {
+    sleep(interval).await;
+
+    match get_engagement(&client, &comment_id).await {
+      Ok(Some(engagement)) if engagement.likes > last.likes || engagement.replies > last.replies => {
+        console::success(format!(
+          "Your comment on video {video_id} has {} likes and {} replies",
+          engagement.likes, engagement.replies
+        ));
+        events.send(WatchEvent::CommentEngagement {
+          video_id: video_id.clone(),
+          comment_id: comment_id.clone(),
+          likes: engagement.likes,
+          replies: engagement.replies,
+        });
+        last = engagement;
+      }
+      // Unliked or deleted replies aren't news
+      Ok(Some(engagement)) => last = engagement,
+      Ok(None) => {}
+      Err(e) => console::error(format!("Failed to check the likes and replies of your comment: {e}")),
+    }
+  }
+}
+
+/// Replies `text` to the comment after `delay`, with --follow-up
+pub async fn post_follow_up(
+  client: YoutubeClient,
+  video_id: String,
+  comment_id: String,
+  text: String,
+  delay: Duration,
+  events: EventSender,
+) {
+  sleep(delay).await;
+
+  if shutdown::requested() {
+    return;
+  }
+
+  match client.post(&video_id, &text, Some(&comment_id)).await {
+    Ok(reply_id) => events.send(WatchEvent::FollowUpPosted { video_id, reply_id }),
+    Err(e) => console::error(format!("Failed to post the follow-up reply on video {video_id}: {e}")),
+  }
+}
+
+/// How often --verify-first-for checks the rank again
+pub const VERIFY_FIRST_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Checks the rank of the comment every minute for `window` (--verify-first-for), as comments held for review show up
+/// late, ahead of it. Reports and records in the history where it ended up, and how many older comments showed up.
+pub async fn verify_first(
+  client: YoutubeClient,
+  video_id: String,
+  comment_id: String,
+  window: Duration,
+  events: EventSender,
+) {
+  let started_at = Instant::now();
+  let mut first_position = None;
+  let mut rank = None;
+
+  loop {
+    match get_comment_rank(&client, &video_id, &comment_id).await {
+      Ok(current) => {
+        if rank.is_some_and(|rank| rank != current) {
+          console::info(format!("Your comment on video {video_id} is now {current}"));
+        }
+
+        first_position = first_position.or(current.position());
+        rank = Some(current);
+      }
+      Err(e) => console::error(format!("Failed to check the rank of your comment: {e}")),
+    }
+
+    if started_at.elapsed() + VERIFY_FIRST_INTERVAL > window || shutdown::requested() {
+      break;
+    }
+
+    sleep(VERIFY_FIRST_INTERVAL).await;
+  }
+
+  let Some(rank) = rank else {
+    return;
+  };
+  // The rank only counts older comments, so it grows when comments held for review get approved
+  let earlier_appeared = match (first_position, rank.position()) {
+    (Some(first), Some(last)) => last.saturating_sub(first),
+    _ => 0,
+  };
+
+  History::open(&get_data_dir()).record_final_rank(&comment_id, &rank.to_string(), earlier_appeared);
+  events.send(WatchEvent::RankVerified {
+    video_id,
+    rank,
+    earlier_appeared,
+  });
+}
+
+pub async fn report_comment_rank(client: &YoutubeClient, video_id: &str, comment_id: &str) -> Option<CommentRank> {
+  let rank = get_comment_rank(client, video_id, comment_id)
+    .await
+    .inspect_err(|e| console::error(format!("Failed to check the rank of your comment: {e}")))
+    .ok()?;
+
+  match rank {
+    CommentRank::Exact(1) => console::success("Your comment is the first one!"),
+    CommentRank::Exact(rank) => console::info(format!("Your comment is #{rank}")),
+    CommentRank::AtLeast(rank) => console::info(format!("Your comment is #{rank} or further down")),
+    CommentRank::NotVisible => console::warning("Your comment is not visible yet, it may be held for review"),
+  }
+
+  Some(rank)
+}
+
+/// Why the comment couldn't be posted
+#[derive(Debug)]
+pub enum PostError {
+  Api(google_youtube3::Error),
+  /// The post kept failing and the next retry would have run past the wait limit
+  Deadline(google_youtube3::Error),
+  /// The video already has the same comment
+  Duplicate(google_youtube3::Error),
+}
+
+impl Error for PostError {}
+
+impl fmt::Display for PostError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PostError::Api(e) => match rejection_reason(e) {
+        Some(reason) => write!(f, "{reason}: {e}"),
+        None => e.fmt(f),
+      },
+      PostError::Deadline(e) => write!(
+        f,
+        "gave up due to deadline, the wait limit expires before the next retry: {e}"
+      ),
+      PostError::Duplicate(e) => write!(f, "the video already has this comment: {e}"),
+    }
+  }
+}
+
+/// The wait before the retry following `attempt` failed ones
+pub fn retry_delay(args: &Args, attempt: u32) -> Duration {
+  match args.post_retry_strategy {
+    RetryStrategy::Fixed => args.post_retry_delay,
+    RetryStrategy::Exponential => args.post_retry_delay.saturating_mul(1 << attempt.min(16)),
+  }
+}
+
+/// Posts the comment, retrying transient failures as set by --post-retries, --post-retry-delay and
+/// --post-retry-strategy. A retry that would start after the deadline is not attempted, so retrying never outlasts the
+/// wait limit.
+pub async fn post_comment_with_retries(
+  sink: &dyn CommentSink,
+  video_id: &str,
+  comment: &str,
+  parent_id: Option<&str>,
+  args: &Args,
+  deadline: Option<Instant>,
+  events: &EventSender,
+) -> Result<String, PostError> {
+  let retries = args.post_retries;
+  let mut attempt = 0;
+
+  loop {
+    let error = match sink.post(video_id, comment, parent_id).await {
+      Ok(comment_id) => return Ok(comment_id),
+      Err(e) if is_duplicate(&e) => return Err(PostError::Duplicate(e)),
+      Err(e) if attempt < retries && is_transient(&e) => e,
+      Err(e) => return Err(PostError::Api(e)),
+    };
+
+    // The API may ask for longer than the retry strategy
+    let backoff = retry_delay(args, attempt).max(ratelimit::remaining().unwrap_or_default());
+
+    if deadline.is_some_and(|deadline| Instant::now() + backoff >= deadline) {
+      return Err(PostError::Deadline(error));
+    }
+
+    attempt += 1;
+    events.send(WatchEvent::RetryScheduled {
+      video_id: video_id.into(),
+      attempt,
+      retries,
+      delay: backoff,
+      error: error.to_string(),
+    });
+
+    sleep(backoff).await;
+  }
+}
+
+/// Posts with every account at once, each one --race-stagger after the previous one, and reports which comment landed
+/// first, as told by the insert answering first. The account that landed first goes on posting (liking, deleting,
+/// following up). Its comment ID is the result, or the error of the first account to fail when none landed.
+pub async fn race_accounts(
+  accounts: &Accounts,
+  video_id: &str,
+  comment: &str,
+  parent_id: Option<&str>,
+  args: &Args,
+  deadline: Option<Instant>,
+  events: &EventSender,
+) -> Result<String, PostError> {
+  console::info(format!(
+    "Racing {} accounts to comment on video {video_id}",
+    accounts.len()
+  ));
+
+  let started_at = Instant::now();
+  let attempts = accounts.clients().enumerate().map(|(index, client)| async move {
+    sleep(args.race_stagger.saturating_mul(index as u32)).await;
+
+    let result = post_comment_with_retries(client, video_id, comment, parent_id, args, deadline, events).await;
+
+    (index, result, started_at.elapsed())
+  });
+  let mut results = join_all(attempts).await;
+
+  results.sort_by_key(|(_, result, answered_after)| (result.is_err(), *answered_after));
+
+  let first_landed_after = results
+    .first()
+    .filter(|(_, result, _)| result.is_ok())
+    .map(|(_, _, answered_after)| *answered_after);
+
+  for (index, result, answered_after) in &results {
+    let name = accounts.name(*index);
+
+    match (result, first_landed_after) {
+      (Err(e), _) => console::warning(format!("Account {name} failed to comment: {e}")),
+      (Ok(_), Some(first)) if *answered_after == first => console::success(format!(
+        "Account {name} landed first, {}ms after the race started",
+        answered_after.as_millis()
+      )),
+      (Ok(_), first) => console::info(format!(
+        "Account {name} landed {}ms later",
+        answered_after.saturating_sub(first.unwrap_or_default()).as_millis()
+      )),
+    }
+  }
+
+  let (index, result, _) = results.into_iter().next().expect("at least one account races");
+  accounts.select(index);
+
+  result
+}
+
+/// Sleeps until --start-at, or for --start-in. The target is a wall clock time, so it's still honored when the host
+/// sleeps meanwhile.
+pub async fn wait_for_start(args: &Args) {
+  let start_at = match (args.start_at, args.start_in) {
+    (Some(start_at), _) => start_at,
+    (None, Some(start_in)) => Utc::now() + chrono::Duration::from_std(start_in).unwrap_or_default(),
+    (None, None) => return,
+  };
+  let mut clock = ClockGuard::new();
+
+  console::info(format!("Waiting until {} to start", console::format_time(start_at)));
+
+  while let Ok(remaining) = (start_at - Utc::now()).to_std() {
+    clock.sleep(remaining, |_| Utc::now() >= start_at).await;
+  }
+}
+
+/// Watches a channel until its comment is posted, telling how it goes through its events
+pub struct Watcher<'a> {
+  pub args: &'a Args,
+  pub comment_source: &'a dyn CommentSource,
+  pub heartbeat: Option<&'a Heartbeat>,
+  /// Shown by the status server and the dashboard
+  pub recent_events: EventRing,
+  pub safe_mode: &'a mut SafeMode,
+}
+
+impl<'a> Watcher<'a> {
+  /// The events of the watcher, and the watch itself to be run alongside their consumer. The stream ends once the
+  /// watch does.
+  pub fn run(self) -> (EventStream, impl Future<Output = Result<(), Box<dyn Error>>> + 'a) {
+    let (events, stream) = events::channel();
+    let watching = async move {
+      watch(
+        self.args,
+        self.comment_source,
+        self.heartbeat,
+        &events,
+        self.recent_events,
+        self.safe_mode,
+      )
+      .await
+    };
+
+    (stream, watching)
+  }
+}
+
+/// Logs in every account to post with and every project to read with
+async fn log_in(args: &Args, safe_mode: &mut SafeMode) -> Result<(Accounts, ReadProjects), Box<dyn Error>> {
+  // Channels watched at once share their logins, the first one to log in saves the tokens for the others
+  let logging_in = LOGGING_IN.lock().await;
+
+  // Read data and create comments
+  let client_secret = get_client_secret(&args.google_client_id, args.google_client_secret.as_deref())?;
+  let names = match args.account.as_slice() {
+    [] => vec![String::new()],
+    names => names.to_vec(),
+  };
+  let mut accounts = Vec::new();
+
+  for name in names {
+    if !name.is_empty() {
+      console::info(format!("Authenticating account {name}"));
+    }
+
+    let mut client = authenticate_account(&args.google_client_id, &client_secret, &name, args.login_flow())
+      .await
+      .map_err(|e| {
+        safe_mode.record(RunOutcome::AuthFailed);
+        Failure::Auth(e.into())
+      })?;
+
+    if let Some(post_as) = &args.post_as {
+      if !acts_as(&client, post_as).await? {
+        // The only way to switch channels is to log in again and pick another one on the consent screen
+        clear_tokens(get_token_storage_path(&name))?;
+        client = authenticate_account(&args.google_client_id, &client_secret, &name, args.login_flow())
+          .await
+          .map_err(|e| {
+            safe_mode.record(RunOutcome::AuthFailed);
+            Failure::Auth(e.into())
+          })?;
+
+        if !acts_as(&client, post_as).await? {
+          safe_mode.record(RunOutcome::AuthFailed);
+          return Err(Failure::Auth(format!("The login doesn't post as channel {post_as}").into()).into());
+        }
+      }
+    }
+
+    accounts.push((name, client));
+  }
+
+  let mut read_clients = Vec::new();
+
+  for project in &args.read_project {
+    console::info(format!("Authenticating read project {}", project.client_id));
+
+    let read_client = get_youtube_client(
+      &project.client_id,
+      &project.client_secret,
+      get_read_token_storage_path(&project.client_id),
+      &[READ_SCOPE],
+      args.login_flow(),
+    )
+    .await
+    .map_err(|e| {
+      safe_mode.record(RunOutcome::AuthFailed);
+      Failure::Auth(e.into())
+    })?;
+
+    read_clients.push((project.client_id.clone(), read_client));
+  }
+
+  drop(logging_in);
+
+  Ok((Accounts::new(accounts), ReadProjects::new(read_clients)))
+}
+
+/// Held while a watcher logs in, see `watch`
+pub static LOGGING_IN: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+pub async fn watch(
+  args: &Args,
+  comment_source: &dyn CommentSource,
+  heartbeat: Option<&Heartbeat>,
+  events: &EventSender,
+  recent_events: EventRing,
+  safe_mode: &mut SafeMode,
+) -> Result<(), Box<dyn Error>> {
+  // Until the first poll there's nothing to save, a stop request ends the watcher right away
+  shutdown::listen();
+
+  let (accounts, read_projects) = shutdown::unless_requested(async {
+    let logins = log_in(args, safe_mode).await?;
+
+    // Started as far as systemd is concerned, a delayed start would otherwise run into its start timeout
+    systemd::ready(&format!("Watching {}", args.channel_id));
+    wait_for_start(args).await;
+
+    Ok::<_, Box<dyn Error>>(logins)
+  })
+  .await??;
+
+  watch_channel(
+    &accounts,
+    read_projects,
+    args,
+    comment_source,
+    heartbeat,
+    events,
+    recent_events,
+    safe_mode,
+  )
+  .await
+}
+
+/// Why commenting now would go over --max-comments-per-day or --cooldown, if it would
+pub fn comment_limit_reached(args: &Args, history: &History) -> Option<String> {
+  if args.max_comments_per_day.is_none() && args.cooldown.is_none() {
+    return None;
+  }
+
+  let recent = history.recent_comments();
+
+  if let Some(max_comments_per_day) = args.max_comments_per_day {
+    if recent.today >= max_comments_per_day {
+      return Some(format!(
+        "{} comments were already posted today, the daily cap is {max_comments_per_day}",
+        recent.today
+      ));
+    }
+  }
+
+  let since_last = recent
+    .last_posted_at
+    .and_then(|last_posted_at| (Utc::now() - last_posted_at).to_std().ok());
+
+  match (args.cooldown, since_last) {
+    (Some(cooldown), Some(since_last)) if since_last < cooldown => Some(format!(
+      "the last comment was posted {} ago, the cooldown is {}",
+      format_duration(since_last),
+      format_duration(cooldown)
+    )),
+    _ => None,
+  }
+}
+
+/// Stops on a stop request, saving the state for the next run to resume the wait
+fn interrupt(watch_state: &SharedWatchState, state_store: &mut StateStore) -> Box<dyn Error> {
+  systemd::stopping();
+  console::warning("Interrupted, the next run resumes the wait");
+  watch_state.lock().unwrap().last_result = Some("Interrupted".into());
+  state_store.save();
+
+  Box::new(Interrupted)
+}
+
+/// Gives up once the wait limit (and the grace period) is over, the next run starts a new wait
+fn wait_limit_reached(
+  args: &Args,
+  max_wait: Option<Duration>,
+  watch_state: &SharedWatchState,
+  state_store: &mut StateStore,
+  safe_mode: &mut SafeMode,
+  events: &EventSender,
+) -> Box<dyn Error> {
+  console::warning(format!(
+    "The wait limit of {} was reached",
+    format_duration(max_wait.unwrap_or_default())
+  ));
+  watch_state.lock().unwrap().last_result = Some("Wait limit reached".into());
+  safe_mode.record(RunOutcome::WaitLimitReached);
+  state_store.channel(&args.channel_id).end_wait();
+  state_store.save();
+  events.send(WatchEvent::WaitLimitReached);
+
+  Failure::WaitLimitReached.into()
+}
+
+/// Goes back to watching after leaving the video alone, the baseline moving past it unless it was pushed
+fn skip_video(
+  args: &Args,
+  video: &LatestVideo,
+  last_result: String,
+  watch_state: &SharedWatchState,
+  state_store: &mut StateStore,
+  baseline: &mut Baseline,
+) {
+  {
+    let mut state = watch_state.lock().unwrap();
+    state.phase = Phase::Watching;
+    state.last_result = Some(last_result);
+  }
+
+  if !video.pushed {
+    state_store
+      .channel(&args.channel_id)
+      .set_baseline(Some(video.id.clone()), video.published_at);
+    baseline.advance(video);
+  }
+
+  state_store.save();
+}
+
+/// How the watch loop spaces its polls
+struct Pacing {
+  poll_interval: Duration,
+  /// Set when the quota ran out with --on-quota-exceeded wait, the next sleep lasts until it resets
+  quota_reset_in: Option<Duration>,
+  /// Set when a video is pending before the first poll
+  post_right_away: bool,
+  /// The interval runs from the start of the previous poll, so a slow one doesn't push the next one back
+  last_poll_duration: Duration,
+  /// Whether the last poll was already moved to the wait limit, which is only told once
+  moved_last_poll: bool,
+}
+
+/// The sleep before the next step of the watch loop
+struct NextSleep {
+  duration: Duration,
+  /// Paced by the quota or a rate limit, neither a hot window nor a hint cuts it short
+  paced: bool,
+  /// Outside of the watch schedule or waiting for the quota, the time asleep doesn't count against the wait limit
+  off_the_clock: bool,
+}
+
+impl Pacing {
+  fn new(args: &Args, post_right_away: bool) -> Self {
+    Self {
+      poll_interval: args.poll_interval,
+      quota_reset_in: None,
+      post_right_away,
+      last_poll_duration: Duration::ZERO,
+      moved_last_poll: false,
+    }
+  }
+
+  /// The sleep before the next poll, or before the next video of a batch: until the quota resets, none for a video to
+  /// post on right away, the batch spacing, until the next window of the schedule, or else the poll interval, short when
+  /// hot and paced by the quota budget. The last poll comes at the wait limit, and a rate limit comes first.
+  #[allow(clippy::too_many_arguments)]
+  fn next_sleep(
+    &mut self,
+    args: &Args,
+    feed: &Feed,
+    upload_pattern: Option<&UploadPattern>,
+    watch_state: &SharedWatchState,
+    started_at: Instant,
+    wait_limit: Duration,
+    batch_pending: bool,
+    events: &EventSender,
+  ) -> NextSleep {
+    let quota_wait = self.quota_reset_in.take();
+    let mut paced = quota_wait.is_some();
+    let next_start = (!batch_pending).then(|| next_scheduled_start(args)).flatten();
+    let interval = if let Some(quota_wait) = quota_wait {
+      quota_wait
+    } else if mem::take(&mut self.post_right_away) {
+      Duration::ZERO
+    } else if batch_pending {
+      // The next video of a batch is posted after the batch spacing rather than at the next poll
+      args.batch_spacing
+    } else if let Some(next_start) = next_start {
+      console::info(format!(
+        "Outside of the watch schedule, sleeping until {}",
+        console::format_time(next_start)
+      ));
+      self.poll_interval = args.burst_interval;
+
+      (next_start - Utc::now()).to_std().unwrap_or_default()
+    } else {
+      self.poll_interval =
+        if is_hot(args, upload_pattern, started_at) || hints::is_bursting(watch_state, args.hint_burst) {
+          args.burst_interval
+        } else {
+          self.poll_interval.saturating_mul(2).min(args.poll_interval)
+        };
+
+      let interval = args.interval_jitter.map_or(self.poll_interval, |jitter| {
+        jitter.apply(self.poll_interval).max(MIN_POLL_INTERVAL)
+      });
+
+      // A feed costing nothing has no need for pacing
+      match args.quota_budget.filter(|_| feed.poll_cost() > 0) {
+        Some(budget) => {
+          let paced_interval = quota::pace(interval, budget, feed.poll_cost());
+          paced = paced_interval > interval;
+
+          if paced {
+            events.send(WatchEvent::QuotaWarning {
+              used: quota::used_today(),
+              budget,
+              next_poll_in: paced_interval,
+            });
+          }
+
+          paced_interval
+        }
+        None => interval,
+      }
+    };
+
+    let interval = interval.saturating_sub(mem::take(&mut self.last_poll_duration));
+    let off_the_clock = next_start.is_some() || quota_wait.is_some();
+
+    // The last poll comes right at the wait limit rather than never, and the grace period is only polled at its end.
+    // The time asleep outside of the schedule or waiting for the quota doesn't count, it's left alone.
+    let elapsed = started_at.elapsed();
+    let interval = match wait_limit.checked_sub(elapsed) {
+      _ if off_the_clock => interval,
+      Some(until_limit) if !until_limit.is_zero() => {
+        if until_limit < interval && !mem::replace(&mut self.moved_last_poll, true) {
+          console::warning(format!(
+            "The next poll would come after the wait limit, polling one last time in {} instead",
+            format_duration((until_limit + Duration::from_millis(500)).max(Duration::from_secs(1)))
+          ));
+        }
+
+        interval.min(until_limit)
+      }
+      _ => wait_limit.saturating_add(args.grace_period).saturating_sub(elapsed),
+    };
+
+    // A rate limit (Retry-After, or backing off after several in a row) takes precedence over the poll interval
+    let duration = match ratelimit::remaining() {
+      Some(backoff) if backoff > interval => {
+        console::info(format!(
+          "Rate limited by the API, next call in {}",
+          format_duration(backoff.max(Duration::from_secs(1)))
+        ));
+        paced = true;
+        backoff
+      }
+      _ => interval,
+    };
+
+    NextSleep {
+      duration,
+      paced,
+      off_the_clock,
+    }
+  }
+}
+
+/// Whether the watch is paused through the control socket or the API
+fn is_paused(watch_state: &SharedWatchState) -> bool {
+  let mut state = watch_state.lock().unwrap();
+
+  // A post finishing after the pause sets the phase back
+  if let (true, Phase::Watching) = (state.paused, state.phase) {
+    state.phase = Phase::Paused;
+  }
+
+  state.paused
+}
+
+/// Puts the videos pushed through the control socket or the API at the front of the queue, they are posted on right
+/// away. Those already commented on or not found are left out.
+async fn queue_pushed_videos(
+  source: &dyn VideoSource,
+  watch_state: &SharedWatchState,
+  channel_state: &ChannelState,
+  pending: &mut VecDeque<(LatestVideo, Instant)>,
+) {
+  let pushed_video_ids: Vec<String> = watch_state.lock().unwrap().pushed_video_ids.drain(..).collect();
+
+  for video_id in pushed_video_ids.into_iter().rev() {
+    if channel_state.has_commented(&video_id) {
+      console::info(format!("Pushed video {video_id} was already commented on, skipping it"));
+      continue;
+    }
+
+    match source.video(&video_id).await {
+      Ok(Some(video)) => {
+        console::info(format!("Video {video_id} was pushed, posting on it right away"));
+
+        let snippet = video.snippet.unwrap_or_default();
+
+        pending.push_front((
+          LatestVideo {
+            id: video_id,
+            title: snippet.title.unwrap_or_default(),
+            is_short: false,
+            may_be_short: false,
+            published_at: snippet.published_at,
+            pushed: true,
+          },
+          Instant::now(),
+        ));
+      }
+      Ok(None) => console::warning(format!("Pushed video {video_id} doesn't exist, skipping it")),
+      Err(e) => console::error(format!("Failed to get pushed video {video_id}: {e}")),
+    }
+  }
+}
+
+/// The comments posted lately, to cap those on videos published at once however many polls find them: no more than
+/// --max-per-batch within the time the batch takes to post, --batch-spacing apart
+#[derive(Default)]
+struct BatchWindow {
+  posted_at: VecDeque<Instant>,
+}
+
+impl BatchWindow {
+  fn window(args: &Args) -> Duration {
+    args
+      .batch_spacing
+      .saturating_mul(args.max_per_batch.try_into().unwrap_or(u32::MAX))
+  }
+
+  /// How many more videos can be commented on now
+  fn room(&mut self, args: &Args) -> usize {
+    let window = Self::window(args);
+
+    self.posted_at.retain(|posted_at| posted_at.elapsed() < window);
+    args.max_per_batch.saturating_sub(self.posted_at.len())
+  }
+
+  fn record(&mut self) {
+    self.posted_at.push_back(Instant::now());
+  }
+}
+
+/// Queues the new videos (newest first) to comment on oldest first, the order they were published in. Only the latest
+/// ones the batch window and `remaining_comments` leave room for are, the others are remembered as seen.
+fn queue_batch(
+  args: &Args,
+  mut new_videos: Vec<LatestVideo>,
+  remaining_comments: usize,
+  batch: &mut BatchWindow,
+  baseline: &mut Baseline,
+  pending: &mut VecDeque<(LatestVideo, Instant)>,
+) {
+  let room = batch.room(args);
+  let batch_size = room.min(remaining_comments);
+
+  if new_videos.len() > batch_size {
+    if room == 0 {
+      console::info(format!(
+        "{} more videos were published, {} were already commented on within {}, skipping them",
+        new_videos.len(),
+        args.max_per_batch,
+        format_duration(BatchWindow::window(args))
+      ));
+    } else {
+      console::info(format!(
+        "{} videos were published at once, only commenting on the latest {batch_size}",
+        new_videos.len()
+      ));
+    }
+  }
+
+  let left_out = new_videos.split_off(batch_size.min(new_videos.len()));
+  baseline
+    .seen_video_ids
+    .extend(left_out.into_iter().map(|video| video.id));
+
+  let detected_at = Instant::now();
+  pending.extend(new_videos.into_iter().rev().map(|video| (video, detected_at)));
+}
+
+/// What the watch loop keeps from one poll to the next
+#[derive(Default)]
+struct Polls {
+  count: u32,
+  /// The uploads are only sent again once they changed
+  etag: Option<String>,
+  /// Unavailable uploads without a video ID can't be remembered by the baseline, so only the first one is reported
+  reported_unidentified: bool,
+}
+
+impl Polls {
+  /// The latest videos of the feed, newest first, the heartbeat being pinged once it answered. The unavailable uploads
+  /// are reported once, then remembered as seen.
+  async fn poll(
+    &mut self,
+    feed: &Feed,
+    source: &dyn VideoSource,
+    args: &Args,
+    heartbeat: Option<&Heartbeat>,
+    baseline: &mut Baseline,
+  ) -> google_youtube3::Result<Vec<LatestVideo>> {
+    self.count += 1;
+
+    let page = feed
+      .latest_videos(source, BATCH_LOOKBACK, args.api_key.as_deref(), self.etag.as_deref())
+      .await?;
+
+    if let Some(heartbeat) = heartbeat {
+      heartbeat.ping().await;
+    }
+
+    let Some(page) = page else {
+      console::info("The uploads didn't change since the last poll");
+      return Ok(Vec::new());
+    };
+
+    self.etag = page.etag;
+
+    for item in page.unavailable {
+      match item.video_id {
+        Some(video_id) if baseline.seen_video_ids.insert(video_id.clone()) => {
+          console::warning(format!("Video {video_id} is {}, skipping it", item.reason))
+        }
+        None if !mem::replace(&mut self.reported_unidentified, true) => {
+          console::warning(format!("An upload is {}, skipping it", item.reason))
+        }
+        _ => {}
+      }
+    }
+
+    Ok(page.videos)
+  }
+}
+
+/// Reports a failed poll. When the quota ran out on every read project, --on-quota-exceeded either ends the watch for
+/// the next run to resume it, or has the next poll wait for the quota to reset.
+fn poll_failed(
+  e: google_youtube3::Error,
+  args: &Args,
+  read_projects: &mut ReadProjects,
+  watch_state: &SharedWatchState,
+  state_store: &mut StateStore,
+  pacing: &mut Pacing,
+  events: &EventSender,
+) -> Result<(), Box<dyn Error>> {
+  console::error(format!("Failed to get the latest video: {e}"));
+
+  if !is_quota_exceeded(&e) || read_projects.exhausted() {
+    return Ok(());
+  }
+
+  match args.on_quota_exceeded {
+    QuotaPolicy::Exit => {
+      console::warning("The quota ran out, the next run resumes the wait");
+      watch_state.lock().unwrap().last_result = Some("Quota exceeded".into());
+      state_store.save();
+      events.send(WatchEvent::QuotaExceeded { resumes_in: None });
+      Err(Failure::QuotaExceeded(e.into()).into())
+    }
+    QuotaPolicy::Wait => {
+      let resumes_in = quota::until_reset();
+
+      console::info(format!(
+        "The quota ran out, polling again once it resets in {}",
+        format_duration(resumes_in)
+      ));
+      events.send(WatchEvent::QuotaExceeded {
+        resumes_in: Some(resumes_in),
+      });
+      pacing.quota_reset_in = Some(resumes_in);
+      Ok(())
+    }
+  }
+}
+
+/// Why the video isn't wanted: the filters, or being for channel members only with --members-only-policy skip. A pushed
+/// video is wanted whatever it is.
+async fn filtered_out(client: &YoutubeClient, args: &Args, new_video: &LatestVideo, video: &Video) -> Option<String> {
+  if new_video.pushed {
+    return None;
+  }
+
+  if let Some(reason) = filters::skip_reason(args, video) {
+    return Some(reason);
+  }
+
+  if args.members_only_policy != MembersOnlyPolicy::Skip {
+    return None;
+  }
+
+  match is_members_only(client, &args.channel_id, &new_video.id, None).await {
+    Ok(true) => Some("it's for channel members only".into()),
+    Ok(false) => None,
+    Err(e) => {
+      console::error(format!(
+        "Failed to tell whether video {} is for members only: {e}",
+        new_video.id
+      ));
+      None
+    }
+  }
+}
+
+/// How the video is known to have a comment of the account already: the history has it, or the account has a comment
+/// on it with --check-own-comments. The state only remembers the latest videos of this channel, and may be lost.
+async fn already_commented(
+  client: &YoutubeClient,
+  history: &History,
+  own_channel_ids: &[String],
+  video_id: &str,
+) -> Option<String> {
+  if history.has_posted(video_id) {
+    return Some("the history has a comment posted on it".into());
+  }
+
+  if own_channel_ids.is_empty() {
+    return None;
+  }
+
+  match find_own_comment(client, video_id, own_channel_ids).await {
+    Ok(comment_id) => comment_id.map(|comment_id| format!("the account has comment {comment_id} on it")),
+    Err(e) => {
+      console::error(format!(
+        "Failed to look for a comment of the account on video {video_id}: {e}"
+      ));
+      None
+    }
+  }
+}
+
+/// Fetches the video again once the post delay is over (--refresh-after-delay), and tells why it isn't wanted anymore
+/// when the owner changed it meanwhile. When it can't be fetched, the post goes on with what was known of it.
+async fn refresh_after_delay(
+  client: &YoutubeClient,
+  args: &Args,
+  new_video: &mut LatestVideo,
+  video: &mut Video,
+) -> Option<String> {
+  let video_id = &new_video.id;
+
+  match client.video(video_id).await {
+    Ok(Some(refreshed)) => {
+      if let Some(title) = refreshed.snippet.as_ref().and_then(|snippet| snippet.title.clone()) {
+        new_video.title = title;
+      }
+
+      *video = refreshed;
+
+      // Only what the owner can change during the delay, the rest was checked before it
+      if let Some(reason) = filters::skip_reason(args, video) {
+        Some(format!("it's filtered out now, {reason}"))
+      } else if is_age_restricted(video) && args.age_restricted_policy == AgeRestrictedPolicy::Skip {
+        Some("it's age-restricted now".to_string())
+      } else if comments_disabled(client, &new_video.id, video).await {
+        Some("its comments are disabled now".to_string())
+      } else {
+        None
+      }
+    }
+    Ok(None) => {
+      console::warning(format!("Video {video_id} is gone, posting with what was known of it"));
+      None
+    }
+    Err(e) => {
+      console::error(format!(
+        "Failed to fetch video {video_id} again, posting with what was known of it: {e}"
+      ));
+      None
+    }
+  }
+}
+
+/// Posts the comment, racing the accounts with --race-accounts. A rejected comment is posted with the next account, one
+/// flagged as spam is replaced, and a duplicate is handled as --on-duplicate says. The turn among the watched channels
+/// ends with the first post. Returns the comment eventually posted along with the result.
+#[allow(clippy::too_many_arguments)]
+async fn post_on_video(
+  accounts: &Accounts,
+  comment_source: &dyn CommentSource,
+  context: &CommentContext<'_>,
+  mut comment: String,
+  parent_id: Option<&str>,
+  turn: Option<channels::Turn>,
+  args: &Args,
+  deadline: Option<Instant>,
+  events: &EventSender,
+) -> (String, Result<String, PostError>) {
+  let video_id = context.video_id;
+  let mut result = if args.race_accounts {
+    race_accounts(accounts, video_id, &comment, parent_id, args, deadline, events).await
+  } else {
+    post_comment_with_retries(accounts.poster(), video_id, &comment, parent_id, args, deadline, events).await
+  };
+
+  drop(turn);
+
+  // Every other account gets one try when the comment is rejected, racing they all had theirs
+  let other_accounts = if args.race_accounts { 0 } else { accounts.len() - 1 };
+
+  for _ in 0..other_accounts {
+    let Err(PostError::Api(e)) = &result else {
+      break;
+    };
+
+    if !is_rejected(e) {
+      break;
+    }
+
+    let rejected = accounts.poster_name().to_string();
+    accounts.next();
+    console::info(format!(
+      "Account {rejected} got its comment rejected ({e}), posting with account {}",
+      accounts.poster_name()
+    ));
+    result = post_comment_with_retries(accounts.poster(), video_id, &comment, parent_id, args, deadline, events).await;
+  }
+
+  // The same text would only be flagged again
+  if let Err(PostError::Api(e)) = &result {
+    if rejection_reason(e) == Some(RejectionReason::Spam) {
+      match resolve_unflagged(comment_source, context, &comment, args).await {
+        Some(alternate) => {
+          console::info(format!(
+            "Video {video_id} rejected the comment as spam, posting another one"
+          ));
+          comment = alternate;
+          result =
+            post_comment_with_retries(accounts.poster(), video_id, &comment, parent_id, args, deadline, events).await;
+        }
+        None => console::info(format!(
+          "Video {video_id} rejected the comment as spam and there's no other one to post"
+        )),
+      }
+    }
+  }
+
+  if let Err(PostError::Duplicate(_)) = result {
+    match args.on_duplicate {
+      DuplicatePolicy::Success => {
+        console::info(format!(
+          "Video {video_id} already has this comment, counting it as posted"
+        ));
+        // The ID is only needed to check the rank and the visibility, which are skipped without it
+        result = Ok(
+          find_comment(accounts.main(), video_id, &comment)
+            .await
+            .unwrap_or_default(),
+        );
+      }
+      DuplicatePolicy::Variant => {
+        if let Some(alternate) = resolve_alternate(comment_source, context, &comment).await {
+          console::info(format!(
+            "Video {video_id} already has this comment, posting another one"
+          ));
+          comment = alternate;
+          result =
+            post_comment_with_retries(accounts.poster(), video_id, &comment, parent_id, args, deadline, events).await;
+        }
+      }
+      DuplicatePolicy::Fail => {}
+    }
+  }
+
+  (comment, result)
+}
+
+/// Checks on the posted comment: likes the video with --like-video, reports the rank of the comment with --verify-rank,
+/// deleting it when beaten with --delete-if-not-first, and whether it's visible with --verify-visible-after, posting
+/// another one when it isn't with --repost-if-hidden
+#[allow(clippy::too_many_arguments)]
+async fn check_posted_comment(
+  accounts: &Accounts,
+  viewer: &YoutubeClient,
+  comment_source: &dyn CommentSource,
+  context: &CommentContext<'_>,
+  comment: &str,
+  comment_id: &str,
+  parent_id: Option<&str>,
+  args: &Args,
+  deadline: Option<Instant>,
+  history: &History,
+  events: &EventSender,
+) {
+  let client = accounts.main();
+  let video_id = context.video_id;
+
+  // Only once the comment is in, liking can't delay it
+  if args.like_video {
+    match like_video(accounts.poster(), video_id).await {
+      Ok(()) => console::info(format!("Liked video {video_id}")),
+      Err(e) => console::error(format!("Failed to like video {video_id}: {e}")),
+    }
+  }
+
+  // The rank is among the comment threads, which a reply isn't
+  if args.verify_rank && parent_id.is_some() {
+    console::info("Your comment is a reply, its rank isn't checked");
+  } else if args.verify_rank && !comment_id.is_empty() {
+    let mut rank = report_comment_rank(client, video_id, comment_id).await;
+
+    if let Some(delay) = args.verify_rank_delay {
+      // Comments held for review only show up once approved. The comment is in already, a stop request only skips the
+      // check.
+      if shutdown::unless_requested(sleep(delay)).await.is_ok() {
+        rank = report_comment_rank(client, video_id, comment_id).await;
+      }
+    }
+
+    // More comments than were read means older ones, so at least one came before
+    let beaten = matches!(rank, Some(CommentRank::Exact(2..) | CommentRank::AtLeast(_)));
+
+    if args.delete_if_not_first && beaten {
+      match delete_comment(accounts.poster(), comment_id).await {
+        Ok(()) => {
+          console::warning("Someone commented first, your comment was deleted");
+          history.record_deleted(comment_id);
+        }
+        Err(e) => console::error(format!("Failed to delete your comment: {e}")),
+      }
+    }
+  }
+
+  let Some(delay) = args.verify_visible_after.filter(|_| !comment_id.is_empty()) else {
+    return;
+  };
+
+  // Like --verify-rank-delay, a stop request ends the delay and skips the check
+  if shutdown::unless_requested(sleep(delay)).await.is_err() {
+    return;
+  }
+
+  match is_comment_visible(viewer, comment_id, args.viewer_api_key.as_deref()).await {
+    Ok(true) => console::success("Your comment is publicly visible"),
+    Ok(false) => {
+      console::warning(format!(
+        "Your comment is still not publicly visible after {}s, it was probably held for review or filtered as spam",
+        delay.as_secs()
+      ));
+
+      if args.repost_if_hidden {
+        repost_alternate(
+          accounts.poster(),
+          comment_source,
+          context,
+          comment,
+          parent_id,
+          args,
+          deadline,
+          history,
+          events,
+        )
+        .await;
+      }
+    }
+    Err(e) => console::error(format!("Failed to check whether your comment is visible: {e}")),
+  }
+}
+
+/// Starts what goes on after the post on its own: checking whether the comment stays first with --verify-first-for,
+/// the follow-up reply with --follow-up, and monitoring the likes and replies with --monitor-for
+fn spawn_follow_ups(
+  accounts: &Accounts,
+  args: &Args,
+  video_id: &str,
+  comment_id: &str,
+  parent_id: Option<&str>,
+  events: &EventSender,
+) {
+  let client = accounts.main();
+
+  if let Some(window) = args.verify_first_for {
+    if parent_id.is_some() {
+      console::info("Your comment is a reply, whether it stays first isn't checked");
+    } else if !comment_id.is_empty() {
+      console::info(format!(
+        "Checking whether your comment stays first for {}",
+        format_duration(window)
+      ));
+      tokio::spawn(verify_first(
+        client.clone(),
+        video_id.into(),
+        comment_id.into(),
+        window,
+        events.clone(),
+      ));
+    }
+  }
+
+  if let Some(follow_up) = &args.follow_up {
+    // Replies only go one level deep, so a reply follows up under the same comment
+    let replied_id = parent_id.unwrap_or(comment_id);
+    let follow_up = sanitize_comment(args, follow_up);
+
+    if replied_id.is_empty() {
+      console::info("The ID of your comment is unknown, the follow-up reply isn't posted");
+    } else if let Err(e) = comment::validate(&follow_up) {
+      console::error(format!("Invalid follow-up reply, it isn't posted: {e}"));
+    } else {
+      console::info(format!(
+        "Posting the follow-up reply in {}",
+        format_duration(args.follow_up_delay)
+      ));
+      tokio::spawn(post_follow_up(
+        accounts.poster().clone(),
+        video_id.into(),
+        replied_id.into(),
+        follow_up,
+        args.follow_up_delay,
+        events.clone(),
+      ));
+    }
+  }
+
+  if let Some(window) = args.monitor_for {
+    if parent_id.is_some() {
+      console::info("Your comment is a reply, its likes and replies aren't monitored");
+    } else if !comment_id.is_empty() {
+      console::info(format!(
+        "Monitoring the likes and replies of your comment for {}",
+        format_duration(window)
+      ));
+      tokio::spawn(monitor_engagement(
+        client.clone(),
+        video_id.into(),
+        comment_id.into(),
+        window,
+        args.monitor_interval,
+        events.clone(),
+      ));
+    }
+  }
+}
+
+/// Records the failed post, the watch ending with the failure matching the error
+#[allow(clippy::too_many_arguments)]
+fn post_failed(
+  args: &Args,
+  video_id: &str,
+  comment: &str,
+  e: PostError,
+  watch_state: &SharedWatchState,
+  safe_mode: &mut SafeMode,
+  history: &History,
+  events: &EventSender,
+) -> Box<dyn Error> {
+  {
+    let mut state = watch_state.lock().unwrap();
+    state.phase = Phase::Failed;
+    state.last_result = Some(format!("Failed to comment on video {video_id}: {e}"));
+  }
+
+  if let PostError::Deadline(_) = e {
+    console::error(format!(
+      "Gave up posting on video {video_id}, the wait limit expires before the next retry"
+    ));
+    safe_mode.record(RunOutcome::WaitLimitReached);
+  } else {
+    safe_mode.record(RunOutcome::CommentRejected);
+  }
+
+  history.record(&Attempt {
+    channel_id: &args.channel_id,
+    video_id,
+    comment,
+    comment_id: None,
+    result: AttemptResult::Failed,
+    latency: None,
+    error: Some(&e.to_string()),
+  });
+
+  events.send(WatchEvent::PostFailed {
+    video_id: video_id.into(),
+    error: e.to_string(),
+  });
+
+  let failure = match e {
+    PostError::Deadline(_) => Failure::WaitLimitReached,
+    PostError::Api(ref api_error) if is_quota_exceeded(api_error) => Failure::QuotaExceeded(e.into()),
+    e => Failure::CommentRejected(e.into()),
+  };
+
+  failure.into()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn watch_channel(
+  accounts: &Accounts,
+  mut read_projects: ReadProjects,
+  args: &Args,
+  comment_source: &dyn CommentSource,
+  heartbeat: Option<&Heartbeat>,
+  events: &EventSender,
+  recent_events: EventRing,
+  safe_mode: &mut SafeMode,
+) -> Result<(), Box<dyn Error>> {
+  quota::track(&get_data_dir());
+
+  let _ = REQUEST_TIMEOUT.set(args.request_timeout);
+
+  let _lock = if args.allow_multiple {
+    None
+  } else {
+    Some(ChannelLock::acquire(&get_data_dir(), &args.channel_id)?)
+  };
+  let client = accounts.main();
+
+  for poster in accounts.clients() {
+    tokio::spawn(keep_connection_warm(poster.client.clone()));
+  }
+
+  let watch_state = WatchState::new(&args.channel_id, recent_events);
+  // Up until the summary is printed, it closes when returning
+  let _dashboard = args
+    .tui
+    .then(|| tui::Dashboard::start(watch_state.clone()))
+    .transpose()?;
+  let anonymous_client = if args.viewer_api_key.is_some() || args.api_key.is_some() {
+    Some(new_client(NoToken)?)
+  } else {
+    None
+  };
+  let viewer = anonymous_client
+    .as_ref()
+    .filter(|_| args.viewer_api_key.is_some())
+    .unwrap_or(client);
+  // Reads the channel and its uploads
+  let reader = anonymous_client
+    .as_ref()
+    .filter(|_| args.api_key.is_some())
+    .unwrap_or(client);
+
+  if let Some(addr) = args.status_addr {
+    status::serve(addr, watch_state.clone(), client.auth.clone_box())?;
+  }
+
+  if let Some(path) = &args.control_socket {
+    control::serve(path, watch_state.clone(), client.auth.clone_box())?;
+  }
+
+  if let (Some(addr), Some(token)) = (args.api_addr, &args.api_token) {
+    api::serve(addr, token, watch_state.clone(), client.auth.clone_box())?;
+  }
+
+  let comment_source = &ControlledSource::new(watch_state.clone(), comment_source);
+  let _hints = Hints::start(args, &watch_state)?;
+
+  // The channels the accounts post as, to recognize their comments with --check-own-comments
+  let mut own_channel_ids = Vec::new();
+
+  if args.check_own_comments {
+    for poster in accounts.clients() {
+      match get_acting_channel(poster).await {
+        Ok(Some((channel_id, _))) => own_channel_ids.push(channel_id),
+        Ok(None) => {}
+        Err(e) => console::error(format!("Failed to get the channel a login posts as: {e}")),
+      }
+    }
+  }
+
+  let feed = match (Feed::search(args), &args.playlist_id) {
+    (Some(feed), _) => {
+      console::info(format!(
+        "Watching the search results for \"{}\"",
+        args.query.as_deref().unwrap_or_default()
+      ));
+      feed
+    }
+    (None, Some(playlist_id)) => {
+      console::info(format!("Watching playlist {playlist_id} instead of the uploads"));
+      Feed::Playlist(playlist_id.clone())
+    }
+    (None, None) => {
+      let playlist_id = playlists::uploads_playlist_id(
+        reader,
+        &get_data_dir(),
+        &args.channel_id,
+        args.api_key.as_deref(),
+        args.refresh_cache,
+      )
+      .await
+      .ok_or("Failed to get uploads playlist ID")?;
+
+      console::info(format!("Uploads Playlist ID: {playlist_id}"));
+      uploads_feed(args, playlist_id)?
+    }
+  };
+
+  let upload_pattern = if args.auto_schedule {
+    // `yfc analyze` saves a longer history than the RSS feed holds, the latest uploads are added to it
+    let mut published = analyze::saved_publish_times(&get_data_dir(), &args.channel_id);
+    let analyzed = published.len();
+
+    match schedule::fetch_rss_publish_times(&build_https_client()?, &args.channel_id).await {
+      Ok(rss) => published.extend(rss),
+      Err(e) => console::error(format!("Failed to fetch the RSS feed: {e}")),
+    }
+
+    published.sort_unstable();
+    published.dedup();
+
+    if published.is_empty() {
+      console::warning("No upload pattern to learn from, polling at a fixed interval");
+      None
+    } else {
+      console::info(format!(
+        "Learned the upload pattern from {} uploads ({analyzed} saved by yfc analyze)",
+        published.len()
+      ));
+      Some(UploadPattern::new(&published))
+    }
+  } else {
+    None
+  };
+
+  let history = History::open(&get_data_dir());
+  let mut state_store = StateStore::load(&get_data_dir())?;
+  let channel_state = state_store.channel(&args.channel_id);
+  // --wait-limit 0 waits forever, like leaving it out
+  let max_wait = args.wait_limit.filter(|limit| !limit.is_zero());
+  let mut wait_limit = max_wait.unwrap_or(Duration::MAX);
+
+  let mut pending = VecDeque::new();
+  let mut baseline = match channel_state.wait_started_at {
+    Some(wait_started_at) if !args.fresh => {
+      // Only what is left of the wait limit applies to the resumed wait
+      let waited = (Utc::now() - wait_started_at).to_std().unwrap_or_default();
+      wait_limit = wait_limit.saturating_sub(waited);
+
+      console::info(format!("Resuming the wait started {} ago", format_duration(waited)));
+
+      Baseline {
+        published_at: channel_state.baseline_published_at,
+        seen_video_ids: channel_state.baseline_video_id.iter().cloned().collect(),
+      }
+    }
+    _ => {
+      // Everything currently in the playlist is seen, not only the latest video
+      let page = feed
+        .latest_videos(reader, BATCH_LOOKBACK, args.api_key.as_deref(), None)
+        .await?;
+      let (videos, unavailable) = page.map(|page| (page.videos, page.unavailable)).unwrap_or_default();
+      let latest = videos.first();
+
+      channel_state.start_wait(
+        latest.map(|video| video.id.clone()),
+        latest.and_then(|video| video.published_at),
+      );
+      state_store.save();
+
+      let mut baseline = Baseline::new(&videos);
+
+      // Deleted and private videos already there aren't news
+      baseline
+        .seen_video_ids
+        .extend(unavailable.into_iter().filter_map(|item| item.video_id));
+
+      if args.comment_on_current {
+        let channel_state = state_store.channel(&args.channel_id);
+        let mut current = None;
+
+        for video in videos {
+          if !channel_state.has_commented(&video.id) && is_short(args, reader, &video).await == args.shorts_only {
+            current = Some(video);
+            break;
+          }
+        }
+
+        match current {
+          Some(video) => {
+            console::info(format!("Commenting on the current video {} right away", video.id));
+            pending.push_back((video, Instant::now()));
+          }
+          None if args.shorts_only => {
+            console::info("The current short was already commented on or there's none, waiting for the next one")
+          }
+          None => console::info("The current video was already commented on or is a short, waiting for the next one"),
+        }
+      }
+
+      baseline
+    }
+  };
+  let baseline_video_id = state_store.channel(&args.channel_id).baseline_video_id.clone();
+  let started_at = Instant::now();
+
+  {
+    let mut state = watch_state.lock().unwrap();
+    state.phase = Phase::Watching;
+    state.started_at = Some(started_at);
+    state.last_poll_at = Some(Utc::now());
+    state.last_seen_video_id = baseline_video_id;
+    state.wait_limit = max_wait.map(|_| wait_limit);
+  }
+
+  let mut pacing = Pacing::new(args, !pending.is_empty());
+  let mut polls = Polls::default();
+  let mut batch = BatchWindow::default();
+  let mut comments_posted = 0;
+  let mut last_publish_latency = None;
+  let mut clock = ClockGuard::new();
+  let mut reloaded_args = None;
+  // Set once the wait limit (and the grace period) is over, the poll that follows is the last one
+  let mut last_poll = false;
+
+  shutdown::listen();
+
+  // Only once nothing can return early anymore, every exit from the loop flushes the traces
+  #[cfg(feature = "otel")]
+  if let Some(endpoint) = &args.otel_endpoint {
+    otel::init(endpoint);
+  }
+
+  let result = loop {
+    // Options reloaded from the config file apply from the next poll on
+    if let Some(args) = reload::take_args() {
+      reloaded_args = Some(args);
+    }
+
+    let args = reloaded_args.as_ref().unwrap_or(args);
+
+    if last_poll {
+      break Err(wait_limit_reached(
+        args,
+        max_wait,
+        &watch_state,
+        &mut state_store,
+        safe_mode,
+        events,
+      ));
+    }
+
+    let next_sleep = pacing.next_sleep(
+      args,
+      &feed,
+      upload_pattern.as_ref(),
+      &watch_state,
+      started_at,
+      wait_limit,
+      !pending.is_empty(),
+      events,
+    );
+    let interval = next_sleep.duration;
+
+    watch_state.lock().unwrap().next_poll_at = Some(Utc::now() + interval);
+
+    // A hot window may start during a slow interval, or the clock may jump right into one. A budget running low takes
+    // precedence over hot windows though.
+    let sleep_started_at = Instant::now();
+    let show_countdown = |slept: Duration| {
+      if !watch_state.lock().unwrap().paused {
+        let until_limit = max_wait.map(|_| wait_limit.saturating_sub(started_at.elapsed()));
+
+        console::countdown(format_countdown(interval.saturating_sub(slept), until_limit));
+      }
+    };
+
+    show_countdown(Duration::ZERO);
+
+    let suspended = clock
+      .sleep(interval, |slept| {
+        show_countdown(slept);
+
+        // A hint wakes it up right away, to confirm the upload through the API
+        (pending.is_empty()
+          && !next_sleep.paced
+          && ((slept >= args.burst_interval && is_hot(args, upload_pattern.as_ref(), started_at))
+            || watch_state
+              .lock()
+              .unwrap()
+              .hinted_at
+              .is_some_and(|hinted_at| hinted_at > sleep_started_at)))
+          || !watch_state.lock().unwrap().pushed_video_ids.is_empty()
+          || shutdown::requested()
+      })
+      .await;
+
+    console::clear_countdown();
+
+    if shutdown::requested() {
+      break Err(interrupt(&watch_state, &mut state_store));
+    }
+
+    // The wait limit is measured with the monotonic clock, which doesn't count the time the host was asleep, nor
+    // the time spent outside of the watch schedule or waiting for the quota
+    if next_sleep.off_the_clock {
+      wait_limit = wait_limit.saturating_add(sleep_started_at.elapsed());
+    }
+
+    if let Some(suspended) = suspended {
+      wait_limit = wait_limit.saturating_sub(suspended);
+    }
+
+    watch_state.lock().unwrap().wait_limit = max_wait.map(|_| wait_limit);
+    // Polls one last time before giving up, the limit is checked again once it's done
+    last_poll = started_at.elapsed() >= wait_limit.saturating_add(args.grace_period);
+
+    if is_paused(&watch_state) {
+      continue;
+    }
+
+    queue_pushed_videos(
+      client,
+      &watch_state,
+      state_store.channel(&args.channel_id),
+      &mut pending,
+    )
+    .await;
+
+    if pending.is_empty() {
+      let poll_started_at = Instant::now();
+      #[cfg(feature = "otel")]
+      let poll_started_time = SystemTime::now();
+      let polled_videos = match polls
+        .poll(&feed, read_projects.client(reader), args, heartbeat, &mut baseline)
+        .await
+      {
+        Ok(polled_videos) => polled_videos,
+        Err(e) => {
+          if let Err(e) = poll_failed(
+            e,
+            args,
+            &mut read_projects,
+            &watch_state,
+            &mut state_store,
+            &mut pacing,
+            events,
+          ) {
+            break Err(e);
+          }
+
+          Vec::new()
+        }
+      };
+
+      pacing.last_poll_duration = poll_started_at.elapsed();
+
+      #[cfg(feature = "otel")]
+      otel::span(
+        "poll",
+        poll_started_time,
+        &[
+          ("yfc.channel_id", args.channel_id.as_str().into()),
+          ("yfc.poll", polls.count.into()),
+          ("yfc.videos", polled_videos.len().into()),
+        ],
+      );
+
+      {
+        let mut state = watch_state.lock().unwrap();
+        state.last_poll_at = Some(Utc::now());
+
+        if let Some(video) = polled_videos.first() {
+          state.last_seen_video_id = Some(video.id.clone());
+          state.last_seen_video_title = Some(video.title.clone());
+        }
+      }
+
+      let Some(newest_video) = polled_videos.first() else {
+        continue;
+      };
+
+      console::info(format!("Latest Video ID: {}", newest_video.id));
+
+      let new_videos = new_videos(
+        args,
+        reader,
+        &mut baseline,
+        state_store.channel(&args.channel_id),
+        polled_videos,
+        events,
+      )
+      .await;
+
+      let remaining_comments = match args.max_comments {
+        0 => usize::MAX,
+        max_comments => max_comments.saturating_sub(comments_posted),
+      };
+
+      // --max-comments lowered by a reload below what was already posted
+      if remaining_comments == 0 {
+        console::info(format!(
+          "{comments_posted} comments were posted, --max-comments is reached"
+        ));
+        state_store.channel(&args.channel_id).end_wait();
+        state_store.save();
+        break Ok(());
+      }
+
+      queue_batch(
+        args,
+        new_videos,
+        remaining_comments,
+        &mut batch,
+        &mut baseline,
+        &mut pending,
+      );
+
+      if pending.is_empty() {
+        continue;
+      }
+    }
+
+    let Some((mut new_video, detected_at)) = pending.pop_front() else {
+      continue;
+    };
+    let new_video_id = new_video.id.clone();
+    // The race runs from the publication, when known, to the comment being posted
+    #[cfg(feature = "otel")]
+    let mut race = {
+      let detected_at = SystemTime::now() - detected_at.elapsed();
+      let mut race = otel::Trace::new(new_video.published_at.map_or(detected_at, SystemTime::from));
+
+      if let Some(published_at) = new_video.published_at {
+        race.span_between("detection", published_at.into(), detected_at, &[]);
+      }
+
+      race
+    };
+    #[cfg(feature = "otel")]
+    let step_started_at = SystemTime::now();
+
+    // Pushed videos may come without a title
+    let video_name = match new_video.title.as_str() {
+      "" => new_video_id.clone(),
+      title => format!("{title} ({new_video_id})"),
+    };
+
+    match new_video.published_at {
+      Some(published_at) => console::info(format!(
+        "New Video Published: {video_name}, uploaded at {}",
+        console::format_time(published_at)
+      )),
+      None => console::info(format!("New Video Published: {video_name}")),
+    }
+
+    watch_state.lock().unwrap().phase = Phase::WaitingForPublic;
+
+    events.send(WatchEvent::VideoDetected {
+      video_id: new_video_id.clone(),
+      title: new_video.title.clone(),
+      published_at: new_video.published_at,
+    });
+
+    let waited = wait_until_public(
+      client,
+      &new_video_id,
+      args.privacy_poll_interval,
+      started_at,
+      wait_limit,
+    )
+    .await;
+    let Ok(waited) = waited else {
+      break Err(interrupt(&watch_state, &mut state_store));
+    };
+    let Some(mut video) = waited else {
+      break Err(wait_limit_reached(
+        args,
+        max_wait,
+        &watch_state,
+        &mut state_store,
+        safe_mode,
+        events,
+      ));
+    };
+
+    #[cfg(feature = "otel")]
+    race.span("wait_until_public", step_started_at, &[]);
+    #[cfg(feature = "otel")]
+    let step_started_at = SystemTime::now();
+
+    if args.observe {
+      observe_video(client, &new_video_id, &video, detected_at, args.observe_delay, events).await;
+
+      // Observed videos count against --max-comments like commented ones
+      comments_posted += 1;
+
+      let done = args.max_comments != 0 && comments_posted >= args.max_comments;
+      let channel_state = state_store.channel(&args.channel_id);
+
+      if done {
+        channel_state.end_wait();
+      } else if !new_video.pushed {
+        channel_state.set_baseline(Some(new_video_id.clone()), new_video.published_at);
+        baseline.advance(&new_video);
+      }
+
+      state_store.save();
+
+      {
+        let mut state = watch_state.lock().unwrap();
+        state.phase = Phase::Watching;
+        state.last_result = Some(format!("Observed video {new_video_id}"));
+      }
+
+      if done {
+        break Ok(());
+      }
+
+      continue;
+    }
+
+    if let Some(reason) = filtered_out(client, args, &new_video, &video).await {
+      console::info(format!("Video {new_video_id} is filtered out, {reason}, skipping it"));
+      skip_video(
+        args,
+        &new_video,
+        format!("Skipped video {new_video_id}, {reason}"),
+        &watch_state,
+        &mut state_store,
+        &mut baseline,
+      );
+      continue;
+    }
+
+    if comments_disabled(client, &new_video_id, &video).await {
+      // Only keep watching when more videos are to be commented on, otherwise there's nothing left to do
+      if args.max_comments != 0 && comments_posted + 1 >= args.max_comments {
+        watch_state.lock().unwrap().phase = Phase::Failed;
+        break Err(Failure::CommentRejected(format!("Comments are disabled on video {new_video_id}").into()).into());
+      }
+
+      console::warning(format!("Comments are disabled on video {new_video_id}, skipping it"));
+      skip_video(
+        args,
+        &new_video,
+        format!("Skipped video {new_video_id}, comments are disabled"),
+        &watch_state,
+        &mut state_store,
+        &mut baseline,
+      );
+      continue;
+    }
+
+    if is_age_restricted(&video) && args.age_restricted_policy == AgeRestrictedPolicy::Skip {
+      console::warning(format!("Video {new_video_id} is age-restricted, skipping it"));
+      skip_video(
+        args,
+        &new_video,
+        format!("Skipped age-restricted video {new_video_id}"),
+        &watch_state,
+        &mut state_store,
+        &mut baseline,
+      );
+      continue;
+    }
+
+    if let Some(reason) = comment_limit_reached(args, &history) {
+      console::warning(format!("Not commenting on video {new_video_id}: {reason}"));
+      skip_video(
+        args,
+        &new_video,
+        format!("Skipped video {new_video_id}, {reason}"),
+        &watch_state,
+        &mut state_store,
+        &mut baseline,
+      );
+      continue;
+    }
+
+    if let Some(reason) = already_commented(client, &history, &own_channel_ids, &new_video_id).await {
+      console::info(format!(
+        "Video {new_video_id} was already commented on, {reason}, skipping it"
+      ));
+      state_store.channel(&args.channel_id).add_commented(&new_video_id);
+      skip_video(
+        args,
+        &new_video,
+        format!("Skipped video {new_video_id}, already commented on"),
+        &watch_state,
+        &mut state_store,
+        &mut baseline,
+      );
+      continue;
+    }
+
+    watch_state.lock().unwrap().phase = Phase::Posting;
+
+    #[cfg(feature = "otel")]
+    race.span("checks", step_started_at, &[]);
+    #[cfg(feature = "otel")]
+    let step_started_at = SystemTime::now();
+
+    // Counted from the detection, waiting for the video to become public included. A pushed video is wanted right away.
+    let post_delay = if new_video.pushed {
+      Duration::ZERO
+    } else {
+      args
+        .post_delay
+        .saturating_sub(detected_at.elapsed())
+        .min(wait_limit.saturating_sub(started_at.elapsed()))
+    };
+
+    if !post_delay.is_zero() {
+      console::info(format!(
+        "Posting on video {new_video_id} in {}",
+        format_duration(post_delay.max(Duration::from_secs(1)))
+      ));
+      if shutdown::unless_requested(sleep(post_delay)).await.is_err() {
+        break Err(interrupt(&watch_state, &mut state_store));
+      }
+
+      if args.refresh_after_delay {
+        if let Some(reason) = refresh_after_delay(client, args, &mut new_video, &mut video).await {
+          console::warning(format!(
+            "Video {new_video_id} changed during the delay, {reason}, skipping it"
+          ));
+          skip_video(
+            args,
+            &new_video,
+            format!("Skipped video {new_video_id}, {reason}"),
+            &watch_state,
+            &mut state_store,
+            &mut baseline,
+          );
+          continue;
+        }
+      }
+
+      #[cfg(feature = "otel")]
+      race.span("post_delay", step_started_at, &[]);
+    }
+
+    #[cfg(feature = "otel")]
+    let step_started_at = SystemTime::now();
+
+    let progress = history.progress(&args.channel_id, &new_video_id);
+    let context = CommentContext {
+      channel_id: &args.channel_id,
+      video_id: &new_video_id,
+      title: &new_video.title,
+      my_streak: progress.streak,
+      upload_number_this_month: progress.uploads_this_month,
+      language: get_video_language(&video),
+    };
+    // Breaking rather than returning, the run still ends with its stats and traces
+    let mut comment = match comment_source.resolve(&context).await {
+      Ok(resolved_comment) => sanitize_comment(args, &resolved_comment),
+      Err(e) => {
+        watch_state.lock().unwrap().phase = Phase::Failed;
+        break Err(format!("Failed to resolve the comment: {e}").into());
+      }
+    };
+
+    if let Err(e) = comment::validate(&comment) {
+      watch_state.lock().unwrap().phase = Phase::Failed;
+      break Err(format!("Invalid comment: {e}").into());
+    }
+
+    #[cfg(feature = "otel")]
+    race.span("resolve_comment", step_started_at, &[]);
+    #[cfg(feature = "otel")]
+    let step_started_at = SystemTime::now();
+
+    if is_age_restricted(&video) {
+      match args.age_restricted_policy {
+        // Already skipped before the post delay
+        AgeRestrictedPolicy::Skip => {}
+        AgeRestrictedPolicy::Post => console::info(format!("Video {new_video_id} is age-restricted, posting anyway")),
+        AgeRestrictedPolicy::Alternate => {
+          console::info(format!(
+            "Video {new_video_id} is age-restricted, posting the alternate comment"
+          ));
+          comment = sanitize_comment(args, args.age_restricted_comment.as_deref().unwrap_or(&comment));
+        }
+      }
+    }
+
+    let deadline = started_at.checked_add(wait_limit);
+    let parent_id = match args.target {
+      CommentTarget::Thread => None,
+      CommentTarget::ReplyToPinned => {
+        let pinned_deadline = Instant::now() + args.pinned_wait;
+        let owner_comment = wait_for_owner_comment(
+          client,
+          &new_video_id,
+          &args.channel_id,
+          args.burst_interval,
+          deadline.map_or(pinned_deadline, |deadline| deadline.min(pinned_deadline)),
+        )
+        .await;
+
+        if owner_comment.is_none() {
+          console::warning(format!(
+            "The channel owner didn't comment on video {new_video_id}, posting a new thread instead"
+          ));
+        }
+
+        owner_comment
+      }
+    };
+    // Watching several channels, the one with the highest priority posts first
+    let turn = channels::turn(args.priority).await;
+
+    #[cfg(feature = "otel")]
+    race.span("before_insert", step_started_at, &[]);
+    #[cfg(feature = "otel")]
+    let step_started_at = SystemTime::now();
+
+    let (comment, result) = post_on_video(
+      accounts,
+      comment_source,
+      &context,
+      comment,
+      parent_id.as_deref(),
+      turn,
+      args,
+      deadline,
+      events,
+    )
+    .await;
+
+    #[cfg(feature = "otel")]
+    {
+      race.span(
+        "insert_comment",
+        step_started_at,
+        &[("yfc.success", result.is_ok().into())],
+      );
+      race.end(
+        "race",
+        &[
+          ("yfc.channel_id", args.channel_id.as_str().into()),
+          ("yfc.video_id", new_video_id.as_str().into()),
+          ("yfc.success", result.is_ok().into()),
+          ("yfc.error", result.as_ref().err().map(|e| e.to_string()).into()),
+        ],
+      );
+    }
+
+    if let Err(PostError::Api(ref e)) = result {
+      if is_quota_exceeded(e) && args.on_quota_exceeded == QuotaPolicy::Wait {
+        let resumes_in = quota::until_reset();
+
+        console::warning(format!(
+          "The quota ran out while posting on video {new_video_id}, posting again once it resets in {}",
+          format_duration(resumes_in)
+        ));
+        events.send(WatchEvent::QuotaExceeded {
+          resumes_in: Some(resumes_in),
+        });
+        watch_state.lock().unwrap().phase = Phase::Watching;
+        pending.push_front((new_video, detected_at));
+        pacing.quota_reset_in = Some(resumes_in);
+        continue;
+      }
+    }
+
+    break match result {
+      Ok(comment_id) => {
+        let publish_latency = get_publish_latency(&video);
+
+        console::success("Comment created successfuly!");
+
+        if let Some(publish_latency) = publish_latency {
+          console::info(format!(
+            "The comment went live {}ms after the video was published",
+            publish_latency.as_millis()
+          ));
+          last_publish_latency = Some(publish_latency);
+        }
+
+        {
+          let mut state = watch_state.lock().unwrap();
+          state.phase = Phase::Posted;
+          state.last_result = Some(format!("Commented on video {new_video_id}"));
+        }
+
+        safe_mode.record(RunOutcome::Posted);
+        history.record(&Attempt {
+          channel_id: &args.channel_id,
+          video_id: &new_video_id,
+          comment: &comment,
+          comment_id: Some(comment_id.as_str()).filter(|id| !id.is_empty()),
+          result: AttemptResult::Posted,
+          latency: Some(detected_at.elapsed()),
+          error: None,
+        });
+
+        comments_posted += 1;
+        batch.record();
+
+        let done = args.max_comments != 0 && comments_posted >= args.max_comments;
+        let channel_state = state_store.channel(&args.channel_id);
+        channel_state.add_commented(&new_video_id);
+
+        if done {
+          channel_state.end_wait();
+        } else if !new_video.pushed {
+          channel_state.set_baseline(Some(new_video_id.clone()), new_video.published_at);
+        }
+
+        state_store.save();
+
+        events.send(WatchEvent::CommentPosted {
+          video_id: new_video_id.clone(),
+          comment_id: comment_id.clone(),
+          latency: detected_at.elapsed(),
+          publish_latency,
+        });
+
+        check_posted_comment(
+          accounts,
+          viewer,
+          comment_source,
+          &context,
+          &comment,
+          &comment_id,
+          parent_id.as_deref(),
+          args,
+          deadline,
+          &history,
+          events,
+        )
+        .await;
+        spawn_follow_ups(accounts, args, &new_video_id, &comment_id, parent_id.as_deref(), events);
+
+        if !done {
+          watch_state.lock().unwrap().phase = Phase::Watching;
+
+          if !new_video.pushed {
+            baseline.advance(&new_video);
+          }
+
+          if args.rotate_accounts {
+            accounts.next();
+          }
+
+          continue;
+        }
+
+        watch_for_reuploads(accounts.poster(), reader, &feed, new_video, &comment, args, &history)
+          .await
+          .map_err(|e| Box::new(e) as Box<dyn Error>)
+      }
+      Err(e) => Err(post_failed(
+        args,
+        &new_video_id,
+        &comment,
+        e,
+        &watch_state,
+        safe_mode,
+        &history,
+        events,
+      )),
+    };
+  };
+
+  console::info(format!(
+    "The elapsed time was {}",
+    format_duration(started_at.elapsed())
+  ));
+  console::info(format!("Polls performed: {}", polls.count));
+
+  if let Some(publish_latency) = last_publish_latency {
+    console::info(format!(
+      "The last comment went live {}ms after its video was published",
+      publish_latency.as_millis()
+    ));
+  }
+
+  console::info(format!(
+    "Estimated quota used: {} units ({} today)",
+    quota::used(),
+    quota::used_today()
+  ));
+
+  #[cfg(feature = "otel")]
+  otel::flush().await;
+
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    cli::parse_watcher_args,
+    events::EventStream,
+    fake::{api_client, permanent_error, transient_error, FakeYoutube},
+  };
+
+  fn args(options: &[&str]) -> Args {
+    let command_line = ["yfc", "--google-client-id", "id", "--google-client-secret", "secret"]
+      .iter()
+      .chain(["--channel-id", "UCfake"].iter())
+      .chain(options)
+      .map(Into::into)
+      .collect();
+
+    parse_watcher_args(command_line).unwrap()
+  }
+
+  /// The videos of the fake channel the watcher would comment on after polling it
+  async fn poll(youtube: &FakeYoutube, args: &Args, baseline: &mut Baseline, events: &EventSender) -> Vec<String> {
+    let feed = Feed::Playlist("UUfake".into());
+    let page = feed
+      .latest_videos(youtube, BATCH_LOOKBACK, None, None)
+      .await
+      .unwrap()
+      .unwrap();
+
+    new_videos(args, youtube, baseline, &ChannelState::default(), page.videos, events)
+      .await
+      .into_iter()
+      .map(|video| video.id)
+      .collect()
+  }
+
+  async fn baseline(youtube: &FakeYoutube) -> Baseline {
+    let feed = Feed::Playlist("UUfake".into());
+    let page = feed
+      .latest_videos(youtube, BATCH_LOOKBACK, None, None)
+      .await
+      .unwrap()
+      .unwrap();
+
+    Baseline::new(&page.videos)
+  }
+
+  fn count_events(stream: &mut EventStream, matches: fn(&WatchEvent) -> bool) -> usize {
+    let mut count = 0;
+
+    while let Ok(event) = stream.try_recv() {
+      count += usize::from(matches(&event));
+    }
+
+    count
+  }
+
+  #[tokio::test]
+  async fn detects_the_new_video() {
+    let youtube = FakeYoutube::with_uploads(&["old1", "old2"]);
+    let args = args(&[]);
+    let (events, _stream) = events::channel();
+    let mut baseline = baseline(&youtube).await;
+
+    assert!(poll(&youtube, &args, &mut baseline, &events).await.is_empty());
+
+    youtube.upload("new1");
+
+    assert_eq!(poll(&youtube, &args, &mut baseline, &events).await, ["new1"]);
+  }
+
+  #[tokio::test]
+  async fn detects_videos_published_at_once_newest_first() {
+    let youtube = FakeYoutube::with_uploads(&["old1"]);
+    let args = args(&[]);
+    let (events, _stream) = events::channel();
+    let mut baseline = baseline(&youtube).await;
+
+    youtube.upload("new1");
+    youtube.upload("new2");
+
+    assert_eq!(poll(&youtube, &args, &mut baseline, &events).await, ["new2", "new1"]);
+  }
+
+  #[tokio::test]
+  async fn the_latest_video_taken_down_does_not_make_an_older_one_new() {
+    let youtube = FakeYoutube::with_uploads(&["old1", "old2"]);
+    let args = args(&[]);
+    let (events, _stream) = events::channel();
+    let mut baseline = baseline(&youtube).await;
+
+    youtube.remove("old2");
+
+    assert!(poll(&youtube, &args, &mut baseline, &events).await.is_empty());
+  }
+
+  #[tokio::test]
+  async fn skips_videos_already_commented_on() {
+    let youtube = FakeYoutube::with_uploads(&["old1"]);
+    let args = args(&[]);
+    let (events, _stream) = events::channel();
+    let mut baseline = baseline(&youtube).await;
+    let mut channel_state = ChannelState::default();
+
+    youtube.upload("new1");
+    channel_state.add_commented("new1");
+
+    let page = youtube
+      .playlist_videos("UUfake", BATCH_LOOKBACK, None, None)
+      .await
+      .unwrap()
+      .unwrap();
+
+    assert!(
+      new_videos(&args, &youtube, &mut baseline, &channel_state, page.videos, &events)
+        .await
+        .is_empty()
+    );
+    assert!(baseline.seen_video_ids.contains("new1"));
+  }
+
+  #[tokio::test]
+  async fn skips_shorts() {
+    let youtube = FakeYoutube::with_uploads(&["old1"]);
+    let args = args(&[]);
+    let (events, mut stream) = events::channel();
+    let mut baseline = baseline(&youtube).await;
+
+    youtube.upload("short1");
+    youtube.make_short("short1", true);
+
+    assert!(poll(&youtube, &args, &mut baseline, &events).await.is_empty());
+    assert_eq!(
+      count_events(&mut stream, |event| matches!(event, WatchEvent::ShortSkipped { .. })),
+      1
+    );
+
+    // Skipped once and for all, not reported again on the next poll
+    assert!(poll(&youtube, &args, &mut baseline, &events).await.is_empty());
+    assert_eq!(count_events(&mut stream, |_| true), 0);
+  }
+
+  #[tokio::test]
+  async fn asks_about_the_videos_of_backends_that_cant_tell_shorts_apart() {
+    let youtube = FakeYoutube::with_uploads(&["old1"]);
+    let args = args(&[]);
+    let (events, _stream) = events::channel();
+    let mut baseline = baseline(&youtube).await;
+
+    youtube.upload("new1");
+    youtube.upload("short1");
+    youtube.make_short("short1", false);
+
+    let mut page = youtube
+      .playlist_videos("UUfake", BATCH_LOOKBACK, None, None)
+      .await
+      .unwrap()
+      .unwrap();
+
+    for video in &mut page.videos {
+      video.may_be_short = true;
+    }
+
+    let new_videos = new_videos(
+      &args,
+      &youtube,
+      &mut baseline,
+      &ChannelState::default(),
+      page.videos,
+      &events,
+    )
+    .await;
+
+    assert_eq!(
+      new_videos.iter().map(|video| video.id.as_str()).collect::<Vec<_>>(),
+      ["new1"]
+    );
+  }
+
+  #[tokio::test]
+  async fn shorts_only_finds_the_untagged_shorts() {
+    let youtube = FakeYoutube::with_uploads(&["old1"]);
+    let args = args(&["--shorts-only"]);
+    let (events, _stream) = events::channel();
+    let mut baseline = baseline(&youtube).await;
+
+    youtube.upload("new1");
+    youtube.upload("short1");
+    youtube.make_short("short1", false);
+
+    assert_eq!(poll(&youtube, &args, &mut baseline, &events).await, ["short1"]);
+  }
+
+  #[tokio::test]
+  async fn the_latest_eligible_video_is_not_a_short() {
+    let youtube = FakeYoutube::with_uploads(&["old1", "short1"]);
+    let feed = Feed::Playlist("UUfake".into());
+
+    youtube.make_short("short1", true);
+
+    let latest = get_latest_eligible_video(&youtube, &feed, None).await.unwrap();
+
+    assert_eq!(latest.map(|video| video.id).as_deref(), Some("old1"));
+  }
+
+  #[tokio::test]
+  async fn retries_transient_failures() {
+    let youtube = FakeYoutube::with_uploads(&["new1"]);
+    let args = args(&["--post-retries", "2", "--post-retry-delay", "0s"]);
+    let (events, mut stream) = events::channel();
+
+    youtube.fail_posts([transient_error(), transient_error()]);
+
+    let result = post_comment_with_retries(&youtube, "new1", "First!", None, &args, None, &events).await;
+
+    assert_eq!(result.ok().as_deref(), Some("comment-1"));
+    assert_eq!(
+      count_events(&mut stream, |event| matches!(event, WatchEvent::RetryScheduled { .. })),
+      2
+    );
+    assert_eq!(
+      *youtube.posted.lock().unwrap(),
+      [("new1".to_string(), "First!".to_string())]
+    );
+  }
+
+  #[tokio::test]
+  async fn gives_up_once_the_retries_are_used_up() {
+    let youtube = FakeYoutube::with_uploads(&["new1"]);
+    let args = args(&["--post-retries", "1", "--post-retry-delay", "0s"]);
+    let (events, _stream) = events::channel();
+
+    youtube.fail_posts([transient_error(), transient_error()]);
+
+    let result = post_comment_with_retries(&youtube, "new1", "First!", None, &args, None, &events).await;
+
+    assert!(matches!(result, Err(PostError::Api(_))));
+    assert!(youtube.posted.lock().unwrap().is_empty());
+  }
+
+  #[tokio::test]
+  async fn does_not_retry_permanent_failures() {
+    let youtube = FakeYoutube::with_uploads(&["new1"]);
+    let args = args(&["--post-retry-delay", "0s"]);
+    let (events, mut stream) = events::channel();
+
+    youtube.fail_posts([permanent_error()]);
+
+    let result = post_comment_with_retries(&youtube, "new1", "First!", None, &args, None, &events).await;
+
+    assert!(matches!(result, Err(PostError::Api(_))));
+    assert_eq!(count_events(&mut stream, |_| true), 0);
+  }
+
+  #[tokio::test]
+  async fn does_not_retry_past_the_deadline() {
+    let youtube = FakeYoutube::with_uploads(&["new1"]);
+    let args = args(&["--post-retry-delay", "1m"]);
+    let (events, _stream) = events::channel();
+    let deadline = Instant::now() + Duration::from_secs(1);
+
+    youtube.fail_posts([transient_error()]);
+
+    let result = post_comment_with_retries(&youtube, "new1", "First!", None, &args, Some(deadline), &events).await;
+
+    assert!(matches!(result, Err(PostError::Deadline(_))));
+  }
+
+  #[tokio::test]
+  async fn retries_a_server_error_of_the_api() {
+    let args = args(&["--post-retries", "1", "--post-retry-delay", "0s"]);
+    let (events, mut stream) = events::channel();
+
+    let result = post_comment_with_retries(&api_client(), "flaky", "First!", None, &args, None, &events).await;
+
+    assert_eq!(result.ok().as_deref(), Some("thread-flaky"));
+    assert_eq!(
+      count_events(&mut stream, |event| matches!(event, WatchEvent::RetryScheduled { .. })),
+      1
+    );
+  }
+
+  #[tokio::test]
+  async fn does_not_retry_once_the_quota_ran_out() {
+    let args = args(&["--post-retries", "3", "--post-retry-delay", "0s"]);
+    let (events, mut stream) = events::channel();
+
+    let result = post_comment_with_retries(&api_client(), "quota", "First!", None, &args, None, &events).await;
+
+    assert!(matches!(result, Err(PostError::Api(ref e)) if is_quota_exceeded(e)));
+    assert_eq!(count_events(&mut stream, |_| true), 0);
+  }
+
+  #[tokio::test]
+  async fn waits_for_the_video_to_become_public() {
+    let youtube = FakeYoutube::with_uploads(&["new1"]);
+
+    youtube.set_privacy_status("new1", "private");
+
+    let (video, _) = tokio::join!(
+      wait_until_public(
+        &youtube,
+        "new1",
+        Duration::from_millis(10),
+        Instant::now(),
+        Duration::from_secs(5)
+      ),
+      async {
+        sleep(Duration::from_millis(50)).await;
+        youtube.set_privacy_status("new1", "public");
+      }
+    );
+
+    assert_eq!(video.ok().flatten().and_then(|video| video.id).as_deref(), Some("new1"));
+  }
+
+  #[tokio::test]
+  async fn keeps_waiting_when_the_lookup_fails() {
+    let youtube = FakeYoutube::with_uploads(&["new1"]);
+
+    youtube.set_privacy_status("new1", "private");
+    youtube.fail_lookups([transient_error()]);
+
+    let (video, _) = tokio::join!(
+      wait_until_public(
+        &youtube,
+        "new1",
+        Duration::from_millis(10),
+        Instant::now(),
+        Duration::from_secs(5)
+      ),
+      async {
+        sleep(Duration::from_millis(50)).await;
+        youtube.set_privacy_status("new1", "public");
+      }
+    );
+    let video = video.ok().flatten().unwrap();
+
+    assert_eq!(get_privacy_status(&video), Some("public"));
+  }
+
+  #[tokio::test]
+  async fn stops_waiting_at_the_wait_limit() {
+    let youtube = FakeYoutube::with_uploads(&["new1"]);
+
+    youtube.set_privacy_status("new1", "private");
+
+    let video = wait_until_public(
+      &youtube,
+      "new1",
+      Duration::from_millis(10),
+      Instant::now(),
+      Duration::from_millis(50),
+    )
+    .await;
+
+    assert!(matches!(video, Ok(None)));
+  }
+
+  fn watch_state() -> SharedWatchState {
+    WatchState::new("UCfake", EventRing::default())
+  }
+
+  #[test]
+  fn spaces_the_videos_of_a_batch() {
+    let args = args(&["--poll-interval", "2h", "--batch-spacing", "1h"]);
+    let (events, _stream) = events::channel();
+    let feed = Feed::Playlist("UUfake".into());
+    let mut pacing = Pacing::new(&args, false);
+
+    let next_sleep = pacing.next_sleep(
+      &args,
+      &feed,
+      None,
+      &watch_state(),
+      Instant::now(),
+      Duration::MAX,
+      true,
+      &events,
+    );
+
+    assert_eq!(next_sleep.duration, Duration::from_secs(60 * 60));
+    assert!(!next_sleep.off_the_clock);
+  }
+
+  #[test]
+  fn polls_one_last_time_at_the_wait_limit() {
+    let args = args(&["--poll-interval", "2h"]);
+    let (events, _stream) = events::channel();
+    let feed = Feed::Playlist("UUfake".into());
+    let mut pacing = Pacing::new(&args, false);
+    let wait_limit = Duration::from_secs(30 * 60);
+
+    let next_sleep = pacing.next_sleep(
+      &args,
+      &feed,
+      None,
+      &watch_state(),
+      Instant::now(),
+      wait_limit,
+      false,
+      &events,
+    );
+
+    assert!(next_sleep.duration <= wait_limit);
+    assert!(next_sleep.duration > wait_limit - Duration::from_secs(60));
+  }
+
+  #[test]
+  fn waiting_for_the_quota_does_not_count_against_the_wait_limit() {
+    let args = args(&[]);
+    let (events, _stream) = events::channel();
+    let feed = Feed::Playlist("UUfake".into());
+    let mut pacing = Pacing::new(&args, false);
+    let quota_reset_in = Duration::from_secs(2 * 60 * 60);
+
+    pacing.quota_reset_in = Some(quota_reset_in);
+
+    let next_sleep = pacing.next_sleep(
+      &args,
+      &feed,
+      None,
+      &watch_state(),
+      Instant::now(),
+      Duration::from_secs(30 * 60),
+      false,
+      &events,
+    );
+
+    assert_eq!(next_sleep.duration, quota_reset_in);
+    assert!(next_sleep.paced && next_sleep.off_the_clock);
+  }
+
+  #[tokio::test]
+  async fn pushed_videos_jump_the_queue() {
+    let youtube = FakeYoutube::with_uploads(&["batch1", "done1", "new1"]);
+    let watch_state = watch_state();
+    let mut channel_state = ChannelState::default();
+    let page = youtube
+      .playlist_videos("UUfake", BATCH_LOOKBACK, None, None)
+      .await
+      .unwrap()
+      .unwrap();
+    let mut pending: VecDeque<_> = page
+      .videos
+      .into_iter()
+      .filter(|video| video.id == "batch1")
+      .map(|video| (video, Instant::now()))
+      .collect();
+
+    channel_state.add_commented("done1");
+    watch_state
+      .lock()
+      .unwrap()
+      .pushed_video_ids
+      .extend(["new1", "done1", "gone1"].map(String::from));
+
+    queue_pushed_videos(&youtube, &watch_state, &channel_state, &mut pending).await;
+
+    let queued: Vec<_> = pending
+      .iter()
+      .map(|(video, _)| (video.id.as_str(), video.pushed))
+      .collect();
+
+    assert_eq!(queued, [("new1", true), ("batch1", false)]);
+    assert_eq!(pending[0].0.title, "Video new1");
+    assert!(watch_state.lock().unwrap().pushed_video_ids.is_empty());
+  }
+
+  #[tokio::test]
+  async fn polls_the_uploads_again_only_once_they_changed() {
+    let args = args(&[]);
+    let feed = Feed::Playlist("PLuploads".into());
+    let client = api_client();
+    let mut polls = Polls::default();
+    let mut baseline = Baseline::default();
+
+    let videos = polls.poll(&feed, &client, &args, None, &mut baseline).await.unwrap();
+
+    assert_eq!(
+      videos.iter().map(|video| video.id.as_str()).collect::<Vec<_>>(),
+      ["new0000001"]
+    );
+    assert!(baseline.seen_video_ids.contains("private001"));
+
+    let videos = polls.poll(&feed, &client, &args, None, &mut baseline).await.unwrap();
+
+    assert!(videos.is_empty());
+    assert_eq!(polls.count, 2);
+  }
+
+  /// What the watcher queues after polling the fake channel, oldest first
+  async fn queue_new_videos(
+    youtube: &FakeYoutube,
+    args: &Args,
+    batch: &mut BatchWindow,
+    baseline: &mut Baseline,
+    events: &EventSender,
+  ) -> Vec<String> {
+    let page = youtube
+      .playlist_videos("UUfake", BATCH_LOOKBACK, None, None)
+      .await
+      .unwrap()
+      .unwrap();
+    let new_videos = new_videos(args, youtube, baseline, &ChannelState::default(), page.videos, events).await;
+    let mut pending = VecDeque::new();
+
+    queue_batch(args, new_videos, usize::MAX, batch, baseline, &mut pending);
+
+    pending.into_iter().map(|(video, _)| video.id).collect()
+  }
+
+  #[tokio::test]
+  async fn caps_the_comments_on_uploads_found_by_several_polls() {
+    let youtube = FakeYoutube::with_uploads(&["old1"]);
+    let args = args(&["--max-per-batch", "2", "--batch-spacing", "1h"]);
+    let (events, _stream) = events::channel();
+    let mut baseline = baseline(&youtube).await;
+    let mut batch = BatchWindow::default();
+
+    youtube.upload("new1");
+    youtube.upload("new2");
+    youtube.upload("new3");
+
+    let queued = queue_new_videos(&youtube, &args, &mut batch, &mut baseline, &events).await;
+
+    assert_eq!(queued, ["new2", "new3"]);
+
+    for video_id in &queued {
+      batch.record();
+      baseline.seen_video_ids.insert(video_id.clone());
+    }
+
+    // The rest of the dump shows up on the next poll
+    youtube.upload("new4");
+    youtube.upload("new5");
+
+    assert!(queue_new_videos(&youtube, &args, &mut batch, &mut baseline, &events)
+      .await
+      .is_empty());
+    // Left alone for good, not commented on once the window is over
+    assert!(poll(&youtube, &args, &mut baseline, &events).await.is_empty());
+  }
+
+  #[tokio::test]
+  async fn comments_again_once_the_batch_window_is_over() {
+    let youtube = FakeYoutube::with_uploads(&["old1"]);
+    let args = args(&["--max-per-batch", "1", "--batch-spacing", "0s"]);
+    let (events, _stream) = events::channel();
+    let mut baseline = baseline(&youtube).await;
+    let mut batch = BatchWindow::default();
+
+    youtube.upload("new1");
+
+    assert_eq!(
+      queue_new_videos(&youtube, &args, &mut batch, &mut baseline, &events).await,
+      ["new1"]
+    );
+
+    batch.record();
+    baseline.seen_video_ids.insert("new1".into());
+    youtube.upload("new2");
+
+    assert_eq!(
+      queue_new_videos(&youtube, &args, &mut batch, &mut baseline, &events).await,
+      ["new2"]
+    );
+  }
+}
@@ -0,0 +1,139 @@
+use crate::youtube::{with_timeout, HttpsClient, LatestVideo};
+use google_youtube3::hyper::{self, header::CONTENT_TYPE, Body, Method, Request};
+use serde_json::{json, Value};
+use std::io;
+
+/// The endpoint the website loads the tabs of a channel from. It's not a public API: it may change or go away at any
+/// time, which is why --detect web is experimental and falls back to the API.
+const BROWSE_URL: &str = "https://www.youtube.com/youtubei/v1/browse?prettyPrint=false";
+
+/// The web client the requests pretend to come from
+const CLIENT_NAME: &str = "WEB";
+const CLIENT_VERSION: &str = "2.20241001.00.00";
+
+/// Selects the Videos tab of the channel, newest first
+const VIDEOS_TAB_PARAMS: &str = "EgZ2aWRlb3PyBgQKAjoA";
+
+fn parse_error(message: &str) -> google_youtube3::Error {
+  google_youtube3::Error::Io(io::Error::other(format!("unexpected web page: {message}")))
+}
+
+/// The text of a title, either plain or split in runs
+fn text(value: &Value) -> Option<String> {
+  value["simpleText"]
+    .as_str()
+    .or_else(|| value["content"].as_str())
+    .map(String::from)
+    .or_else(|| {
+      value["runs"]
+        .as_array()
+        .map(|runs| runs.iter().filter_map(|run| run["text"].as_str()).collect())
+    })
+}
+
+/// A video of the grid. The website has two layouts, the older video renderer and the newer lockup view model.
+/// Upcoming premieres and streams are listed too, they aren't out yet.
+fn video(value: &Value) -> Option<LatestVideo> {
+  let (id, title) = if let Some(renderer) = value.get("videoRenderer") {
+    if renderer.get("upcomingEventData").is_some() {
+      return None;
+    }
+
+    (renderer["videoId"].as_str()?, text(&renderer["title"]))
+  } else if let Some(lockup) = value.get("lockupViewModel") {
+    if lockup["contentType"] != "LOCKUP_CONTENT_TYPE_VIDEO" {
+      return None;
+    }
+
+    (
+      lockup["contentId"].as_str()?,
+      text(&lockup["metadata"]["lockupMetadataViewModel"]["title"]),
+    )
+  } else {
+    return None;
+  };
+
+  Some(LatestVideo {
+    id: id.into(),
+    title: title.unwrap_or_default(),
+    // The Videos tab leaves the Shorts out, but the layout changes often enough not to rely on it
+    is_short: false,
+    may_be_short: true,
+    // Only a relative time is shown ("3 minutes ago"), too coarse for the baseline which goes by IDs then
+    published_at: None,
+    pushed: false,
+  })
+}
+
+/// Walks the whole page rather than a fixed path to the grid, which changes more often than the renderers
+fn collect_videos(value: &Value, videos: &mut Vec<LatestVideo>) {
+  match value {
+    Value::Object(object) => {
+      if let Some(video) = video(value) {
+        videos.push(video);
+        return;
+      }
+
+      for value in object.values() {
+        collect_videos(value, videos);
+      }
+    }
+    Value::Array(values) => {
+      for value in values {
+        collect_videos(value, videos);
+      }
+    }
+    _ => {}
+  }
+}
+
+/// The latest `count` videos of the channel, newest first, as listed by its Videos tab on the website. It costs no
+/// quota and doesn't lag behind like the uploads playlist sometimes does, but it's unsupported by YouTube.
+pub async fn channel_videos(
+  client: &HttpsClient,
+  channel_id: &str,
+  count: u32,
+) -> google_youtube3::Result<Vec<LatestVideo>> {
+  let body = json!({
+    "context": { "client": { "clientName": CLIENT_NAME, "clientVersion": CLIENT_VERSION, "hl": "en" } },
+    "browseId": channel_id,
+    "params": VIDEOS_TAB_PARAMS,
+  });
+  let request = Request::builder()
+    .method(Method::POST)
+    .uri(BROWSE_URL)
+    .header(CONTENT_TYPE, "application/json")
+    .body(Body::from(body.to_string()))
+    .map_err(|e| google_youtube3::Error::Io(io::Error::other(e)))?;
+
+  with_timeout(async {
+    let response = client
+      .request(request)
+      .await
+      .map_err(google_youtube3::Error::HttpError)?;
+
+    if !response.status().is_success() {
+      return Err(parse_error(&format!("status {}", response.status())));
+    }
+
+    let body = hyper::body::to_bytes(response.into_body())
+      .await
+      .map_err(google_youtube3::Error::HttpError)?;
+    let page: Value = serde_json::from_slice(&body)
+      .map_err(|e| google_youtube3::Error::JsonDecodeError(String::from_utf8_lossy(&body).into(), e))?;
+    let mut videos = Vec::new();
+
+    collect_videos(&page["contents"], &mut videos);
+
+    // A channel without any video would be fine, but it's far more likely the layout changed
+    if videos.is_empty() {
+      return Err(parse_error("no video found in the Videos tab"));
+    }
+
+    videos.dedup_by(|a, b| a.id == b.id);
+    videos.truncate(count as usize);
+
+    Ok(videos)
+  })
+  .await
+}
@@ -0,0 +1,1043 @@
+use crate::auth::READ_SCOPE;
+use crate::{
+  console, quota,
+  ratelimit::{self, RateLimitHint},
+};
+use google_youtube3::{
+  api::{Comment, CommentSnippet, CommentThread, CommentThreadSnippet, PlaylistItem, PlaylistItemListResponse, Video},
+  chrono::{DateTime, Utc},
+  client::GetToken,
+  hyper::{
+    self,
+    client::HttpConnector,
+    header::{AUTHORIZATION, ETAG, IF_NONE_MATCH},
+    Body, Client, Request, Response, StatusCode,
+  },
+  hyper_rustls::{HttpsConnector, HttpsConnectorBuilder},
+  YouTube,
+};
+use std::{
+  fmt,
+  future::Future,
+  io::{self},
+  sync::OnceLock,
+  time::Duration,
+};
+use tokio::time::sleep;
+
+/// Longest an API call may take when --request-timeout isn't given
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Set from --request-timeout when the watcher starts
+pub static REQUEST_TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+/// Gives up on the API call after --request-timeout, failing it with an I/O error, which is retried like any network
+/// error. A hung connection would otherwise block the watcher for good.
+pub async fn with_timeout<T>(call: impl Future<Output = google_youtube3::Result<T>>) -> google_youtube3::Result<T> {
+  let timeout = *REQUEST_TIMEOUT.get().unwrap_or(&DEFAULT_REQUEST_TIMEOUT);
+
+  match tokio::time::timeout(timeout, call).await {
+    Ok(result) => result,
+    Err(_) => Err(google_youtube3::Error::Io(io::Error::new(
+      io::ErrorKind::TimedOut,
+      format!("no response from the API after {}", humantime::format_duration(timeout)),
+    ))),
+  }
+}
+
+pub type HttpsClient = Client<HttpsConnector<HttpConnector>>;
+pub type YoutubeClient = YouTube<HttpsConnector<HttpConnector>>;
+
+pub async fn get_uploads_playlist_id(
+  client: &YoutubeClient,
+  channel_id: &str,
+  api_key: Option<&str>,
+) -> Option<String> {
+  quota::spend(quota::LIST_COST);
+
+  let mut call = client
+    .channels()
+    .list(&vec!["contentDetails".into()])
+    .add_id(channel_id)
+    .param("fields", CHANNEL_FIELDS);
+
+  if let Some(api_key) = api_key {
+    call = call.param("key", api_key);
+  }
+
+  let response = with_timeout(call.doit()).await;
+
+  if let Ok((_, result)) = response {
+    result.items.and_then(|items| {
+      items
+        .first()
+        .and_then(|item| item.content_details.as_ref())
+        .and_then(|details| details.related_playlists.as_ref())
+        .and_then(|playlists| playlists.uploads.clone())
+    })
+  } else {
+    None
+  }
+}
+
+/// Parts of the responses actually read, the API leaves the rest out which makes the responses (and every poll) lighter
+pub const CHANNEL_FIELDS: &str = "items(contentDetails/relatedPlaylists/uploads)";
+pub const PLAYLIST_ITEM_FIELDS: &str =
+  "etag,items(snippet(resourceId/videoId,publishedAt,title,description),status/privacyStatus)";
+
+#[derive(Clone)]
+pub struct LatestVideo {
+  pub id: String,
+  pub title: String,
+  pub is_short: bool,
+  /// Listed by a backend that can't tell the Shorts apart (the RSS feed, the website), so not being tagged as one
+  /// doesn't mean it isn't a Short
+  pub may_be_short: bool,
+  /// When the video was added to the uploads playlist
+  pub published_at: Option<DateTime<Utc>>,
+  /// Pushed by hand with `yfc push` rather than detected. It may not be in the uploads playlist yet, so it never
+  /// becomes the baseline.
+  pub pushed: bool,
+}
+
+/// A playlist item that can't be commented on, left out of the videos of the page
+pub struct UnavailableItem {
+  /// `None` when the item doesn't even say which video it was
+  pub video_id: Option<String>,
+  /// Why it's unavailable, e.g. "private"
+  pub reason: &'static str,
+}
+
+/// The latest uploads of a playlist, and the ETag to ask for them again conditionally
+pub struct PlaylistPage {
+  pub videos: Vec<LatestVideo>,
+  /// Deleted and private videos among the latest uploads
+  pub unavailable: Vec<UnavailableItem>,
+  pub etag: Option<String>,
+}
+
+/// Why the playlist item can't be commented on, if it can't. A video deleted or made private stays in the playlist
+/// with a placeholder title and description, and its privacy status unspecified or private.
+fn unavailable_reason(item: &PlaylistItem) -> Option<&'static str> {
+  let Some(snippet) = &item.snippet else {
+    return Some("missing from the playlist details");
+  };
+
+  if snippet
+    .resource_id
+    .as_ref()
+    .and_then(|resource_id| resource_id.video_id.as_ref())
+    .is_none()
+  {
+    return Some("missing its video ID");
+  }
+
+  let privacy_status = item.status.as_ref().and_then(|status| status.privacy_status.as_deref());
+
+  match (privacy_status, snippet.title.as_deref()) {
+    (Some("privacyStatusUnspecified"), _) | (_, Some("Deleted video")) => Some("deleted"),
+    (Some("private"), _) | (_, Some("Private video")) => Some("private"),
+    _ => None,
+  }
+}
+
+/// Fetches the latest `count` uploads of the playlist, newest first. Given the ETag of the previous page, `None` means
+/// the playlist didn't change. The client can't send `If-None-Match`, so the call is made by hand, failing with the
+/// same errors. With an API key, the call is made with it rather than with the OAuth token of the client.
+pub async fn get_latest_videos(
+  client: &YoutubeClient,
+  playlist_id: &str,
+  count: u32,
+  api_key: Option<&str>,
+  etag: Option<&str>,
+) -> google_youtube3::Result<Option<PlaylistPage>> {
+  with_timeout(fetch_latest_videos(client, playlist_id, count, api_key, etag)).await
+}
+
+pub async fn fetch_latest_videos(
+  client: &YoutubeClient,
+  playlist_id: &str,
+  count: u32,
+  api_key: Option<&str>,
+  etag: Option<&str>,
+) -> google_youtube3::Result<Option<PlaylistPage>> {
+  quota::spend(quota::LIST_COST);
+
+  let mut url = format!(
+    "{}youtube/v3/playlistItems?part=snippet,status&playlistId={playlist_id}&maxResults={count}&fields={PLAYLIST_ITEM_FIELDS}",
+    get_api_base_url()
+  );
+  let mut token = None;
+
+  match api_key {
+    Some(api_key) => url.push_str(&format!("&key={api_key}")),
+    None => {
+      token = client
+        .auth
+        .get_token(&[READ_SCOPE])
+        .await
+        .map_err(google_youtube3::Error::MissingToken)?
+    }
+  }
+
+  let mut request = Request::get(&url);
+
+  if let Some(token) = token {
+    request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+  }
+
+  if let Some(etag) = etag {
+    request = request.header(IF_NONE_MATCH, etag);
+  }
+
+  let request = request
+    .body(Body::empty())
+    .map_err(|e| google_youtube3::Error::Io(io::Error::other(e)))?;
+  let response = client
+    .client
+    .request(request)
+    .await
+    .map_err(google_youtube3::Error::HttpError)?;
+  let status = response.status();
+
+  if status == StatusCode::NOT_MODIFIED {
+    return Ok(None);
+  }
+
+  let headers = response.headers().clone();
+  let etag = headers.get(ETAG).and_then(|etag| etag.to_str().ok()).map(String::from);
+  let body = hyper::body::to_bytes(response.into_body())
+    .await
+    .map_err(google_youtube3::Error::HttpError)?;
+
+  if !status.is_success() {
+    ratelimit::record(status, &headers, serde_json::from_slice(&body).ok().as_ref());
+
+    return Err(match serde_json::from_slice(&body) {
+      Ok(error) => google_youtube3::Error::BadRequest(error),
+      Err(_) => {
+        let mut response = Response::new(Body::from(body));
+        *response.status_mut() = status;
+        google_youtube3::Error::Failure(response)
+      }
+    });
+  }
+
+  ratelimit::cleared();
+
+  let result: PlaylistItemListResponse = serde_json::from_slice(&body)
+    .map_err(|e| google_youtube3::Error::JsonDecodeError(String::from_utf8_lossy(&body).into(), e))?;
+  let etag = etag.or(result.etag);
+
+  let mut videos = Vec::new();
+  let mut unavailable = Vec::new();
+
+  for item in result.items.unwrap_or_default() {
+    if let Some(reason) = unavailable_reason(&item) {
+      unavailable.push(UnavailableItem {
+        video_id: item
+          .snippet
+          .and_then(|snippet| snippet.resource_id)
+          .and_then(|resource_id| resource_id.video_id),
+        reason,
+      });
+      continue;
+    }
+
+    let Some(snippet) = item.snippet else {
+      continue;
+    };
+    let Some(id) = snippet.resource_id.and_then(|resource_id| resource_id.video_id) else {
+      continue;
+    };
+
+    videos.push(LatestVideo {
+      id,
+      title: snippet.title.unwrap_or_default(),
+      // Check for #shorts in the description
+      is_short: snippet.description.unwrap_or_default().contains("#shorts"),
+      may_be_short: false,
+      published_at: snippet.published_at,
+      pushed: false,
+    });
+  }
+
+  Ok(Some(PlaylistPage {
+    videos,
+    unavailable,
+    etag,
+  }))
+}
+
+pub async fn get_video(client: &YoutubeClient, video_id: &str) -> google_youtube3::Result<Option<Video>> {
+  quota::spend(quota::LIST_COST);
+
+  let (_, result) = with_timeout(
+    client
+      .videos()
+      .list(&vec![
+        "status".into(),
+        "contentDetails".into(),
+        "snippet".into(),
+        "liveStreamingDetails".into(),
+      ])
+      .add_id(video_id)
+      .doit(),
+  )
+  .await?;
+
+  Ok(result.items.and_then(|items| items.first().cloned()))
+}
+
+/// Seconds of an ISO 8601 duration such as `PT1H2M3S`, the format of the API. Upcoming streams have `P0D`.
+pub fn parse_duration(duration: &str) -> Option<u64> {
+  let mut seconds = 0;
+  let mut number = String::new();
+
+  for c in duration.strip_prefix('P')?.chars() {
+    match c {
+      '0'..='9' => number.push(c),
+      'T' => {}
+      unit => {
+        let value: u64 = number.parse().ok()?;
+        number.clear();
+
+        seconds += value
+          * match unit {
+            'D' => 86400,
+            'H' => 3600,
+            'M' => 60,
+            'S' => 1,
+            _ => return None,
+          };
+      }
+    }
+  }
+
+  Some(seconds)
+}
+
+/// Shorts are up to 3 minutes long
+const MAX_SHORT_LENGTH: u64 = 3 * 60;
+
+/// Whether the video is a Short. Its page on the Shorts shelf only exists for Shorts, the one of a regular video
+/// redirects to the watch page. When that can't be told, it falls back to the length of the video (a quota unit).
+pub async fn is_short_video(client: &YoutubeClient, video_id: &str) -> google_youtube3::Result<bool> {
+  let request = Request::head(format!("https://www.youtube.com/shorts/{video_id}")).body(Body::empty());
+
+  if let Ok(request) = request {
+    let timeout = *REQUEST_TIMEOUT.get().unwrap_or(&DEFAULT_REQUEST_TIMEOUT);
+
+    if let Ok(Ok(response)) = tokio::time::timeout(timeout, client.client.request(request)).await {
+      if response.status().is_success() {
+        return Ok(true);
+      }
+
+      if response.status().is_redirection() {
+        return Ok(false);
+      }
+    }
+  }
+
+  Ok(get_video(client, video_id).await?.is_some_and(|video| {
+    video
+      .content_details
+      .and_then(|details| details.duration)
+      .as_deref()
+      .and_then(parse_duration)
+      .is_some_and(|seconds| seconds > 0 && seconds <= MAX_SHORT_LENGTH)
+  }))
+}
+
+/// Whether the video is for the members of the channel only. The API doesn't tell, but such videos are also listed in
+/// the members-only uploads playlist of the channel, with `UUMO` in front of the channel ID rather than `UC`. Channels
+/// without memberships have no such playlist.
+pub async fn is_members_only(
+  client: &YoutubeClient,
+  channel_id: &str,
+  video_id: &str,
+  api_key: Option<&str>,
+) -> google_youtube3::Result<bool> {
+  let Some(rest) = channel_id.strip_prefix("UC") else {
+    return Ok(false);
+  };
+
+  quota::spend(quota::LIST_COST);
+
+  let mut call = client
+    .playlist_items()
+    .list(&vec!["id".into()])
+    .playlist_id(&format!("UUMO{rest}"))
+    .video_id(video_id)
+    .max_results(1)
+    .param("fields", "items(id)");
+
+  if let Some(api_key) = api_key {
+    call = call.param("key", api_key);
+  }
+
+  match with_timeout(call.doit()).await {
+    Ok((_, result)) => Ok(result.items.is_some_and(|items| !items.is_empty())),
+    Err(google_youtube3::Error::BadRequest(body)) if body["error"]["code"] == 404 => Ok(false),
+    Err(google_youtube3::Error::Failure(response)) if response.status() == StatusCode::NOT_FOUND => Ok(false),
+    Err(e) => Err(e),
+  }
+}
+
+/// Whether the video is the recording of a live stream that already ended. It comes back at the top of the uploads
+/// once the stream is over.
+pub fn is_stream_recording(video: &Video) -> bool {
+  video
+    .live_streaming_details
+    .as_ref()
+    .is_some_and(|details| details.actual_end_time.is_some())
+}
+
+/// The language the creator set for the video: the one spoken in it, or else the one of its title and description
+pub fn get_video_language(video: &Video) -> Option<&str> {
+  let snippet = video.snippet.as_ref()?;
+
+  snippet
+    .default_audio_language
+    .as_deref()
+    .or(snippet.default_language.as_deref())
+}
+
+pub fn get_privacy_status(video: &Video) -> Option<&str> {
+  video
+    .status
+    .as_ref()
+    .and_then(|status| status.privacy_status.as_deref())
+}
+
+/// How long ago the video was published, for scheduled videos that's when they went public
+pub fn get_publish_latency(video: &Video) -> Option<Duration> {
+  let published_at = video.snippet.as_ref()?.published_at?;
+
+  (Utc::now() - published_at).to_std().ok()
+}
+
+/// Whether comments are turned off on the video. They always are on videos made for kids, otherwise listing the
+/// comment threads tells, which is much cheaper than a rejected insert.
+pub async fn comments_disabled(client: &YoutubeClient, video_id: &str, video: &Video) -> bool {
+  if video.status.as_ref().and_then(|status| status.made_for_kids) == Some(true) {
+    return true;
+  }
+
+  quota::spend(quota::LIST_COST);
+
+  let response = with_timeout(
+    client
+      .comment_threads()
+      .list(&vec!["id".into()])
+      .video_id(video_id)
+      .max_results(1)
+      .doit(),
+  )
+  .await;
+
+  match response {
+    Err(google_youtube3::Error::BadRequest(body)) => body["error"]["errors"]
+      .as_array()
+      .is_some_and(|errors| errors.iter().any(|error| error["reason"] == "commentsDisabled")),
+    _ => false,
+  }
+}
+
+pub fn is_age_restricted(video: &Video) -> bool {
+  video
+    .content_details
+    .as_ref()
+    .and_then(|details| details.content_rating.as_ref())
+    .and_then(|rating| rating.yt_rating.as_deref())
+    == Some("ytAgeRestricted")
+}
+
+/// Whether the comment is publicly listed. With an API key it's checked as an anonymous viewer, otherwise as the
+/// author, who may still see their own comments while they are held for review.
+pub async fn is_comment_visible(
+  viewer: &YoutubeClient,
+  comment_id: &str,
+  api_key: Option<&str>,
+) -> google_youtube3::Result<bool> {
+  quota::spend(quota::LIST_COST);
+
+  let mut call = viewer.comments().list(&vec!["id".into()]).add_id(comment_id);
+
+  if let Some(api_key) = api_key {
+    call = call.param("key", api_key);
+  }
+
+  let (_, result) = with_timeout(call.doit()).await?;
+
+  Ok(result.items.is_some_and(|items| !items.is_empty()))
+}
+
+/// The comment of the channel owner on the video, which is the one they usually pin. The API doesn't tell which
+/// comment is pinned, but the most relevant comments are listed first and the owner's tops them.
+pub async fn find_owner_comment(
+  client: &YoutubeClient,
+  video_id: &str,
+  owner_channel_id: &str,
+) -> google_youtube3::Result<Option<String>> {
+  find_comment_by(client, video_id, "relevance", &[owner_channel_id.into()]).await
+}
+
+/// A comment of one of these channels among the latest 100 on the video, e.g. left by an earlier run whose state was
+/// lost. A new video has far fewer.
+pub async fn find_own_comment(
+  client: &YoutubeClient,
+  video_id: &str,
+  channel_ids: &[String],
+) -> google_youtube3::Result<Option<String>> {
+  find_comment_by(client, video_id, "time", channel_ids).await
+}
+
+/// The first top level comment of one of `channel_ids` among the first 100 comments in `order`, returning its ID
+async fn find_comment_by(
+  client: &YoutubeClient,
+  video_id: &str,
+  order: &str,
+  channel_ids: &[String],
+) -> google_youtube3::Result<Option<String>> {
+  quota::spend(quota::LIST_COST);
+
+  let (_, result) = with_timeout(
+    client
+      .comment_threads()
+      .list(&vec!["snippet".into()])
+      .video_id(video_id)
+      .order(order)
+      .max_results(100)
+      .doit(),
+  )
+  .await?;
+
+  Ok(result.items.unwrap_or_default().into_iter().find_map(|thread| {
+    let author = thread
+      .snippet
+      .as_ref()?
+      .top_level_comment
+      .as_ref()?
+      .snippet
+      .as_ref()?
+      .author_channel_id
+      .as_ref()?
+      .value
+      .as_deref()?;
+
+    channel_ids.iter().any(|id| id == author).then_some(thread.id?)
+  }))
+}
+
+/// How many pages of 100 comments are read to find the rank of the posted comment
+pub const RANK_MAX_PAGES: usize = 5;
+
+/// Where the posted comment stands among the comments of the video, oldest first
+#[derive(Clone, Copy, PartialEq)]
+pub enum CommentRank {
+  Exact(usize),
+  /// The video has more comments than were read, so older ones may not have been counted
+  AtLeast(usize),
+  /// Not listed (yet), which usually means it is held for review
+  NotVisible,
+}
+
+impl CommentRank {
+  /// The position, exact or not, `None` when the comment isn't listed
+  pub fn position(self) -> Option<usize> {
+    match self {
+      CommentRank::Exact(rank) | CommentRank::AtLeast(rank) => Some(rank),
+      CommentRank::NotVisible => None,
+    }
+  }
+}
+
+impl fmt::Display for CommentRank {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      CommentRank::Exact(rank) => write!(f, "#{rank}"),
+      CommentRank::AtLeast(rank) => write!(f, "#{rank} or further down"),
+      CommentRank::NotVisible => write!(f, "not visible"),
+    }
+  }
+}
+
+/// How much attention a comment thread got
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Engagement {
+  pub likes: u32,
+  pub replies: u32,
+}
+
+/// The likes and replies of the comment thread, `None` when it isn't listed
+pub async fn get_engagement(client: &YoutubeClient, comment_id: &str) -> google_youtube3::Result<Option<Engagement>> {
+  quota::spend(quota::LIST_COST);
+
+  let (_, result) = with_timeout(
+    client
+      .comment_threads()
+      .list(&vec!["snippet".into()])
+      .add_id(comment_id)
+      .doit(),
+  )
+  .await?;
+
+  Ok(
+    result
+      .items
+      .and_then(|items| items.into_iter().next())
+      .and_then(|thread| thread.snippet)
+      .map(|snippet| Engagement {
+        likes: snippet
+          .top_level_comment
+          .and_then(|comment| comment.snippet)
+          .and_then(|snippet| snippet.like_count)
+          .unwrap_or_default(),
+        replies: snippet.total_reply_count.unwrap_or_default(),
+      }),
+  )
+}
+
+pub async fn get_comment_rank(
+  client: &YoutubeClient,
+  video_id: &str,
+  comment_id: &str,
+) -> google_youtube3::Result<CommentRank> {
+  let mut page_token: Option<String> = None;
+  let mut comments_after = None;
+
+  // Comment threads are listed newest first, so the rank is one more than the number of comments listed after ours
+  for _ in 0..RANK_MAX_PAGES {
+    quota::spend(quota::LIST_COST);
+
+    let mut call = client
+      .comment_threads()
+      .list(&vec!["id".into()])
+      .video_id(video_id)
+      .order("time")
+      .max_results(100);
+
+    if let Some(page_token) = &page_token {
+      call = call.page_token(page_token);
+    }
+
+    let (_, result) = with_timeout(call.doit()).await?;
+
+    for thread in result.items.unwrap_or_default() {
+      match &mut comments_after {
+        Some(count) => *count += 1,
+        None if thread.id.as_deref() == Some(comment_id) => comments_after = Some(0),
+        None => {}
+      }
+    }
+
+    page_token = result.next_page_token;
+
+    if page_token.is_none() {
+      return Ok(comments_after.map_or(CommentRank::NotVisible, |count| CommentRank::Exact(count + 1)));
+    }
+  }
+
+  Ok(comments_after.map_or(CommentRank::NotVisible, |count| CommentRank::AtLeast(count + 1)))
+}
+
+/// The author and the time of the first comment of the video. `None` when it has no comment yet, or too many to page
+/// through.
+pub async fn get_first_comment(
+  client: &YoutubeClient,
+  video_id: &str,
+) -> google_youtube3::Result<Option<(String, Option<DateTime<Utc>>)>> {
+  let mut page_token: Option<String> = None;
+  let mut oldest = None;
+
+  // Comment threads are listed newest first, so the first comment is the last one listed
+  for _ in 0..RANK_MAX_PAGES {
+    quota::spend(quota::LIST_COST);
+
+    let mut call = client
+      .comment_threads()
+      .list(&vec!["snippet".into()])
+      .video_id(video_id)
+      .order("time")
+      .max_results(100);
+
+    if let Some(page_token) = &page_token {
+      call = call.page_token(page_token);
+    }
+
+    let (_, result) = with_timeout(call.doit()).await?;
+
+    if let Some(snippet) = result
+      .items
+      .unwrap_or_default()
+      .pop()
+      .and_then(|thread| thread.snippet?.top_level_comment?.snippet)
+    {
+      oldest = Some((snippet.author_display_name.unwrap_or_default(), snippet.published_at));
+    }
+
+    page_token = result.next_page_token;
+
+    if page_token.is_none() {
+      return Ok(oldest);
+    }
+  }
+
+  console::warning(format!("Video {video_id} has too many comments to find the first one"));
+
+  Ok(None)
+}
+
+/// Whether the project ran out of its daily quota
+pub fn is_quota_exceeded(error: &google_youtube3::Error) -> bool {
+  match error {
+    google_youtube3::Error::BadRequest(body) => body["error"]["errors"]
+      .as_array()
+      .is_some_and(|errors| errors.iter().any(|error| error["reason"] == "quotaExceeded")),
+    _ => false,
+  }
+}
+
+/// Whether retrying the same request may succeed: network errors, rate limiting and server errors
+pub fn is_transient(error: &google_youtube3::Error) -> bool {
+  match error {
+    google_youtube3::Error::HttpError(_) | google_youtube3::Error::Io(_) => true,
+    google_youtube3::Error::Failure(response) => {
+      response.status().is_server_error() || response.status().as_u16() == 429
+    }
+    google_youtube3::Error::BadRequest(body) => {
+      body["error"]["code"].as_u64().is_some_and(|code| code >= 500)
+        || body["error"]["code"]
+          .as_u64()
+          .and_then(|code| StatusCode::from_u16(code as u16).ok())
+          .is_some_and(|status| ratelimit::is_rate_limit(status, Some(body)))
+    }
+    _ => false,
+  }
+}
+
+/// Whether the API rejected the comment as a duplicate (409 Conflict)
+pub fn is_duplicate(error: &google_youtube3::Error) -> bool {
+  match error {
+    google_youtube3::Error::Failure(response) => response.status().as_u16() == 409,
+    google_youtube3::Error::BadRequest(body) => {
+      body["error"]["code"] == 409
+        || body["error"]["errors"]
+          .as_array()
+          .is_some_and(|errors| errors.iter().any(|error| error["reason"] == "duplicate"))
+    }
+    _ => false,
+  }
+}
+
+/// Whether YouTube refused the comment because of the account that posted it (flagged as spam, not allowed to
+/// comment), which another account may get past
+pub fn is_rejected(error: &google_youtube3::Error) -> bool {
+  const REASONS: &[&str] = &["commentRejected", "spam", "forbidden", "ineligibleAccount"];
+
+  match error {
+    google_youtube3::Error::Failure(response) => response.status().as_u16() == 403,
+    google_youtube3::Error::BadRequest(body) => body["error"]["errors"].as_array().is_some_and(|errors| {
+      errors
+        .iter()
+        .any(|error| REASONS.iter().any(|reason| error["reason"] == *reason))
+    }),
+    _ => false,
+  }
+}
+
+/// Why YouTube refused the comment itself, as told by the reason of the error
+#[derive(Clone, Copy, PartialEq)]
+pub enum RejectionReason {
+  CommentsDisabled,
+  TooLong,
+  /// Flagged as spam, the same text would be flagged again
+  Spam,
+}
+
+impl fmt::Display for RejectionReason {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      RejectionReason::CommentsDisabled => write!(f, "comments are disabled on the video"),
+      RejectionReason::TooLong => write!(f, "the comment is too long"),
+      RejectionReason::Spam => write!(f, "the comment was rejected as spam"),
+    }
+  }
+}
+
+pub fn rejection_reason(error: &google_youtube3::Error) -> Option<RejectionReason> {
+  let google_youtube3::Error::BadRequest(body) = error else {
+    return None;
+  };
+
+  body["error"]["errors"]
+    .as_array()?
+    .iter()
+    .find_map(|error| match error["reason"].as_str()? {
+      "commentsDisabled" => Some(RejectionReason::CommentsDisabled),
+      "commentTextTooLong" => Some(RejectionReason::TooLong),
+      "commentRejected" | "spam" => Some(RejectionReason::Spam),
+      _ => None,
+    })
+}
+
+/// Looks for a top level comment on the video with exactly this text, returning its ID
+pub async fn find_comment(client: &YoutubeClient, video_id: &str, comment: &str) -> Option<String> {
+  quota::spend(quota::LIST_COST);
+
+  let (_, response) = with_timeout(
+    client
+      .comment_threads()
+      .list(&vec!["snippet".into()])
+      .video_id(video_id)
+      .search_terms(comment)
+      .max_results(20)
+      .doit(),
+  )
+  .await
+  .ok()?;
+
+  response.items.unwrap_or_default().into_iter().find_map(|thread| {
+    let text = thread
+      .snippet
+      .as_ref()?
+      .top_level_comment
+      .as_ref()?
+      .snippet
+      .as_ref()?
+      .text_original
+      .as_deref()?;
+
+    (text == comment).then_some(thread.id?)
+  })
+}
+
+/// Creates the comment thread, or the reply to `parent_id`, and returns the ID of the new comment
+pub async fn post_comment(
+  client: &YoutubeClient,
+  video_id: &str,
+  comment: &str,
+  parent_id: Option<&str>,
+) -> google_youtube3::Result<String> {
+  if let Some(parent_id) = parent_id {
+    let reply = Comment {
+      snippet: Some(CommentSnippet {
+        parent_id: Some(parent_id.into()),
+        text_original: Some(comment.into()),
+        ..Default::default()
+      }),
+      ..Default::default()
+    };
+
+    quota::spend(quota::INSERT_COST);
+
+    let (_, reply) = with_timeout(client.comments().insert(reply).delegate(&mut RateLimitHint).doit()).await?;
+
+    return Ok(reply.id.unwrap_or_default());
+  }
+
+  let comment_thread = CommentThread {
+    snippet: Some(CommentThreadSnippet {
+      video_id: Some(video_id.into()),
+      top_level_comment: Some(Comment {
+        snippet: Some(CommentSnippet {
+          text_original: Some(comment.into()),
+          ..Default::default()
+        }),
+        ..Default::default()
+      }),
+      ..Default::default()
+    }),
+    ..Default::default()
+  };
+
+  quota::spend(quota::INSERT_COST);
+
+  let (_, thread) = with_timeout(
+    client
+      .comment_threads()
+      .insert(comment_thread)
+      .delegate(&mut RateLimitHint)
+      .doit(),
+  )
+  .await?;
+
+  Ok(thread.id.unwrap_or_default())
+}
+
+pub async fn like_video(client: &YoutubeClient, video_id: &str) -> google_youtube3::Result<()> {
+  quota::spend(quota::RATE_COST);
+  with_timeout(client.videos().rate(video_id, "like").doit()).await?;
+
+  Ok(())
+}
+
+pub async fn delete_comment(client: &YoutubeClient, comment_id: &str) -> google_youtube3::Result<()> {
+  quota::spend(quota::DELETE_COST);
+  with_timeout(client.comments().delete(comment_id).doit()).await?;
+
+  Ok(())
+}
+
+const DEFAULT_API_BASE_URL: &str = "https://youtube.googleapis.com/";
+
+/// Overrides the base URL of the API calls made by hand, e.g. to point the demo at its fake API
+pub static API_BASE_URL: OnceLock<String> = OnceLock::new();
+
+pub fn get_api_base_url() -> &'static str {
+  API_BASE_URL.get_or_init(|| DEFAULT_API_BASE_URL.into())
+}
+
+/// A client of the API, or of the local one --record and --replay put in its place
+pub fn new_client(auth: impl GetToken + 'static) -> io::Result<YoutubeClient> {
+  let base_url = get_api_base_url();
+
+  if base_url == DEFAULT_API_BASE_URL {
+    return Ok(YouTube::new(build_https_client()?, auth));
+  }
+
+  // The local API is plain HTTP
+  let mut client = YouTube::new(build_http_client()?, auth);
+  client.base_url(base_url.into());
+  client.root_url(base_url.into());
+
+  Ok(client)
+}
+
+/// Interval of the HTTP/2 pings keeping the idle connections alive
+pub const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+pub fn build_https_client() -> io::Result<HttpsClient> {
+  let https_connector = HttpsConnectorBuilder::new()
+    .with_native_roots()?
+    .https_only()
+    .enable_http2()
+    .build();
+
+  // Idle connections are kept for as long as the server allows, so posting reuses one
+  Ok(
+    Client::builder()
+      .pool_idle_timeout(None)
+      .http2_keep_alive_interval(KEEP_ALIVE_INTERVAL)
+      .http2_keep_alive_while_idle(true)
+      .build(https_connector),
+  )
+}
+
+/// How often a connection to the API is opened again if the server closed it
+pub const WARM_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Keeps a connection to the API open so posting the comment doesn't pay for DNS, TCP and TLS. The request isn't an
+/// API call, so it costs no quota. On an open connection it's next to free, and it opens a new one when the server
+/// closed the previous one.
+pub async fn keep_connection_warm(client: HttpsClient) {
+  loop {
+    let request = Request::get(get_api_base_url()).body(Body::empty());
+
+    if let Ok(request) = request {
+      if let Err(e) = client.request(request).await {
+        console::error(format!("Failed to open a connection to the API ahead of posting: {e}"));
+      }
+    }
+
+    sleep(WARM_INTERVAL).await;
+  }
+}
+
+/// Same as `build_https_client`, but also allows plain HTTP for user-provided endpoints living on the local network
+pub fn build_http_client() -> io::Result<HttpsClient> {
+  let https_connector = HttpsConnectorBuilder::new()
+    .with_native_roots()?
+    .https_or_http()
+    .enable_http1()
+    .enable_http2()
+    .build();
+
+  Ok(Client::builder().build(https_connector))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fake::{api_client, UPLOADS_ETAG};
+
+  #[tokio::test]
+  async fn reads_the_uploads_and_their_etag() {
+    let page = get_latest_videos(&api_client(), "PLuploads", 5, Some("key"), None)
+      .await
+      .unwrap()
+      .unwrap();
+
+    assert_eq!(page.etag.as_deref(), Some(UPLOADS_ETAG));
+    assert_eq!(page.videos.len(), 1);
+    assert_eq!(page.videos[0].id, "new0000001");
+    assert!(page.videos[0].is_short);
+    assert_eq!(page.unavailable.len(), 1);
+    assert_eq!(page.unavailable[0].video_id.as_deref(), Some("private001"));
+  }
+
+  #[tokio::test]
+  async fn unchanged_uploads_are_not_read_again() {
+    let page = get_latest_videos(&api_client(), "PLuploads", 5, Some("key"), Some(UPLOADS_ETAG)).await;
+
+    assert!(matches!(page, Ok(None)));
+  }
+
+  #[tokio::test]
+  async fn tells_running_out_of_quota_apart() {
+    let e = get_latest_videos(&api_client(), "PLquota", 5, Some("key"), None)
+      .await
+      .err()
+      .unwrap();
+
+    assert!(is_quota_exceeded(&e));
+    assert!(!is_transient(&e));
+  }
+
+  #[tokio::test]
+  async fn retries_server_errors() {
+    let client = api_client();
+
+    for playlist_id in ["PLbusy", "PLdown"] {
+      let e = get_latest_videos(&client, playlist_id, 5, Some("key"), None)
+        .await
+        .err()
+        .unwrap();
+
+      assert!(is_transient(&e), "{playlist_id}: {e}");
+      assert!(!is_quota_exceeded(&e));
+    }
+  }
+
+  #[tokio::test]
+  async fn posts_the_comment() {
+    let comment_id = post_comment(&api_client(), "video00001", "First!", None).await;
+
+    assert_eq!(comment_id.ok().as_deref(), Some("thread-video00001"));
+  }
+
+  #[tokio::test]
+  async fn classifies_the_insert_errors() {
+    let client = api_client();
+
+    let e = post_comment(&client, "quota", "First!", None).await.err().unwrap();
+    assert!(is_quota_exceeded(&e) && !is_transient(&e));
+
+    let e = post_comment(&client, "limited", "First!", None).await.err().unwrap();
+    assert!(is_transient(&e) && !is_quota_exceeded(&e));
+
+    let e = post_comment(&client, "disabled", "First!", None).await.err().unwrap();
+    assert!(!is_transient(&e));
+    assert!(rejection_reason(&e) == Some(RejectionReason::CommentsDisabled));
+  }
+
+  #[tokio::test]
+  async fn reads_the_privacy_status() {
+    let client = api_client();
+    let video = get_video(&client, "private001").await.unwrap().unwrap();
+
+    assert_eq!(get_privacy_status(&video), Some("private"));
+    assert!(get_video(&client, "missing001").await.unwrap().is_none());
+  }
+}